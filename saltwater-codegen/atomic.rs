@@ -0,0 +1,221 @@
+//! Atomic operations.
+//!
+//! Cranelift has no atomic instructions, so every atomic operation holds a single global lock,
+//! a `pthread_mutex_t` named `__saltwater_atomic_lock`. The lock is defined as a weak symbol
+//! in every object file that uses it, so the linker merges them into one.
+//!
+//! Since every operation holds the same lock, all atomic operations are sequentially consistent,
+//! regardless of the memory order passed in.
+
+use cranelift::codegen::ir::{
+    condcodes::IntCC,
+    stackslot::{StackSlotData, StackSlotKind},
+    types, AbiParam, Signature,
+};
+use cranelift::prelude::{FunctionBuilder, InstBuilder, Type as IrType, Value as IrValue};
+use cranelift_module::{Backend, DataContext, Linkage};
+
+use super::expr::Value;
+use super::Compiler;
+use saltwater_parser::data::{hir::Expr, lex::AtomicOp, *};
+
+const LOCK_NAME: &str = "__saltwater_atomic_lock";
+/// Larger than `sizeof(pthread_mutex_t)` on all supported platforms.
+const LOCK_SIZE: usize = 64;
+
+/// Convert `val` to an integer with the same representation.
+fn to_bits(val: IrValue, ir_type: IrType, builder: &mut FunctionBuilder) -> IrValue {
+    match ir_type {
+        types::B1 => builder.ins().bint(types::I8, val),
+        types::F32 => builder.ins().bitcast(types::I32, val),
+        types::F64 => builder.ins().bitcast(types::I64, val),
+        _ => val,
+    }
+}
+
+/// The inverse of `to_bits`.
+fn from_bits(bits: IrValue, ir_type: IrType, builder: &mut FunctionBuilder) -> IrValue {
+    match ir_type {
+        types::B1 => builder.ins().icmp_imm(IntCC::NotEqual, bits, 0),
+        types::F32 | types::F64 => builder.ins().bitcast(ir_type, bits),
+        _ => bits,
+    }
+}
+
+impl<B: Backend> Compiler<B> {
+    /// Take the global lock, unless this atomic operation is part of one that already holds it.
+    ///
+    /// Returns whether the lock was taken; pass this to `unlock_atomic`.
+    pub(super) fn lock_atomic(&mut self, builder: &mut FunctionBuilder) -> bool {
+        if self.holding_atomic_lock {
+            return false;
+        }
+        self.call_mutex_func("pthread_mutex_lock", builder);
+        self.holding_atomic_lock = true;
+        true
+    }
+    /// Release the global lock if `acquired` is true.
+    pub(super) fn unlock_atomic(&mut self, acquired: bool, builder: &mut FunctionBuilder) {
+        if acquired {
+            self.call_mutex_func("pthread_mutex_unlock", builder);
+            self.holding_atomic_lock = false;
+        }
+    }
+    fn call_mutex_func(&mut self, name: &str, builder: &mut FunctionBuilder) {
+        let lock = match self.atomic_lock {
+            Some(id) => id,
+            None => {
+                let id = self
+                    .module
+                    .declare_data(LOCK_NAME, Linkage::Preemptible, true, false, Some(8))
+                    .unwrap_or_else(|err| panic!("{}", err));
+                let mut ctx = DataContext::new();
+                // all zeros is `PTHREAD_MUTEX_INITIALIZER` on both glibc and musl
+                ctx.define_zeroinit(LOCK_SIZE);
+                self.module
+                    .define_data(id, &ctx)
+                    .unwrap_or_else(|err| panic!("{}", err));
+                self.atomic_lock = Some(id);
+                id
+            }
+        };
+        let lock = self.module.declare_data_in_func(lock, builder.func);
+        let lock = builder.ins().global_value(Type::ptr_type(), lock);
+        // this has to match the declaration in `<pthread.h>`, if the program includes it
+        let signature = Signature {
+            call_conv: self.module.isa().default_call_conv(),
+            params: vec![AbiParam::new(Type::ptr_type())],
+            returns: vec![AbiParam::new(types::I32)],
+        };
+        let func = self
+            .module
+            .declare_function(name, Linkage::Import, &signature)
+            .unwrap_or_else(|err| panic!("{}", err));
+        let func = self.module.declare_func_in_func(func, builder.func);
+        builder.ins().call(func, &[lock]);
+    }
+    /// Load the `_Atomic` object of type `ctype` at `addr`.
+    ///
+    /// Structs, unions, and complex numbers are copied into a temporary while holding the lock,
+    /// so the value doesn't change if the object is modified later.
+    pub(super) fn atomic_load(
+        &mut self,
+        ctype: &Type,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let acquired = self.lock_atomic(builder);
+        let val = match ctype {
+            Type::Struct(_) | Type::Union(_) | Type::Complex(_) => {
+                let size = ctype.sizeof().expect("atomic objects should be complete");
+                let align = ctype
+                    .alignof()
+                    .expect("if sizeof() succeeds so should alignof()")
+                    as u8;
+                let slot = builder.create_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: size as u32,
+                    offset: None,
+                });
+                let temp = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
+                builder.emit_small_memory_copy(
+                    self.module.target_config(),
+                    temp,
+                    addr,
+                    size,
+                    align,
+                    align,
+                    true,
+                );
+                temp
+            }
+            _ => Self::load_scalar(ctype, ctype.as_ir_type(), addr, builder),
+        };
+        self.unlock_atomic(acquired, builder);
+        val
+    }
+    /// `__atomic_fetch_add(&i, 1, __ATOMIC_SEQ_CST)`
+    ///
+    /// All the arguments are evaluated before taking the lock.
+    pub(super) fn atomic_builtin(
+        &mut self,
+        op: AtomicOp,
+        args: Vec<Expr>,
+        ctype: Type,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<Value> {
+        use AtomicOp::*;
+
+        // the type of the object, without `_Atomic`
+        let object = match (op, &args[0].ctype) {
+            (ThreadFence, _) => Type::Void,
+            (_, Type::Pointer(pointee, _)) => pointee.non_atomic().clone(),
+            _ => unreachable!("analyzer should only allow pointers to atomic builtins"),
+        };
+        let ir_type = object.as_ir_type();
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.compile_expr(arg, builder)?.ir_val);
+        }
+
+        let acquired = self.lock_atomic(builder);
+        let result = match op {
+            // taking the lock is all a fence needs to do
+            ThreadFence => None,
+            Load => Some(Self::load_scalar(&object, ir_type, values[0], builder)),
+            Store => {
+                Self::store_scalar(&object, values[1], values[0], builder);
+                None
+            }
+            Exchange => {
+                let old = Self::load_scalar(&object, ir_type, values[0], builder);
+                Self::store_scalar(&object, values[1], values[0], builder);
+                Some(old)
+            }
+            // if `*obj == *expected`, then `*obj = desired`, otherwise `*expected = *obj`
+            CompareExchange => {
+                let (addr, expected_addr, desired) = (values[0], values[1], values[2]);
+                let current = Self::load_scalar(&object, ir_type, addr, builder);
+                let expected = Self::load_scalar(&object, ir_type, expected_addr, builder);
+                // this compares representations, not values, the same as `memcmp`
+                let current_bits = to_bits(current, ir_type, builder);
+                let expected_bits = to_bits(expected, ir_type, builder);
+                let desired_bits = to_bits(desired, ir_type, builder);
+                let equal = builder
+                    .ins()
+                    .icmp(IntCC::Equal, current_bits, expected_bits);
+                let new = builder.ins().select(equal, desired_bits, current_bits);
+                let new = from_bits(new, ir_type, builder);
+                Self::store_scalar(&object, new, addr, builder);
+                // if they were equal, this stores the value `*expected` already had
+                Self::store_scalar(&object, current, expected_addr, builder);
+                Some(equal)
+            }
+            FetchAdd | FetchSub | FetchAnd | FetchOr | FetchXor => {
+                let old = Self::load_scalar(&object, ir_type, values[0], builder);
+                let bits = to_bits(old, ir_type, builder);
+                let operand = to_bits(values[1], ir_type, builder);
+                let func: fn(_, _, _) -> _ = match op {
+                    FetchAdd => InstBuilder::iadd,
+                    FetchSub => InstBuilder::isub,
+                    FetchAnd => InstBuilder::band,
+                    FetchOr => InstBuilder::bor,
+                    FetchXor => InstBuilder::bxor,
+                    _ => unreachable!(),
+                };
+                let new = func(builder.ins(), bits, operand);
+                let new = from_bits(new, ir_type, builder);
+                Self::store_scalar(&object, new, values[0], builder);
+                Some(old)
+            }
+        };
+        self.unlock_atomic(acquired, builder);
+
+        Ok(Value {
+            // just a placeholder for `void` results, like a call to a `void` function
+            ir_val: result.unwrap_or_else(|| builder.ins().iconst(types::I32, 0)),
+            ir_type: ctype.as_ir_type(),
+            ctype,
+        })
+    }
+}
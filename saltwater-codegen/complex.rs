@@ -0,0 +1,297 @@
+//! Complex arithmetic.
+//!
+//! A complex value is represented in the IR as the address of its real part,
+//! which is immediately followed by the imaginary part, the same as in memory.
+//! Operations on complex values load the parts, compute with them as scalars,
+//! and store the result in a new stack slot.
+
+use cranelift::codegen::ir::{
+    condcodes::FloatCC,
+    stackslot::{StackSlotData, StackSlotKind},
+    types, AbiParam, MemFlags, Signature,
+};
+use cranelift::prelude::{FunctionBuilder, InstBuilder, Type as IrType, Value as IrValue};
+use cranelift_module::{Backend, Linkage};
+
+use super::expr::Value;
+use super::Compiler;
+use saltwater_parser::data::{hir::BinaryOp, lex::ComparisonToken, *};
+
+/// The offset of the imaginary part of a complex number with real type `real`.
+fn imag_offset(real: &Type) -> i32 {
+    real.sizeof().expect("floating types should have a size") as i32
+}
+
+fn float_zero(ir_type: IrType, builder: &mut FunctionBuilder) -> IrValue {
+    if ir_type == types::F32 {
+        builder.ins().f32const(0.0)
+    } else {
+        builder.ins().f64const(0.0)
+    }
+}
+
+impl<B: Backend> Compiler<B> {
+    /// Load the real and imaginary parts of the complex number at `addr`.
+    pub(super) fn complex_parts(
+        real: &Type,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> (IrValue, IrValue) {
+        let ir_type = real.as_ir_type();
+        let imag_addr = builder.ins().iadd_imm(addr, i64::from(imag_offset(real)));
+        (
+            Self::load_scalar(real, ir_type, addr, builder),
+            Self::load_scalar(real, ir_type, imag_addr, builder),
+        )
+    }
+    /// Store `re` and `im` to the complex number at `addr`.
+    fn store_parts(
+        real: &Type,
+        re: IrValue,
+        im: IrValue,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) {
+        let imag_addr = builder.ins().iadd_imm(addr, i64::from(imag_offset(real)));
+        Self::store_scalar(real, re, addr, builder);
+        Self::store_scalar(real, im, imag_addr, builder);
+    }
+    /// Allocate a temporary for a complex number with real type `real`.
+    fn complex_temporary(real: &Type, builder: &mut FunctionBuilder) -> IrValue {
+        let slot = builder.create_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: 2 * imag_offset(real) as u32,
+            offset: None,
+        });
+        builder.ins().stack_addr(Type::ptr_type(), slot, 0)
+    }
+    /// Store `re` and `im` in a new temporary and return its address.
+    pub(super) fn new_complex(
+        real: &Type,
+        re: IrValue,
+        im: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let addr = Self::complex_temporary(real, builder);
+        Self::store_parts(real, re, im, addr, builder);
+        addr
+    }
+    /// Build a complex number out of a real one: `re + 0i`.
+    pub(super) fn real_to_complex(
+        real: &Type,
+        re: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let im = float_zero(real.as_ir_type(), builder);
+        Self::new_complex(real, re, im, builder)
+    }
+    /// Split the complex number at `addr` into the values used to pass it to a function.
+    pub(super) fn complex_to_abi(
+        real: &Type,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> Vec<IrValue> {
+        if *real == Type::Float {
+            // both parts are packed into the low 64 bits of an SSE register
+            let packed = builder.ins().load(types::I64, MemFlags::new(), addr, 0);
+            vec![builder.ins().bitcast(types::F64, packed)]
+        } else {
+            let (re, im) = Self::complex_parts(real, addr, builder);
+            vec![re, im]
+        }
+    }
+    /// The inverse of `complex_to_abi`: store the values passed to a function at `addr`.
+    pub(super) fn store_complex_abi(
+        real: &Type,
+        vals: &[IrValue],
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) {
+        match vals {
+            [packed] => {
+                let packed = builder.ins().bitcast(types::I64, *packed);
+                builder.ins().store(MemFlags::new(), packed, addr, 0);
+            }
+            [re, im] => Self::store_parts(real, *re, *im, addr, builder),
+            _ => unreachable!("complex numbers are passed as one or two values"),
+        }
+    }
+    /// Store the values passed to a function in a new temporary and return its address.
+    pub(super) fn complex_from_abi(
+        real: &Type,
+        vals: &[IrValue],
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let addr = Self::complex_temporary(real, builder);
+        Self::store_complex_abi(real, vals, addr, builder);
+        addr
+    }
+    /// Convert a value to or from a complex type.
+    ///
+    /// At least one of `original.ctype` and `ctype` must be complex.
+    pub(super) fn complex_cast(
+        original: Value,
+        orig_signed: bool,
+        ctype: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        match (&original.ctype, ctype) {
+            // `(float _Complex)z`: convert each part
+            (Type::Complex(from), Type::Complex(to)) => {
+                let (re, im) = Self::complex_parts(from, original.ir_val, builder);
+                let (from_ir, to_ir) = (from.as_ir_type(), to.as_ir_type());
+                let re = Self::cast_ir(from_ir, to_ir, re, true, true, builder);
+                let im = Self::cast_ir(from_ir, to_ir, im, true, true, builder);
+                Self::new_complex(to, re, im, builder)
+            }
+            // `(double _Complex)1`: the imaginary part is zero
+            (_, Type::Complex(to)) => {
+                let re = Self::cast_ir(
+                    original.ir_type,
+                    to.as_ir_type(),
+                    original.ir_val,
+                    orig_signed,
+                    true,
+                    builder,
+                );
+                Self::real_to_complex(to, re, builder)
+            }
+            // > When a value of complex type is converted to _Bool, the result is 0
+            // > if both parts compare equal to 0; otherwise, the result is 1.
+            (Type::Complex(from), Type::Bool) => {
+                let (re, im) = Self::complex_parts(from, original.ir_val, builder);
+                let zero = float_zero(from.as_ir_type(), builder);
+                let re = builder.ins().fcmp(FloatCC::NotEqual, re, zero);
+                let im = builder.ins().fcmp(FloatCC::NotEqual, im, zero);
+                builder.ins().bor(re, im)
+            }
+            // > When a value of complex type is converted to a real type,
+            // > the imaginary part of the complex value is discarded
+            (Type::Complex(from), _) => {
+                let re = Self::load_scalar(from, from.as_ir_type(), original.ir_val, builder);
+                Self::cast_ir(
+                    from.as_ir_type(),
+                    ctype.as_ir_type(),
+                    re,
+                    true,
+                    ctype.is_signed(),
+                    builder,
+                )
+            }
+            _ => unreachable!("complex_cast called with real types"),
+        }
+    }
+    /// `-z`
+    pub(super) fn complex_negate(
+        real: &Type,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let (re, im) = Self::complex_parts(real, addr, builder);
+        let (re, im) = (builder.ins().fneg(re), builder.ins().fneg(im));
+        Self::new_complex(real, re, im, builder)
+    }
+    /// Perform `op` on two complex numbers with real type `real`.
+    pub(super) fn complex_binary(
+        &mut self,
+        left: Value,
+        right: Value,
+        real: &Type,
+        op: BinaryOp,
+        builder: &mut FunctionBuilder,
+    ) -> Value {
+        let (a, b) = Self::complex_parts(real, left.ir_val, builder);
+        let (c, d) = Self::complex_parts(real, right.ir_val, builder);
+        let (re, im) = match op {
+            // two complex numbers are equal if both their parts are equal
+            BinaryOp::Compare(token) => {
+                let code = token.to_float_compare();
+                let (re, im) = (
+                    builder.ins().fcmp(code, a, c),
+                    builder.ins().fcmp(code, b, d),
+                );
+                let ir_val = match token {
+                    ComparisonToken::EqualEqual => builder.ins().band(re, im),
+                    ComparisonToken::NotEqual => builder.ins().bor(re, im),
+                    _ => unreachable!("parser should not allow ordering complex numbers"),
+                };
+                return Value {
+                    ir_val,
+                    ir_type: types::B1,
+                    ctype: Type::Bool,
+                };
+            }
+            BinaryOp::Add => (builder.ins().fadd(a, c), builder.ins().fadd(b, d)),
+            BinaryOp::Sub => (builder.ins().fsub(a, c), builder.ins().fsub(b, d)),
+            BinaryOp::Mul | BinaryOp::Div if self.annex_g => {
+                self.annex_g_call(op, real, [a, b, c, d], builder)
+            }
+            // (a + bi)(c + di) = (ac - bd) + (ad + bc)i
+            BinaryOp::Mul => {
+                let (ac, bd) = (builder.ins().fmul(a, c), builder.ins().fmul(b, d));
+                let (ad, bc) = (builder.ins().fmul(a, d), builder.ins().fmul(b, c));
+                (builder.ins().fsub(ac, bd), builder.ins().fadd(ad, bc))
+            }
+            // (a + bi)/(c + di) = ((ac + bd) + (bc - ad)i) / (c^2 + d^2)
+            BinaryOp::Div => {
+                let (ac, bd) = (builder.ins().fmul(a, c), builder.ins().fmul(b, d));
+                let (bc, ad) = (builder.ins().fmul(b, c), builder.ins().fmul(a, d));
+                let (cc, dd) = (builder.ins().fmul(c, c), builder.ins().fmul(d, d));
+                let denominator = builder.ins().fadd(cc, dd);
+                let (re, im) = (builder.ins().fadd(ac, bd), builder.ins().fsub(bc, ad));
+                (
+                    builder.ins().fdiv(re, denominator),
+                    builder.ins().fdiv(im, denominator),
+                )
+            }
+            _ => unreachable!("bug in parser: invalid complex operation {}", op),
+        };
+        Value {
+            ir_val: Self::new_complex(real, re, im, builder),
+            ir_type: Type::ptr_type(),
+            ctype: Type::Complex(Box::new(real.clone())),
+        }
+    }
+    /// Multiply or divide with the runtime library, which recovers infinities
+    /// from results that would otherwise be NaN, as described in Annex G.
+    ///
+    /// All complex types are computed as `double _Complex`.
+    fn annex_g_call(
+        &mut self,
+        op: BinaryOp,
+        real: &Type,
+        args: [IrValue; 4],
+        builder: &mut FunctionBuilder,
+    ) -> (IrValue, IrValue) {
+        let name = if op == BinaryOp::Mul {
+            "__muldc3"
+        } else {
+            "__divdc3"
+        };
+        let signature = Signature {
+            call_conv: self.module.isa().default_call_conv(),
+            params: vec![AbiParam::new(types::F64); 4],
+            returns: vec![AbiParam::new(types::F64); 2],
+        };
+        let func_id = self
+            .module
+            .declare_function(name, Linkage::Import, &signature)
+            .unwrap_or_else(|err| panic!("{}", err));
+        let func_ref = self.module.declare_func_in_func(func_id, builder.func);
+
+        let ir_type = real.as_ir_type();
+        let args: Vec<_> = args
+            .iter()
+            .map(|&arg| Self::cast_ir(ir_type, types::F64, arg, true, true, builder))
+            .collect();
+        let call = builder.ins().call(func_ref, &args);
+        let (re, im) = match *builder.inst_results(call) {
+            [re, im] => (re, im),
+            _ => unreachable!("{} returns two values", name),
+        };
+        (
+            Self::cast_ir(types::F64, ir_type, re, true, true, builder),
+            Self::cast_ir(types::F64, ir_type, im, true, true, builder),
+        )
+    }
+}
@@ -0,0 +1,82 @@
+//! Weak references in ELF object files.
+//!
+//! `__attribute__((weak)) int f(void);` declares `f` as a weak reference:
+//! if nothing defines `f`, the linker resolves it to null instead of giving an error.
+//! Such declarations get `Linkage::Preemptible`, which `cranelift-object` marks as weak,
+//! but the version of `object` it uses always writes undefined symbols as `STB_GLOBAL`.
+//! So after the object file is written, the binding of each weak import is changed to `STB_WEAK`.
+
+use super::Product;
+
+const STB_WEAK: u8 = 2;
+const SHT_SYMTAB: u32 = 2;
+const SHN_UNDEF: u16 = 0;
+
+/// The names of the symbols in `product` that are weak and undefined.
+pub(crate) fn weak_imports(product: &Product) -> Vec<Vec<u8>> {
+    product
+        .functions
+        .values()
+        .chain(product.data_objects.values())
+        .filter_map(|&id| id)
+        .map(|id| product.object.symbol(id))
+        .filter(|symbol| symbol.weak && symbol.is_undefined())
+        .map(|symbol| symbol.name.clone())
+        .collect()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    let mut buf = [0; 2];
+    buf.copy_from_slice(&bytes[offset..offset + 2]);
+    u16::from_le_bytes(buf)
+}
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+fn read_u64(bytes: &[u8], offset: usize) -> usize {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buf) as usize
+}
+
+/// Change the binding of the undefined symbols named in `names` to `STB_WEAK`.
+///
+/// `elf` must be a little-endian 64-bit ELF file, as written by `cranelift-object` for x86_64.
+pub(crate) fn mark_weak(elf: &mut [u8], names: &[Vec<u8>]) {
+    if names.is_empty() {
+        return;
+    }
+    let section_headers = read_u64(elf, 0x28);
+    let header_size = usize::from(read_u16(elf, 0x3a));
+    let header_count = usize::from(read_u16(elf, 0x3c));
+    let header = |i: usize| section_headers + i * header_size;
+    let symtab = match (0..header_count).find(|&i| read_u32(elf, header(i) + 4) == SHT_SYMTAB) {
+        Some(i) => header(i),
+        None => return,
+    };
+    let (symbols, size) = (read_u64(elf, symtab + 0x18), read_u64(elf, symtab + 0x20));
+    let entry_size = read_u64(elf, symtab + 0x38);
+    // the string table with the symbol names is given by `sh_link`
+    let strtab = header(read_u32(elf, symtab + 0x28) as usize);
+    let strings = read_u64(elf, strtab + 0x18);
+
+    for symbol in (symbols..symbols + size).step_by(entry_size) {
+        if read_u16(elf, symbol + 6) != SHN_UNDEF {
+            continue;
+        }
+        let name_start = strings + read_u32(elf, symbol) as usize;
+        let name_len = elf[name_start..]
+            .iter()
+            .position(|&b| b == 0)
+            .expect("symbol names should be null-terminated");
+        if names
+            .iter()
+            .any(|name| **name == elf[name_start..name_start + name_len])
+        {
+            // the binding is in the high 4 bits of `st_info`, and the type in the low 4
+            elf[symbol + 4] = STB_WEAK << 4 | elf[symbol + 4] & 0xf;
+        }
+    }
+}
@@ -2,10 +2,13 @@ use cranelift::codegen::ir::{condcodes, types, MemFlags};
 use cranelift::prelude::{FunctionBuilder, InstBuilder, Type as IrType, Value as IrValue};
 use cranelift_module::Backend;
 
-use super::{Compiler, Id};
+use super::long_double::{self, X87Helper};
+use super::{int128, Compiler, Id};
+use saltwater_parser::arch::{BitField, STACK_ALIGN};
 use saltwater_parser::data::{
-    hir::{self, BinaryOp, Expr, ExprType, LiteralValue, Symbol, Variable},
+    hir::{self, BinaryOp, Expr, ExprType, LiteralValue, Stmt, Symbol, Variable},
     lex::ComparisonToken,
+    types::ArrayType,
     *,
 };
 
@@ -14,8 +17,8 @@ type IrResult = CompileResult<Value>;
 #[derive(Clone, Debug, PartialEq)]
 pub(super) struct Value {
     pub(super) ir_val: IrValue,
-    ir_type: IrType,
-    ctype: Type,
+    pub(super) ir_type: IrType,
+    pub(super) ctype: Type,
 }
 
 enum FuncCall {
@@ -36,19 +39,68 @@ impl<B: Backend> Compiler<B> {
             expr.ctype.as_ir_type()
         };
         match expr.expr {
+            // `(double _Complex)1.0`: constant folding only creates complex literals without an imaginary part
+            ExprType::Literal(token) if expr.ctype.is_complex() => {
+                let real = expr.ctype.real_type().clone();
+                let re = self.compile_literal(real.as_ir_type(), real, token, location, builder)?;
+                Ok(Value {
+                    ir_val: Self::real_to_complex(&re.ctype, re.ir_val, builder),
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
             ExprType::Literal(token) => {
                 self.compile_literal(ir_type, expr.ctype, token, location, builder)
             }
             ExprType::Id(var) => self.load_addr(var, builder),
+            // each compound literal has its own stack slot, which is initialized every time it's evaluated
+            ExprType::CompoundLiteral(var, init) => {
+                let decl = hir::Declaration {
+                    symbol: var,
+                    init: Some(*init),
+                };
+                self.declare_stack(decl, location, builder)?;
+                self.load_addr(var, builder)
+            }
 
             // unary operators
+            // `_Atomic int i; i + 1`: only the load itself holds the lock
+            ExprType::Deref(pointer) if pointer.ctype.is_atomic() => {
+                let addr = self.compile_expr(*pointer, builder)?;
+                let ir_type = match expr.ctype {
+                    Type::Struct(_) | Type::Union(_) => Type::ptr_type(),
+                    _ => ir_type,
+                };
+                Ok(Value {
+                    ir_val: self.atomic_load(&expr.ctype, addr.ir_val, builder),
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+            // complex numbers are copied into a temporary, so later assignments don't change the value
+            ExprType::Deref(pointer) if expr.ctype.is_complex() => {
+                let real = expr.ctype.real_type().clone();
+                let addr = self.compile_expr(*pointer, builder)?;
+                let (re, im) = Self::complex_parts(&real, addr.ir_val, builder);
+                Ok(Value {
+                    ir_val: Self::new_complex(&real, re, im, builder),
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
             ExprType::Deref(pointer) => {
+                let bitfield = Self::bitfield(&pointer);
                 let val = self.compile_expr(*pointer, builder)?;
-                let flags = MemFlags::new();
+                let ir_val = match bitfield {
+                    Some(bitfield) => {
+                        Self::load_bitfield(val.ir_val, &expr.ctype, bitfield, builder)
+                    }
+                    None => Self::load_scalar(&expr.ctype, ir_type, val.ir_val, builder),
+                };
                 Ok(Value {
                     ir_type,
                     ctype: expr.ctype,
-                    ir_val: builder.ins().load(ir_type, flags, val.ir_val, 0),
+                    ir_val,
                 })
             }
             // NOTE: this may be an implicit cast (float f = 1.2) not an explicit cast (1 + (int)1.2)
@@ -101,22 +153,70 @@ impl<B: Backend> Compiler<B> {
                     ctype,
                 })
             }
+            // `__real__ z` and `__imag__ z` are the addresses of the parts of `z`
+            ExprType::Real(inner) => {
+                let val = self.compile_expr(*inner, builder)?;
+                Ok(Value {
+                    ir_val: val.ir_val,
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+            ExprType::Imag(inner) => {
+                let val = self.compile_expr(*inner, builder)?;
+                let offset = expr
+                    .ctype
+                    .sizeof()
+                    .expect("floating types should have a size");
+                Ok(Value {
+                    ir_val: builder.ins().iadd_imm(val.ir_val, offset as i64),
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+            ExprType::Complex(real, imag) => {
+                let (re, im) = (
+                    self.compile_expr(*real, builder)?,
+                    self.compile_expr(*imag, builder)?,
+                );
+                let ir_val =
+                    Self::new_complex(expr.ctype.real_type(), re.ir_val, im.ir_val, builder);
+                Ok(Value {
+                    ir_val,
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
             ExprType::PostIncrement(lval, increase) => {
+                let bitfield = Self::bitfield(&lval);
+                let member_type = lval.ctype.clone();
                 let lval = self.compile_expr(*lval, builder)?;
                 let loaded_ctype = match lval.ctype {
+                    _ if bitfield.is_some() => member_type,
                     Type::Pointer(t, _) => *t,
                     _ => lval.ctype,
                 };
+                // `i++` is a single atomic operation if `i` is `_Atomic`
+                let acquired = loaded_ctype.is_atomic() && self.lock_atomic(builder);
+                let loaded_ctype = loaded_ctype.non_atomic().clone();
                 let ir_type = loaded_ctype.as_ir_type();
+                let ir_val = match bitfield {
+                    Some(bitfield) => {
+                        Self::load_bitfield(lval.ir_val, &loaded_ctype, bitfield, builder)
+                    }
+                    None => Self::load_scalar(&loaded_ctype, ir_type, lval.ir_val, builder),
+                };
                 let previous_value = Value {
-                    ir_val: builder.ins().load(ir_type, MemFlags::new(), lval.ir_val, 0),
+                    ir_val,
                     ir_type,
                     ctype: loaded_ctype,
                 };
 
                 let addend = if increase { 1 } else { -1 };
                 let (addend_ir, add_func): (_, fn(_, _, _) -> _) = match previous_value.ctype {
-                    Type::Double => (builder.ins().f64const(addend as f64), InstBuilder::fadd),
+                    Type::Double | Type::LongDouble => {
+                        (builder.ins().f64const(addend as f64), InstBuilder::fadd)
+                    }
                     Type::Float => (builder.ins().f32const(addend as f32), InstBuilder::fadd),
                     _ => (
                         builder.ins().iconst(previous_value.ir_type, addend),
@@ -124,11 +224,29 @@ impl<B: Backend> Compiler<B> {
                     ),
                 };
                 let new_value = add_func(builder.ins(), previous_value.ir_val, addend_ir);
-                builder
-                    .ins()
-                    .store(MemFlags::new(), new_value, lval.ir_val, 0);
+                match bitfield {
+                    Some(bitfield) => Self::store_bitfield(
+                        new_value,
+                        lval.ir_val,
+                        &previous_value.ctype,
+                        bitfield,
+                        builder,
+                    ),
+                    None => {
+                        Self::store_scalar(&previous_value.ctype, new_value, lval.ir_val, builder)
+                    }
+                }
+                self.unlock_atomic(acquired, builder);
                 Ok(previous_value)
             }
+            // `i += 1` where `i` is `_Atomic`: the load and store happen while holding the lock
+            ExprType::AtomicUpdate(inner) => {
+                let acquired = self.lock_atomic(builder);
+                let val = self.compile_expr(*inner, builder);
+                self.unlock_atomic(acquired, builder);
+                val
+            }
+            ExprType::AtomicBuiltin(op, args) => self.atomic_builtin(op, args, expr.ctype, builder),
             ExprType::Noop(inner) => {
                 let mut val = self.compile_expr(*inner, builder)?;
                 val.ctype = expr.ctype;
@@ -137,7 +255,21 @@ impl<B: Backend> Compiler<B> {
             ExprType::Ternary(condition, left, right) => {
                 self.ternary(*condition, *left, *right, builder)
             }
-            ExprType::Sizeof(_) => unimplemented!("sizeof variable length arrays"),
+            ExprType::StmtExpr(stmts, value) => self.stmt_expr(stmts, value, expr.ctype, builder),
+            // `&&label` is not a real address, only an id that `goto *` knows how to jump to
+            ExprType::LabelAddress(label) => {
+                let id = self.label_address(label);
+                Ok(Value {
+                    ir_val: builder.ins().iconst(ir_type, id as i64),
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+            ExprType::Sizeof(ctype) => Ok(Value {
+                ir_val: self.runtime_sizeof(&ctype, location, builder)?,
+                ir_type: types::I64,
+                ctype: expr.ctype,
+            }),
             ExprType::StaticRef(_) => {
                 unreachable!("static refs can only appear in top level declarations")
             }
@@ -174,6 +306,38 @@ impl<B: Backend> Compiler<B> {
             ctype: left_val.ctype,
         })
     }
+    // `({ int x = f(); x; })`
+    fn stmt_expr(
+        &mut self,
+        stmts: Vec<Stmt>,
+        value: Option<Box<Expr>>,
+        ctype: Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrResult {
+        let height = self.cleanups.len();
+        self.compile_all(stmts, builder)?;
+        // `({ break; 1; })`: control never reaches the value,
+        // but the rest of the enclosing expression still needs somewhere to be compiled
+        if builder.is_filled() {
+            let unreachable = builder.create_block();
+            builder.switch_to_block(unreachable);
+        }
+        let value = match value {
+            Some(value) => self.compile_expr(*value, builder)?,
+            // Just a placeholder.
+            None => Value {
+                ir_val: builder.ins().iconst(types::I32, 0),
+                ir_type: ctype.as_ir_type(),
+                ctype,
+            },
+        };
+        // the objects declared inside go out of scope once the value is computed
+        if !builder.is_filled() {
+            self.run_cleanups(height, builder)?;
+        }
+        self.cleanups.truncate(height);
+        Ok(value)
+    }
     fn logical_expr(
         &mut self,
         left: Expr,
@@ -216,6 +380,10 @@ impl<B: Backend> Compiler<B> {
     ) -> IrResult {
         let ir_val = match (token, ir_type) {
             (LiteralValue::Int(i), types::B1) => builder.ins().bconst(ir_type, i != 0),
+            (LiteralValue::Int(i), types::I128) => int128::iconst(i.into(), builder),
+            (LiteralValue::UnsignedInt(u), types::I128) => int128::iconst(u as i128, builder),
+            (LiteralValue::Int128(i), _) => int128::iconst(i, builder),
+            (LiteralValue::UnsignedInt128(u), _) => int128::iconst(u as i128, builder),
             (LiteralValue::Int(i), _) => builder.ins().iconst(ir_type, i),
             (LiteralValue::UnsignedInt(u), types::B1) => builder.ins().bconst(ir_type, u != 0),
             (LiteralValue::UnsignedInt(u), _) => builder.ins().iconst(ir_type, u as i64),
@@ -257,10 +425,28 @@ impl<B: Backend> Compiler<B> {
         op: BinaryOp,
         builder: &mut FunctionBuilder,
     ) -> IrResult {
+        let real = match &left.ctype {
+            Type::Complex(real) => Some((**real).clone()),
+            _ => None,
+        };
         let (left, right) = (
             self.compile_expr(left, builder)?,
             self.compile_expr(right, builder)?,
         );
+        if let Some(real) = real {
+            return Ok(self.complex_binary(left, right, &real, op, builder));
+        }
+        if left.ir_type == types::I128 {
+            let signed = ctype.is_signed();
+            if let Some(ir_val) = self.int128_binary(left.ir_val, right.ir_val, signed, op, builder)
+            {
+                return Ok(Value {
+                    ir_val,
+                    ir_type: types::I128,
+                    ctype,
+                });
+            }
+        }
         Self::binary_assign_ir(left, right, ctype, op, builder)
     }
     fn binary_assign_ir(
@@ -319,22 +505,40 @@ impl<B: Backend> Compiler<B> {
             // this cast is a no-op, it's just here for the frontend
             return Ok(original);
         }
+        if original.ctype.is_complex() || ctype.is_complex() {
+            return Ok(Value {
+                ir_val: Self::complex_cast(original, orig_signed, &ctype, builder),
+                ir_type: ctype.as_ir_type(),
+                ctype,
+            });
+        }
         let cast_type = ctype.as_ir_type();
-        let cast = Self::cast_ir(
+        let to_signed = ctype.is_signed();
+        let cast = match self.int128_float_cast(
             original.ir_type,
             cast_type,
             original.ir_val,
             orig_signed,
-            ctype.is_signed(),
+            to_signed,
             builder,
-        );
+        ) {
+            Some(cast) => cast,
+            None => Self::cast_ir(
+                original.ir_type,
+                cast_type,
+                original.ir_val,
+                orig_signed,
+                to_signed,
+                builder,
+            ),
+        };
         Ok(Value {
             ir_val: cast,
             ir_type: cast_type,
             ctype,
         })
     }
-    fn cast_ir(
+    pub(super) fn cast_ir(
         from: IrType,
         to: IrType,
         val: IrValue,
@@ -353,6 +557,13 @@ impl<B: Backend> Compiler<B> {
             (types::F32, types::F64) => builder.ins().fpromote(to, val),
             (types::F64, types::F32) => builder.ins().fdemote(to, val),
             // narrowing and widening integer conversions
+            (b, types::I128) if b.is_bool() => {
+                let int_val = builder.ins().bint(types::I64, val);
+                int128::extend(int_val, false, builder)
+            }
+            (types::I128, b) if b.is_bool() => int128::to_bool(val, builder),
+            (types::I128, i) if i.is_int() => int128::reduce(i, val, builder),
+            (i, types::I128) if i.is_int() => int128::extend(val, from_signed, builder),
             (b, i) if b.is_bool() && i.is_int() => builder.ins().bint(to, val),
             (i, b) if i.is_int() && b.is_bool() => {
                 builder.ins().icmp_imm(condcodes::IntCC::NotEqual, val, 0)
@@ -405,13 +616,25 @@ impl<B: Backend> Compiler<B> {
         }
     }
     fn negate(&mut self, expr: Expr, builder: &mut FunctionBuilder) -> IrResult {
+        if let Type::Complex(real) = &expr.ctype {
+            let real = (**real).clone();
+            let val = self.compile_expr(expr, builder)?;
+            return Ok(Value {
+                ir_val: Self::complex_negate(&real, val.ir_val, builder),
+                ..val
+            });
+        }
         self.unary_op(expr, builder, |ir_val, ir_type, _, builder| match ir_type {
+            types::I128 => {
+                let zero = int128::iconst(0, builder);
+                builder.ins().isub(zero, ir_val)
+            }
             i if i.is_int() => builder.ins().irsub_imm(ir_val, 0),
             f if f.is_float() => builder.ins().fneg(ir_val),
             _ => unreachable!("parser should catch illegal types"),
         })
     }
-    fn load_addr(&self, var: Symbol, builder: &mut FunctionBuilder) -> IrResult {
+    pub(super) fn load_addr(&self, var: Symbol, builder: &mut FunctionBuilder) -> IrResult {
         let metadata = var.get();
         let ptr_type = Type::ptr_type();
         let ir_val = match self
@@ -425,9 +648,26 @@ impl<B: Backend> Compiler<B> {
             }
             Id::Global(static_id) => {
                 let global = self.module.declare_data_in_func(*static_id, builder.func);
-                builder.ins().global_value(ptr_type, global)
+                // `_Thread_local int i;`: the address is different for each thread
+                if metadata.thread_local {
+                    builder.ins().tls_value(ptr_type, global)
+                } else {
+                    builder.ins().global_value(ptr_type, global)
+                }
+            }
+            Id::Local(stack_slot) => {
+                let addr = builder.ins().stack_addr(ptr_type, *stack_slot, 0);
+                match metadata.alignof() {
+                    // _Alignas(64) int i;
+                    // round up to the next multiple of `align`; `declare_stack` left room for this
+                    Ok(align) if align > u64::from(STACK_ALIGN) => {
+                        let addr = builder.ins().iadd_imm(addr, align as i64 - 1);
+                        builder.ins().band_imm(addr, -(align as i64))
+                    }
+                    _ => addr,
+                }
             }
-            Id::Local(stack_slot) => builder.ins().stack_addr(ptr_type, *stack_slot, 0),
+            Id::Vla(stack_slot) => builder.ins().stack_load(ptr_type, *stack_slot, 0),
         };
         let ctype = Type::Pointer(Box::new(metadata.ctype.clone()), hir::Qualifiers::default());
         Ok(Value {
@@ -436,6 +676,34 @@ impl<B: Backend> Compiler<B> {
             ctype,
         })
     }
+    /// Calculate the size of a type which may contain variable length arrays.
+    pub(super) fn runtime_sizeof(
+        &self,
+        ctype: &Type,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<IrValue> {
+        match ctype {
+            Type::Array(inner, ArrayType::Variable(length)) => {
+                let inner_size = self.runtime_sizeof(inner, location, builder)?;
+                let addr = self.load_addr(*length, builder)?;
+                let length = builder
+                    .ins()
+                    .load(types::I64, MemFlags::new(), addr.ir_val, 0);
+                Ok(builder.ins().imul(length, inner_size))
+            }
+            Type::Array(inner, ArrayType::Fixed(length)) if inner.is_vla() => {
+                let inner_size = self.runtime_sizeof(inner, location, builder)?;
+                Ok(builder.ins().imul_imm(inner_size, *length as i64))
+            }
+            _ => {
+                let size = ctype
+                    .sizeof()
+                    .map_err(|err| CompileError::semantic(location.with(err.to_string())))?;
+                Ok(builder.ins().iconst(types::I64, size as i64))
+            }
+        }
+    }
     fn compare(
         left: Value,
         right: Value,
@@ -466,11 +734,35 @@ impl<B: Backend> Compiler<B> {
     fn assignment(&mut self, lval: Expr, rval: Expr, builder: &mut FunctionBuilder) -> IrResult {
         let ctype = lval.ctype.clone();
         let location = lval.location;
+        let bitfield = Self::bitfield(&lval);
         let (target, value) = (
             self.compile_expr(lval, builder)?,
             self.compile_expr(rval, builder)?,
         );
-        if let Type::Union(_) | Type::Struct(_) = ctype {
+        // `_Atomic int i; i = f()`: only the store itself holds the lock
+        let acquired = ctype.is_atomic() && self.lock_atomic(builder);
+        let result = self.store_assigned(
+            ctype.non_atomic(),
+            bitfield,
+            target,
+            value,
+            location,
+            builder,
+        );
+        self.unlock_atomic(acquired, builder);
+        result
+    }
+    /// Store `value` to `target`, returning the new value of the assignment.
+    fn store_assigned(
+        &mut self,
+        ctype: &Type,
+        bitfield: Option<BitField>,
+        target: Value,
+        value: Value,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> IrResult {
+        if let Type::Union(_) | Type::Struct(_) | Type::Complex(_) = ctype {
             use std::convert::TryInto;
             let size = ctype.sizeof().map_err(|e| location.with(e.to_string()))?;
             let align = ctype
@@ -490,13 +782,120 @@ impl<B: Backend> Compiler<B> {
             );
             return Ok(value);
         }
+        if let Some(bitfield) = bitfield {
+            Self::store_bitfield(value.ir_val, target.ir_val, ctype, bitfield, builder);
+            // the value of the assignment is the new value of the bit-field,
+            // which may have been truncated
+            let ir_val = Self::load_bitfield(target.ir_val, ctype, bitfield, builder);
+            return Ok(Value { ir_val, ..value });
+        }
         // scalar assignment
-        let target_val = target.ir_val;
-        builder
-            .ins()
-            .store(MemFlags::new(), value.ir_val, target_val, 0);
+        Self::store_scalar(ctype, value.ir_val, target.ir_val, builder);
         Ok(value)
     }
+    /// Load a scalar of type `ctype` from `addr`.
+    pub(super) fn load_scalar(
+        ctype: &Type,
+        ir_type: IrType,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        match ctype.non_atomic() {
+            Type::LongDouble => long_double::load(addr, builder),
+            // booleans are stored as a byte, since cranelift can't load a `b1`
+            Type::Bool => {
+                let byte = builder.ins().load(types::I8, MemFlags::new(), addr, 0);
+                builder.ins().icmp_imm(condcodes::IntCC::NotEqual, byte, 0)
+            }
+            _ => builder.ins().load(ir_type, MemFlags::new(), addr, 0),
+        }
+    }
+    /// Store the scalar `val` of type `ctype` to `addr`.
+    pub(super) fn store_scalar(
+        ctype: &Type,
+        val: IrValue,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) {
+        match ctype.non_atomic() {
+            Type::LongDouble => long_double::store(val, addr, builder),
+            Type::Bool => {
+                let byte = builder.ins().bint(types::I8, val);
+                builder.ins().store(MemFlags::new(), byte, addr, 0);
+            }
+            _ => {
+                builder.ins().store(MemFlags::new(), val, addr, 0);
+            }
+        }
+    }
+    /// If `expr` is a bit-field member, return its position within the storage unit.
+    fn bitfield(expr: &Expr) -> Option<BitField> {
+        match &expr.expr {
+            ExprType::Member(cstruct, id) => cstruct.ctype.member_bitfield(*id),
+            _ => None,
+        }
+    }
+    /// The IR type used to load and store the storage unit of a bit-field.
+    fn bitfield_unit(ctype: &Type) -> IrType {
+        // booleans have to be loaded as integers so we can shift them
+        if *ctype == Type::Bool {
+            types::I8
+        } else {
+            ctype.as_ir_type()
+        }
+    }
+    /// Load the bit-field at `addr`, sign-extending it if `ctype` is signed.
+    fn load_bitfield(
+        addr: IrValue,
+        ctype: &Type,
+        bitfield: BitField,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let unit_type = Self::bitfield_unit(ctype);
+        let unit = builder.ins().load(unit_type, MemFlags::new(), addr, 0);
+        let unit_bits = i64::from(unit_type.bits());
+        let (shift, width) = (i64::from(bitfield.shift), i64::from(bitfield.width));
+        // move the bit-field to the top of the unit, then back down to the bottom:
+        // this gets rid of the other bit-fields and fills in the sign bits at the same time
+        let high = builder.ins().ishl_imm(unit, unit_bits - shift - width);
+        let val = if ctype.is_signed() {
+            builder.ins().sshr_imm(high, unit_bits - width)
+        } else {
+            builder.ins().ushr_imm(high, unit_bits - width)
+        };
+        if *ctype == Type::Bool {
+            builder.ins().icmp_imm(condcodes::IntCC::NotEqual, val, 0)
+        } else {
+            val
+        }
+    }
+    /// Store `value` into the bit-field at `addr`, leaving the rest of the storage unit unchanged.
+    pub(super) fn store_bitfield(
+        value: IrValue,
+        addr: IrValue,
+        ctype: &Type,
+        bitfield: BitField,
+        builder: &mut FunctionBuilder,
+    ) {
+        let unit_type = Self::bitfield_unit(ctype);
+        let value = if *ctype == Type::Bool {
+            builder.ins().bint(unit_type, value)
+        } else {
+            value
+        };
+        let mask = (((1_u128 << bitfield.width) - 1) << bitfield.shift) as i64;
+        let old = builder.ins().load(unit_type, MemFlags::new(), addr, 0);
+        let old = builder.ins().band_imm(old, !mask);
+        let new = builder.ins().ishl_imm(value, i64::from(bitfield.shift));
+        let new = builder.ins().band_imm(new, mask);
+        let unit = builder.ins().bor(old, new);
+        builder.ins().store(MemFlags::new(), unit, addr, 0);
+    }
+    /// The value of `&&label`, which is the same for every use of `label` in a function.
+    pub(super) fn label_address(&mut self, label: InternedStr) -> u64 {
+        let next_id = self.label_addresses.len() as u64 + 1;
+        *self.label_addresses.entry(label).or_insert(next_id)
+    }
     fn call(
         &mut self,
         func: FuncCall,
@@ -504,7 +903,6 @@ impl<B: Backend> Compiler<B> {
         args: Vec<Expr>,
         builder: &mut FunctionBuilder,
     ) -> IrResult {
-        use cranelift::codegen::ir::{AbiParam, ArgumentPurpose};
         use hir::Qualifiers;
 
         let mut ftype = match ctype {
@@ -520,8 +918,10 @@ impl<B: Backend> Compiler<B> {
             // this is an utter hack
             // https://github.com/CraneStation/cranelift/issues/212#issuecomment-549111736
             for arg in &args[ftype.params.len()..] {
-                if arg.ctype.is_floating() {
-                    float_variadic += 1;
+                // `%al` is the number of SSE registers used, and x87 floats are passed in memory
+                let is_sse = arg.ctype.is_floating() || arg.ctype.is_complex();
+                if is_sse && *arg.ctype.real_type() != Type::LongDouble {
+                    float_variadic += arg.ctype.abi_types().len() as i64;
                 }
                 ftype.params.push(
                     Variable {
@@ -529,19 +929,38 @@ impl<B: Backend> Compiler<B> {
                         id: Default::default(),
                         qualifiers: Qualifiers::NONE,
                         storage_class: StorageClass::Auto,
+                        bitfield: None,
+                        alignment: None,
+                        thread_local: false,
+                        constexpr: None,
+                        attributes: Default::default(),
                     }
                     .insert(),
                 );
             }
         }
-        let mut compiled_args: Vec<IrValue> = args
-            .into_iter()
-            .map(|arg| self.compile_expr(arg, builder).map(|val| val.ir_val))
-            .collect::<CompileResult<_>>()?;
+        // see `FunctionType::signature`
+        let explicit = ftype.uses_long_double();
+        let mut compiled_args = Vec::with_capacity(args.len());
+        for arg in args {
+            let val = self.compile_expr(arg, builder)?;
+            match &val.ctype {
+                _ if explicit => compiled_args.extend(Self::to_eightbytes(val, builder)),
+                Type::Complex(real) => {
+                    compiled_args.extend(Self::complex_to_abi(real, val.ir_val, builder))
+                }
+                _ => compiled_args.push(val.ir_val),
+            }
+        }
         if ftype.varargs {
             let float_ir = builder.ins().iconst(types::I8, float_variadic);
             compiled_args.push(float_ir);
         }
+        // `ftype.params` now includes the variadic arguments
+        let signature = ftype.signature(self.module.isa());
+        if explicit {
+            long_double::spill_stack_args(&signature, &mut compiled_args, builder);
+        }
         let call = match func {
             FuncCall::Named(func_name) => {
                 let func_id = match self.declarations.get(&func_name) {
@@ -553,36 +972,32 @@ impl<B: Backend> Compiler<B> {
                 // stolen from https://github.com/bjorn3/rustc_codegen_cranelift/blob/82fde5b62281fa51a/src/abi/mod.rs#L535
                 if ftype.varargs {
                     let call_sig = builder.func.dfg.call_signature(call).unwrap();
-                    let al = self
-                        .module
-                        .isa()
-                        .register_info()
-                        .parse_regunit("rax")
-                        .expect("x86 should have an rax register");
-                    let float_arg = AbiParam::special_reg(types::I8, ArgumentPurpose::Normal, al);
-                    // NOTE: this is added both here and in signature() because we overwrite the previous params
-                    let abi_params = ftype
-                        .params
-                        .into_iter()
-                        .map(|param| AbiParam::new(param.get().ctype.as_ir_type()))
-                        .chain(std::iter::once(float_arg))
-                        .collect();
-                    builder.func.dfg.signatures[call_sig].params = abi_params;
+                    builder.func.dfg.signatures[call_sig].params = signature.params;
                 }
                 call
             }
             FuncCall::Indirect(callee) => {
-                let sig = ftype.signature(self.module.isa());
-                let sigref = builder.import_signature(sig);
+                let sigref = builder.import_signature(signature);
                 builder
                     .ins()
                     .call_indirect(sigref, callee.ir_val, compiled_args.as_slice())
             }
         };
-        let ir_val = match builder.inst_results(call).first() {
+        // a `long double` is returned on the x87 stack, which has to be popped even if it's unused
+        let x87_result = match ftype.return_type.non_atomic() {
+            Type::LongDouble => Some(X87Helper::Result),
+            Type::Complex(real) if **real == Type::LongDouble => Some(X87Helper::ResultComplex),
+            _ => None,
+        };
+        let results = match x87_result {
+            Some(helper) => self.call_x87_helper(helper, &[], builder),
+            None => builder.inst_results(call).to_vec(),
+        };
+        let ir_val = match (&*ftype.return_type, results.as_slice()) {
             // Just a placeholder.
-            None => builder.ins().iconst(types::I32, 0),
-            Some(ret) => *ret,
+            (_, []) => builder.ins().iconst(types::I32, 0),
+            (Type::Complex(real), rets) => Self::complex_from_abi(real, rets, builder),
+            (_, rets) => rets[0],
         };
         Ok(Value {
             ir_val,
@@ -0,0 +1,138 @@
+//! 128-bit integers.
+//!
+//! Cranelift legalizes most operations on an `I128` into operations on its two `I64` halves.
+//! This includes passing one to or returning one from a function,
+//! which uses a pair of registers as required by the System V ABI.
+//! Division, remainder, shifts, and conversions to and from floating types are not legalized,
+//! so they call the same runtime library functions as GCC and Clang, like `__divti3`.
+
+use cranelift::codegen::ir::{condcodes::IntCC, types, AbiParam, Signature};
+use cranelift::prelude::{FunctionBuilder, InstBuilder, Type as IrType, Value as IrValue};
+use cranelift_module::{Backend, Linkage};
+
+use super::Compiler;
+use saltwater_parser::data::hir::BinaryOp;
+
+/// Build an `I128` constant out of its two halves.
+pub(crate) fn iconst(value: i128, builder: &mut FunctionBuilder) -> IrValue {
+    let low = builder.ins().iconst(types::I64, value as i64);
+    let high = builder.ins().iconst(types::I64, (value >> 64) as i64);
+    builder.ins().iconcat(low, high)
+}
+
+/// Return whether the `I128` value `val` is nonzero, as a `B1`.
+pub(crate) fn to_bool(val: IrValue, builder: &mut FunctionBuilder) -> IrValue {
+    let (low, high) = builder.ins().isplit(val);
+    let either = builder.ins().bor(low, high);
+    builder.ins().icmp_imm(IntCC::NotEqual, either, 0)
+}
+
+/// Widen the integer `val` to an `I128`.
+///
+/// Cranelift doesn't legalize `sextend` or `uextend` to an `I128`,
+/// so this builds the high half by hand.
+pub(crate) fn extend(val: IrValue, signed: bool, builder: &mut FunctionBuilder) -> IrValue {
+    let from = builder.func.dfg.value_type(val);
+    let low = match (from, signed) {
+        (types::I64, _) => val,
+        (_, true) => builder.ins().sextend(types::I64, val),
+        (_, false) => builder.ins().uextend(types::I64, val),
+    };
+    let high = if signed {
+        builder.ins().sshr_imm(low, 63)
+    } else {
+        builder.ins().iconst(types::I64, 0)
+    };
+    builder.ins().iconcat(low, high)
+}
+
+/// Truncate the `I128` value `val` to the smaller integer type `to`.
+pub(crate) fn reduce(to: IrType, val: IrValue, builder: &mut FunctionBuilder) -> IrValue {
+    let (low, _) = builder.ins().isplit(val);
+    if to == types::I64 {
+        low
+    } else {
+        builder.ins().ireduce(to, low)
+    }
+}
+
+impl<B: Backend> Compiler<B> {
+    /// Compile `left op right` for two `I128`s,
+    /// or return `None` if Cranelift can legalize `op` by itself.
+    pub(super) fn int128_binary(
+        &mut self,
+        left: IrValue,
+        right: IrValue,
+        signed: bool,
+        op: BinaryOp,
+        builder: &mut FunctionBuilder,
+    ) -> Option<IrValue> {
+        let name = match (op, signed) {
+            (BinaryOp::Div, true) => "__divti3",
+            (BinaryOp::Div, false) => "__udivti3",
+            (BinaryOp::Mod, true) => "__modti3",
+            (BinaryOp::Mod, false) => "__umodti3",
+            // Cranelift doesn't legalize 128-bit shifts on x86,
+            // and the shift amount is never larger than 127, so only its low half matters
+            (BinaryOp::Shl, _) | (BinaryOp::Shr, _) => {
+                let name = match (op, signed) {
+                    (BinaryOp::Shl, _) => "__ashlti3",
+                    (_, true) => "__ashrti3",
+                    (_, false) => "__lshrti3",
+                };
+                let amount = reduce(types::I32, right, builder);
+                return Some(self.int128_libcall(name, types::I128, &[left, amount], builder));
+            }
+            _ => return None,
+        };
+        Some(self.int128_libcall(name, types::I128, &[left, right], builder))
+    }
+    /// Convert between a 128-bit integer and a floating type,
+    /// or return `None` if this is any other conversion.
+    pub(super) fn int128_float_cast(
+        &mut self,
+        from: IrType,
+        to: IrType,
+        val: IrValue,
+        from_signed: bool,
+        to_signed: bool,
+        builder: &mut FunctionBuilder,
+    ) -> Option<IrValue> {
+        let name = match (from, to) {
+            (types::I128, types::F32) if from_signed => "__floattisf",
+            (types::I128, types::F32) => "__floatuntisf",
+            (types::I128, types::F64) if from_signed => "__floattidf",
+            (types::I128, types::F64) => "__floatuntidf",
+            (types::F32, types::I128) if to_signed => "__fixsfti",
+            (types::F32, types::I128) => "__fixunssfti",
+            (types::F64, types::I128) if to_signed => "__fixdfti",
+            (types::F64, types::I128) => "__fixunsdfti",
+            _ => return None,
+        };
+        Some(self.int128_libcall(name, to, &[val], builder))
+    }
+    /// Call the runtime library function `name`, which returns a single value of type `ret`.
+    fn int128_libcall(
+        &mut self,
+        name: &str,
+        ret: IrType,
+        args: &[IrValue],
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let signature = Signature {
+            call_conv: self.module.isa().default_call_conv(),
+            params: args
+                .iter()
+                .map(|&arg| AbiParam::new(builder.func.dfg.value_type(arg)))
+                .collect(),
+            returns: vec![AbiParam::new(ret)],
+        };
+        let func_id = self
+            .module
+            .declare_function(name, Linkage::Import, &signature)
+            .unwrap_or_else(|err| panic!("{}", err));
+        let func_ref = self.module.declare_func_in_func(func_id, builder.func);
+        let call = builder.ins().call(func_ref, args);
+        builder.inst_results(call)[0]
+    }
+}
@@ -10,34 +10,41 @@ macro_rules! semantic_err {
     };
 }
 
+mod atomic;
+mod complex;
+mod elf;
 mod expr;
+mod int128;
+mod long_double;
 mod static_init;
 mod stmt;
 
 use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 use std::path::Path;
 
 use cranelift::codegen::{
     self,
     ir::{
+        condcodes::IntCC,
         entities::StackSlot,
         function::Function,
         stackslot::{StackSlotData, StackSlotKind},
-        ExternalName, InstBuilder, MemFlags,
+        ExternalName, InstBuilder, MemFlags, TrapCode,
     },
     isa::TargetIsa,
-    settings::{self, Configurable, Flags},
+    settings::{self, Configurable, Flags, TlsModel},
 };
 use cranelift::frontend::Switch;
-use cranelift::prelude::{Block, FunctionBuilder, FunctionBuilderContext};
-use cranelift_module::{self, Backend, DataId, FuncId, Linkage, Module};
+use cranelift::prelude::{Block, FunctionBuilder, FunctionBuilderContext, Value as IrValue};
+use cranelift_module::{self, Backend, DataContext, DataId, FuncId, Linkage, Module};
 use cranelift_object::{ObjectBackend, ObjectBuilder};
-use saltwater_parser::arch::TARGET;
+use saltwater_parser::arch::{PTR_SIZE, STACK_ALIGN, TARGET};
 use saltwater_parser::{Opt, Program};
+use target_lexicon::BinaryFormat;
 
 use saltwater_parser::data::{
-    hir::{Declaration, Initializer, Stmt, Symbol},
+    hir::{Attributes, Declaration, Expr, Initializer, Stmt, Symbol, Visibility},
     types::FunctionType,
     StorageClass, *,
 };
@@ -50,6 +57,16 @@ pub(crate) fn get_isa(jit: bool) -> Box<dyn TargetIsa + 'static> {
         flags_builder
             .enable("is_pic")
             .expect("is_pic should be a valid option");
+        // `simplejit` doesn't support thread-local storage, so this is only set for object files
+        let tls_model = match TARGET.binary_format {
+            BinaryFormat::Elf => "elf_gd",
+            BinaryFormat::Macho => "macho",
+            BinaryFormat::Coff => "coff",
+            _ => "none",
+        };
+        flags_builder
+            .set("tls_model", tls_model)
+            .expect("tls_model should be a valid option");
     }
     // use debug assertions
     flags_builder
@@ -74,41 +91,104 @@ pub fn initialize_aot_module(name: String) -> Module<ObjectBackend> {
     Module::new(builder.expect("unsupported binary format or target architecture"))
 }
 
+/// Unwrap the expression in a scalar initializer, which may be surrounded by braces: `int i = {1};`
+///
+/// Returns `None` for an empty initializer list.
+fn scalar_initializer(init: Initializer) -> Option<Expr> {
+    match init {
+        Initializer::Scalar(expr) => Some(*expr),
+        Initializer::InitializerList(list) => list.into_iter().next().and_then(scalar_initializer),
+        Initializer::FunctionBody(_) => unreachable!("functions can't be stored on the stack"),
+    }
+}
+
 enum Id {
     Function(FuncId),
     Global(DataId),
     Local(StackSlot),
+    /// A variable length array.
+    /// The stack slot holds a pointer to the array, which is allocated on the VLA stack,
+    /// followed by how much of the VLA stack was in use before it.
+    Vla(StackSlot),
 }
 
+/// Something that has to happen when a variable goes out of scope.
+#[derive(Copy, Clone, PartialEq)]
+enum Cleanup {
+    /// Give the space used by a variable length array back to the VLA stack.
+    PopVla(StackSlot),
+    /// Call a function with the address of a variable: `__attribute__((cleanup(f))) int i;`
+    Call(FuncId, Symbol),
+}
+
+const VLA_STACK_NAME: &str = "__saltwater_vla_stack";
+/// The space for variable length arrays in each thread.
+const VLA_STACK_SIZE: i64 = 1 << 20;
+/// The number of bytes in use comes before the arrays, padded to keep them aligned.
+const VLA_STACK_HEADER: i64 = 16;
+
+/// switch, default, end, number of cleanups in scope, case ranges
+type SwitchState = (Switch, Option<Block>, Block, usize, Vec<(u64, u64, Block)>);
+
 struct Compiler<T: Backend> {
     module: Module<T>,
     debug: bool,
+    // use the runtime library for complex multiplication and division
+    annex_g: bool,
+    // the lock used for atomic operations, defined the first time it's used
+    atomic_lock: Option<DataId>,
+    // if true, we're in the middle of an atomic operation and shouldn't lock again
+    holding_atomic_lock: bool,
+    // the memory variable length arrays are allocated from, defined the first time it's used
+    vla_stack: Option<DataId>,
+    // the functions that move `long double` return values to and from the x87 stack,
+    // defined the first time they're used
+    x87_helpers: HashMap<long_double::X87Helper, FuncId>,
     // if false, we last saw a switch
     last_saw_loop: bool,
     strings: HashMap<Vec<u8>, DataId>,
     declarations: HashMap<Symbol, Id>,
-    loops: Vec<(Block, Block)>,
-    // switch, default, end
+    // `continue` target, end, number of cleanups in scope
+    loops: Vec<(Block, Block, usize)>,
     // if default is empty once we get to the end of a switch body,
     // we didn't see a default case
-    switches: Vec<(Switch, Option<Block>, Block)>,
-    labels: HashMap<InternedStr, Block>,
+    switches: Vec<SwitchState>,
+    // block, cleanups in scope
+    labels: HashMap<InternedStr, (Block, Vec<Cleanup>)>,
+    // the value of `&&label` for each label whose address was taken, starting at 1
+    label_addresses: HashMap<InternedStr, u64>,
+    // the block that each `goto *ptr` jumps to, which takes `ptr` as a parameter,
+    // and the cleanups in scope at the `goto`
+    indirect_gotos: Vec<(Block, Vec<Cleanup>)>,
+    // the cleanups for the variables currently in scope, innermost last
+    cleanups: Vec<Cleanup>,
+    // the number of `static` variables declared at block scope so far, used to give each a unique name
+    local_statics: usize,
     error_handler: ErrorHandler,
 }
 
 impl<B: Backend> Compiler<B> {
-    fn new(module: Module<B>, debug: bool) -> Compiler<B> {
+    fn new(module: Module<B>, debug: bool, annex_g: bool) -> Compiler<B> {
         Compiler {
             module,
             declarations: HashMap::new(),
             loops: Vec::new(),
             switches: Vec::new(),
             labels: HashMap::new(),
+            label_addresses: HashMap::new(),
+            indirect_gotos: Vec::new(),
+            cleanups: Vec::new(),
+            local_statics: 0,
             // the initial value doesn't really matter
             last_saw_loop: true,
             strings: Default::default(),
             error_handler: Default::default(),
             debug,
+            annex_g,
+            atomic_lock: None,
+            holding_atomic_lock: false,
+            vla_stack: None,
+            x87_helpers: HashMap::new(),
         }
     }
     // we have to consider the following cases:
@@ -135,14 +215,24 @@ impl<B: Backend> Compiler<B> {
         };
         let signature = func_type.signature(self.module.isa());
         let linkage = match metadata.storage_class {
-            StorageClass::Auto | StorageClass::Extern if is_definition => Linkage::Export,
-            StorageClass::Auto | StorageClass::Extern => Linkage::Import,
+            StorageClass::Auto | StorageClass::Extern if is_definition => {
+                export_linkage(&metadata.attributes)
+            }
+            StorageClass::Auto | StorageClass::Extern => {
+                import_linkage(&metadata.attributes, self.is_jit())
+            }
             StorageClass::Static => Linkage::Local,
             StorageClass::Register | StorageClass::Typedef => unreachable!(),
         };
+        // `int f(void) __asm__("g");` is called `g` in the object file
+        let name = &metadata.attributes.asm_label;
         let func_id = self
             .module
-            .declare_function(get_str!(metadata.id), linkage, &signature)
+            .declare_function(
+                name.as_deref().unwrap_or(get_str!(metadata.id)),
+                linkage,
+                &signature,
+            )
             .unwrap_or_else(|err| panic!("{}", err));
         self.declarations.insert(symbol, Id::Function(func_id));
         Ok(func_id)
@@ -162,15 +252,30 @@ impl<B: Backend> Compiler<B> {
             self.declare_func(decl.symbol, false)?;
             return Ok(());
         }
-        let u64_size = match meta.ctype.sizeof() {
-            Ok(size) => size,
-            Err(err) => {
+        if meta.ctype.is_vla() {
+            self.declare_vla(decl.symbol, &meta.ctype, location, builder)?;
+            return self.register_cleanup(decl.symbol);
+        }
+        // `static int i;` or `extern int i;` has static storage duration
+        if let StorageClass::Static | StorageClass::Extern = meta.storage_class {
+            return self.store_static(decl.symbol, decl.init, true, location);
+        }
+        let (u64_size, align) = match (meta.ctype.sizeof(), meta.alignof()) {
+            (Ok(size), Ok(align)) => (size, std::cmp::max(align, 1)),
+            (Err(err), _) | (_, Err(err)) => {
                 return Err(CompileError::semantic(Locatable {
                     data: err.into(),
                     location,
                 }))
             }
         };
+        // Cranelift aligns a stack slot to the largest power of two that divides its size,
+        // up to the alignment of the stack pointer.
+        // Objects that need more alignment than that are given extra space and aligned at runtime in `load_addr`.
+        let mut u64_size = u64_size.div_ceil(align) * align;
+        if align > u64::from(STACK_ALIGN) {
+            u64_size += align;
+        }
         let kind = StackSlotKind::ExplicitSlot;
         let size = match u32::try_from(u64_size) {
             Ok(size) => size,
@@ -187,25 +292,245 @@ impl<B: Backend> Compiler<B> {
         let stack_slot = builder.create_stack_slot(data);
         self.declarations.insert(decl.symbol, Id::Local(stack_slot));
         if let Some(init) = decl.init {
-            self.store_stack(init, stack_slot, builder)?;
+            let addr = self.load_addr(decl.symbol, builder)?.ir_val;
+            self.store_stack(init, &meta.ctype, addr, location, builder)?;
+        }
+        self.register_cleanup(decl.symbol)
+    }
+    /// Call the function given by `__attribute__((cleanup(f)))` when `symbol` goes out of scope.
+    fn register_cleanup(&mut self, symbol: Symbol) -> CompileResult<()> {
+        if let Some(func) = symbol.get().attributes.cleanup {
+            let func = self.declare_func(func, false)?;
+            self.cleanups.push(Cleanup::Call(func, symbol));
+        }
+        Ok(())
+    }
+    /// Allocate a variable length array.
+    ///
+    /// Cranelift can't allocate a stack slot whose size is only known at runtime,
+    /// and it addresses every stack slot (including register spills) relative to the stack pointer,
+    /// so moving the stack pointer would break the rest of the frame.
+    /// Instead, VLAs are allocated from a separate stack, `__saltwater_vla_stack`:
+    /// declaring an array bumps the number of bytes in use, and leaving its scope restores it.
+    /// Running out of space traps, the same as overflowing the stack would.
+    fn declare_vla(
+        &mut self,
+        symbol: Symbol,
+        ctype: &Type,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        let ptr_type = Type::ptr_type();
+        let size = self.runtime_sizeof(ctype, location, builder)?;
+        let stack = self.vla_stack_addr(builder);
+        let used = builder.ins().load(ptr_type, MemFlags::trusted(), stack, 0);
+        let available = builder.ins().irsub_imm(used, VLA_STACK_SIZE);
+        let overflow = builder
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThan, size, available);
+        builder.ins().trapnz(overflow, TrapCode::StackOverflow);
+        // keep every array aligned to 16 bytes, like the real stack
+        let padded = builder.ins().iadd_imm(size, 15);
+        let padded = builder.ins().band_imm(padded, -16);
+        let now_used = builder.ins().iadd(used, padded);
+        builder.ins().store(MemFlags::trusted(), now_used, stack, 0);
+        let base = builder.ins().iadd_imm(stack, VLA_STACK_HEADER);
+        let ptr = builder.ins().iadd(base, used);
+        let slot = builder.create_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: 2 * ptr_type.bytes(),
+            offset: None,
+        });
+        builder.ins().stack_store(ptr, slot, 0);
+        builder
+            .ins()
+            .stack_store(used, slot, ptr_type.bytes() as i32);
+        self.cleanups.push(Cleanup::PopVla(slot));
+        self.declarations.insert(symbol, Id::Vla(slot));
+        Ok(())
+    }
+    /// The address of the VLA stack for the current thread.
+    ///
+    /// The first word is the number of bytes in use, and the arrays start at `VLA_STACK_HEADER`.
+    /// Like the atomic lock, it's defined as a weak symbol in every object file that uses it.
+    /// Since restoring the number of bytes in use doesn't depend on what happened in between,
+    /// leaving a scope with `longjmp` only holds on to the space until an enclosing scope ends.
+    fn vla_stack_addr(&mut self, builder: &mut FunctionBuilder) -> IrValue {
+        // `simplejit` doesn't support thread-local storage, so the JIT has one stack for all threads
+        let thread_local = !self.is_jit();
+        let stack = match self.vla_stack {
+            Some(id) => id,
+            None => {
+                let id = self
+                    .module
+                    .declare_data(
+                        VLA_STACK_NAME,
+                        Linkage::Preemptible,
+                        true,
+                        thread_local,
+                        Some(16),
+                    )
+                    .unwrap_or_else(|err| panic!("{}", err));
+                let mut ctx = DataContext::new();
+                ctx.define_zeroinit((VLA_STACK_HEADER + VLA_STACK_SIZE) as usize);
+                self.module
+                    .define_data(id, &ctx)
+                    .unwrap_or_else(|err| panic!("{}", err));
+                self.vla_stack = Some(id);
+                id
+            }
+        };
+        let stack = self.module.declare_data_in_func(stack, builder.func);
+        if thread_local {
+            builder.ins().tls_value(Type::ptr_type(), stack)
+        } else {
+            builder.ins().global_value(Type::ptr_type(), stack)
+        }
+    }
+    /// Run all cleanups registered after the first `height` cleanups, innermost first.
+    ///
+    /// This pops variable length arrays and calls the functions given by `__attribute__((cleanup))`.
+    /// It does not remove them from scope, since `break`, `continue`, and `return`
+    /// can leave a scope without reaching the end.
+    fn run_cleanups(&mut self, height: usize, builder: &mut FunctionBuilder) -> CompileResult<()> {
+        for i in (height..self.cleanups.len()).rev() {
+            match self.cleanups[i] {
+                Cleanup::PopVla(slot) => {
+                    let ptr_type = Type::ptr_type();
+                    let used = builder
+                        .ins()
+                        .stack_load(ptr_type, slot, ptr_type.bytes() as i32);
+                    let stack = self.vla_stack_addr(builder);
+                    builder.ins().store(MemFlags::trusted(), used, stack, 0);
+                }
+                Cleanup::Call(func, symbol) => {
+                    let func = self.module.declare_func_in_func(func, builder.func);
+                    let addr = self.load_addr(symbol, builder)?.ir_val;
+                    builder.ins().call(func, &[addr]);
+                }
+            }
         }
         Ok(())
     }
     fn store_stack(
         &mut self,
         init: Initializer,
-        stack_slot: StackSlot,
+        ctype: &Type,
+        addr: IrValue,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        // subobjects without an initializer are zero-initialized: `int a[3] = {1};`
+        if let (Initializer::InitializerList(_), _) | (_, Type::Array(_, _)) = (&init, ctype) {
+            let size = ctype
+                .sizeof()
+                .map_err(|err| CompileError::semantic(location.with(err.to_string())))?;
+            let align = ctype
+                .alignof()
+                .expect("if sizeof() succeeds so should alignof()")
+                .try_into()
+                .expect("align should never be more than 255 bytes");
+            builder.emit_small_memset(self.module.target_config(), addr, 0, size, align);
+        }
+        self.store_initializer(init, ctype, addr, 0, location, builder)
+    }
+    /// Store `init` in the object of type `ctype` at `addr + offset`.
+    ///
+    /// The object must already be zeroed, since empty initializer lists are skipped.
+    fn store_initializer(
+        &mut self,
+        init: Initializer,
+        ctype: &Type,
+        addr: IrValue,
+        offset: u64,
+        location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
+        let size_of = |ctype: &Type| {
+            ctype
+                .sizeof()
+                .map_err(|err| CompileError::semantic(location.with(err.to_string())))
+        };
+        // initialization is not an atomic operation: `_Atomic int i = 1;`
+        let ctype = ctype.non_atomic();
         match init {
             Initializer::Scalar(expr) => {
+                let expr_size = size_of(&expr.ctype);
                 let val = self.compile_expr(*expr, builder)?;
-                // TODO: replace with `builder.ins().stack_store(val.ir_val, stack_slot, 0);`
-                // when Cranelift implements stack_store for i8 and i16
-                let addr = builder.ins().stack_addr(Type::ptr_type(), stack_slot, 0);
-                builder.ins().store(MemFlags::new(), val.ir_val, addr, 0);
+                let target = builder.ins().iadd_imm(addr, offset as i64);
+                match ctype {
+                    // char s[] = "hi"; or struct s s2 = s1;
+                    Type::Array(_, _) | Type::Struct(_) | Type::Union(_) | Type::Complex(_) => {
+                        // the string literal may be shorter than the array: `char s[10] = "hi";`
+                        let size = std::cmp::min(size_of(ctype)?, expr_size?);
+                        let align = ctype
+                            .alignof()
+                            .expect("if sizeof() succeeds so should alignof()")
+                            .try_into()
+                            .expect("align should never be more than 255 bytes");
+                        builder.emit_small_memory_copy(
+                            self.module.target_config(),
+                            target,
+                            val.ir_val,
+                            size,
+                            align,
+                            1,
+                            true,
+                        );
+                    }
+                    _ => Self::store_scalar(ctype, val.ir_val, target, builder),
+                }
             }
-            Initializer::InitializerList(_) => unimplemented!("aggregate dynamic initialization"),
+            Initializer::InitializerList(list) => match ctype {
+                Type::Array(inner, _) => {
+                    let inner_size = size_of(inner)?;
+                    for (i, init) in list.into_iter().enumerate() {
+                        let offset = offset + i as u64 * inner_size;
+                        self.store_initializer(init, inner, addr, offset, location, builder)?;
+                    }
+                }
+                Type::Struct(struct_type) | Type::Union(struct_type) => {
+                    for (member, init) in struct_type.initialized_members().iter().zip(list) {
+                        let offset = offset
+                            + ctype
+                                .member_offset(member.id)
+                                .expect("only structs and unions can have members");
+                        let bitfield = match ctype.member_bitfield(member.id) {
+                            Some(bitfield) => bitfield,
+                            None => {
+                                self.store_initializer(
+                                    init,
+                                    &member.ctype,
+                                    addr,
+                                    offset,
+                                    location,
+                                    builder,
+                                )?;
+                                continue;
+                            }
+                        };
+                        // bit-fields can't be stored directly since they share a unit with other members
+                        if let Some(expr) = scalar_initializer(init) {
+                            let val = self.compile_expr(expr, builder)?;
+                            let target = builder.ins().iadd_imm(addr, offset as i64);
+                            Self::store_bitfield(
+                                val.ir_val,
+                                target,
+                                &member.ctype,
+                                bitfield,
+                                builder,
+                            );
+                        }
+                    }
+                }
+                // int i = {1};
+                ty if ty.is_scalar() => {
+                    if let Some(init) = list.into_iter().next() {
+                        self.store_initializer(init, ctype, addr, offset, location, builder)?;
+                    }
+                }
+                _ => unreachable!("initializer list for {}", ctype),
+            },
             Initializer::FunctionBody(_) => unreachable!("functions can't be stored on the stack"),
         }
         Ok(())
@@ -214,20 +539,31 @@ impl<B: Backend> Compiler<B> {
     // there's an easier way to make parameters modifiable.
     fn store_stack_params(
         &mut self,
-        params: &[Symbol],
+        func_type: &FunctionType,
         func_start: Block,
         location: &Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
+        let params = &func_type.params;
+        // see `FunctionType::signature`
+        let explicit = func_type.uses_long_double();
         // Cranelift requires that all block params are declared up front
-        let ir_vals: Vec<_> = params
+        let ir_vals: Vec<Vec<_>> = params
             .iter()
             .map(|param| {
-                let ir_type = param.get().ctype.as_ir_type();
-                Ok(builder.append_block_param(func_start, ir_type))
+                let ctype = &param.get().ctype;
+                let ir_types = if explicit {
+                    ctype.eightbytes()
+                } else {
+                    ctype.abi_types()
+                };
+                ir_types
+                    .into_iter()
+                    .map(|ir_type| builder.append_block_param(func_start, ir_type))
+                    .collect()
             })
-            .collect::<CompileResult<_>>()?;
-        for (&param, ir_val) in params.iter().zip(ir_vals) {
+            .collect();
+        for (&param, param_vals) in params.iter().zip(ir_vals) {
             let u64_size = match param.get().ctype.sizeof() {
                 Err(data) => semantic_err!(data.into(), *location),
                 Ok(size) => size,
@@ -253,7 +589,13 @@ impl<B: Backend> Compiler<B> {
             // then this can be replaced with `builder.ins().stack_store(ir_val, slot, 0);`
             // See https://github.com/CraneStation/cranelift/issues/433
             let addr = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
-            builder.ins().store(MemFlags::new(), ir_val, addr, 0);
+            match param.get().ctype.non_atomic() {
+                ctype if explicit && long_double::split_in_memory(ctype) => {
+                    Self::store_eightbytes(&param_vals, addr, builder)
+                }
+                Type::Complex(real) => Self::store_complex_abi(real, &param_vals, addr, builder),
+                ctype => Self::store_scalar(ctype, param_vals[0], addr, builder),
+            }
             self.declarations.insert(param, Id::Local(slot));
         }
         Ok(())
@@ -280,21 +622,24 @@ impl<B: Backend> Compiler<B> {
 
         let func_start = builder.create_block();
         builder.switch_to_block(func_start);
+        // labels are local to a function
+        self.labels.clear();
+        self.label_addresses.clear();
+        self.indirect_gotos.clear();
 
         let should_ret = func_type.should_return();
         if func_type.has_params() {
-            self.store_stack_params(
-                // TODO: get rid of this clone
-                &func_type.params,
-                func_start,
-                &location,
-                &mut builder,
-            )?;
+            self.store_stack_params(func_type, func_start, &location, &mut builder)?;
         }
         self.compile_all(stmts, &mut builder)?;
         if !builder.is_filled() {
+            self.run_cleanups(0, &mut builder)?;
             let id = symbol.get().id;
-            if id == InternedStr::get_or_intern("main") {
+            if symbol.get().qualifiers.func.no_return || builder.is_unreachable() {
+                // _Noreturn void f() {}
+                // or a `switch` where every case returns
+                builder.ins().trap(TrapCode::UnreachableCodeReached);
+            } else if id == InternedStr::get_or_intern("main") {
                 let ir_int = func_type.return_type.as_ir_type();
                 let zero = [builder.ins().iconst(ir_int, 0)];
                 builder.ins().return_(&zero);
@@ -311,6 +656,10 @@ impl<B: Backend> Compiler<B> {
                 builder.ins().return_(&[]);
             }
         }
+        self.cleanups.clear();
+        for (dispatch, cleanups) in std::mem::take(&mut self.indirect_gotos) {
+            self.indirect_goto_dispatch(dispatch, cleanups, &mut builder)?;
+        }
         builder.seal_all_blocks();
         builder.finalize();
 
@@ -339,8 +688,107 @@ impl<B: Backend> Compiler<B> {
             );
         }
 
+        self.register_init_functions(symbol, func_id, location)
+    }
+    /// Add a function with `__attribute__((constructor))` or `__attribute__((destructor))`
+    /// to the functions the C runtime calls before `main` or after `main` returns.
+    fn register_init_functions(
+        &mut self,
+        symbol: Symbol,
+        func_id: FuncId,
+        location: Location,
+    ) -> CompileResult<()> {
+        let metadata = symbol.get();
+        for &(priority, attribute, kind, macho_section) in &[
+            (
+                metadata.attributes.constructor,
+                "constructor",
+                "init",
+                "__mod_init_func",
+            ),
+            (
+                metadata.attributes.destructor,
+                "destructor",
+                "fini",
+                "__mod_term_func",
+            ),
+        ] {
+            let priority = match priority {
+                Some(priority) => priority,
+                None => continue,
+            };
+            if self.is_jit() {
+                let err = SemanticError::AttributeNotSupportedByJit(attribute.into());
+                return Err(location.error(err));
+            }
+            // ELF sorts `.init_array.NNNNN` sections by priority, and runs `.init_array` last.
+            // Mach-O doesn't support priorities.
+            let section = match TARGET.binary_format {
+                BinaryFormat::Macho => format!("__DATA,{}", macho_section),
+                _ if priority == u16::MAX => format!(".{}_array", kind),
+                _ => format!(".{}_array.{:05}", kind, priority),
+            };
+            let (segment, section) = section_names(&section);
+            let name = format!("__saltwater_{}.{}", kind, metadata.id);
+            let id = self
+                .module
+                .declare_data(&name, Linkage::Local, true, false, Some(PTR_SIZE as u8))
+                .unwrap_or_else(|err| panic!("{}", err));
+            let mut ctx = DataContext::new();
+            // the address is filled in by a relocation, but a custom section can't hold zero-initialized (BSS) data
+            ctx.define(vec![0; PTR_SIZE.into()].into_boxed_slice());
+            ctx.set_segment_section(segment, section);
+            let func_ref = self.module.declare_func_in_data(func_id, &mut ctx);
+            ctx.write_function_addr(0, func_ref);
+            self.module
+                .define_data(id, &ctx)
+                .unwrap_or_else(|err| panic!("{}", err));
+        }
         Ok(())
     }
+    /// Whether this is compiling for the JIT instead of an object file.
+    fn is_jit(&self) -> bool {
+        // `get_isa` only sets a TLS model when compiling to an object file
+        self.module.isa().flags().tls_model() == TlsModel::None
+    }
+}
+
+/// Split `__attribute__((section(name)))` into a segment and a section.
+///
+/// Only Mach-O has segments, which are given as `"__DATA,__mysection"`.
+fn section_names(name: &str) -> (&str, &str) {
+    match TARGET.binary_format {
+        BinaryFormat::Macho => {
+            let mut parts = name.splitn(2, ',');
+            let segment = parts.next().unwrap_or_default();
+            (segment, parts.next().unwrap_or_default())
+        }
+        _ => ("", name),
+    }
+}
+
+/// The linkage of a definition with external linkage: `int i = 1;` or `int f() {}`
+fn export_linkage(attributes: &Attributes) -> Linkage {
+    match attributes.visibility {
+        // __attribute__((weak)) int f() {}
+        _ if attributes.weak => Linkage::Preemptible,
+        // __attribute__((visibility("hidden"))) int f() {}
+        Visibility::Hidden | Visibility::Internal => Linkage::Hidden,
+        // cranelift has no way to mark a symbol as protected
+        Visibility::Default | Visibility::Protected => Linkage::Export,
+    }
+}
+
+/// The linkage of a declaration with external linkage that isn't a definition: `extern int i;` or `int f();`
+fn import_linkage(attributes: &Attributes, jit: bool) -> Linkage {
+    // __attribute__((weak)) int f(); is a weak reference, which is null if nothing defines `f`.
+    // See the `elf` module for how this is written to the object file.
+    // `simplejit` can't resolve an undefined weak symbol, so the JIT requires a definition.
+    if attributes.weak && !jit {
+        Linkage::Preemptible
+    } else {
+        Linkage::Import
+    }
 }
 
 pub type Product = <cranelift_object::ObjectBackend as Backend>::Product;
@@ -349,7 +797,7 @@ pub type Product = <cranelift_object::ObjectBackend as Backend>::Product;
 pub fn compile<B: Backend>(module: Module<B>, buf: &str, opt: Opt) -> Program<Module<B>> {
     use saltwater_parser::{check_semantics, vec_deque};
 
-    let debug_asm = opt.debug_asm;
+    let (debug_asm, annex_g) = (opt.debug_asm, opt.annex_g);
     let mut program = check_semantics(buf, opt);
     let hir = match program.result {
         Ok(hir) => hir,
@@ -363,7 +811,7 @@ pub fn compile<B: Backend>(module: Module<B>, buf: &str, opt: Opt) -> Program<Mo
     };
     // really we'd like to have all errors but that requires a refactor
     let mut err = None;
-    let mut compiler = Compiler::new(module, debug_asm);
+    let mut compiler = Compiler::new(module, debug_asm, annex_g);
     for decl in hir {
         let meta = decl.data.symbol.get();
         if let StorageClass::Typedef = meta.storage_class {
@@ -382,7 +830,7 @@ pub fn compile<B: Backend>(module: Module<B>, buf: &str, opt: Opt) -> Program<Mo
                 if let Some(Initializer::FunctionBody(_)) = &decl.data.init {
                     unreachable!("only functions should have a function body")
                 }
-                compiler.store_static(decl.data.symbol, decl.data.init, decl.location)
+                compiler.store_static(decl.data.symbol, decl.data.init, false, decl.location)
             }
         };
         if let Err(e) = current {
@@ -408,7 +856,11 @@ pub fn assemble(product: Product, output: &Path) -> Result<(), saltwater_parser:
     use std::fs::File;
     use std::io::{self, Write};
 
-    let bytes = product.emit().map_err(saltwater_parser::Error::Platform)?;
+    let weak_imports = elf::weak_imports(&product);
+    let mut bytes = product.emit().map_err(saltwater_parser::Error::Platform)?;
+    if TARGET.binary_format == BinaryFormat::Elf {
+        elf::mark_weak(&mut bytes, &weak_imports);
+    }
     File::create(output)?
         .write_all(&bytes)
         .map_err(io::Error::into)
@@ -419,8 +871,9 @@ pub fn link(obj_file: &Path, output: &Path) -> Result<(), std::io::Error> {
     use std::process::Command;
 
     // link the .o file using host linker
+    // atomic operations use `pthread_mutex_lock`, which isn't in libc on older glibc
     let status = Command::new("cc")
-        .args(&[&obj_file, Path::new("-o"), output])
+        .args([obj_file, Path::new("-o"), output, Path::new("-lpthread")])
         .status()
         .map_err(|err| {
             if err.kind() == ErrorKind::NotFound {
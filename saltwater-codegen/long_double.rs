@@ -0,0 +1,339 @@
+//! Conversions between `double` and the x87 extended precision format.
+//!
+//! Cranelift has no 80-bit float type, so `long double` is computed as an `F64`
+//! and only converted to the 80-bit format when it is stored to memory.
+//! This keeps the layout compatible with the System V ABI (and glibc),
+//! at the cost of extra precision.
+//!
+//! The in-memory format is a 64-bit mantissa with an explicit integer bit,
+//! followed by a 16-bit sign and exponent, followed by 6 bytes of padding.
+//!
+//! The System V ABI passes a `long double` to a function in memory and returns it in `st0`,
+//! the top of the x87 register stack.
+//! Parameters are handled by `FunctionType::signature`, which gives them explicit stack locations.
+//! Cranelift can't access the x87 stack, so return values go through small helper functions
+//! written in machine code.
+
+use cranelift::codegen::ir::{
+    condcodes::IntCC,
+    stackslot::{StackSlotData, StackSlotKind},
+    types, AbiParam, ArgumentLoc, MemFlags, Signature, ValueLoc,
+};
+use cranelift::prelude::{FunctionBuilder, InstBuilder, Value as IrValue};
+use cranelift_module::{Backend, Linkage};
+
+use super::expr::Value;
+use super::Compiler;
+use saltwater_parser::data::Type;
+
+/// The difference between the exponent biases of an x87 float (16383) and a double (1023)
+const BIAS_DIFFERENCE: i64 = 16383 - 1023;
+const DOUBLE_MANTISSA_BITS: i64 = 52;
+const DOUBLE_MAX_EXP: i64 = 0x7ff;
+const X87_MAX_EXP: i64 = 0x7fff;
+const INTEGER_BIT: i64 = 1 << 63;
+
+/// Store the `F64` value `val` to `addr` in x87 format.
+pub(crate) fn store(val: IrValue, addr: IrValue, builder: &mut FunctionBuilder) {
+    let bits = builder.ins().bitcast(types::I64, val);
+    let sign = builder.ins().ushr_imm(bits, 63);
+    let exp = builder.ins().ushr_imm(bits, DOUBLE_MANTISSA_BITS);
+    let exp = builder.ins().band_imm(exp, DOUBLE_MAX_EXP);
+    let frac = builder
+        .ins()
+        .band_imm(bits, (1 << DOUBLE_MANTISSA_BITS) - 1);
+
+    // normal numbers, infinity, and NaN: the integer bit is always set
+    let shifted = builder.ins().ishl_imm(frac, 11);
+    let normal_mant = builder.ins().bor_imm(shifted, INTEGER_BIT);
+    let normal_exp = builder.ins().iadd_imm(exp, BIAS_DIFFERENCE);
+    let is_special = builder.ins().icmp_imm(IntCC::Equal, exp, DOUBLE_MAX_EXP);
+    let max_exp = builder.ins().iconst(types::I64, X87_MAX_EXP);
+    let normal_exp = builder.ins().select(is_special, max_exp, normal_exp);
+
+    // subnormal numbers become normal numbers, since the exponent range is larger
+    let leading_zeros = builder.ins().clz(frac);
+    let subnormal_mant = builder.ins().ishl(frac, leading_zeros);
+    let subnormal_exp = builder.ins().irsub_imm(leading_zeros, BIAS_DIFFERENCE + 12);
+    let is_zero = builder.ins().icmp_imm(IntCC::Equal, frac, 0);
+    let zero = builder.ins().iconst(types::I64, 0);
+    let subnormal_exp = builder.ins().select(is_zero, zero, subnormal_exp);
+
+    let is_subnormal = builder.ins().icmp_imm(IntCC::Equal, exp, 0);
+    let mant = builder
+        .ins()
+        .select(is_subnormal, subnormal_mant, normal_mant);
+    let exp = builder
+        .ins()
+        .select(is_subnormal, subnormal_exp, normal_exp);
+
+    let sign = builder.ins().ishl_imm(sign, 15);
+    let sign_exp = builder.ins().bor(sign, exp);
+    builder.ins().store(MemFlags::new(), mant, addr, 0);
+    builder.ins().istore16(MemFlags::new(), sign_exp, addr, 8);
+}
+
+/// Load an x87 float from `addr` and round it towards zero to an `F64`.
+pub(crate) fn load(addr: IrValue, builder: &mut FunctionBuilder) -> IrValue {
+    let mant = builder.ins().load(types::I64, MemFlags::new(), addr, 0);
+    let sign_exp = builder.ins().uload16(types::I64, MemFlags::new(), addr, 8);
+    let sign = builder.ins().ushr_imm(sign_exp, 15);
+    let sign = builder.ins().ishl_imm(sign, 63);
+    let x87_exp = builder.ins().band_imm(sign_exp, X87_MAX_EXP);
+    let exp = builder.ins().iadd_imm(x87_exp, -BIAS_DIFFERENCE);
+
+    // normal numbers: drop the integer bit and the low bits of the mantissa
+    let frac = builder.ins().ishl_imm(mant, 1);
+    let frac = builder.ins().ushr_imm(frac, 12);
+    let normal_exp = builder.ins().ishl_imm(exp, DOUBLE_MANTISSA_BITS);
+    let normal = builder.ins().bor(normal_exp, frac);
+
+    // too large for a double: overflow to infinity
+    let infinity = builder
+        .ins()
+        .iconst(types::I64, DOUBLE_MAX_EXP << DOUBLE_MANTISSA_BITS);
+    let overflow = builder
+        .ins()
+        .icmp_imm(IntCC::SignedGreaterThanOrEqual, exp, DOUBLE_MAX_EXP);
+    let bits = builder.ins().select(overflow, infinity, normal);
+
+    // too small for a normal double: make it subnormal, or underflow to zero
+    let shift = builder.ins().irsub_imm(exp, 12);
+    let subnormal = builder.ins().ushr(mant, shift);
+    let zero = builder.ins().iconst(types::I64, 0);
+    let underflow = builder
+        .ins()
+        .icmp_imm(IntCC::SignedGreaterThanOrEqual, shift, 64);
+    let subnormal = builder.ins().select(underflow, zero, subnormal);
+    let is_subnormal = builder.ins().icmp_imm(IntCC::SignedLessThanOrEqual, exp, 0);
+    let bits = builder.ins().select(is_subnormal, subnormal, bits);
+
+    // NaN: keep as much of the payload as fits, and make sure it stays quiet
+    let quiet_nan = builder.ins().bor_imm(frac, 0x7ff8 << 48);
+    let is_special = builder.ins().icmp_imm(IntCC::Equal, x87_exp, X87_MAX_EXP);
+    let no_integer_bit = builder.ins().ishl_imm(mant, 1);
+    let is_nonzero = builder.ins().icmp_imm(IntCC::NotEqual, no_integer_bit, 0);
+    let is_nan = builder.ins().band(is_special, is_nonzero);
+    let bits = builder.ins().select(is_nan, quiet_nan, bits);
+
+    let bits = builder.ins().bor(bits, sign);
+    builder.ins().bitcast(types::F64, bits)
+}
+
+/// Convert `f` to the in-memory representation of a `long double`, including padding.
+pub(crate) fn to_bytes(f: f64) -> [u8; 16] {
+    let bits = f.to_bits();
+    let sign = bits >> 63;
+    let exp = ((bits >> DOUBLE_MANTISSA_BITS) & DOUBLE_MAX_EXP as u64) as i64;
+    let frac = bits & ((1 << DOUBLE_MANTISSA_BITS) - 1);
+    let (mant, exp) = match exp {
+        0 if frac == 0 => (0, 0),
+        0 => {
+            let leading_zeros = frac.leading_zeros();
+            (
+                frac << leading_zeros,
+                BIAS_DIFFERENCE + 12 - i64::from(leading_zeros),
+            )
+        }
+        DOUBLE_MAX_EXP => (INTEGER_BIT as u64 | frac << 11, X87_MAX_EXP),
+        _ => (INTEGER_BIT as u64 | frac << 11, exp + BIAS_DIFFERENCE),
+    };
+    let sign_exp = (sign << 15) as u16 | exp as u16;
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&mant.to_le_bytes());
+    bytes[8..10].copy_from_slice(&sign_exp.to_le_bytes());
+    bytes
+}
+
+/// A function that moves a `long double` return value between SSE registers and the x87 stack.
+///
+/// Each one goes through the red zone below the stack pointer,
+/// rounding to a `double` the same as `long_double::load`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum X87Helper {
+    /// Push `xmm0`, before returning a `long double`.
+    Return,
+    /// Push `xmm1` and then `xmm0`, before returning a `long double _Complex`.
+    ReturnComplex,
+    /// Pop into `xmm0`, after calling a function that returns a `long double`.
+    Result,
+    /// Pop into `xmm0` and then `xmm1`, after calling a function that returns a `long double _Complex`.
+    ResultComplex,
+}
+
+/// `movsd [rsp - 8], xmm0`
+const STORE_XMM0: [u8; 6] = [0xf2, 0x0f, 0x11, 0x44, 0x24, 0xf8];
+/// `movsd [rsp - 8], xmm1`
+const STORE_XMM1: [u8; 6] = [0xf2, 0x0f, 0x11, 0x4c, 0x24, 0xf8];
+/// `movsd xmm0, [rsp - 8]`
+const LOAD_XMM0: [u8; 6] = [0xf2, 0x0f, 0x10, 0x44, 0x24, 0xf8];
+/// `movsd xmm1, [rsp - 8]`
+const LOAD_XMM1: [u8; 6] = [0xf2, 0x0f, 0x10, 0x4c, 0x24, 0xf8];
+/// `fld qword [rsp - 8]`
+const PUSH_X87: [u8; 4] = [0xdd, 0x44, 0x24, 0xf8];
+/// `fstp qword [rsp - 8]`
+const POP_X87: [u8; 4] = [0xdd, 0x5c, 0x24, 0xf8];
+/// `ret`
+const RET: u8 = 0xc3;
+
+impl X87Helper {
+    fn name(self) -> &'static str {
+        match self {
+            X87Helper::Return => "__saltwater_x87_return",
+            X87Helper::ReturnComplex => "__saltwater_x87_return_complex",
+            X87Helper::Result => "__saltwater_x87_result",
+            X87Helper::ResultComplex => "__saltwater_x87_result_complex",
+        }
+    }
+    fn code(self) -> Vec<u8> {
+        let instructions: &[&[u8]] = match self {
+            X87Helper::Return => &[&STORE_XMM0, &PUSH_X87],
+            X87Helper::ReturnComplex => &[&STORE_XMM1, &PUSH_X87, &STORE_XMM0, &PUSH_X87],
+            X87Helper::Result => &[&POP_X87, &LOAD_XMM0],
+            X87Helper::ResultComplex => &[&POP_X87, &LOAD_XMM0, &POP_X87, &LOAD_XMM1],
+        };
+        let mut code = instructions.concat();
+        code.push(RET);
+        code
+    }
+    /// The number of `F64` parameters and return values.
+    fn arity(self) -> (usize, usize) {
+        match self {
+            X87Helper::Return => (1, 0),
+            X87Helper::ReturnComplex => (2, 0),
+            X87Helper::Result => (0, 1),
+            X87Helper::ResultComplex => (0, 2),
+        }
+    }
+}
+
+/// Whether `ctype` is passed differently to a function that uses `long double`,
+/// as described by `Type::eightbytes`.
+pub(crate) fn split_in_memory(ctype: &Type) -> bool {
+    match ctype.non_atomic() {
+        Type::LongDouble | Type::Int128(_) => true,
+        Type::Complex(real) => **real == Type::LongDouble,
+        _ => false,
+    }
+}
+
+/// Move the arguments that `signature` passes on the stack to their outgoing argument slots.
+///
+/// Cranelift's legalizer does this for the locations it assigns itself,
+/// but the verifier runs first and rejects explicit stack locations that haven't been spilled yet.
+/// This has to be done immediately before the call.
+pub(crate) fn spill_stack_args(
+    signature: &Signature,
+    args: &mut [IrValue],
+    builder: &mut FunctionBuilder,
+) {
+    for (arg, param) in args.iter_mut().zip(&signature.params) {
+        if let ArgumentLoc::Stack(offset) = param.location {
+            let slot = builder
+                .func
+                .stack_slots
+                .get_outgoing_arg(param.value_type, offset);
+            let spilled = builder.ins().spill(*arg);
+            builder.func.locations[spilled] = ValueLoc::Stack(slot);
+            *arg = spilled;
+        }
+    }
+}
+
+impl<B: Backend> Compiler<B> {
+    /// Call `helper` with `args`, defining it the first time it's used.
+    pub(super) fn call_x87_helper(
+        &mut self,
+        helper: X87Helper,
+        args: &[IrValue],
+        builder: &mut FunctionBuilder,
+    ) -> Vec<IrValue> {
+        let func_id = match self.x87_helpers.get(&helper) {
+            Some(&id) => id,
+            None => {
+                let (params, returns) = helper.arity();
+                let signature = Signature {
+                    call_conv: self.module.isa().default_call_conv(),
+                    params: vec![AbiParam::new(types::F64); params],
+                    returns: vec![AbiParam::new(types::F64); returns],
+                };
+                let id = self
+                    .module
+                    .declare_function(helper.name(), Linkage::Local, &signature)
+                    .unwrap_or_else(|err| panic!("{}", err));
+                self.module
+                    .define_function_bytes(id, &helper.code())
+                    .unwrap_or_else(|err| panic!("{}", err));
+                self.x87_helpers.insert(helper, id);
+                id
+            }
+        };
+        let func_ref = self.module.declare_func_in_func(func_id, builder.func);
+        let call = builder.ins().call(func_ref, args);
+        builder.inst_results(call).to_vec()
+    }
+    /// Convert `val` to the values used to pass it to a function that uses `long double`.
+    pub(super) fn to_eightbytes(val: Value, builder: &mut FunctionBuilder) -> Vec<IrValue> {
+        match val.ctype.non_atomic() {
+            Type::LongDouble => {
+                let slot = builder.create_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: 16,
+                    offset: None,
+                });
+                let addr = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
+                store(val.ir_val, addr, builder);
+                (0..2)
+                    .map(|i| builder.ins().load(types::I64, MemFlags::new(), addr, 8 * i))
+                    .collect()
+            }
+            Type::Complex(real) if **real == Type::LongDouble => (0..4)
+                .map(|i| {
+                    builder
+                        .ins()
+                        .load(types::I64, MemFlags::new(), val.ir_val, 8 * i)
+                })
+                .collect(),
+            Type::Int128(_) => {
+                let (low, high) = builder.ins().isplit(val.ir_val);
+                vec![low, high]
+            }
+            Type::Complex(real) => Self::complex_to_abi(real, val.ir_val, builder),
+            _ => vec![val.ir_val],
+        }
+    }
+    /// The inverse of `to_eightbytes` for a type that is `split_in_memory`:
+    /// store the values passed to a function at `addr`.
+    pub(super) fn store_eightbytes(vals: &[IrValue], addr: IrValue, builder: &mut FunctionBuilder) {
+        for (i, &val) in vals.iter().enumerate() {
+            builder
+                .ins()
+                .store(MemFlags::new(), val, addr, 8 * i as i32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_bytes;
+
+    fn x87(mant: u64, sign_exp: u16) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&mant.to_le_bytes());
+        bytes[8..10].copy_from_slice(&sign_exp.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        assert_eq!(to_bytes(0.0), x87(0, 0));
+        assert_eq!(to_bytes(-0.0), x87(0, 0x8000));
+        assert_eq!(to_bytes(1.0), x87(1 << 63, 0x3fff));
+        assert_eq!(to_bytes(-2.0), x87(1 << 63, 0xc000));
+        assert_eq!(to_bytes(1.5), x87(0xc000_0000_0000_0000, 0x3fff));
+        assert_eq!(to_bytes(f64::INFINITY), x87(1 << 63, 0x7fff));
+        // the smallest subnormal double is 2^-1074
+        assert_eq!(to_bytes(f64::from_bits(1)), x87(1 << 63, 0x3fff - 1074));
+        assert!(to_bytes(f64::NAN)[..8] != x87(1 << 63, 0x7fff)[..8]);
+    }
+}
@@ -6,16 +6,16 @@ use std::convert::{TryFrom, TryInto};
 use cranelift::codegen::ir::types;
 use cranelift_module::{Backend, DataContext, DataId, Linkage};
 
-use super::{Compiler, Id};
+use super::{export_linkage, import_linkage, long_double, section_names, Compiler, Id};
 use saltwater_parser::arch::{PTR_SIZE, TARGET};
 use saltwater_parser::const_assert;
 use saltwater_parser::data::{
-    hir::{Expr, ExprType, Initializer, LiteralValue, Symbol},
+    hir::{Expr, ExprType, Initializer, LiteralValue, Symbol, Variable},
     types::ArrayType,
     StorageClass, *,
 };
 
-const_assert!(PTR_SIZE <= std::usize::MAX as u16);
+const_assert!(PTR_SIZE <= usize::MAX as u16);
 const ZERO_PTR: [u8; PTR_SIZE as usize] = [0; PTR_SIZE as usize];
 
 macro_rules! cast {
@@ -46,10 +46,14 @@ macro_rules! bytes {
 }
 
 impl<B: Backend> Compiler<B> {
+    /// Declare an object with static storage duration and emit its initializer.
+    ///
+    /// `block_scope` is true for objects declared inside a function, like `static int n;`.
     pub(super) fn store_static(
         &mut self,
         symbol: Symbol,
         init: Option<Initializer>,
+        block_scope: bool,
         location: Location,
     ) -> CompileResult<()> {
         use saltwater_parser::get_str;
@@ -62,7 +66,6 @@ impl<B: Backend> Compiler<B> {
             location,
         };
         let align = metadata
-            .ctype
             .alignof()
             .map_err(|err| err.to_string())
             .and_then(|size| {
@@ -74,14 +77,38 @@ impl<B: Backend> Compiler<B> {
             // struct that was declared but never used
             return Ok(());
         }
-        let linkage = linkage_from_storage_class(metadata.storage_class).map_err(err_closure)?;
+        let linkage = match linkage_from_storage_class(metadata.storage_class) {
+            Ok(Linkage::Export) => export_linkage(&metadata.attributes),
+            Ok(Linkage::Import) => import_linkage(&metadata.attributes, self.is_jit()),
+            linkage => linkage.map_err(err_closure)?,
+        };
+        if metadata.thread_local && self.is_jit() {
+            semantic_err!(
+                format!(
+                    "thread-local variable '{}' is not supported by the JIT, compile to an object file instead",
+                    metadata.id
+                ),
+                location
+            );
+        }
+        // `extern int i __asm__("j");` is called `j` in the object file
+        let name = match &metadata.attributes.asm_label {
+            Some(label) => label.clone(),
+            // `static int n;` in two different functions are different objects, so give them
+            // different names, like GCC's `n.1`
+            None if block_scope && linkage == Linkage::Local => {
+                self.local_statics += 1;
+                format!("{}.{}", metadata.id, self.local_statics)
+            }
+            None => get_str!(metadata.id).to_string(),
+        };
         let id = self
             .module
             .declare_data(
-                get_str!(metadata.id),
+                &name,
                 linkage,
                 !metadata.qualifiers.c_const,
-                false,
+                metadata.thread_local,
                 Some(align),
             )
             .map_err(|err| Locatable {
@@ -91,7 +118,7 @@ impl<B: Backend> Compiler<B> {
 
         self.declarations.insert(symbol, Id::Global(id));
 
-        if linkage == Linkage::Import {
+        if metadata.storage_class == StorageClass::Extern {
             debug_assert!(init.is_none());
             return Ok(());
         }
@@ -100,22 +127,16 @@ impl<B: Backend> Compiler<B> {
         // TODO: all of this should happen in the `analyze` module
         if let Some(init) = init {
             let mut ctype = metadata.ctype.clone();
-            if let Type::Array(_, size @ ArrayType::Unbounded) = &mut ctype {
-                if let Some(len) = match &init {
-                    Initializer::InitializerList(list) => Some(list.len()),
-                    Initializer::Scalar(expr) => match &expr.expr {
-                        ExprType::Literal(LiteralValue::Str(s)) => Some(s.len()),
-                        _ => None,
-                    },
-                    _ => None,
-                } {
-                    *size = ArrayType::Fixed(len.try_into().unwrap());
-                };
-            }
-            let size_t = ctype.sizeof().map_err(|err| Locatable {
+            ctype.complete_array_type(&init);
+            let mut size_t = ctype.sizeof().map_err(|err| Locatable {
                 data: err.to_string(),
                 location,
             })?;
+            // struct s { int len; char data[]; } s = { 3, "hi" };
+            // the object is large enough to hold the initialized flexible array member
+            if let Some(end) = flexible_array_end(&ctype, &init) {
+                size_t = std::cmp::max(size_t, end);
+            }
             let size = size_t
                 .try_into()
                 .expect("initializer is larger than SIZE_T on host platform");
@@ -124,13 +145,22 @@ impl<B: Backend> Compiler<B> {
             self.init_symbol(&mut ctx, &mut buf, offset, init, &ctype, &location)?;
             ctx.define(buf.into_boxed_slice());
         } else {
-            ctx.define_zeroinit(
-                metadata
-                    .ctype
-                    .sizeof()
-                    .map_err(|err| err_closure(err.to_string()))? as usize,
-            );
+            let size = metadata
+                .ctype
+                .sizeof()
+                .map_err(|err| err_closure(err.to_string()))? as usize;
+            // `__attribute__((section(".mydata"))) int i;`: only the default section can hold BSS data
+            if metadata.attributes.section.is_some() && !self.is_jit() {
+                ctx.define(vec![0; size].into_boxed_slice());
+            } else {
+                ctx.define_zeroinit(size);
+            }
         };
+        // the JIT doesn't have sections, so there's nothing to do
+        if let (Some(section), false) = (&metadata.attributes.section, self.is_jit()) {
+            let (segment, section) = section_names(section);
+            ctx.set_segment_section(segment, section);
+        }
         self.module.define_data(id, &ctx).map_err(|err| {
             CompileError::semantic(Locatable {
                 data: format!("error defining static variable: {}", err),
@@ -185,13 +215,20 @@ impl<B: Backend> Compiler<B> {
         // static address-of
         match expr.expr {
             ExprType::StaticRef(inner) => match inner.expr {
-                ExprType::Id(symbol) => self.static_ref(symbol, 0, offset, ctx),
+                ExprType::Id(symbol) => self.static_ref(symbol, 0, offset, ctx, expr.location)?,
                 ExprType::Literal(LiteralValue::Str(str_ref)) => {
                     let str_id = self.compile_string(str_ref, expr.location)?;
                     let str_addr = self.module.declare_data_in_data(str_id, ctx);
                     ctx.write_data_addr(offset, str_addr, 0);
                 }
                 ExprType::Literal(ref token) if token.is_zero() => buf.copy_from_slice(&ZERO_PTR),
+                // `static void *labels[] = { &&a, &&b };` in a function
+                ExprType::LabelAddress(label) => {
+                    let id = LiteralValue::UnsignedInt(self.label_address(label));
+                    let ctype = Type::Long(false);
+                    let bytes = into_bytes(id, &ctype, &expr.location, &mut self.error_handler)?;
+                    buf.copy_from_slice(&bytes);
+                }
                 ExprType::Cast(ref inner) if inner.is_zero() => buf.copy_from_slice(&ZERO_PTR),
                 ExprType::Member(struct_expr, member) => {
                     let member_offset = struct_expr
@@ -199,7 +236,8 @@ impl<B: Backend> Compiler<B> {
                         .member_offset(member)
                         .expect("parser shouldn't allow Member for non-struct types");
                     if let ExprType::Id(symbol) = struct_expr.expr {
-                        self.static_ref(symbol, member_offset.try_into().unwrap(), offset, ctx);
+                        let member_offset = member_offset.try_into().unwrap();
+                        self.static_ref(symbol, member_offset, offset, ctx, expr.location)?;
                     } else {
                         semantic_err!(
                             "expression is not a compile time constant".into(),
@@ -209,6 +247,24 @@ impl<B: Backend> Compiler<B> {
                 }
                 _ => semantic_err!("cannot take the address of an rvalue".into(), expr.location),
             },
+            // the string literal may be shorter than the array: `char s[10] = "hi";`
+            ExprType::Literal(LiteralValue::Str(string)) => {
+                let len = std::cmp::min(buf.len(), string.len());
+                buf[..len].copy_from_slice(&string[..len]);
+            }
+            // `(double _Complex)1.0`: the imaginary part is already zeroed
+            ExprType::Literal(token) if expr.ctype.is_complex() => {
+                let real = expr.ctype.real_type();
+                let bytes = into_bytes(token, real, &expr.location, &mut self.error_handler)?;
+                buf[..bytes.len()].copy_from_slice(&bytes);
+            }
+            // `CMPLX(1.0, 2.0)`
+            ExprType::Complex(real, imag) => {
+                let (real_buf, imag_buf) = buf.split_at_mut(buf.len() / 2);
+                let imag_offset = offset + real_buf.len() as u32;
+                self.init_expr(ctx, real_buf, offset, *real)?;
+                self.init_expr(ctx, imag_buf, imag_offset, *imag)?;
+            }
             ExprType::Literal(token) => {
                 let bytes =
                     into_bytes(token, &expr.ctype, &expr.location, &mut self.error_handler)?;
@@ -221,7 +277,21 @@ impl<B: Backend> Compiler<B> {
         }
         Ok(())
     }
-    fn static_ref(&self, symbol: Symbol, member_offset: i64, offset: u32, ctx: &mut DataContext) {
+    fn static_ref(
+        &self,
+        symbol: Symbol,
+        member_offset: i64,
+        offset: u32,
+        ctx: &mut DataContext,
+        location: Location,
+    ) -> CompileResult<()> {
+        // each thread has its own copy, so the address isn't known until runtime
+        if symbol.get().thread_local {
+            semantic_err!(
+                "the address of a thread-local variable is not a compile time constant".into(),
+                location
+            );
+        }
         match self.declarations.get(&symbol) {
             Some(Id::Function(func_id)) => {
                 let func_ref = self.module.declare_func_in_data(*func_id, ctx);
@@ -232,19 +302,24 @@ impl<B: Backend> Compiler<B> {
                 let global_val = self.module.declare_data_in_data(*data_id, ctx);
                 ctx.write_data_addr(offset, global_val, member_offset);
             }
-            Some(Id::Local(_)) => unreachable!("cannot have local variable at global scope"),
+            Some(Id::Local(_)) | Some(Id::Vla(_)) => {
+                unreachable!("cannot have local variable at global scope")
+            }
             None => unreachable!("parser should catch undeclared variables"),
         }
+        Ok(())
     }
     fn init_symbol(
         &mut self,
         ctx: &mut DataContext,
         buf: &mut [u8],
-        mut offset: u32,
+        offset: u32,
         initializer: Initializer,
         ctype: &Type,
         location: &Location,
     ) -> CompileResult<()> {
+        // `static _Atomic int i = 1;` is stored the same as an `int`
+        let ctype = ctype.non_atomic();
         match initializer {
             Initializer::InitializerList(mut initializers) => match ctype {
                 Type::Array(ty, ArrayType::Unbounded) => {
@@ -265,35 +340,16 @@ impl<B: Backend> Compiler<B> {
                         self.init_array(ctx, buf, offset, initializers, ty, location)
                     }
                 }
-                ty if ty.is_scalar() => {
-                    assert_eq!(initializers.len(), 1);
-                    self.init_symbol(ctx, buf, offset, initializers.remove(0), ctype, location)
-                }
-                Type::Union(struct_type) => self.init_symbol(
-                    ctx,
-                    buf,
-                    offset,
-                    initializers.remove(0),
-                    &struct_type.members().first().unwrap().ctype,
-                    location,
-                ),
-                Type::Struct(struct_ref) => {
-                    let mut current_offset = 0;
-                    for (member, init) in struct_ref.members().iter().zip(initializers.into_iter())
+                ty if ty.is_scalar() => match initializers.pop() {
+                    Some(init) => self.init_symbol(ctx, buf, offset, init, ctype, location),
+                    // `buf` is already zeroed
+                    None => Ok(()),
+                },
+                // for unions, every initializer except the last is an empty list
+                Type::Struct(struct_type) | Type::Union(struct_type) => {
+                    for (member, init) in struct_type.initialized_members().iter().zip(initializers)
                     {
-                        let size_host: usize = member
-                            .ctype
-                            .sizeof()
-                            .map_err(|err| CompileError::semantic(location.with(err.to_string())))?
-                            .try_into()
-                            .expect("cannot initialize struct larger than u32");
-                        let size: u32 = size_host
-                            .try_into()
-                            .expect("cannot intialize struct larger than host address space");
-                        let buf_slice = &mut buf[current_offset..current_offset + size_host];
-                        self.init_symbol(ctx, buf_slice, offset, init, &member.ctype, location)?;
-                        offset += size;
-                        current_offset += size_host;
+                        self.init_member(ctx, buf, offset, init, ctype, member, location)?;
                     }
                     Ok(())
                 }
@@ -307,6 +363,69 @@ impl<B: Backend> Compiler<B> {
             }
         }
     }
+    /// Initialize `member` of the struct or union `ctype`, which is stored in `buf`.
+    #[allow(clippy::too_many_arguments)]
+    fn init_member(
+        &mut self,
+        ctx: &mut DataContext,
+        buf: &mut [u8],
+        offset: u32,
+        initializer: Initializer,
+        ctype: &Type,
+        member: &Variable,
+        location: &Location,
+    ) -> CompileResult<()> {
+        let member_offset: usize = ctype
+            .member_offset(member.id)
+            .expect("only structs and unions can have members")
+            .try_into()
+            .expect("cannot initialize struct larger than host address space");
+        let mut member_type = member.ctype.clone();
+        // flexible array members have the size of their initializer
+        member_type.complete_array_type(&initializer);
+        let size: usize = member_type
+            .sizeof()
+            .map_err(|err| CompileError::semantic(location.with(err.to_string())))?
+            .try_into()
+            .expect("cannot initialize struct larger than host address space");
+        let offset = offset
+            + u32::try_from(member_offset).expect("cannot initialize struct larger than u32");
+        if member_offset + size > buf.len() {
+            // struct { int i; struct s inner; } s = { 1, { 3, "hi" } };
+            semantic_err!(
+                "initialization of nested flexible array member is not supported".into(),
+                *location
+            );
+        }
+        let buf = &mut buf[member_offset..member_offset + size];
+        let bitfield = match ctype.member_bitfield(member.id) {
+            Some(bitfield) => bitfield,
+            None => return self.init_symbol(ctx, buf, offset, initializer, &member_type, location),
+        };
+        // bit-fields can share a storage unit with other bit-fields,
+        // so initialize a copy of the unit and only keep the bits for this member
+        let mut unit = vec![0; size];
+        self.init_symbol(ctx, &mut unit, offset, initializer, &member.ctype, location)?;
+        let big_endian = TARGET
+            .endianness()
+            .expect("target should be big or little endian")
+            == target_lexicon::Endianness::Big;
+        let to_int = |bytes: &[u8]| {
+            let fold = |n: u64, byte: &u8| n << 8 | u64::from(*byte);
+            if big_endian {
+                bytes.iter().fold(0, fold)
+            } else {
+                bytes.iter().rev().fold(0, fold)
+            }
+        };
+        let mask = (((1_u128 << bitfield.width) - 1) << bitfield.shift) as u64;
+        let value = (to_int(&*buf) & !mask) | ((to_int(&unit) << bitfield.shift) & mask);
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let shift = if big_endian { size - 1 - i } else { i } * 8;
+            *byte = (value >> shift) as u8;
+        }
+        Ok(())
+    }
     fn init_array(
         &mut self,
         ctx: &mut DataContext,
@@ -350,6 +469,22 @@ impl<B: Backend> Compiler<B> {
     }
 }
 
+/// Get the offset of the end of the flexible array member of `ctype`, as initialized by `init`.
+///
+/// Returns `None` if `ctype` has no flexible array member or it is not initialized.
+fn flexible_array_end(ctype: &Type, init: &Initializer) -> Option<u64> {
+    let (struct_type, list) = match (ctype, init) {
+        (Type::Struct(struct_type), Initializer::InitializerList(list)) => (struct_type, list),
+        _ => return None,
+    };
+    let member = struct_type.flexible_array_member()?;
+    let member_init = list.get(struct_type.initialized_members().len() - 1)?;
+    let mut member_type = member.ctype;
+    member_type.complete_array_type(member_init);
+    let size = member_type.sizeof().ok()?;
+    Some(ctype.member_offset(member.id).ok()? + size)
+}
+
 fn into_bytes(
     value: LiteralValue,
     ctype: &Type,
@@ -364,6 +499,8 @@ fn into_bytes(
 
     match value {
         LiteralValue::Int(i) => Ok(match ir_type {
+            // `static _Bool b = 1;`
+            types::B1 => bytes!(u8::from(i != 0), big_endian),
             types::I8 => bytes!(
                 cast!(i, i64, i8, &ctype, *location, error_handler),
                 big_endian
@@ -377,12 +514,14 @@ fn into_bytes(
                 big_endian
             ),
             types::I64 => bytes!(i, big_endian),
+            types::I128 => bytes!(i128::from(i), big_endian),
             x => unreachable!(format!(
                 "ir_type {} for integer {} is not of integer type",
                 x, i
             )),
         }),
         LiteralValue::UnsignedInt(i) => Ok(match ir_type {
+            types::B1 => bytes!(u8::from(i != 0), big_endian),
             types::I8 => bytes!(
                 cast!(i, u64, u8, &ctype, *location, error_handler),
                 big_endian
@@ -396,11 +535,18 @@ fn into_bytes(
                 big_endian
             ),
             types::I64 => bytes!(i, big_endian),
+            types::I128 => bytes!(u128::from(i), big_endian),
             x => unreachable!(format!(
                 "ir_type {} for integer {} is not of integer type",
                 x, i
             )),
         }),
+        // `const_cast` only produces 128-bit literals for 128-bit types
+        LiteralValue::Int128(i) => Ok(bytes!(i, big_endian)),
+        LiteralValue::UnsignedInt128(u) => Ok(bytes!(u, big_endian)),
+        LiteralValue::Float(f) if *ctype == Type::LongDouble => {
+            Ok(Box::new(long_double::to_bytes(f)))
+        }
         LiteralValue::Float(f) => Ok(match ir_type {
             types::F32 => {
                 let cast = f as f32;
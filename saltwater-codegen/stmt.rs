@@ -1,9 +1,11 @@
 use cranelift::codegen::cursor::Cursor;
+use cranelift::codegen::ir::{condcodes::IntCC, TrapCode};
 use cranelift::frontend::Switch;
 use cranelift::prelude::{Block, FunctionBuilder, InstBuilder};
 use cranelift_module::Backend;
 
-use super::Compiler;
+use super::long_double::X87Helper;
+use super::{Cleanup, Compiler};
 use saltwater_parser::data::{
     hir::{Expr, Stmt, StmtType},
     *,
@@ -29,7 +31,15 @@ impl<B: Backend> Compiler<B> {
             return Err(stmt.location.error(SemanticError::UnreachableStatement));
         }
         match stmt.data {
-            StmtType::Compound(stmts) => self.compile_all(stmts, builder),
+            StmtType::Compound(stmts) => {
+                let height = self.cleanups.len();
+                self.compile_all(stmts, builder)?;
+                if !builder.is_filled() {
+                    self.run_cleanups(height, builder)?;
+                }
+                self.cleanups.truncate(height);
+                Ok(())
+            }
             // INVARIANT: symbol has not yet been declared in this scope
             StmtType::Decl(decls) => {
                 for decl in decls {
@@ -43,9 +53,28 @@ impl<B: Backend> Compiler<B> {
             }
             StmtType::Return(expr) => {
                 let mut ret = vec![];
+                // a `long double` is returned on the x87 stack
+                let mut x87_helper = None;
                 if let Some(e) = expr {
                     let val = self.compile_expr(e, builder)?;
-                    ret.push(val.ir_val);
+                    match val.ctype.non_atomic() {
+                        Type::LongDouble => x87_helper = Some(X87Helper::Return),
+                        Type::Complex(real) if **real == Type::LongDouble => {
+                            x87_helper = Some(X87Helper::ReturnComplex)
+                        }
+                        _ => {}
+                    }
+                    match &val.ctype {
+                        Type::Complex(real) => {
+                            ret = Self::complex_to_abi(real, val.ir_val, builder);
+                        }
+                        _ => ret.push(val.ir_val),
+                    }
+                }
+                self.run_cleanups(0, builder)?;
+                if let Some(helper) = x87_helper {
+                    self.call_x87_helper(helper, &ret, builder);
+                    ret.clear();
                 }
                 builder.ins().return_(&ret);
                 Ok(())
@@ -53,7 +82,9 @@ impl<B: Backend> Compiler<B> {
             StmtType::If(condition, body, otherwise) => {
                 self.if_stmt(condition, *body, otherwise, builder)
             }
-            StmtType::While(condition, body) => self.while_stmt(Some(condition), *body, builder),
+            StmtType::While(condition, body) => {
+                self.while_stmt(Some(condition), *body, None, builder)
+            }
             StmtType::Break | StmtType::Continue => {
                 self.loop_exit(stmt.data == StmtType::Break, stmt.location, builder)
             }
@@ -62,7 +93,6 @@ impl<B: Backend> Compiler<B> {
                 condition.map(|e| *e),
                 post_loop.map(|e| *e),
                 *body,
-                stmt.location,
                 builder,
             ),
             StmtType::Do(body, condition) => self.do_loop(*body, condition, builder),
@@ -71,7 +101,8 @@ impl<B: Backend> Compiler<B> {
                 let new_block = builder.create_block();
                 Self::jump_to_block(new_block, builder);
                 builder.switch_to_block(new_block);
-                if let Some(previous) = self.labels.insert(name, new_block) {
+                let cleanups = self.cleanups.clone();
+                if let Some((previous, _)) = self.labels.insert(name, (new_block, cleanups)) {
                     Err(stmt
                         .location
                         .error(SemanticError::LabelRedeclaration(previous)))
@@ -80,16 +111,34 @@ impl<B: Backend> Compiler<B> {
                 }
             }
             StmtType::Goto(name) => match self.labels.get(&name) {
-                Some(block) => {
-                    Self::jump_to_block(*block, builder);
+                Some((block, cleanups)) => {
+                    let (block, height) = (*block, cleanups.len());
+                    // the analyzer guarantees that every VLA in scope at the label is still in scope
+                    self.run_cleanups(height, builder)?;
+                    Self::jump_to_block(block, builder);
                     Ok(())
                 }
                 None => Err(stmt.location.error(SemanticError::UndeclaredLabel(name))),
             },
-            StmtType::Case(constexpr, inner) => {
+            // the target isn't known until runtime, so which cleanups to run is decided
+            // once every label has been seen, at the end of the function
+            StmtType::IndirectGoto(target) => {
+                let target = self.compile_expr(target, builder)?;
+                let dispatch = builder.create_block();
+                builder.append_block_param(dispatch, Type::ptr_type());
+                self.indirect_gotos.push((dispatch, self.cleanups.clone()));
+                builder.ins().jump(dispatch, &[target.ir_val]);
+                Ok(())
+            }
+            StmtType::Case(constexpr, None, inner) => {
                 self.case(constexpr.into(), *inner, stmt.location, builder)
             }
+            StmtType::Case(lower, Some(upper), inner) => {
+                self.case_range(lower, upper, *inner, stmt.location, builder)
+            }
             StmtType::Default(inner) => self.default(*inner, stmt.location, builder),
+            // only used for warnings
+            StmtType::Fallthrough => Ok(()),
         }
     }
     fn if_stmt(
@@ -146,7 +195,7 @@ impl<B: Backend> Compiler<B> {
     /// - Return (start, end, previous_last_saw_loop)
     fn enter_loop(&mut self, builder: &mut FunctionBuilder) -> (Block, Block, bool) {
         let (loop_body, end_body) = (builder.create_block(), builder.create_block());
-        self.loops.push((loop_body, end_body));
+        self.loops.push((loop_body, end_body, self.cleanups.len()));
         let old_saw_loop = self.last_saw_loop;
         self.last_saw_loop = true;
 
@@ -163,9 +212,16 @@ impl<B: Backend> Compiler<B> {
         &mut self,
         maybe_condition: Option<Expr>,
         body: Stmt,
+        post_loop: Option<Expr>,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
         let (loop_body, end_body, old_saw_loop) = self.enter_loop(builder);
+        // `for (;; i++) { continue; }` still runs `i++`
+        let post_block = post_loop.as_ref().map(|_| {
+            let post_block = builder.create_block();
+            self.loops.last_mut().unwrap().0 = post_block;
+            post_block
+        });
 
         // for loops can loop forever: `for (;;) {}`
         if let Some(condition) = maybe_condition {
@@ -175,6 +231,11 @@ impl<B: Backend> Compiler<B> {
         }
 
         self.compile_stmt(body, builder)?;
+        if let (Some(post_block), Some(post_loop)) = (post_block, post_loop) {
+            Self::jump_to_block(post_block, builder);
+            builder.switch_to_block(post_block);
+            self.compile_expr(post_loop, builder)?;
+        }
         Self::jump_to_block(loop_body, builder);
 
         builder.switch_to_block(end_body);
@@ -193,9 +254,16 @@ impl<B: Backend> Compiler<B> {
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
         let (loop_body, end_body, old_saw_loop) = self.enter_loop(builder);
+        // `do { continue; } while (i++ < 3);` still checks the condition
+        let condition_block = builder.create_block();
+        self.loops.last_mut().unwrap().0 = condition_block;
 
         self.compile_stmt(body, builder)?;
-        if builder.is_filled() {
+        Self::jump_to_block(condition_block, builder);
+        builder.switch_to_block(condition_block);
+        // the condition is only reachable by falling off the body or through `continue`
+        builder.seal_block(condition_block);
+        if builder.is_unreachable() {
             return Err(condition
                 .location
                 .error(SemanticError::UnreachableStatement));
@@ -213,30 +281,11 @@ impl<B: Backend> Compiler<B> {
         init: Stmt,
         condition: Option<Expr>,
         post_loop: Option<Expr>,
-        mut body: Stmt,
-        location: Location,
+        body: Stmt,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
         self.compile_stmt(init, builder)?;
-        if let Some(post_loop) = post_loop {
-            let post_loop = Stmt {
-                data: StmtType::Expr(post_loop),
-                location,
-            };
-            if let Stmt {
-                data: StmtType::Compound(stmts),
-                ..
-            } = &mut body
-            {
-                stmts.push(post_loop);
-            } else {
-                body = Stmt {
-                    data: StmtType::Compound(vec![body, post_loop]),
-                    location,
-                };
-            };
-        }
-        self.while_stmt(condition, body, builder)
+        self.while_stmt(condition, body, post_loop, builder)
     }
     fn switch(
         &mut self,
@@ -256,14 +305,34 @@ impl<B: Backend> Compiler<B> {
         let old_saw_loop = self.last_saw_loop;
         self.last_saw_loop = false;
 
-        self.switches
-            .push((Switch::new(), None, builder.create_block()));
+        self.switches.push((
+            Switch::new(),
+            None,
+            builder.create_block(),
+            self.cleanups.len(),
+            Vec::new(),
+        ));
         self.compile_stmt(body, builder)?;
-        let (switch, default, end) = self.switches.pop().unwrap();
+        let (switch, default, end, _, ranges) = self.switches.pop().unwrap();
         self.last_saw_loop = old_saw_loop;
 
         Self::jump_to_block(end, builder);
         builder.switch_to_block(dummy_block);
+        // `case 'a' ... 'z':` is `if ((unsigned)(c - 'a') <= 'z' - 'a')`
+        for (lower, upper, target) in ranges {
+            let offset = builder
+                .ins()
+                .iadd_imm(cond_val.ir_val, (lower as i64).wrapping_neg());
+            let in_range = builder.ins().icmp_imm(
+                IntCC::UnsignedLessThanOrEqual,
+                offset,
+                upper.wrapping_sub(lower) as i64,
+            );
+            let next = builder.create_block();
+            builder.ins().brnz(in_range, target, &[]);
+            builder.ins().jump(next, &[]);
+            builder.switch_to_block(next);
+        }
         switch.emit(
             builder,
             cond_val.ir_val,
@@ -274,6 +343,8 @@ impl<B: Backend> Compiler<B> {
             },
         );
         builder.switch_to_block(end);
+        // every jump to `end` has been emitted by now
+        builder.seal_block(end);
         Ok(())
     }
     fn case(
@@ -283,13 +354,18 @@ impl<B: Backend> Compiler<B> {
         location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        let (switch, _, _) = match self.switches.last_mut() {
+        let (switch, _, _, _, ranges) = match self.switches.last_mut() {
             Some(x) => x,
             None => {
                 return Err(location.error(SemanticError::CaseOutsideSwitch { is_default: false }))
             }
         };
-        if switch.entries().contains_key(&constexpr) {
+        let value = constexpr as u64;
+        if switch.entries().contains_key(&constexpr)
+            || ranges
+                .iter()
+                .any(|&(lower, upper, _)| in_range(value, lower, upper))
+        {
             return Err(location.error(SemanticError::DuplicateCase { is_default: false }));
         }
         if builder.is_pristine() {
@@ -303,13 +379,100 @@ impl<B: Backend> Compiler<B> {
         };
         self.compile_stmt(stmt, builder)
     }
+    /// GNU case range: `case 'a' ... 'z':`
+    ///
+    /// The analyzer guarantees that the range is not empty.
+    fn case_range(
+        &mut self,
+        lower: u64,
+        upper: u64,
+        stmt: Stmt,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        let (switch, _, _, _, ranges) = match self.switches.last_mut() {
+            Some(x) => x,
+            None => {
+                return Err(location.error(SemanticError::CaseOutsideSwitch { is_default: false }))
+            }
+        };
+        let overlaps_case = switch
+            .entries()
+            .keys()
+            .any(|&value| in_range(value as u64, lower, upper));
+        // two ranges overlap if either contains the start of the other
+        let overlaps_range = ranges.iter().any(|&(other_lower, other_upper, _)| {
+            in_range(lower, other_lower, other_upper) || in_range(other_lower, lower, upper)
+        });
+        if overlaps_case || overlaps_range {
+            return Err(location.error(SemanticError::DuplicateCase { is_default: false }));
+        }
+        let target = if builder.is_pristine() {
+            builder.cursor().current_block().unwrap()
+        } else {
+            let new = builder.create_block();
+            Self::jump_to_block(new, builder);
+            builder.switch_to_block(new);
+            new
+        };
+        ranges.push((lower, upper, target));
+        self.compile_stmt(stmt, builder)
+    }
+    /// Emit the block that a `goto *ptr` jumps to.
+    ///
+    /// `&&label` is a small integer rather than a real address,
+    /// so this switches on it to find the label.
+    /// `cleanups` are the cleanups in scope at the `goto`;
+    /// the ones that are not also in scope at the label are run before jumping.
+    pub(super) fn indirect_goto_dispatch(
+        &mut self,
+        dispatch: Block,
+        cleanups: Vec<Cleanup>,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        builder.switch_to_block(dispatch);
+        let target = builder.block_params(dispatch)[0];
+        let mut switch = Switch::new();
+        // label, number of cleanups still in scope at the label, block that runs the rest
+        let mut leave_scopes = Vec::new();
+        for (label, &id) in &self.label_addresses {
+            // the analyzer checks that every label whose address is taken exists
+            let (block, label_cleanups) = &self.labels[label];
+            let height = cleanups
+                .iter()
+                .zip(label_cleanups)
+                .take_while(|(goto, label)| goto == label)
+                .count();
+            if height == cleanups.len() {
+                switch.set_entry(id.into(), *block);
+            } else {
+                let leave_scope = builder.create_block();
+                switch.set_entry(id.into(), leave_scope);
+                leave_scopes.push((*block, height, leave_scope));
+            }
+        }
+        // jumping to anything other than the address of a label is undefined behavior
+        let invalid = builder.create_block();
+        switch.emit(builder, target, invalid);
+        builder.switch_to_block(invalid);
+        builder.ins().trap(TrapCode::UnreachableCodeReached);
+
+        let outer_cleanups = std::mem::replace(&mut self.cleanups, cleanups);
+        for (block, height, leave_scope) in leave_scopes {
+            builder.switch_to_block(leave_scope);
+            self.run_cleanups(height, builder)?;
+            builder.ins().jump(block, &[]);
+        }
+        self.cleanups = outer_cleanups;
+        Ok(())
+    }
     fn default(
         &mut self,
         inner: Stmt,
         location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        let (_, default, _) = match self.switches.last_mut() {
+        let (_, default, _, _, _) = match self.switches.last_mut() {
             Some(x) => x,
             None => {
                 return Err(location.error(SemanticError::CaseOutsideSwitch { is_default: true }));
@@ -338,11 +501,12 @@ impl<B: Backend> Compiler<B> {
     ) -> CompileResult<()> {
         if self.last_saw_loop {
             // break from loop
-            if let Some((loop_start, loop_end)) = self.loops.last() {
+            if let Some(&(continue_target, loop_end, height)) = self.loops.last() {
+                self.run_cleanups(height, builder)?;
                 if is_break {
-                    Self::jump_to_block(*loop_end, builder);
+                    Self::jump_to_block(loop_end, builder);
                 } else {
-                    Self::jump_to_block(*loop_start, builder);
+                    Self::jump_to_block(continue_target, builder);
                 }
                 Ok(())
             } else {
@@ -358,11 +522,12 @@ impl<B: Backend> Compiler<B> {
             semantic_err!("'continue' not in loop".into(), location);
         } else {
             // break from switch
-            let (_, _, end_block) = self
+            let &(_, _, end_block, height, _) = self
                 .switches
                 .last()
                 .expect("should be in a switch if last_saw_loop is false");
-            builder.ins().jump(*end_block, &[]);
+            self.run_cleanups(height, builder)?;
+            builder.ins().jump(end_block, &[]);
             Ok(())
         }
     }
@@ -376,7 +541,14 @@ impl<B: Backend> Compiler<B> {
 
 fn is_jump_target(stmt: &StmtType) -> bool {
     match stmt {
-        StmtType::Case(_, _) | StmtType::Default(_) | StmtType::Label(_, _) => true,
+        StmtType::Case(..) | StmtType::Default(_) | StmtType::Label(_, _) => true,
         _ => false,
     }
 }
+
+/// Whether `value` is in the non-empty range `lower ... upper`.
+///
+/// This works for both signed and unsigned ranges, since it only uses wrapping arithmetic.
+fn in_range(value: u64, lower: u64, upper: u64) -> bool {
+    value.wrapping_sub(lower) <= upper.wrapping_sub(lower)
+}
@@ -0,0 +1,866 @@
+//! GNU attributes, `__attribute__((noreturn))`, and standard attributes, `[[noreturn]]`
+//!
+//! <https://gcc.gnu.org/onlinedocs/gcc/Attribute-Syntax.html>
+//!
+//! Both kinds are parsed into the same `ast::Attribute` and looked up by name,
+//! so GNU attributes can also be written as `[[gnu::aligned(16)]]` or `[[aligned(16)]]`.
+
+use super::PureAnalyzer;
+use crate::data::{error::Warning, hir::*, lex::Locatable, *};
+
+type AstAttribute = Locatable<ast::Attribute>;
+
+impl Attributes {
+    /// Add the attributes from an earlier declaration of the same entity.
+    ///
+    /// GCC merges the attributes of all declarations: `void f() __attribute__((weak)); void f() {}`
+    pub(super) fn inherit(&mut self, previous: &Attributes) {
+        if self.section.is_none() {
+            self.section = previous.section.clone();
+        }
+        if self.asm_label.is_none() {
+            self.asm_label = previous.asm_label.clone();
+        }
+        self.weak |= previous.weak;
+        if self.visibility == Visibility::Default {
+            self.visibility = previous.visibility;
+        }
+        self.constructor = self.constructor.or(previous.constructor);
+        self.destructor = self.destructor.or(previous.destructor);
+        if self.deprecated.is_none() {
+            self.deprecated = previous.deprecated.clone();
+        }
+        self.format = self.format.or(previous.format);
+        if self.nodiscard.is_none() {
+            self.nodiscard = previous.nodiscard.clone();
+        }
+        self.reproducible |= previous.reproducible;
+        self.unsequenced |= previous.unsequenced;
+    }
+}
+
+impl PureAnalyzer {
+    /// Apply attributes to the declaration of `symbol`.
+    ///
+    /// `is_member` is true for struct and union members.
+    pub(super) fn apply_attributes(
+        &mut self,
+        symbol: &mut Variable,
+        attributes: Vec<AstAttribute>,
+        is_member: bool,
+    ) {
+        for attr in attributes {
+            self.apply_attribute(symbol, attr.data, attr.location, is_member);
+        }
+    }
+    fn apply_attribute(
+        &mut self,
+        symbol: &mut Variable,
+        attr: ast::Attribute,
+        location: Location,
+        is_member: bool,
+    ) {
+        let name = attr.name;
+        let is_typedef = symbol.storage_class == StorageClass::Typedef;
+        let is_function = symbol.ctype.is_function() && !is_typedef;
+        let is_static = symbol.storage_class == StorageClass::Static;
+        // an object with automatic storage duration
+        let is_local = !is_member
+            && !is_function
+            && !is_typedef
+            && !is_static
+            && !self.scope.is_global()
+            && symbol.storage_class != StorageClass::Extern;
+        // an object with static storage duration
+        let is_global_object = !is_member && !is_function && !is_typedef && !is_local;
+        // what sort of declaration this is, for warnings
+        let kind = if is_typedef {
+            "a typedef"
+        } else if is_member {
+            "a struct member"
+        } else if is_function && is_static {
+            "a static function"
+        } else if is_function {
+            "a function"
+        } else if is_local {
+            "a local variable"
+        } else if is_static {
+            "a static variable"
+        } else {
+            "a variable"
+        };
+        let has_linkage = (is_function || is_global_object) && !is_static;
+        let mut args = attr.args;
+        match name.resolve_and_clone().as_str() {
+            // these only affect warnings, and saltwater doesn't warn about unused variables
+            "unused" | "used" | "maybe_unused" => {}
+            // __attribute__((noreturn)) void abort();
+            // [[_Noreturn]] is an obsolescent spelling of [[noreturn]]
+            "noreturn" | "_Noreturn" if is_function => symbol.qualifiers.func.no_return = true,
+            // int i __attribute__((aligned(16)));
+            // typedef int T __attribute__((aligned(8)));
+            "aligned" if !is_function => {
+                let requested = match self.attribute_alignment(name, args, location) {
+                    Some(align) => align,
+                    None => return,
+                };
+                if symbol.bitfield.is_some() {
+                    self.warn(Warning::IgnoredAttribute(name, "a bit-field"), location);
+                    return;
+                }
+                // GCC only allows `aligned` to make the alignment stricter,
+                // unless `packed` is also used
+                let current = match symbol.alignof() {
+                    Ok(align) => align,
+                    Err(_) => return,
+                };
+                if requested > current {
+                    symbol.alignment = Some(requested);
+                }
+            }
+            // struct s { char c; int i __attribute__((packed)); };
+            "packed" if is_member => {
+                if symbol.bitfield.is_some() {
+                    self.warn(Warning::IgnoredAttribute(name, "a bit-field"), location);
+                } else if symbol.alignment.is_none() {
+                    symbol.alignment = Some(1);
+                }
+            }
+            // int i __attribute__((section(".data.mine")));
+            "section" if is_global_object => match string_argument(&args) {
+                Some(section) => symbol.attributes.section = Some(section),
+                None => {
+                    let err = SemanticError::BadAttributeArgument(name, "a string literal");
+                    self.err(err, location);
+                }
+            },
+            // __attribute__((weak)) int f();
+            "weak" if has_linkage => symbol.attributes.weak = true,
+            // __attribute__((visibility("hidden"))) int f();
+            "visibility" if has_linkage => {
+                let visibility = match string_argument(&args).as_deref() {
+                    Some("default") => Visibility::Default,
+                    Some("hidden") => Visibility::Hidden,
+                    Some("internal") => Visibility::Internal,
+                    Some("protected") => Visibility::Protected,
+                    _ => {
+                        let expected = "\"default\", \"hidden\", \"internal\", or \"protected\"";
+                        let err = SemanticError::BadAttributeArgument(name, expected);
+                        return self.err(err, location);
+                    }
+                };
+                symbol.attributes.visibility = visibility;
+            }
+            // __attribute__((deprecated("use g instead"))) void f();
+            "deprecated" if !is_member => match message_argument(&args) {
+                Some(message) => symbol.attributes.deprecated = Some(message),
+                None => {
+                    let err = SemanticError::BadAttributeArgument(name, "a string literal");
+                    self.err(err, location);
+                }
+            },
+            // [[nodiscard("check for errors")]] int f();
+            "nodiscard" | "warn_unused_result" if is_function => match message_argument(&args) {
+                Some(message) => symbol.attributes.nodiscard = Some(message),
+                None => {
+                    let err = SemanticError::BadAttributeArgument(name, "a string literal");
+                    self.err(err, location);
+                }
+            },
+            // int square(int) [[unsequenced]];
+            // these are only hints for optimization, which saltwater doesn't use yet
+            "reproducible" if is_function => symbol.attributes.reproducible = true,
+            "unsequenced" if is_function => {
+                symbol.attributes.reproducible = true;
+                symbol.attributes.unsequenced = true;
+            }
+            // int my_printf(const char *, ...) __attribute__((format(printf, 1, 2)));
+            "format" if is_function => {
+                if let Some(format) = self.format_attribute(name, symbol, args, location) {
+                    symbol.attributes.format = Some(format);
+                }
+            }
+            // __attribute__((constructor)) void init() {}
+            "constructor" | "destructor" if is_function => {
+                let priority = match args.len() {
+                    0 => u16::MAX,
+                    1 => {
+                        let priority = self.expr(args.remove(0));
+                        match Self::const_uint(priority) {
+                            Ok(priority) if priority <= u64::from(u16::MAX) => priority as u16,
+                            _ => {
+                                let expected = "a priority between 0 and 65535";
+                                let err = SemanticError::BadAttributeArgument(name, expected);
+                                return self.err(err, location);
+                            }
+                        }
+                    }
+                    _ => {
+                        let err = SemanticError::BadAttributeArgument(name, "one argument");
+                        return self.err(err, location);
+                    }
+                };
+                if name == "constructor".into() {
+                    symbol.attributes.constructor = Some(priority);
+                } else {
+                    symbol.attributes.destructor = Some(priority);
+                }
+            }
+            // __attribute__((cleanup(free_ptr))) char *p = malloc(1);
+            "cleanup" if is_local => {
+                if let Some(func) = self.cleanup_function(name, symbol, args, location) {
+                    symbol.attributes.cleanup = Some(func);
+                }
+            }
+            other if is_known(other) => self.warn(Warning::IgnoredAttribute(name, kind), location),
+            _ => self.warn(Warning::UnknownAttribute(name), location),
+        }
+    }
+    /// Apply attributes to the definition of a struct or union.
+    ///
+    /// `struct s { char c; int i; } __attribute__((packed));`
+    pub(super) fn apply_struct_attributes(
+        &mut self,
+        members: &mut [Variable],
+        attributes: Vec<AstAttribute>,
+    ) {
+        for Locatable {
+            data: attr,
+            location,
+        } in attributes
+        {
+            match attr.name.resolve_and_clone().as_str() {
+                "unused" | "used" | "maybe_unused" => {}
+                // the struct layout only looks at the alignment of each member,
+                // so packing a struct is the same as packing all its members
+                "packed" => {
+                    for member in members.iter_mut() {
+                        // NOTE: bit-fields are not packed
+                        if member.alignment.is_none() && member.bitfield.is_none() {
+                            member.alignment = Some(1);
+                        }
+                    }
+                }
+                // the alignment of a struct is the strictest alignment of its members,
+                // so raising the alignment of the first member raises the alignment of the whole struct
+                // without changing the offsets of any members
+                "aligned" => {
+                    let requested = match self.attribute_alignment(attr.name, attr.args, location) {
+                        Some(align) => align,
+                        None => continue,
+                    };
+                    let first = members
+                        .iter_mut()
+                        .find(|member| !member.is_unnamed_bitfield() && member.bitfield.is_none());
+                    match first {
+                        Some(first) => {
+                            if first.alignof().is_ok_and(|align| requested > align) {
+                                first.alignment = Some(requested);
+                            }
+                        }
+                        None => {
+                            let warning =
+                                Warning::IgnoredAttribute(attr.name, "a struct of bit-fields");
+                            self.warn(warning, location);
+                        }
+                    }
+                }
+                other if is_known(other) => {
+                    self.warn(Warning::IgnoredAttribute(attr.name, "a struct"), location)
+                }
+                _ => self.warn(Warning::UnknownAttribute(attr.name), location),
+            }
+        }
+    }
+    /// An attribute declaration: `[[fallthrough]];`
+    ///
+    /// Returns whether this contained a `fallthrough` attribute.
+    pub(super) fn attribute_declaration(
+        &mut self,
+        attributes: Vec<ast::DeclarationSpecifier>,
+    ) -> bool {
+        let mut fallthrough = false;
+        for spec in attributes {
+            let attr = match spec {
+                ast::DeclarationSpecifier::Attribute(attr) => attr,
+                _ => unreachable!("attribute declarations should only contain attributes"),
+            };
+            let name = attr.data.name;
+            if name == "fallthrough".into() {
+                // switch (i) { case 1: f(); [[fallthrough]]; case 2: g(); }
+                fallthrough = true;
+                if self.scope.is_global() {
+                    let warning = Warning::IgnoredAttribute(name, "a global declaration");
+                    self.warn(warning, attr.location);
+                }
+            } else if is_known(&name.resolve_and_clone()) {
+                let warning = Warning::IgnoredAttribute(name, "an empty declaration");
+                self.warn(warning, attr.location);
+            } else {
+                self.warn(Warning::UnknownAttribute(name), attr.location);
+            }
+        }
+        fallthrough
+    }
+    /// The alignment requested by `aligned` or `aligned(n)`
+    fn attribute_alignment(
+        &mut self,
+        name: InternedStr,
+        mut args: Vec<ast::Expr>,
+        location: Location,
+    ) -> Option<crate::arch::SIZE_T> {
+        match args.len() {
+            // > the compiler automatically sets the alignment for the declared variable or field
+            // > to __BIGGEST_ALIGNMENT__, which is the largest alignment ever used for any data type
+            // > on the target machine you are compiling for.
+            0 => Some(crate::arch::STACK_ALIGN.into()),
+            1 => {
+                let align = self.expr(args.remove(0));
+                match Self::const_sint(align) {
+                    Ok(align) if align > 0 && (align as u64).is_power_of_two() => {
+                        Some(align as u64)
+                    }
+                    Ok(align) => {
+                        self.err(SemanticError::InvalidAlignment(align), location);
+                        None
+                    }
+                    Err(err) => {
+                        self.error_handler.push_back(err);
+                        None
+                    }
+                }
+            }
+            _ => {
+                let err = SemanticError::BadAttributeArgument(name, "one argument");
+                self.err(err, location);
+                None
+            }
+        }
+    }
+    /// `format(printf, 1, 2)`
+    fn format_attribute(
+        &mut self,
+        name: InternedStr,
+        symbol: &Variable,
+        mut args: Vec<ast::Expr>,
+        location: Location,
+    ) -> Option<FormatAttribute> {
+        let ftype = match &symbol.ctype {
+            Type::Function(ftype) => ftype,
+            _ => unreachable!("format should only be applied to functions"),
+        };
+        if args.len() != 3 {
+            let err = SemanticError::BadAttributeArgument(name, "three arguments");
+            self.err(err, location);
+            return None;
+        }
+        let kind = match &args[0].data {
+            ast::ExprType::Id(archetype) => match archetype.resolve_and_clone().as_str() {
+                "printf" | "__printf__" | "gnu_printf" => Some(FormatKind::Printf),
+                "scanf" | "__scanf__" | "gnu_scanf" => Some(FormatKind::Scanf),
+                _ => None,
+            },
+            _ => None,
+        };
+        let kind = match kind {
+            Some(kind) => kind,
+            None => {
+                let err = SemanticError::BadAttributeArgument(name, "'printf' or 'scanf'");
+                self.err(err, location);
+                return None;
+            }
+        };
+        let first_arg = self.expr(args.remove(2));
+        let format_index = self.expr(args.remove(1));
+        let (format_index, first_arg) =
+            match (Self::const_uint(format_index), Self::const_uint(first_arg)) {
+                (Ok(format), Ok(first)) => (format as usize, first as usize),
+                _ => {
+                    let err = SemanticError::BadAttributeArgument(name, "integer constants");
+                    self.err(err, location);
+                    return None;
+                }
+            };
+        // the format string must be a `char *`
+        let format_is_string = format_index
+            .checked_sub(1)
+            .and_then(|i| ftype.params.get(i))
+            .is_some_and(|param| match &param.get().ctype {
+                Type::Pointer(inner, _) => matches!(**inner, Type::Char(_)),
+                _ => false,
+            });
+        if !format_is_string {
+            let expected = "the index of a 'char *' parameter";
+            self.err(
+                SemanticError::BadAttributeArgument(name, expected),
+                location,
+            );
+            return None;
+        }
+        // the arguments to check must be the variadic ones, or 0 for `vprintf`-style functions
+        if first_arg != 0 && !(ftype.varargs && first_arg == ftype.params.len() + 1) {
+            let expected = "the index of the first variadic argument";
+            self.err(
+                SemanticError::BadAttributeArgument(name, expected),
+                location,
+            );
+            return None;
+        }
+        Some(FormatAttribute {
+            kind,
+            format_index,
+            first_arg,
+        })
+    }
+    /// `cleanup(free_ptr)`
+    ///
+    /// The cleanup function must take a single pointer to the type of the variable.
+    fn cleanup_function(
+        &mut self,
+        name: InternedStr,
+        symbol: &Variable,
+        args: Vec<ast::Expr>,
+        location: Location,
+    ) -> Option<Symbol> {
+        let func_name = match args.as_slice() {
+            [Locatable {
+                data: ast::ExprType::Id(func_name),
+                ..
+            }] => *func_name,
+            _ => {
+                let err = SemanticError::BadAttributeArgument(name, "the name of a function");
+                self.err(err, location);
+                return None;
+            }
+        };
+        let func = match self.scope.get(&func_name) {
+            Some(&func) => func,
+            None => {
+                self.err(SemanticError::UndeclaredVar(func_name), location);
+                return None;
+            }
+        };
+        let expected = Type::Pointer(Box::new(symbol.ctype.clone()), Qualifiers::default());
+        let valid = match &func.get().ctype {
+            Type::Function(ftype) if ftype.params.len() == 1 => {
+                match &ftype.params[0].get().ctype {
+                    Type::Pointer(pointee, _) => {
+                        **pointee == Type::Void || **pointee == symbol.ctype
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if valid {
+            Some(func)
+        } else {
+            let err = SemanticError::BadCleanupFunction(func_name, expected);
+            self.err(err, location);
+            None
+        }
+    }
+    /// Check the arguments to a function declared with `__attribute__((format(printf, 1, 2)))`.
+    ///
+    /// `args` are the arguments after default argument promotion, starting at `first_arg`.
+    /// This only checks the number of arguments and whether they have roughly the right type:
+    /// `int` vs. `long` is not checked.
+    pub(super) fn check_format(
+        &mut self,
+        kind: FormatKind,
+        format: &[u8],
+        args: &[(Type, Location)],
+        location: Location,
+    ) {
+        let conversions = match parse_format(kind, format) {
+            Some(conversions) => conversions,
+            // there was a conversion saltwater doesn't know about, so it can't tell what the arguments should be
+            None => return,
+        };
+        if conversions.len() != args.len() {
+            let warning = Warning::FormatArgumentCount(conversions.len(), args.len());
+            self.warn(warning, location);
+        }
+        for ((conversion, expected), (ctype, location)) in conversions.into_iter().zip(args) {
+            let matches = match expected {
+                FormatArg::Int => ctype.is_integral(),
+                FormatArg::Float => ctype.is_floating(),
+                FormatArg::String => match ctype {
+                    Type::Pointer(inner, _) => match &**inner {
+                        Type::Char(_) | Type::Void => true,
+                        other => other.is_integral(),
+                    },
+                    _ => false,
+                },
+                FormatArg::Pointer => ctype.is_pointer(),
+            };
+            if !matches && *ctype != Type::Error {
+                let warning =
+                    Warning::FormatArgumentType(conversion, expected.description(), ctype.clone());
+                self.warn(warning, *location);
+            }
+        }
+    }
+}
+
+/// Whether saltwater implements the attribute `name`.
+fn is_known(name: &str) -> bool {
+    matches!(
+        name,
+        "unused"
+            | "used"
+            | "noreturn"
+            | "aligned"
+            | "packed"
+            | "section"
+            | "weak"
+            | "visibility"
+            | "deprecated"
+            | "format"
+            | "constructor"
+            | "destructor"
+            | "cleanup"
+            | "fallthrough"
+            | "maybe_unused"
+            | "nodiscard"
+            | "warn_unused_result"
+            | "_Noreturn"
+            | "reproducible"
+            | "unsequenced"
+    )
+}
+
+/// The value of `__has_c_attribute(prefix::name)` in the preprocessor.
+///
+/// This is the date a standard attribute was added to C, 1 for GNU attributes saltwater implements
+/// (`gnu::packed`), and 0 for anything else.
+/// `name` should already have any surrounding underscores removed.
+#[allow(clippy::inconsistent_digit_grouping)]
+pub(crate) fn has_c_attribute(prefix: Option<&str>, name: &str) -> u32 {
+    match prefix {
+        None => match name {
+            "deprecated" | "fallthrough" | "maybe_unused" => 2019_04,
+            "nodiscard" => 2020_03,
+            "noreturn" | "_Noreturn" => 2022_02,
+            "reproducible" | "unsequenced" => 2022_07,
+            _ => 0,
+        },
+        Some("gnu") if is_known(name) => 1,
+        Some(_) => 0,
+    }
+}
+
+/// The optional message for `deprecated` or `nodiscard`, which is empty if not given.
+fn message_argument(args: &[ast::Expr]) -> Option<String> {
+    if args.is_empty() {
+        Some(String::new())
+    } else {
+        string_argument(args)
+    }
+}
+
+/// The contents of a single string literal argument: `"hidden"`
+fn string_argument(args: &[ast::Expr]) -> Option<String> {
+    match args {
+        [Locatable {
+            data: ast::ExprType::Literal(LiteralValue::Str(s)),
+            ..
+        }] => {
+            // remove the null terminator
+            let s = &s[..s.len() - 1];
+            Some(String::from_utf8_lossy(s).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// What sort of argument a conversion specification expects.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FormatArg {
+    Int,
+    Float,
+    String,
+    Pointer,
+}
+
+impl FormatArg {
+    fn description(self) -> &'static str {
+        match self {
+            FormatArg::Int => "an integer",
+            FormatArg::Float => "a floating-point number",
+            FormatArg::String => "a string",
+            FormatArg::Pointer => "a pointer",
+        }
+    }
+}
+
+/// Find all the conversion specifications in a format string.
+///
+/// Returns the text of each conversion along with the argument it expects,
+/// or `None` if there was a conversion that saltwater doesn't recognize.
+/// `*` widths and precisions are returned as separate `int` arguments.
+fn parse_format(kind: FormatKind, format: &[u8]) -> Option<Vec<(String, FormatArg)>> {
+    let mut conversions = Vec::new();
+    let mut chars = format.iter().copied().peekable();
+    while let Some(c) = chars.next() {
+        if c != b'%' {
+            continue;
+        }
+        let mut spec = String::new();
+        // printf("%%")
+        if chars.peek() == Some(&b'%') {
+            chars.next();
+            continue;
+        }
+        // scanf("%*d") reads an integer without storing it
+        let mut suppressed = false;
+        if kind == FormatKind::Scanf && chars.peek() == Some(&b'*') {
+            chars.next();
+            spec.push('*');
+            suppressed = true;
+        }
+        // flags, width, precision, and length modifiers
+        while let Some(&c) = chars.peek() {
+            match c {
+                b'*' if kind == FormatKind::Printf => {
+                    conversions.push(("*".to_string(), FormatArg::Int));
+                }
+                b'-' | b'+' | b' ' | b'#' | b'0'..=b'9' | b'.' | b'\'' => {}
+                b'h' | b'l' | b'L' | b'q' | b'j' | b'z' | b't' => {}
+                _ => break,
+            }
+            spec.push(c as char);
+            chars.next();
+        }
+        let conversion = chars.next()?;
+        spec.push(conversion as char);
+        let expected = match (kind, conversion) {
+            (FormatKind::Printf, b'd')
+            | (FormatKind::Printf, b'i')
+            | (FormatKind::Printf, b'o')
+            | (FormatKind::Printf, b'u')
+            | (FormatKind::Printf, b'x')
+            | (FormatKind::Printf, b'X')
+            | (FormatKind::Printf, b'c') => FormatArg::Int,
+            (FormatKind::Printf, b'e')
+            | (FormatKind::Printf, b'E')
+            | (FormatKind::Printf, b'f')
+            | (FormatKind::Printf, b'F')
+            | (FormatKind::Printf, b'g')
+            | (FormatKind::Printf, b'G')
+            | (FormatKind::Printf, b'a')
+            | (FormatKind::Printf, b'A') => FormatArg::Float,
+            (FormatKind::Printf, b's') => FormatArg::String,
+            (FormatKind::Printf, b'p') | (FormatKind::Printf, b'n') => FormatArg::Pointer,
+            // scanf("%[a-z]", s)
+            (FormatKind::Scanf, b'[') => {
+                // `]` is part of the set if it comes first: `%[]a]` or `%[^]a]`
+                if chars.peek() == Some(&b'^') {
+                    spec.push('^');
+                    chars.next();
+                }
+                if chars.peek() == Some(&b']') {
+                    spec.push(']');
+                    chars.next();
+                }
+                loop {
+                    let c = chars.next()?;
+                    spec.push(c as char);
+                    if c == b']' {
+                        break;
+                    }
+                }
+                FormatArg::Pointer
+            }
+            // everything in scanf is stored through a pointer
+            (FormatKind::Scanf, b'd')
+            | (FormatKind::Scanf, b'i')
+            | (FormatKind::Scanf, b'o')
+            | (FormatKind::Scanf, b'u')
+            | (FormatKind::Scanf, b'x')
+            | (FormatKind::Scanf, b'X')
+            | (FormatKind::Scanf, b'c')
+            | (FormatKind::Scanf, b'e')
+            | (FormatKind::Scanf, b'E')
+            | (FormatKind::Scanf, b'f')
+            | (FormatKind::Scanf, b'F')
+            | (FormatKind::Scanf, b'g')
+            | (FormatKind::Scanf, b'G')
+            | (FormatKind::Scanf, b'a')
+            | (FormatKind::Scanf, b'A')
+            | (FormatKind::Scanf, b's')
+            | (FormatKind::Scanf, b'p')
+            | (FormatKind::Scanf, b'n') => FormatArg::Pointer,
+            _ => return None,
+        };
+        if !suppressed {
+            conversions.push((spec, expected));
+        }
+    }
+    Some(conversions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_format, FormatArg::*};
+    use crate::analyze::test::{assert_errs_decls, decl};
+    use crate::analyze::Analyzer;
+    use crate::data::hir::*;
+    use crate::parse::test::parser;
+
+    fn printf(format: &str) -> Option<Vec<super::FormatArg>> {
+        parse_format(FormatKind::Printf, format.as_bytes())
+            .map(|conversions| conversions.into_iter().map(|(_, arg)| arg).collect())
+    }
+    fn scanf(format: &str) -> Option<Vec<super::FormatArg>> {
+        parse_format(FormatKind::Scanf, format.as_bytes())
+            .map(|conversions| conversions.into_iter().map(|(_, arg)| arg).collect())
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(printf("hello, world\n"), Some(vec![]));
+        assert_eq!(printf("100%%"), Some(vec![]));
+        assert_eq!(printf("%d %s %p"), Some(vec![Int, String, Pointer]));
+        assert_eq!(printf("%-10.3lf %llu"), Some(vec![Float, Int]));
+        assert_eq!(printf("%*.*s"), Some(vec![Int, Int, String]));
+        assert_eq!(printf("%y"), None);
+        assert_eq!(printf("trailing %"), None);
+        assert_eq!(scanf("%d %*d %lf"), Some(vec![Pointer, Pointer]));
+        assert_eq!(scanf("%[^]a-z] %10s"), Some(vec![Pointer, Pointer]));
+    }
+    #[test]
+    fn test_attributes() {
+        let noreturn = decl("__attribute__((noreturn)) void f();").unwrap();
+        assert!(noreturn.symbol.get().qualifiers.func.no_return);
+        let aligned = decl("int i __attribute__((aligned(16)));").unwrap();
+        assert_eq!(aligned.symbol.get().alignment, Some(16));
+        let weak = decl("int f() __attribute__((weak, visibility(\"hidden\")));").unwrap();
+        let weak = weak.symbol.get();
+        assert!(weak.attributes.weak);
+        assert_eq!(weak.attributes.visibility, Visibility::Hidden);
+        let section = decl("int i __attribute__((section(\".data.mine\")));").unwrap();
+        assert_eq!(
+            section.symbol.get().attributes.section.as_deref(),
+            Some(".data.mine")
+        );
+        let format =
+            decl("int f(const char *, ...) __attribute__((format(printf, 1, 2)));").unwrap();
+        assert_eq!(
+            format.symbol.get().attributes.format,
+            Some(FormatAttribute {
+                kind: FormatKind::Printf,
+                format_index: 1,
+                first_arg: 2
+            })
+        );
+        let constructor = decl("__attribute__((constructor(101))) void f();").unwrap();
+        assert_eq!(constructor.symbol.get().attributes.constructor, Some(101));
+
+        // unknown attributes
+        assert_errs_decls("int i __attribute__((not_an_attribute));", 0, 1, 1);
+        // attributes on the wrong sort of declaration
+        assert_errs_decls("int i __attribute__((noreturn));", 0, 1, 1);
+        assert_errs_decls("static int i __attribute__((weak));", 0, 1, 1);
+        assert_errs_decls("void f() __attribute__((cleanup(f)));", 0, 1, 1);
+        // invalid arguments
+        assert_errs_decls("int i __attribute__((aligned(3)));", 1, 0, 1);
+        assert_errs_decls("int i __attribute__((section(1)));", 1, 0, 1);
+        assert_errs_decls("int f() __attribute__((visibility(\"none\")));", 1, 0, 1);
+        assert_errs_decls("int f(int) __attribute__((format(printf, 1, 2)));", 1, 0, 1);
+        assert_errs_decls(
+            "int f(char *) __attribute__((format(printf, 1, 2)));",
+            1,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(char *, ...) __attribute__((format(strftime, 1, 2)));",
+            1,
+            0,
+            1,
+        );
+    }
+    #[test]
+    fn test_standard_attributes() {
+        let nodiscard = decl("[[nodiscard(\"check for errors\")]] int f(void);").unwrap();
+        assert_eq!(
+            nodiscard.symbol.get().attributes.nodiscard.as_deref(),
+            Some("check for errors")
+        );
+        let noreturn = decl("[[noreturn]] void f(void);").unwrap();
+        assert!(noreturn.symbol.get().qualifiers.func.no_return);
+        let unsequenced = decl("int f(int) [[unsequenced]];").unwrap();
+        let unsequenced = unsequenced.symbol.get();
+        assert!(unsequenced.attributes.unsequenced && unsequenced.attributes.reproducible);
+        let aligned = decl("[[gnu::aligned(16)]] int i;").unwrap();
+        assert_eq!(aligned.symbol.get().alignment, Some(16));
+
+        assert_errs_decls("[[maybe_unused]] int i;", 0, 0, 1);
+        assert_errs_decls("[[clang::noescape]] int i;", 0, 1, 1);
+        assert_errs_decls("[[nodiscard]] int i;", 0, 1, 1);
+        // diagnostics
+        assert_errs_decls(
+            "[[nodiscard]] int f(void); void g(void) { f(); (void)f(); }",
+            0,
+            1,
+            2,
+        );
+        assert_errs_decls(
+            "[[deprecated]] int f(void); int g(void) { return f(); }",
+            0,
+            1,
+            2,
+        );
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 1: i++; [[fallthrough]]; case 2: case 3: return i; default: ; } return 0; }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 1: [[fallthrough]]; i++; } return 0; }",
+            0,
+            1,
+            1,
+        );
+    }
+    #[test]
+    fn test_implicit_fallthrough() {
+        let warnings = |input: &str| {
+            let mut a = Analyzer::new(parser(input), false).implicit_fallthrough(true);
+            assert!(a.all(|res| res.is_ok()), "{} should compile", input);
+            a.inner.warnings().len()
+        };
+        let falls = "int f(int i) { switch (i) { case 1: i++; case 2: return i; } return 0; }";
+        assert_eq!(warnings(falls), 1);
+        // the warning is opt-in
+        let mut a = Analyzer::new(parser(falls), false);
+        assert!(a.all(|res| res.is_ok()));
+        assert!(a.inner.warnings().is_empty());
+        // declarations before the first label are never run
+        assert_eq!(
+            warnings("int f(int i) { switch (i) { int j; case 1: j = i; return j; case 2: return i; } return 0; }"),
+            0
+        );
+        // neither is code after a `return`
+        assert_eq!(
+            warnings("int f(int i) { switch (i) { case 1: return 1; i++; case 2: return i; } return 0; }"),
+            0
+        );
+        assert_eq!(
+            warnings("int f(int i) { switch (i) { case 1: ; case 2: i++; [[fallthrough]]; default: return i; } }"),
+            0
+        );
+    }
+    #[test]
+    fn test_struct_attributes() {
+        let packed = decl("struct __attribute__((packed)) { char c; int i; } s;").unwrap();
+        assert_eq!(packed.symbol.get().ctype.sizeof(), Ok(5));
+        let packed = decl("struct { char c; int i __attribute__((packed)); } s;").unwrap();
+        assert_eq!(packed.symbol.get().ctype.sizeof(), Ok(5));
+        let aligned = decl("struct { char c; } __attribute__((aligned(8))) s;").unwrap();
+        assert_eq!(aligned.symbol.get().ctype.alignof(), Ok(8));
+        assert_eq!(aligned.symbol.get().ctype.sizeof(), Ok(8));
+    }
+}
@@ -1,6 +1,6 @@
-use super::PureAnalyzer;
+use super::{FunctionAnalyzer, PureAnalyzer};
 use crate::arch;
-use crate::data::{hir::*, lex::ComparisonToken, *};
+use crate::data::{error::Warning, hir::*, lex::ComparisonToken, *};
 use crate::intern::InternedStr;
 
 impl PureAnalyzer {
@@ -12,8 +12,25 @@ impl PureAnalyzer {
         match expr.data {
             // 1 | "str" | 'a'
             Literal(lit) => literal(lit, expr.location),
+            // 1LL | 1.5L
+            LongLiteral(lit) => {
+                let mut expr = literal(lit, expr.location);
+                expr.ctype = match expr.ctype {
+                    Type::Long(signed) => Type::LongLong(signed),
+                    Type::Double => Type::LongDouble,
+                    other => other,
+                };
+                expr
+            }
             // x
             Id(id) => self.parse_id(id, expr.location),
+            // nullptr
+            Nullptr => Expr {
+                lval: false,
+                ctype: Type::NullPtr,
+                location: expr.location,
+                expr: ExprType::Literal(LiteralValue::Int(0)),
+            },
             // (int)x
             Cast(ctype, inner) => {
                 let ctype = self.parse_typename(ctype, expr.location);
@@ -54,7 +71,7 @@ impl PureAnalyzer {
             }
             // s->p desguars to (*s).p
             DerefMember(inner, id) => {
-                let inner = self.expr(*inner);
+                let inner = self.operand(*inner);
                 let struct_type = match &inner.ctype {
                     Type::Pointer(ctype, _) => match &**ctype {
                         Type::Union(_) | Type::Struct(_) => (**ctype).clone(),
@@ -78,7 +95,7 @@ impl PureAnalyzer {
             }
             // `*p` or `a[i]`
             Deref(inner) => {
-                let inner = self.expr(*inner);
+                let inner = self.operand(*inner);
                 match &inner.ctype {
                     Type::Array(t, _) | Type::Pointer(t, _) => {
                         let ctype = (**t).clone();
@@ -116,6 +133,10 @@ impl PureAnalyzer {
                     // > The operand of the unary & operator shall be either a function designator,
                     // > the result of a [] or unary * operator,
                     // > or an lvalue that designates an object that is not a bit-field and is not declared with the register storage-class specifier.
+                    _ if inner.is_bitfield() => {
+                        self.err(SemanticError::InvalidAddressOf("bit-field"), expr.location);
+                        inner
+                    }
                     _ if inner.lval => Expr {
                         lval: false,
                         location: expr.location,
@@ -138,9 +159,20 @@ impl PureAnalyzer {
             }
             // a[i]
             Index(left, right) => self.index(*left, *right, expr.location),
+            // (int){1}
+            CompoundLiteral(type_name, init) => {
+                self.compound_literal(*type_name, *init, expr.location)
+            }
             AlignofType(type_name) => {
-                let ctype = self.parse_typename(type_name, expr.location);
-                self.align(ctype, expr.location)
+                let parsed = self.parse_abstract_type(type_name, expr.location);
+                if parsed.qualifiers != Qualifiers::default() {
+                    self.warn(Warning::IgnoredQualifier(parsed.qualifiers), expr.location);
+                }
+                // typedef int T __attribute__((aligned(8))); _Alignof(T)
+                match parsed.typedef_alignment {
+                    Some(align) => literal(LiteralValue::UnsignedInt(align), expr.location),
+                    None => self.align(parsed.ctype, expr.location),
+                }
             }
             AlignofExpr(inner) => {
                 let inner = self.expr(*inner);
@@ -152,6 +184,14 @@ impl PureAnalyzer {
             }
             SizeofExpr(inner) => {
                 let inner = self.expr(*inner);
+                // > The sizeof operator shall not be applied to ... an expression that designates a bit-field member.
+                if inner.is_bitfield() {
+                    self.err(
+                        SemanticError::from("cannot take `sizeof` a bit-field"),
+                        expr.location,
+                    );
+                    return self.sizeof(Type::Error, expr.location);
+                }
                 self.sizeof(inner.ctype, expr.location)
             }
             BitwiseNot(inner) => self.bitwise_not(*inner),
@@ -159,6 +199,16 @@ impl PureAnalyzer {
             Negate(inner) => self.unary_add(*inner, false, expr.location),
             // !x
             LogicalNot(inner) => self.logical_not(*inner),
+            // __real__ z
+            Real(inner) => self.complex_part(*inner, false, expr.location),
+            // __imag__ z
+            Imag(inner) => self.complex_part(*inner, true, expr.location),
+            // __builtin_complex(1.0, 2.0)
+            BuiltinComplex(real, imag) => self.builtin_complex(*real, *imag, expr.location),
+            // __atomic_load_n(&a, __ATOMIC_SEQ_CST)
+            AtomicBuiltin(op, args) => self.atomic_builtin(op, args, expr.location),
+            // ({ int x = f(); x; })
+            StmtExpr(stmts) => self.stmt_expr(stmts, expr.location),
             // x && y
             LogicalAnd(left, right) => {
                 self.binary_helper(left, right, BinaryOp::LogicalAnd, Self::logical_bin_op)
@@ -183,10 +233,32 @@ impl PureAnalyzer {
                 }
             }
             Ternary(condition, then, otherwise) => {
-                self.ternary(*condition, *then, *otherwise, expr.location)
+                let condition = self.operand(*condition);
+                let (then, otherwise) = (self.operand(*then), self.operand(*otherwise));
+                self.ternary(condition, then, otherwise, expr.location)
+            }
+            // x ?: y
+            Elvis(condition, otherwise) => self.elvis(*condition, *otherwise, expr.location),
+            // &&label
+            LabelAddress(label) => self.label_address(label, expr.location),
+            Generic(controlling, associations) => {
+                self.generic_selection(*controlling, associations, expr.location)
             }
         }
     }
+    /// Analyze the operand of an operator that reads its value.
+    ///
+    /// > if the lvalue has atomic type, the value has the non-atomic version of the type of the lvalue
+    ///
+    /// so `_Atomic int i; i + 1` loads `i` before type checking the addition.
+    fn operand(&mut self, expr: ast::Expr) -> Expr {
+        let expr = self.expr(expr);
+        if expr.lval && expr.ctype.is_atomic() {
+            expr.rval()
+        } else {
+            expr
+        }
+    }
     // only meant for use with `expr`
     // TODO: change ast::Expr to use `ExprType::Binary` as well, which would make this unnecessary
     // TODO: these functions should have the locations of the parent expression, not the children
@@ -201,8 +273,8 @@ impl PureAnalyzer {
     where
         F: FnOnce(&mut Self, Expr, Expr, BinaryOp) -> Expr,
     {
-        let left = self.expr(*left);
-        let right = self.expr(*right);
+        let left = self.operand(*left);
+        let right = self.operand(*right);
         expr_checker(self, left, right, op)
     }
     // left OP right, where OP is an operation that requires integral types
@@ -246,6 +318,10 @@ impl PureAnalyzer {
                     self.err(SemanticError::TypedefInExpressionContext, location);
                     return pretend_zero;
                 }
+                // __attribute__((deprecated)) int i; return i;
+                if let Some(message) = &meta.attributes.deprecated {
+                    self.warn(Warning::Deprecated(name, message.clone()), location);
+                }
                 if let Type::Enum(ident, members) = &meta.ctype {
                     let mapper = |(member, value): &(InternedStr, i64)| {
                         if name == *member {
@@ -279,23 +355,46 @@ impl PureAnalyzer {
         token: ComparisonToken,
     ) -> Expr {
         let location = left.location.merge(right.location);
-        let mut left = self.expr(left);
-        let mut right = self.expr(right);
+        let mut left = self.operand(left);
+        let mut right = self.operand(right);
 
+        let is_equality =
+            token == ComparisonToken::EqualEqual || token == ComparisonToken::NotEqual;
         // i == i
         if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
+            // 6.5.8p2: each of the operands shall have real type or pointer type
+            if !is_equality && (left.ctype.is_complex() || right.ctype.is_complex()) {
+                self.err(
+                    SemanticError::InvalidRelationalType(
+                        token,
+                        left.ctype.clone(),
+                        right.ctype.clone(),
+                    ),
+                    location,
+                );
+            }
             let tmp = Expr::binary_promote(left, right, &mut self.error_handler);
             left = tmp.0;
             right = tmp.1;
         } else {
-            let (left_expr, right_expr) = (left.rval(), right.rval());
+            let (mut left_expr, mut right_expr) = (left.rval(), right.rval());
+            // nullptr == 0
+            // compare these as `nullptr_t` so that both sides have the same representation
+            if is_equality
+                && (left_expr.ctype == Type::NullPtr || right_expr.ctype == Type::NullPtr)
+                && left_expr.is_null()
+                && right_expr.is_null()
+            {
+                left_expr = left_expr.implicit_cast(&Type::NullPtr, &mut self.error_handler);
+                right_expr = right_expr.implicit_cast(&Type::NullPtr, &mut self.error_handler);
+            }
             // p1 == p2
             // first check that not already type error
             if left_expr.ctype != Type::Error
                 && right_expr.ctype != Type::Error // Maybe I should pull this out of the if...
                 && !((left_expr.ctype.is_pointer() && left_expr.ctype == right_expr.ctype)
                 // equality operations have different rules :(
-                || ((token == ComparisonToken::EqualEqual || token == ComparisonToken::NotEqual)
+                || (is_equality
                     // shoot me now
                     // (int*)p1 == (void*)p2
                     && ((left_expr.ctype.is_pointer() && right_expr.ctype.is_void_pointer())
@@ -304,7 +403,9 @@ impl PureAnalyzer {
                         // NULL == (int*)p2
                         || (left_expr.is_null() && right_expr.ctype.is_pointer())
                         // (int*)p1 == NULL
-                        || (left_expr.ctype.is_pointer() && right_expr.is_null()))))
+                        || (left_expr.ctype.is_pointer() && right_expr.is_null())
+                        // nullptr == nullptr
+                        || (left_expr.ctype == Type::NullPtr && right_expr.ctype == Type::NullPtr))))
             {
                 self.err(
                     SemanticError::InvalidRelationalType(
@@ -386,18 +487,24 @@ impl PureAnalyzer {
             }
             _ => {}
         };
+        // `p1 - p2`
+        // `p1 + p2` for pointers p1 and p2 is not valid
+        if !is_add && left.ctype.is_pointer_to_complete_object() {
+            left = left.rval();
+            right = right.rval();
+            if let (Type::Pointer(a, _), Type::Pointer(b, _)) = (&left.ctype, &right.ctype) {
+                if a.is_compatible(b) {
+                    let pointee = (**a).clone();
+                    return self.pointer_difference(left, right, pointee, location);
+                }
+            }
+        }
         // `i + i`
-        let (ctype, lval) = if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
+        let ctype = if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
             let tmp = Expr::binary_promote(left, right, &mut self.error_handler);
             left = tmp.0;
             right = tmp.1;
-            (left.ctype.clone(), false)
-        // `p1 - p2`
-        // `p1 + p2` for pointers p1 and p2 is not valid
-        } else if !is_add && left.ctype.is_pointer_to_complete_object() && left.ctype == right.ctype
-        {
-            // not sure what type to use here, C11 standard doesn't mention it
-            (left.ctype.clone(), true)
+            left.ctype.clone()
         } else {
             // check if already type error
             if left.ctype != Type::Error && right.ctype != Type::Error {
@@ -406,19 +513,24 @@ impl PureAnalyzer {
                     location,
                 );
             }
-            (left.ctype.clone(), false)
+            // don't report cascading errors
+            Type::Error
         };
         Expr {
             ctype,
-            lval,
+            lval: false,
             location,
             expr: ExprType::Binary(op, Box::new(left), Box::new(right)),
         }
     }
     // (int)i
     // 6.5.4 Cast operators
-    fn explicit_cast(&mut self, expr: ast::Expr, ctype: Type) -> Expr {
+    fn explicit_cast(&mut self, expr: ast::Expr, mut ctype: Type) -> Expr {
         let location = expr.location;
+        // the result of a cast is never an lvalue, so `(_Atomic int)i` is the same as `(int)i`
+        if let Type::Atomic(inner) = ctype {
+            ctype = *inner;
+        }
         let expr = self.expr(expr).rval();
         // (void)0;
         if ctype == Type::Void {
@@ -435,9 +547,9 @@ impl PureAnalyzer {
         if !ctype.is_scalar() {
             self.err(SemanticError::NonScalarCast(ctype.clone()), location);
         // (int*)1.0
-        } else if expr.ctype.is_floating() && ctype.is_pointer()
+        } else if (expr.ctype.is_floating() || expr.ctype.is_complex()) && ctype.is_pointer()
             // (float)(int*)p
-            || expr.ctype.is_pointer() && ctype.is_floating()
+            || expr.ctype.is_pointer() && (ctype.is_floating() || ctype.is_complex())
         {
             self.err(SemanticError::FloatPointerCast(ctype.clone()), location);
         // struct { int i; } s; (int)s
@@ -465,47 +577,98 @@ impl PureAnalyzer {
         location: Location,
     ) -> Expr {
         // the idea is to desugar to `base + sizeof(base)*index`
+        let offset = self.pointer_offset(&base.ctype, index, pointee, location);
+        Expr {
+            lval: false,
+            location,
+            ctype: base.ctype.clone(),
+            expr: ExprType::Binary(BinaryOp::Add, Box::new(base), Box::new(offset)),
+        }
+    }
+    /// `p1 - p2`: the number of elements between two pointers, with the type `ptrdiff_t`
+    ///
+    /// This desugars to `((long)p1 - (long)p2) / sizeof(*p1)`.
+    fn pointer_difference(
+        &mut self,
+        left: Expr,
+        right: Expr,
+        pointee: Type,
+        location: Location,
+    ) -> Expr {
+        let ptrdiff = Type::Long(true);
+        let cast = |expr: Expr| Expr {
+            lval: false,
+            location: expr.location,
+            ctype: ptrdiff.clone(),
+            expr: ExprType::Cast(Box::new(expr)),
+        };
+        let bytes = Expr {
+            lval: false,
+            location,
+            ctype: ptrdiff.clone(),
+            expr: ExprType::Binary(BinaryOp::Sub, Box::new(cast(left)), Box::new(cast(right))),
+        };
+        let size = cast(self.sizeof(pointee, location));
+        Expr {
+            lval: false,
+            location,
+            ctype: ptrdiff,
+            expr: ExprType::Binary(BinaryOp::Div, Box::new(bytes), Box::new(size)),
+        }
+    }
+    /// `sizeof(*base)*index`, with the type `ptr_type`
+    fn pointer_offset(
+        &mut self,
+        ptr_type: &Type,
+        index: Expr,
+        pointee: &Type,
+        location: Location,
+    ) -> Expr {
         let offset = Expr {
             lval: false,
             location: index.location,
             expr: ExprType::Cast(Box::new(index)),
-            ctype: base.ctype.clone(),
+            ctype: ptr_type.clone(),
         }
         .rval();
-        let size = match pointee.sizeof() {
-            Ok(s) => s,
-            Err(_) => {
-                self.err(
-                    SemanticError::PointerAddUnknownSize(base.ctype.clone()),
-                    location,
-                );
-                1
-            }
+        let size = if pointee.is_vla() {
+            // double (*p)[n]; p + 1
+            self.sizeof(pointee.clone(), offset.location)
+        } else {
+            let size = match pointee.sizeof() {
+                Ok(s) => s,
+                Err(_) => {
+                    self.err(
+                        SemanticError::PointerAddUnknownSize(ptr_type.clone()),
+                        location,
+                    );
+                    1
+                }
+            };
+            literal(LiteralValue::UnsignedInt(size), offset.location)
         };
-        let size_literal = literal(LiteralValue::UnsignedInt(size), offset.location);
         let size_cast = Expr {
             lval: false,
             location: offset.location,
             ctype: offset.ctype.clone(),
-            expr: ExprType::Cast(Box::new(size_literal)),
+            expr: ExprType::Cast(Box::new(size)),
         };
-        let offset = Expr {
+        Expr {
             lval: false,
             location: offset.location,
             ctype: offset.ctype.clone(),
             expr: ExprType::Binary(BinaryOp::Mul, Box::new(size_cast), Box::new(offset)),
-        };
-        Expr {
-            lval: false,
-            location,
-            ctype: base.ctype.clone(),
-            expr: ExprType::Binary(BinaryOp::Add, Box::new(base), Box::new(offset)),
         }
     }
     // `func(args)`
     // 6.5.2.2 Function calls
     fn func_call(&mut self, func: ast::Expr, args: Vec<ast::Expr>) -> Expr {
         let mut func = self.expr(func);
+        // int printf(const char *, ...) __attribute__((format(printf, 1, 2)));
+        let format = match func.expr {
+            ExprType::Id(symbol) => symbol.get().attributes.format,
+            _ => None,
+        };
         // if fp is a function pointer, fp() desugars to (*fp)()
         match &func.ctype {
             Type::Pointer(pointee, _) if pointee.is_function() => {
@@ -532,6 +695,8 @@ impl PureAnalyzer {
             expected = 0;
         }
         // f() takes _any_ number of arguments
+        // NOTE: this also rejects calling an old-style function with the wrong number of arguments,
+        // which is undefined behavior
         if !functype.params.is_empty()
             // `int f(int); f()` or `int f(int); f(1, 2)`
             && (args.len() < expected || args.len() > expected && !functype.varargs)
@@ -542,9 +707,34 @@ impl PureAnalyzer {
             );
         }
         let mut promoted_args = vec![];
+        let mut format_string = None;
+        let mut format_args = Vec::new();
         for (i, arg) in args.into_iter().enumerate() {
             let arg = self.expr(arg);
+            if let Some(format) = format {
+                match &arg.expr {
+                    ExprType::Literal(LiteralValue::Str(s)) if i + 1 == format.format_index => {
+                        format_string = Some(s.clone());
+                    }
+                    _ => {}
+                }
+            }
             let promoted = match functype.params.get(i) {
+                // int f(a) double a; {} f(1)
+                // The standard says this is undefined behavior unless the promoted type matches,
+                // but the backend can't call a function with the wrong argument types, so convert it anyway.
+                Some(expected) if functype.old_style => {
+                    let arg = self.default_promote(arg);
+                    let expected = expected.get().ctype.clone();
+                    if arg.ctype != expected {
+                        let warning = format!(
+                            "passing '{}' to parameter of type '{}' of an old-style function",
+                            arg.ctype, expected
+                        );
+                        self.warn(warning.into(), arg.location);
+                    }
+                    arg.implicit_cast(&expected, &mut self.error_handler)
+                }
                 // int f(int); f(1)
                 Some(expected) => arg
                     .rval()
@@ -552,8 +742,20 @@ impl PureAnalyzer {
                 // `int f(); f(1)` or `int f(int, ...); f(1, 2)`
                 None => self.default_promote(arg),
             };
+            if let Some(format) = format {
+                if format.first_arg != 0 && i + 1 >= format.first_arg {
+                    format_args.push((promoted.ctype.clone(), promoted.location));
+                }
+            }
             promoted_args.push(promoted);
         }
+        // printf("%d\n", 1)
+        // only string literals are checked, since the format could be anything at runtime
+        if let (Some(format), Some(format_string)) = (format, format_string) {
+            // remove the null terminator
+            let format_string = &format_string[..format_string.len() - 1];
+            self.check_format(format.kind, format_string, &format_args, func.location);
+        }
         Expr {
             location: func.location,
             lval: false, // no move semantics here!
@@ -618,7 +820,9 @@ impl PureAnalyzer {
         let expr = self.expr(expr);
         if let Err(err) = expr.modifiable_lval() {
             self.err(err, location);
-        } else if !(expr.ctype.is_arithmetic() || expr.ctype.is_pointer()) {
+        // 6.5.2.4p1: The operand of the postfix increment or decrement operator
+        // shall have atomic, qualified, or unqualified real or pointer type
+        } else if !(expr.ctype.non_atomic().is_real() || expr.ctype.non_atomic().is_pointer()) {
             // check if already encountered type error
             if expr.ctype != Type::Error {
                 self.err(
@@ -631,7 +835,7 @@ impl PureAnalyzer {
         if prefix {
             let rval = Expr {
                 lval: false,
-                ctype: expr.ctype.clone(),
+                ctype: expr.ctype.non_atomic().clone(),
                 location,
                 expr: ExprType::Cast(Box::new(literal(LiteralValue::Int(1), location))),
             };
@@ -648,7 +852,7 @@ impl PureAnalyzer {
         } else {
             Expr {
                 lval: false,
-                ctype: expr.ctype.clone(),
+                ctype: expr.ctype.non_atomic().clone(),
                 // true, false: increment/decrement
                 expr: ExprType::PostIncrement(Box::new(expr), increment),
                 location,
@@ -674,11 +878,87 @@ impl PureAnalyzer {
                 return left;
             }
         };
-        let mut addr = self.pointer_arithmetic(array, index, &target_type, location);
-        addr.ctype = target_type;
+        let addr = self.pointer_arithmetic(array, index, &target_type, location);
         // `p + i` -> `*(p + i)`
-        addr.lval = true;
-        addr
+        // the addition has to keep the pointer type, otherwise `double a[3]; a[i]` would be a float addition
+        addr.indirection(true, target_type)
+    }
+    // (struct point){ .x = 1, .y = 2 }
+    // 6.5.2.5 Compound literals
+    fn compound_literal(
+        &mut self,
+        type_name: ast::TypeName,
+        init: ast::Initializer,
+        location: Location,
+    ) -> Expr {
+        let (mut ctype, qualifiers) = self.parse_qualified_typename(type_name, location);
+        let is_unbounded_array = matches!(ctype, Type::Array(_, types::ArrayType::Unbounded));
+        // > The type name shall specify a complete object type or an array of unknown size,
+        // > but not a variable length array type.
+        if ctype.is_vla() || !ctype.is_complete() && !is_unbounded_array {
+            let err = if ctype.is_vla() {
+                SemanticError::VlaCompoundLiteral(ctype)
+            } else {
+                SemanticError::IncompleteCompoundLiteral(ctype)
+            };
+            self.err(err, location);
+            let mut pretend_zero = Expr::zero(location);
+            pretend_zero.ctype = Type::Error;
+            return pretend_zero;
+        }
+        let init = self.parse_initializer(init, &ctype, location);
+        // (int[]){1, 2, 3}
+        ctype.complete_array_type(&init);
+        self.compound_literals += 1;
+        let is_global = self.scope.is_global();
+        // `static int *p = (int[]){1, 2};` in a function also has static storage duration
+        let is_static = is_global || self.local_static_init;
+        self.check_flexible_initializer(&ctype, &init, is_static, location);
+        let symbol = Variable {
+            id: format!("compound_literal.{}", self.compound_literals).into(),
+            ctype: ctype.clone(),
+            // `(const int){1} = 2;` is an error
+            qualifiers,
+            storage_class: if is_static {
+                StorageClass::Static
+            } else {
+                StorageClass::Auto
+            },
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Attributes::default(),
+        }
+        .insert();
+        // > If the compound literal occurs outside the body of a function,
+        // > the object has static storage duration;
+        // > otherwise, it has automatic storage duration associated with the enclosing block.
+        let expr = if is_static {
+            // declare the object before the declaration that uses it
+            let decl = Locatable::new(
+                Declaration {
+                    symbol,
+                    init: Some(init),
+                },
+                location,
+            );
+            if is_global {
+                self.pending.push_back(decl);
+            } else {
+                self.decl_side_channel.push(decl);
+            }
+            ExprType::Id(symbol)
+        } else {
+            ExprType::CompoundLiteral(symbol, Box::new(init))
+        };
+        // > The result is an lvalue.
+        Expr {
+            expr,
+            ctype,
+            lval: true,
+            location,
+        }
     }
     // _Alignof(int)
     fn align(&mut self, ctype: Type, location: Location) -> Expr {
@@ -691,6 +971,15 @@ impl PureAnalyzer {
     // sizeof(int)
     // 6.5.3.4 The sizeof and _Alignof operators
     fn sizeof(&mut self, ctype: Type, location: Location) -> Expr {
+        // int a[n]; sizeof(a)
+        if ctype.is_vla() {
+            return Expr {
+                lval: false,
+                location,
+                ctype: Type::Long(false),
+                expr: ExprType::Sizeof(ctype),
+            };
+        }
         let align = ctype.sizeof().unwrap_or_else(|err| {
             if ctype != Type::Error {
                 self.err(err.into(), location);
@@ -699,10 +988,248 @@ impl PureAnalyzer {
         });
         literal(LiteralValue::UnsignedInt(align), location)
     }
+    // _Generic(x, int: a, default: b)
+    // 6.5.1.1 Generic selection
+    fn generic_selection(
+        &mut self,
+        controlling: ast::Expr,
+        associations: Vec<ast::GenericAssociation>,
+        location: Location,
+    ) -> Expr {
+        // > The controlling expression of a generic selection is not evaluated.
+        // > [Its type] is the type of the expression as if it had undergone an lvalue conversion
+        // lvalue conversion also drops qualifiers, so `int *const` matches `int *`
+        let mut ctype = self.expr(controlling).rval().ctype;
+        if let Type::Pointer(_, qualifiers) = &mut ctype {
+            *qualifiers = Qualifiers::default();
+        }
+        let mut default = None;
+        let mut selected = None;
+        let mut seen = Vec::new();
+        for association in associations {
+            let type_name = match association.ctype {
+                Some(type_name) => type_name,
+                None => {
+                    let expr = self.expr(association.expr);
+                    // > A generic selection shall have no more than one default generic association.
+                    if default.is_some() {
+                        self.err(SemanticError::DuplicateGenericDefault, expr.location);
+                    } else {
+                        default = Some(expr);
+                    }
+                    continue;
+                }
+            };
+            let assoc_type = self.parse_typename(type_name.data, type_name.location);
+            // Every association is checked, even though all but one are discarded:
+            // `_Generic(1, int: 1, char *: undeclared)` is still an error.
+            let expr = self.expr(association.expr);
+            if assoc_type == Type::Error {
+                continue;
+            }
+            // > The type name in a generic association shall specify a complete object type other than a variably modified type.
+            if !assoc_type.is_complete() {
+                self.err(
+                    SemanticError::InvalidGenericType(assoc_type),
+                    type_name.location,
+                );
+                continue;
+            }
+            // > No two generic associations in the same generic selection shall specify compatible types.
+            if seen.contains(&assoc_type) {
+                self.err(
+                    SemanticError::DuplicateGenericType(assoc_type),
+                    type_name.location,
+                );
+                continue;
+            }
+            if assoc_type == ctype {
+                selected = Some(expr);
+            }
+            seen.push(assoc_type);
+        }
+        // > The type and value of a generic selection are identical to those of its result expression.
+        // > It is an lvalue, a function designator, or a void expression if its result expression is
+        match selected.or(default) {
+            Some(result) => result,
+            None => {
+                if ctype != Type::Error {
+                    self.err(SemanticError::NoGenericMatch(ctype), location);
+                }
+                let mut pretend_zero = Expr::zero(location);
+                pretend_zero.ctype = Type::Error;
+                pretend_zero
+            }
+        }
+    }
+    // `__real__ z` or `__imag__ z`
+    // GNU extension: if `z` is an lvalue, so is the result
+    fn complex_part(&mut self, expr: ast::Expr, imag: bool, location: Location) -> Expr {
+        let expr = self.expr(expr);
+        match &expr.ctype {
+            Type::Complex(real) => {
+                let ctype = (**real).clone();
+                let lval = expr.lval;
+                let constructor = if imag { ExprType::Imag } else { ExprType::Real };
+                let part = Expr {
+                    ctype,
+                    lval: true,
+                    location,
+                    expr: constructor(Box::new(expr)),
+                };
+                if lval {
+                    part
+                } else {
+                    part.rval()
+                }
+            }
+            // for real types, `__real__ x` is `x` and `__imag__ x` is 0
+            ctype if ctype.is_real() => {
+                if !imag {
+                    return expr;
+                }
+                let expr = expr.rval();
+                let zero = Expr::zero(location).implicit_cast(&expr.ctype, &mut self.error_handler);
+                Expr {
+                    ctype: expr.ctype.clone(),
+                    lval: false,
+                    location,
+                    expr: ExprType::Comma(Box::new(expr), Box::new(zero)),
+                }
+            }
+            Type::Error => expr,
+            other => {
+                self.err(SemanticError::NotComplex(other.clone()), location);
+                expr
+            }
+        }
+    }
+    // `__builtin_complex(re, im)`
+    // GNU extension used to implement `CMPLX` in <complex.h>
+    fn builtin_complex(&mut self, real: ast::Expr, imag: ast::Expr, location: Location) -> Expr {
+        let real = self.expr(real).rval();
+        let imag = self.expr(imag).rval();
+        // > both arguments must have the same real floating type
+        if !(real.ctype.is_floating() && real.ctype == imag.ctype)
+            && real.ctype != Type::Error
+            && imag.ctype != Type::Error
+        {
+            self.err(
+                SemanticError::BuiltinComplexArgs(real.ctype.clone(), imag.ctype.clone()),
+                location,
+            );
+        }
+        Expr {
+            ctype: Type::Complex(Box::new(real.ctype.clone())),
+            lval: false,
+            location,
+            expr: ExprType::Complex(Box::new(real), Box::new(imag)),
+        }
+    }
+    // `({ int x = f(); x; })`
+    // GNU extension, used in macros that need to evaluate their arguments only once
+    fn stmt_expr(&mut self, stmts: ast::CompoundStatement, location: Location) -> Expr {
+        if self.function.is_none() {
+            // int a[({ 1; })];
+            self.err(SemanticError::StmtExprOutsideFunction, location);
+            let mut pretend_zero = Expr::zero(location);
+            pretend_zero.ctype = Type::Error;
+            return pretend_zero;
+        }
+        FunctionAnalyzer { analyzer: self }.stmt_expr(stmts, location)
+    }
+    // `__atomic_fetch_add(&a, 1, __ATOMIC_SEQ_CST)`
+    // GNU extension used to implement <stdatomic.h>
+    //
+    // Every operation is sequentially consistent, so the memory orders are evaluated and then ignored.
+    fn atomic_builtin(
+        &mut self,
+        op: lex::AtomicOp,
+        args: Vec<ast::Expr>,
+        location: Location,
+    ) -> Expr {
+        use lex::AtomicOp::*;
+
+        let args: Vec<_> = args.into_iter().map(|arg| self.expr(arg).rval()).collect();
+        if args.len() != op.arity() {
+            self.err(SemanticError::AtomicArguments(op, args.len()), location);
+            let mut pretend_zero = Expr::zero(location);
+            pretend_zero.ctype = Type::Error;
+            return pretend_zero;
+        }
+        // the type of the object being accessed, without `_Atomic`
+        let object = match (op, &args[0].ctype) {
+            (ThreadFence, _) => Type::Void,
+            (_, Type::Pointer(pointee, _))
+                if pointee.non_atomic().is_real() || pointee.non_atomic().is_pointer() =>
+            {
+                pointee.non_atomic().clone()
+            }
+            (_, Type::Error) => Type::Error,
+            (_, other) => {
+                self.err(SemanticError::AtomicPointer(other.clone()), location);
+                Type::Error
+            }
+        };
+        let valid = match op {
+            FetchAdd | FetchSub => object.is_integral() || object.is_pointer(),
+            FetchAnd | FetchOr | FetchXor => object.is_integral(),
+            _ => true,
+        };
+        if !valid && object != Type::Error {
+            let err = SemanticError::InvalidAtomicOperation(op, object.clone());
+            self.err(err, location);
+        }
+        let first_order = match op {
+            ThreadFence => 0,
+            Load => 1,
+            CompareExchange => 3,
+            _ => 2,
+        };
+        let mut converted = Vec::with_capacity(args.len());
+        for (i, arg) in args.into_iter().enumerate() {
+            let arg = match i {
+                // the pointer to the object
+                0 if op != ThreadFence => arg,
+                // `expected` points to a non-atomic object
+                1 if op == CompareExchange => {
+                    let ptr_type = Type::Pointer(Box::new(object.clone()), Qualifiers::default());
+                    arg.implicit_cast(&ptr_type, &mut self.error_handler)
+                }
+                // `atomic_fetch_add(&p, 1)` adds `sizeof(*p)`, like `p + 1`
+                1 if object.is_pointer() && (op == FetchAdd || op == FetchSub) => {
+                    if !arg.ctype.is_integral() {
+                        self.err(SemanticError::NonIntegralExpr(arg.ctype.clone()), location);
+                    }
+                    let pointee = match &object {
+                        Type::Pointer(pointee, _) => (**pointee).clone(),
+                        _ => unreachable!(),
+                    };
+                    self.pointer_offset(&object, arg, &pointee, location)
+                }
+                // the value to store
+                i if i < first_order => arg.implicit_cast(&object, &mut self.error_handler),
+                // `weak` and the memory orders
+                _ => arg.implicit_cast(&Type::Int(true), &mut self.error_handler),
+            };
+            converted.push(arg);
+        }
+        let ctype = match op {
+            Store | ThreadFence => Type::Void,
+            CompareExchange => Type::Bool,
+            _ => object,
+        };
+        Expr {
+            ctype,
+            lval: false,
+            location,
+            expr: ExprType::AtomicBuiltin(op, converted),
+        }
+    }
     // ~expr
     // 6.5.3.3 Unary arithmetic operators
     fn bitwise_not(&mut self, expr: ast::Expr) -> Expr {
-        let expr = self.expr(expr);
+        let expr = self.operand(expr);
         if !expr.ctype.is_integral() {
             // check if already error
             if expr.ctype != Type::Error {
@@ -725,7 +1252,7 @@ impl PureAnalyzer {
     // -x and +x
     // 6.5.3.3 Unary arithmetic operators
     fn unary_add(&mut self, expr: ast::Expr, add: bool, location: Location) -> Expr {
-        let expr = self.expr(expr);
+        let expr = self.operand(expr);
         if !expr.ctype.is_arithmetic() {
             // check if already error
             if expr.ctype != Type::Error {
@@ -753,7 +1280,7 @@ impl PureAnalyzer {
     // 6.5.3.3 Unary arithmetic operators
     // > The expression !E is equivalent to (0==E).
     fn logical_not(&mut self, expr: ast::Expr) -> Expr {
-        let expr = self.expr(expr);
+        let expr = self.operand(expr);
         let boolean = expr.truthy(&mut self.error_handler);
         debug_assert_eq!(boolean.ctype, Type::Bool);
         let zero = Expr::zero(boolean.location).implicit_cast(&Type::Bool, &mut self.error_handler);
@@ -786,14 +1313,14 @@ impl PureAnalyzer {
     // like an `if` in Rust: evaluate `condition`, yield the value of `then` if true, otherwise yield the value of `otherwise`
     fn ternary(
         &mut self,
-        condition: ast::Expr,
-        then: ast::Expr,
-        otherwise: ast::Expr,
+        condition: Expr,
+        then: Expr,
+        otherwise: Expr,
         location: Location,
     ) -> Expr {
-        let condition = self.expr(condition).truthy(&mut self.error_handler);
-        let mut then = self.expr(then).rval();
-        let mut otherwise = self.expr(otherwise).rval();
+        let condition = condition.truthy(&mut self.error_handler);
+        let mut then = then.rval();
+        let mut otherwise = otherwise.rval();
 
         if then.ctype.is_arithmetic() && otherwise.ctype.is_arithmetic() {
             let (tmp1, tmp2) = Expr::binary_promote(then, otherwise, &mut self.error_handler);
@@ -817,6 +1344,57 @@ impl PureAnalyzer {
             expr: ExprType::Ternary(Box::new(condition), Box::new(then), Box::new(otherwise)),
         }
     }
+    // `x ?: y`
+    // GNU extension: the same as `x ? x : y`, except that `x` is only evaluated once
+    fn elvis(&mut self, condition: ast::Expr, otherwise: ast::Expr, location: Location) -> Expr {
+        let condition = self.expr(condition).rval();
+        let condition = match condition.clone().const_fold() {
+            Ok(folded) => folded,
+            Err(_) => condition,
+        };
+        let otherwise = self.expr(otherwise);
+        // constants have no side effects, so they can be evaluated twice.
+        // Outside a function, only constants are allowed anyway.
+        let is_constant = matches!(
+            condition.expr,
+            ExprType::Literal(_) | ExprType::StaticRef(_)
+        );
+        if is_constant || self.function.is_none() {
+            return self.ternary(condition.clone(), condition, otherwise, location);
+        }
+        // `tmp = x, tmp ? tmp : y`
+        let ctype = condition.ctype.clone();
+        let tmp = self.hidden_variable("cond", ctype.clone(), None, location);
+        let store = Expr {
+            ctype,
+            lval: false,
+            location,
+            expr: ExprType::Binary(BinaryOp::Assign, Box::new(tmp.clone()), Box::new(condition)),
+        };
+        let ternary = self.ternary(tmp.clone(), tmp, otherwise, location);
+        Expr {
+            ctype: ternary.ctype.clone(),
+            lval: false,
+            location,
+            expr: ExprType::Comma(Box::new(store), Box::new(ternary)),
+        }
+    }
+    // `&&label`
+    // GNU extension used for computed goto: `goto *labels[i];`
+    fn label_address(&mut self, label: InternedStr, location: Location) -> Expr {
+        if let Some(state) = &mut self.function {
+            // checked for existence at the end of the function
+            state.label_addresses.push(Locatable::new(label, location));
+        } else {
+            self.err(SemanticError::LabelAddressOutsideFunction, location);
+        }
+        Expr {
+            ctype: Type::Pointer(Box::new(Type::Void), Qualifiers::default()),
+            lval: false,
+            location,
+            expr: ExprType::LabelAddress(label),
+        }
+    }
 
     // `a = b` or `a += b`
     fn assignment_expr(
@@ -836,7 +1414,7 @@ impl PureAnalyzer {
                 rval = rval.implicit_cast(&lval.ctype, &mut self.error_handler);
             }
             return Expr {
-                ctype: lval.ctype.clone(),
+                ctype: lval.ctype.non_atomic().clone(),
                 lval: false, // `(i = j) = 4`; is invalid
                 location,
                 expr: ExprType::Binary(BinaryOp::Assign, Box::new(lval), Box::new(rval)),
@@ -848,65 +1426,129 @@ impl PureAnalyzer {
         // `tmp = &f(); *tmp = *tmp + 1;`
         // see also footnote 113 which has a similar algorithm (but is more convoluted because of atomics)
 
-        // declare tmp in a new hidden scope
-        // We really should only be modifying the scope in `FunctionAnalyzer`,
-        // but assignment expressions can never appear in an initializer anyway.
-        self.scope.enter();
-        let tmp_name = "tmp".into();
+        // Bit-fields don't have an address, so use the address of the struct instead:
+        // `tmp = &s; tmp->flags = tmp->flags | 1;`
+        let (lval, bitfield) = if lval.is_bitfield() {
+            match lval.expr {
+                ExprType::Member(cstruct, id) => (*cstruct, Some((id, lval.ctype))),
+                _ => unreachable!("only members can be bit-fields"),
+            }
+        } else {
+            (lval, None)
+        };
+
         let ctype = lval.ctype.clone();
         // TODO: we could probably make these qualifiers stronger
         let ptr_type = Type::Pointer(Box::new(ctype.clone()), Qualifiers::default());
-        let meta = Variable {
-            id: tmp_name,
-            ctype: ptr_type.clone(),
-            qualifiers: Qualifiers::NONE,
-            storage_class: StorageClass::Register,
-        };
-        let tmp_var = self.declare(meta, true, location);
-
         // NOTE: this does _not_ call rval() on `lval`
         // there's no way to do this in C natively - the closest is `&var`, but that doesn't work on expressions
         // `T tmp = &*f()` or `T tmp = &sum`
         let init = Some(Initializer::Scalar(Box::new(lval)));
-        let decl = Declaration {
-            symbol: tmp_var,
-            init,
-        };
-        self.decl_side_channel.push(Locatable::new(decl, location));
-        self.scope.exit();
         // load `tmp`, i.e. `&*f()`, only evaluated once
-        let tmp = Expr {
-            expr: ExprType::Id(tmp_var),
-            ctype: ptr_type,
-            lval: true,
-            location,
-        }
-        // this `rval` is because we have the (pointless) address of `tmp`
-        // instead we want the address of the lval
-        .rval();
+        let tmp = self
+            .hidden_variable("tmp", ptr_type, init, location)
+            // this `rval` is because we have the (pointless) address of `tmp`
+            // instead we want the address of the lval
+            .rval();
 
-        // before we had `&sum`, now we have `sum`
-        // `*tmp`, i.e. `*f()`
-        let lval_as_rval = Expr {
-            ctype: ctype.clone(),
-            lval: false,
-            location,
-            // this clone is pretty cheap since `tmp_assign_expr` is just an id
-            expr: ExprType::Deref(Box::new(tmp.clone())),
+        let (lval_as_rval, target, ctype) = if let Some((id, member_type)) = bitfield {
+            // `tmp->flags` in an lval context
+            let target = Expr {
+                ctype: member_type.clone(),
+                lval: true,
+                location,
+                expr: ExprType::Member(Box::new(tmp.indirection(true, ctype)), id),
+            };
+            (target.clone().rval(), target, member_type)
+        } else {
+            // before we had `&sum`, now we have `sum`
+            // `*tmp` in an lval context
+            let target = tmp.indirection(true, ctype.clone());
+            // `*tmp`, i.e. `*f()`
+            // this clone is pretty cheap since `tmp` is just an id
+            (target.clone().rval(), target, ctype)
+        };
+        let ctype = ctype.non_atomic().clone();
+        let mut rval = rval.rval();
+        // Atomic objects are updated while holding a lock, so the right side has to be evaluated beforehand:
+        // it could call a function that uses atomics itself.
+        // `rhs = f(), atomic { *tmp = *tmp + rhs }`
+        let store_rval = if target.ctype.is_atomic() {
+            let rhs = self.hidden_variable("rhs", rval.ctype.clone(), None, location);
+            let store = Expr {
+                ctype: rval.ctype.clone(),
+                lval: false,
+                location,
+                expr: ExprType::Binary(BinaryOp::Assign, Box::new(rhs.clone()), Box::new(rval)),
+            };
+            rval = rhs.rval();
+            Some(store)
+        } else {
+            None
         };
-        // `*tmp` in an lval context
-        let target = tmp.indirection(true, ctype.clone());
         // `*tmp + 1`
         let new_val = self
-            .desugar_op(lval_as_rval, rval.rval(), token)
+            .desugar_op(lval_as_rval, rval, token)
             .implicit_cast(&target.ctype, &mut self.error_handler);
 
         // *tmp = *f() + 1
-        Expr {
-            ctype,
+        let assign = Expr {
+            ctype: ctype.clone(),
             lval: false,
             location,
             expr: ExprType::Binary(BinaryOp::Assign, Box::new(target), Box::new(new_val)),
+        };
+        match store_rval {
+            Some(store) => {
+                let update = Expr {
+                    ctype: ctype.clone(),
+                    lval: false,
+                    location,
+                    expr: ExprType::AtomicUpdate(Box::new(assign)),
+                };
+                Expr {
+                    ctype,
+                    lval: false,
+                    location,
+                    expr: ExprType::Comma(Box::new(store), Box::new(update)),
+                }
+            }
+            None => assign,
+        }
+    }
+    /// Declare a variable named `name` in a new hidden scope and return it as an lval.
+    ///
+    /// `init` is evaluated before the current statement.
+    /// We really should only be modifying the scope in `FunctionAnalyzer`,
+    /// but assignment expressions can never appear in an initializer anyway.
+    fn hidden_variable(
+        &mut self,
+        name: &str,
+        ctype: Type,
+        init: Option<Initializer>,
+        location: Location,
+    ) -> Expr {
+        self.scope.enter();
+        let meta = Variable {
+            id: name.into(),
+            ctype: ctype.clone(),
+            qualifiers: Qualifiers::NONE,
+            storage_class: StorageClass::Register,
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Attributes::default(),
+        };
+        let symbol = self.declare(meta, true, location);
+        let decl = Declaration { symbol, init };
+        self.decl_side_channel.push(Locatable::new(decl, location));
+        self.scope.exit();
+        Expr {
+            expr: ExprType::Id(symbol),
+            ctype,
+            lval: true,
+            location,
         }
     }
     fn desugar_op(&mut self, left: Expr, right: Expr, token: lex::AssignmentToken) -> Expr {
@@ -936,6 +1578,8 @@ pub(super) fn literal(literal: LiteralValue, location: Location) -> Expr {
         LiteralValue::Char(_) => Type::Char(true),
         LiteralValue::Int(_) => Type::Long(true),
         LiteralValue::UnsignedInt(_) => Type::Long(false),
+        LiteralValue::Int128(_) => Type::Int128(true),
+        LiteralValue::UnsignedInt128(_) => Type::Int128(false),
         LiteralValue::Float(_) => Type::Double,
         LiteralValue::Str(s) => {
             let len = s.len() as arch::SIZE_T;
@@ -1002,7 +1646,9 @@ impl Type {
     fn sign(&self) -> Result<bool, ()> {
         use Type::*;
         match self {
-            Char(sign) | Short(sign) | Int(sign) | Long(sign) => Ok(*sign),
+            Char(sign) | Short(sign) | Int(sign) | Long(sign) | LongLong(sign) | Int128(sign) => {
+                Ok(*sign)
+            }
             Bool => Ok(false),
             // TODO: allow enums with values of UINT_MAX
             Enum(_, _) => Ok(true),
@@ -1023,6 +1669,8 @@ impl Type {
     /// assert!(Char(true).rank() > Bool.rank());
     /// assert!(Long(false).rank() > Bool.rank());
     /// assert!(Long(true).rank() == Long(false).rank());
+    /// assert!(LongLong(true).rank() > Long(true).rank());
+    /// assert!(Int128(true).rank() > LongLong(true).rank());
     /// ```
     fn rank(&self) -> usize {
         use Type::*;
@@ -1032,7 +1680,8 @@ impl Type {
             Short(_) => 2,
             Int(_) => 3,
             Long(_) => 4,
-            // don't make this 5 in case we add `long long` at some point
+            LongLong(_) => 5,
+            Int128(_) => 6,
             _ => std::usize::MAX,
         }
     }
@@ -1051,7 +1700,15 @@ impl Type {
     // 6.3.1.8 Usual arithmetic conversions
     fn binary_promote(mut left: Type, mut right: Type) -> Result<Type, Type> {
         use Type::*;
-        if left == Double || right == Double {
+        // > the corresponding real type of the result is determined by the real types of the operands;
+        // > if either operand is complex, the result is complex
+        if left.is_complex() || right.is_complex() {
+            let real = Self::binary_promote(left.real_type().clone(), right.real_type().clone())?;
+            return Ok(Complex(Box::new(real)));
+        }
+        if left == LongDouble || right == LongDouble {
+            return Ok(LongDouble);
+        } else if left == Double || right == Double {
             return Ok(Double); // toil and trouble
         } else if left == Float || right == Float {
             return Ok(Float);
@@ -1077,10 +1734,18 @@ impl Type {
         } else {
             (right, left)
         };
-        if signed.can_represent(&unsigned) {
+        if unsigned.rank() >= signed.rank() {
+            Ok(unsigned)
+        } else if signed.can_represent(&unsigned) {
             Ok(signed)
         } else {
-            Ok(unsigned)
+            // `long long + unsigned long` is `unsigned long long`
+            Ok(match signed {
+                Long(_) => Long(false),
+                LongLong(_) => LongLong(false),
+                Int128(_) => Int128(false),
+                _ => unsigned,
+            })
         }
     }
     /// 6.5.2.2p6:
@@ -1088,7 +1753,7 @@ impl Type {
     /// > the integer promotions are performed on each argument,
     /// > and arguments that have type float are promoted to double.
     /// > These are called the default argument promotions.
-    fn default_promote(self) -> Type {
+    pub(super) fn default_promote(self) -> Type {
         if self.is_integral() {
             self.integer_promote()
         } else if self == Type::Float {
@@ -1123,6 +1788,10 @@ impl Expr {
     }
     // 6.3.2.3 Pointers
     fn is_null(&self) -> bool {
+        // C23: `nullptr` and any other value of type `nullptr_t` can be converted to any pointer
+        if self.ctype == Type::NullPtr {
+            return true;
+        }
         // TODO: I think we need to const fold this to allow `(void*)0`
         if let ExprType::Literal(token) = &self.expr {
             match token {
@@ -1133,7 +1802,7 @@ impl Expr {
             false
         }
     }
-    fn id(symbol: Symbol, location: Location) -> Self {
+    pub(super) fn id(symbol: Symbol, location: Location) -> Self {
         Self {
             expr: ExprType::Id(symbol),
             // TODO: maybe pass in the type as well to avoid the lookup?
@@ -1234,6 +1903,14 @@ impl Expr {
                 ),
                 ..self
             },
+            // > if the lvalue has atomic type, the value has the non-atomic version of the type of the lvalue
+            // the inner expression keeps the atomic type so the backend knows to load it atomically
+            Type::Atomic(_) if self.lval => Expr {
+                ctype: self.ctype.non_atomic().clone(),
+                lval: false,
+                location: self.location,
+                expr: ExprType::Deref(Box::new(self)),
+            },
             // HACK: structs can't be dereferenced since they're not scalar, so we just fake it
             Type::Struct(_) | Type::Union(_) if self.lval => Expr {
                 lval: false,
@@ -1272,8 +1949,13 @@ impl Expr {
     // float f = (double)1.0
     // 6.3 Conversions
     pub(super) fn implicit_cast(self, ctype: &Type, error_handler: &mut ErrorHandler) -> Expr {
+        // `_Atomic int i = 1;`: there's no such thing as an atomic rvalue
+        // `int *const p = a;`: nor a qualified one
+        let ctype = &ctype.non_atomic().unqualified();
         let mut expr = self.rval();
-        if &expr.ctype == ctype {
+        if expr.ctype.unqualified().is_compatible(ctype) {
+            // `double (*row)[n] = m;`: use the length of the destination VLA, not the source
+            expr.ctype = ctype.clone();
             expr
         // int -> long
         } else if expr.ctype.is_arithmetic() && ctype.is_arithmetic()
@@ -1281,6 +1963,9 @@ impl Expr {
             || expr.is_null() && ctype.is_pointer()
             // if ((int*)p)
             || expr.ctype.is_pointer() && ctype.is_bool()
+            // `bool b = nullptr;` or `nullptr_t n = 0;`
+            || expr.ctype == Type::NullPtr && ctype.is_bool()
+            || expr.is_null() && *ctype == Type::NullPtr
             // p -> void*
             || expr.ctype.is_pointer() && ctype.is_void_pointer()
             // p -> char*
@@ -1307,8 +1992,15 @@ impl Expr {
             // > and (considering the type the left operand would have after lvalue conversion)
             // > both operands are pointers to qualified or unqualified versions of compatible types,
             // > and the type pointed to by the left has all the qualifiers of the type pointed to by the right;
-            if let (Type::Pointer(a, from), Type::Pointer(b, to)) = (&expr.ctype, ctype) {
-                if *a == *b && from.contains_all(*to) {
+            if let (Type::Pointer(a, _), Type::Pointer(b, _)) = (&expr.ctype, ctype) {
+                // `int **` -> `int *const *`
+                let qualifiers = |ctype: &Type| match ctype {
+                    Type::Pointer(_, qualifiers) => *qualifiers,
+                    _ => Qualifiers::default(),
+                };
+                if a.unqualified().is_compatible(&b.unqualified())
+                    && qualifiers(b).contains_all(qualifiers(a))
+                {
                     expr.ctype = ctype.clone();
                     return expr;
                 }
@@ -1324,6 +2016,19 @@ impl Expr {
             expr
         }
     }
+    /// Whether this expression designates a bit-field, e.g. `s.flags` where `struct s { int flags: 3; }`
+    pub(super) fn is_bitfield(&self) -> bool {
+        match &self.expr {
+            ExprType::Member(cstruct, id) => match &cstruct.ctype {
+                Type::Struct(stype) | Type::Union(stype) => stype
+                    .members()
+                    .iter()
+                    .any(|member| member.id == *id && member.bitfield.is_some()),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
     /// See section 6.3.2.1 of the C Standard. In particular:
     /// "A modifiable lvalue is an lvalue that does not have array type,
     /// does not  have an incomplete type, does not have a const-qualified type,
@@ -1339,16 +2044,31 @@ impl Expr {
             return err(format!("expression with incomplete type '{}'", self.ctype));
         }
         // const-qualified type
-        // TODO: handle `*const`
-        if let ExprType::Id(sym) = &self.expr {
+        // only the top-level qualifiers matter: `const int *p; p++;` is fine, but `int *const p; p++;` is not.
+        // The qualifiers in the declaration specifiers belong to the innermost type,
+        // so for pointers they are in the type itself.
+        if let ExprType::Id(sym) | ExprType::CompoundLiteral(sym, _) = &self.expr {
             let meta = sym.get();
-            if meta.qualifiers.c_const {
-                return err(format!("variable '{}' with `const` qualifier", meta.id));
+            let is_const = match meta.ctype.non_atomic() {
+                Type::Pointer(_, qualifiers) => qualifiers.c_const,
+                _ => meta.qualifiers.c_const,
+            };
+            if is_const {
+                return err(match self.expr {
+                    ExprType::CompoundLiteral(..) => {
+                        "compound literal with `const` qualifier".into()
+                    }
+                    _ => format!("variable '{}' with `const` qualifier", meta.id),
+                });
             }
         }
-        match &self.ctype {
+        match self.ctype.non_atomic() {
             // array type
             Type::Array(_, _) => err("array".to_string()),
+            // `int *const *pp; *pp = 0;`
+            Type::Pointer(_, qualifiers) if qualifiers.c_const => {
+                err("pointer with `const` qualifier".to_string())
+            }
             // member with const-qualified type
             Type::Struct(stype) | Type::Union(stype) => {
                 if stype
@@ -1378,8 +2098,9 @@ impl Qualifiers {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::analyze::test::analyze;
+    use crate::analyze::test::{analyze, analyze_with};
     use crate::analyze::*;
+    use crate::parse::test::c23_parser;
     pub(crate) fn expr(input: &str) -> CompileResult<Expr> {
         analyze(input, Parser::expr, PureAnalyzer::expr)
     }
@@ -1432,6 +2153,11 @@ mod test {
             id: InternedStr::get_or_intern("x"),
             qualifiers: Default::default(),
             storage_class: Default::default(),
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Attributes::default(),
         }
         .insert();
         let parsed = expr_with_scope("x", &[x]);
@@ -1452,6 +2178,40 @@ mod test {
         assert_type("3%2", Type::Long(true));
     }
     #[test]
+    fn test_usual_arithmetic_conversions() {
+        assert_type("(long long)1 + 1", Type::LongLong(true));
+        assert_type("(long long)1 + 1u", Type::LongLong(false));
+        assert_type("(unsigned long long)1 + 1", Type::LongLong(false));
+        assert_type("(__int128)1 + 1", Type::Int128(true));
+        assert_type("(__int128)1 + 1ull", Type::Int128(true));
+        assert_type("(unsigned __int128)1 + 1", Type::Int128(false));
+        assert_type("(unsigned __int128)1 + (__int128)1", Type::Int128(false));
+        assert_type("(__int128)1 + 1.0", Type::Double);
+        assert_type("sizeof(__int128)", Type::Long(false));
+        assert_type("(long double)1 * 2.0", Type::LongDouble);
+        assert_type("1.0f - (long double)1", Type::LongDouble);
+        assert_type("(char)1 + (unsigned)1", Type::Int(false));
+        assert_type("1 + (unsigned)1", Type::Long(true));
+    }
+    #[test]
+    fn test_complex() {
+        let complex = |real| Type::Complex(Box::new(real));
+        assert_type("__builtin_complex(1.0, 2.0)", complex(Type::Double));
+        assert_type("2.0i", complex(Type::Double));
+        assert_type("1 + 2.0i", complex(Type::Double));
+        assert_type("(float _Complex)1 + 1", complex(Type::Float));
+        assert_type("(float _Complex)1 * 2.0", complex(Type::Double));
+        assert_type("1.0f / (long double _Complex)1", complex(Type::LongDouble));
+        assert_type("__real__ (float _Complex)1", Type::Float);
+        assert_type("__imag__ 1.0", Type::Double);
+        assert_type("(double _Complex)1 == 1", Type::Bool);
+        assert!(!expr("__real__ (double _Complex)1").unwrap().lval);
+        assert!(expr("(double _Complex)1 < 1").is_err());
+        assert!(expr("(double _Complex)1 % 1").is_err());
+        assert!(expr("__imag__ (int *)0").is_err());
+        assert!(expr("__builtin_complex(1.0, 1)").is_err());
+    }
+    #[test]
     fn test_funcall() {
         let f = Variable {
             id: InternedStr::get_or_intern("f"),
@@ -1463,11 +2223,22 @@ mod test {
                     id: Default::default(),
                     qualifiers: Default::default(),
                     storage_class: StorageClass::Auto,
+                    bitfield: None,
+                    alignment: None,
+                    thread_local: false,
+                    constexpr: None,
+                    attributes: Attributes::default(),
                 }
                 .insert()],
                 return_type: Box::new(Type::Int(true)),
                 varargs: false,
+                old_style: false,
             }),
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Attributes::default(),
         }
         .insert();
         assert!(expr_with_scope("f(1,2,3)", &[f]).is_err());
@@ -1498,4 +2269,60 @@ mod test {
             Type::Pointer(Box::new(Type::Int(true)), Qualifiers::default()),
         );
     }
+    #[test]
+    fn test_compound_literal() {
+        assert_type("(int){1}", Type::Int(true));
+        assert_type(
+            "(int[]){1, 2, 3}",
+            Type::Array(Box::new(Type::Int(true)), types::ArrayType::Fixed(3)),
+        );
+        assert_type("(int[]){1, 2, 3}[1]", Type::Int(true));
+        assert_type("sizeof (int[]){1, 2, [4] = 3}", Type::Long(false));
+        assert!(expr("(int){1}").unwrap().lval);
+        assert!(expr("(void){1}").is_err());
+        assert!(expr("(int){1, 2}").is_err());
+    }
+    #[test]
+    fn test_nullptr() {
+        let expr = |input| analyze_with(c23_parser(input), Parser::expr, PureAnalyzer::expr);
+        let assert_type = |input, ctype| assert_eq!(expr(input).unwrap().ctype, ctype);
+        assert_type("nullptr", Type::NullPtr);
+        assert_type("nullptr == nullptr", Type::Bool);
+        assert_type("nullptr != 0", Type::Bool);
+        assert_type("(int *)0 == nullptr", Type::Bool);
+        assert_type(
+            "(int *)nullptr",
+            Type::Pointer(Box::new(Type::Int(true)), Qualifiers::default()),
+        );
+        assert_type("!nullptr", Type::Bool);
+        assert!(expr("nullptr + 1").is_err());
+        assert!(expr("nullptr < nullptr").is_err());
+        assert!(expr("nullptr == 1").is_err());
+    }
+    #[test]
+    fn test_generic() {
+        assert_type("_Generic(1, long: 1.0, default: 'a')", Type::Double);
+        assert_type("_Generic('b', long: 1.0, default: 'a')", Type::Char(true));
+        assert_type("_Generic(\"hi\", char *: 1.0, char: 'a')", Type::Double);
+        assert_type(
+            "_Generic((const int)1, int: 1.0, default: 'a')",
+            Type::Double,
+        );
+        assert_type(
+            "_Generic((int *const)0, int *: (int)1, default: 'a')",
+            Type::Int(true),
+        );
+        // every association is analyzed, not just the selected one
+        assert!(expr("_Generic(1, long: 1, double: 1 % 2.0)").is_err());
+        assert!(expr("_Generic(1, long: 1, char *: undeclared_thing)").is_err());
+        // no match
+        assert!(expr("_Generic(1, int: 1)").is_err());
+        // duplicate or ambiguous associations
+        assert!(expr("_Generic(1, long: 1, long: 2)").is_err());
+        assert!(expr("_Generic(1, default: 1, default: 2)").is_err());
+        assert!(expr("_Generic(1, signed long: 1, long int: 2)").is_err());
+        // incomplete types
+        assert!(expr("_Generic(1, void: 1, default: 2)").is_err());
+        assert!(expr("_Generic(1, int(): 1, default: 2)").is_err());
+    }
 }
@@ -1,7 +1,15 @@
 //! 6.7.9 Initialization
 
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
 use super::PureAnalyzer;
-use crate::data::{ast, error::SemanticError, hir::*, types, Location, Type};
+use crate::data::{
+    ast,
+    error::{SemanticError, Warning},
+    hir::*,
+    types, Location, Type,
+};
 
 impl PureAnalyzer {
     pub(super) fn parse_initializer(
@@ -10,16 +18,24 @@ impl PureAnalyzer {
         ctype: &Type,
         location: Location,
     ) -> Initializer {
-        use ast::Initializer::{Aggregate, Scalar};
+        use ast::Initializer::{Aggregate, Embed, Scalar};
+        // initializing an atomic object is not an atomic operation: `_Atomic int i = 1;`
+        let ctype = ctype.non_atomic();
         // initializer_list
-        let mut expr = match init {
+        let expr = match init {
             Aggregate(list) => return self.check_aggregate_overflow(list, ctype, location),
             Scalar(expr) => self.expr(*expr),
+            Embed(_) => unreachable!("#embed is only parsed as part of an initializer list"),
         };
+        self.scalar_initializer(expr, ctype)
+    }
+
+    /// Convert an expression that has already been analyzed to the type of the object it initializes.
+    pub(super) fn scalar_initializer(&mut self, mut expr: Expr, ctype: &Type) -> Initializer {
         // The only time (that I know of) that an expression will initialize a non-scalar
         // is for character literals.
         let is_char_array = match ctype {
-            Type::Array(inner, _) => inner.is_char(),
+            Type::Array(inner, _) => inner.is_character(),
             _ => false,
         };
         // See section 6.7.9 of the C11 standard:
@@ -31,7 +47,8 @@ impl PureAnalyzer {
                 // if ctype is not a scalar, this will report an error, so we don't have to handle it specially
                 .implicit_cast(ctype, &mut self.error_handler);
         }
-        if !expr.lval && self.scope.is_global() && ctype.is_pointer() {
+        let is_static = self.scope.is_global() || self.local_static_init;
+        if !expr.lval && is_static && ctype.is_pointer() {
             expr = Expr {
                 lval: false,
                 location: expr.location,
@@ -44,108 +61,377 @@ impl PureAnalyzer {
 
     fn check_aggregate_overflow(
         &mut self,
-        list: Vec<ast::Initializer>,
+        list: Vec<(Vec<ast::Designator>, ast::Initializer)>,
         ctype: &Type,
         location: Location,
     ) -> Initializer {
+        if list.is_empty() {
+            self.err(SemanticError::EmptyInitializer, location);
+            return Initializer::InitializerList(vec![]);
+        }
+        // unsigned char data[] = { #embed "data.bin" };
+        // Treat this the same as a string literal so there isn't an initializer for each byte.
+        if let Some(bytes) = embedded_bytes(&list, ctype) {
+            let expr = super::expr::literal(LiteralValue::Str(bytes), location);
+            return self.scalar_initializer(expr, ctype);
+        }
+        let list = expand_embeds(list);
         let len = list.len();
-        let mut iter = list.into_iter().peekable();
-        let init = self.aggregate_initializer(&mut iter, ctype, location);
-        let leftover = iter.count();
+        let mut elems = vec![];
+        let mut list: VecDeque<_> = list.into_iter().collect();
+        self.aggregate_initializer(&mut list, ctype, &mut elems, vec![], true, location);
+        let leftover = list.len();
         if leftover > 0 {
             self.err(SemanticError::TooManyMembers(len - leftover, len), location);
         }
-        init
+        Initializer::InitializerList(elems)
     }
 
     // handle char[][3] = {{1,2,3}}, but also = {1,2,3} and {{1}, 2, 3}
     // NOTE: this does NOT consume {} except for sub-elements
     // see p17: "Each brace-enclosed initializer list has an associated current object"
     // For each subobject of the enclosing object (`type_at`), initialize it, possibly recursively.
+    //
+    // `elems` holds the initializers for each subobject; any subobject that is skipped over
+    // is zero-initialized with an empty list. Initializing a subobject twice overrides the first initializer.
+    // `designators` are the designators of the next initializer that still have to be resolved
+    // relative to this object. `braced` is false if the braces for this object were elided.
     fn aggregate_initializer(
         &mut self,
-        list: &mut std::iter::Peekable<impl Iterator<Item = ast::Initializer>>,
+        list: &mut VecDeque<(Vec<ast::Designator>, ast::Initializer)>,
         elem_type: &Type,
+        elems: &mut Vec<Initializer>,
+        mut designators: Vec<ast::Designator>,
+        braced: bool,
         location: Location,
-    ) -> Initializer {
-        use ast::Initializer::{Aggregate, Scalar};
+    ) {
+        use ast::Initializer::{Aggregate, Embed, Scalar};
 
-        let mut elems = vec![];
-        if list.peek().is_none() {
-            self.err(SemanticError::EmptyInitializer, location);
-            return Initializer::InitializerList(elems);
-        }
-        // char [][3] = {1};
-        while let Some(elem) = list.peek() {
-            let inner = elem_type.type_at(elems.len()).unwrap_or_else(|err| {
-                // int a[1] = {1, 2};
-                self.err(err, location);
-                Type::Error
-            });
-            // int a[][3] = {{1,2,3}}
-            //               ^
-            // initializer is aggregate, type errors will be caught later
-
-            // If the initializer of a subaggregate or contained union begins with a left brace,
-            // the initializers enclosed by that brace and its matching right brace initialize
-            // the elements or members of the subaggregate or the contained union.
-            let next = match elem {
-                Aggregate(_) => match list.next() {
-                    Some(Aggregate(inner_list)) => {
-                        self.check_aggregate_overflow(inner_list, &inner, location)
+        let len = elem_type.type_len();
+        let mut index = 0;
+        loop {
+            if designators.is_empty() {
+                match list.front_mut() {
+                    None => break,
+                    // int a[][2] = {1, [1] = 2};
+                    Some((next, _)) if !next.is_empty() => {
+                        // a designation always refers to the current object of the closest braces
+                        if !braced {
+                            break;
+                        }
+                        designators = std::mem::take(next);
+                    }
+                    // Otherwise, only enough initializers from the list are taken
+                    // to account for the elements or members of the subaggregate
+                    // or the first member of the contained union;
+                    // any remaining initializers are left to initialize the next
+                    // element or member of the aggregate of which the current
+                    // subaggregate or contained union is a part.
+                    Some(_) => {
+                        if len.is_some_and(|len| index >= len) {
+                            break;
+                        }
                     }
-                    _ => unreachable!(),
-                },
-                Scalar(_) => {
+                }
+            }
+            // If a designator has the form `[ constant-expression ]` ...
+            // the current object shall have array type. If it has the form `. identifier`,
+            // the current object shall have structure or union type.
+            if !designators.is_empty() {
+                match self.designator_index(designators.remove(0), elem_type) {
+                    Some(i) => index = i,
+                    None => {
+                        // the error has already been reported, skip this initializer
+                        designators.clear();
+                        list.pop_front();
+                        continue;
+                    }
+                }
+            }
+            let inner = match elem_type.type_at(index) {
+                // struct { _Atomic _Bool b; } s = { 0 };
+                // initializing an atomic object is not an atomic operation
+                Ok(Type::Atomic(inner)) => *inner,
+                Ok(inner) => inner,
+                // int a[1] = {1, 2};
+                Err(err) => {
+                    self.err(err, location);
+                    Type::Error
+                }
+            };
+            let next = if !designators.is_empty() {
+                // struct { struct { int i; } s; } = { .s.i = 1 };
+                //                                        ^
+                // the remaining designators refer to a subobject of `inner`
+                let mut sub_elems = take_list(elems, index);
+                let designators = std::mem::take(&mut designators);
+                self.aggregate_initializer(
+                    list,
+                    &inner,
+                    &mut sub_elems,
+                    designators,
+                    false,
+                    location,
+                );
+                Initializer::InitializerList(sub_elems)
+            } else {
+                match list.front() {
+                    // int a[][3] = {{1,2,3}}
+                    //               ^
+                    // initializer is aggregate, type errors will be caught later
+
+                    // If the initializer of a subaggregate or contained union begins with a left brace,
+                    // the initializers enclosed by that brace and its matching right brace initialize
+                    // the elements or members of the subaggregate or the contained union.
+                    Some((_, Aggregate(_))) => match list.pop_front() {
+                        Some((_, Aggregate(inner_list))) => {
+                            self.check_aggregate_overflow(inner_list, &inner, location)
+                        }
+                        _ => unreachable!(),
+                    },
                     // int a[][3] = {1,2,3}
                     //               ^
                     // type is aggregate and initializer is scalar
                     // see if we can short circuit int[][3] -> int[3]
-                    if inner != Type::Error && !inner.is_scalar() {
+                    // A string literal initializes a whole character array:
+                    // struct { int len; char s[6]; } s = { 6, "hello" };
+                    Some((_, Scalar(expr)))
+                        if inner != Type::Error
+                            && !inner.is_scalar()
+                            && inner.type_len() != Some(0)
+                            && !is_string_initializer(&inner, expr) =>
+                    {
                         // Note: this element is _not_ consumed
-                        self.aggregate_initializer(list, &inner, location)
+                        let mut sub_elems = take_list(elems, index);
+                        self.aggregate_initializer(
+                            list,
+                            &inner,
+                            &mut sub_elems,
+                            vec![],
+                            false,
+                            location,
+                        );
+                        Initializer::InitializerList(sub_elems)
+                    }
                     // type is scalar and initializer is scalar
                     // int a[][3] = {{1,2,3}}
-                    } else {
-                        let expr = match list.next() {
-                            Some(Scalar(expr)) => self
-                                .expr(*expr)
-                                .rval()
-                                .implicit_cast(&inner, &mut self.error_handler),
-                            _ => unreachable!(),
-                        };
-                        Initializer::Scalar(Box::new(expr))
+                    Some((_, Scalar(_))) => match list.pop_front() {
+                        Some((_, init)) => self.parse_initializer(init, &inner, location),
+                        None => unreachable!(),
+                    },
+                    Some((_, Embed(_))) => {
+                        unreachable!("#embed should have been expanded by check_aggregate_overflow")
                     }
+                    None => unreachable!(
+                        "designators should only be resolved if there is a next initializer"
+                    ),
                 }
             };
-            elems.push(next);
-
-            // Otherwise, only enough initializers from the list are taken
-            // to account for the elements or members of the subaggregate
-            // or the first member of the contained union;
-            // any remaining initializers are left to initialize the next
-            // element or member of the aggregate of which the current
-            // subaggregate or contained union is a part.
-            if elems.len() == elem_type.type_len() {
+            if let Type::Union(_) = elem_type {
+                // only one member of a union can be initialized, the last initializer wins
+                elems.clear();
+            }
+            if elems.len() <= index {
+                elems.resize_with(index + 1, || Initializer::InitializerList(vec![]));
+            }
+            elems[index] = next;
+            index += 1;
+            if !braced && len.is_some_and(|len| index >= len) {
                 break;
             }
         }
-        Initializer::InitializerList(elems)
+    }
+
+    /// Find the index of the subobject of `ctype` that `designator` refers to.
+    ///
+    /// e.g. for `struct { int i; float f; }`, `.f` refers to the subobject at index 1.
+    /// Returns `None` and reports an error if the designator is invalid for `ctype`.
+    fn designator_index(&mut self, designator: ast::Designator, ctype: &Type) -> Option<usize> {
+        use ast::Designator::{Index, Member};
+
+        match (designator, ctype) {
+            (Index(expr), Type::Array(_, size)) => {
+                let location = expr.location;
+                let index = Self::const_uint(self.expr(expr))
+                    .map_err(|err| self.error_handler.push_back(err))
+                    .ok()?;
+                // > If the array is of unknown size, any nonnegative value is valid.
+                if let types::ArrayType::Fixed(size) = size {
+                    if index >= *size {
+                        self.err(SemanticError::DesignatorOutOfBounds(index, *size), location);
+                        return None;
+                    }
+                }
+                Some(index as usize)
+            }
+            (Member(member), Type::Struct(struct_type))
+            | (Member(member), Type::Union(struct_type)) => {
+                let index = struct_type
+                    .initialized_members()
+                    .iter()
+                    .position(|m| m.id == member.data);
+                if index.is_none() {
+                    self.err(
+                        SemanticError::NotAMember(member.data, ctype.clone()),
+                        member.location,
+                    );
+                }
+                index
+            }
+            (_, Type::Error) => None,
+            (Index(expr), _) => {
+                self.err(SemanticError::ArrayDesignator(ctype.clone()), expr.location);
+                None
+            }
+            (Member(member), _) => {
+                self.err(
+                    SemanticError::FieldDesignator(ctype.clone()),
+                    member.location,
+                );
+                None
+            }
+        }
+    }
+
+    /// Check the initializer for a struct with a flexible array member.
+    ///
+    /// Standard C does not allow initializing flexible array members at all.
+    /// As a GNU extension, they can be initialized for objects declared at file scope,
+    /// which makes the object larger than `sizeof` its type.
+    pub(super) fn check_flexible_initializer(
+        &mut self,
+        ctype: &Type,
+        init: &Initializer,
+        is_global: bool,
+        location: Location,
+    ) {
+        let (struct_type, list) = match (ctype, init) {
+            (Type::Struct(struct_type), Initializer::InitializerList(list)) => (struct_type, list),
+            _ => return,
+        };
+        if struct_type.flexible_array_member().is_none() {
+            return;
+        }
+        // the flexible array member is always the last member
+        let index = struct_type.initialized_members().len() - 1;
+        match list.get(index) {
+            // zero-initialized
+            None => {}
+            Some(Initializer::InitializerList(elems)) if elems.is_empty() => {}
+            // struct s s = { 3, "hi" };
+            Some(_) if is_global => self.warn(Warning::FlexibleArrayInitializer, location),
+            // int main() { struct s s = { 3, "hi" }; }
+            Some(_) => self.err(SemanticError::LocalFlexibleInitializer, location),
+        }
+    }
+}
+
+/// Whether `init` is a string literal initializing the character array `ctype`.
+fn is_string_initializer(ctype: &Type, init: &ast::Expr) -> bool {
+    let is_char_array = match ctype {
+        Type::Array(inner, _) => inner.is_character(),
+        _ => false,
+    };
+    is_char_array && matches!(init.data, ast::ExprType::Literal(LiteralValue::Str(_)))
+}
+
+/// If `list` initializes the character array `ctype` with only `#embed` and integer constants,
+/// return the bytes it initializes the array with.
+///
+/// e.g. `{ #embed "data.bin" suffix(, 0) }`
+fn embedded_bytes(
+    list: &[(Vec<ast::Designator>, ast::Initializer)],
+    ctype: &Type,
+) -> Option<Vec<u8>> {
+    let size = match ctype {
+        Type::Array(inner, size) if inner.is_character() => size,
+        _ => return None,
+    };
+    let mut bytes = Vec::new();
+    let mut seen_embed = false;
+    for (designators, init) in list {
+        if !designators.is_empty() {
+            return None;
+        }
+        match init {
+            ast::Initializer::Embed(embedded) => {
+                bytes.extend_from_slice(&embedded.data);
+                seen_embed = true;
+            }
+            ast::Initializer::Scalar(expr) => match expr.data {
+                ast::ExprType::Literal(LiteralValue::Int(i)) => bytes.push(u8::try_from(i).ok()?),
+                ast::ExprType::Literal(LiteralValue::Char(c)) => bytes.push(c),
+                _ => return None,
+            },
+            ast::Initializer::Aggregate(_) => return None,
+        }
+    }
+    // let `aggregate_initializer` report an error if there are too many bytes
+    if let types::ArrayType::Fixed(size) = size {
+        if bytes.len() as crate::arch::SIZE_T > *size {
+            return None;
+        }
+    }
+    if seen_embed {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+/// Replace the bytes from each `#embed` with one initializer for each byte.
+///
+/// A designator before `#embed` only applies to the first byte: `{ [2] = #embed "data.bin" }`
+fn expand_embeds(
+    list: Vec<(Vec<ast::Designator>, ast::Initializer)>,
+) -> Vec<(Vec<ast::Designator>, ast::Initializer)> {
+    let mut expanded = Vec::with_capacity(list.len());
+    for (designators, init) in list {
+        match init {
+            ast::Initializer::Embed(bytes) => {
+                let mut designators = Some(designators);
+                for &byte in &bytes.data {
+                    let literal = ast::ExprType::Literal(LiteralValue::Int(byte.into()));
+                    let init = ast::Initializer::Scalar(Box::new(bytes.location.with(literal)));
+                    expanded.push((designators.take().unwrap_or_default(), init));
+                }
+            }
+            other => expanded.push((designators, other)),
+        }
+    }
+    expanded
+}
+
+/// Take the existing initializers for the subobject at `index`, so they can be partially overridden.
+fn take_list(elems: &mut [Initializer], index: usize) -> Vec<Initializer> {
+    match elems.get_mut(index) {
+        Some(Initializer::InitializerList(list)) => std::mem::take(list),
+        _ => vec![],
     }
 }
 
 impl Type {
-    /// Given a type, return the maximum number of initializers for that type
-    fn type_len(&self) -> usize {
+    /// Whether this is `char`, `signed char`, or `unsigned char`.
+    ///
+    /// > An array of character type may be initialized by a character string literal
+    fn is_character(&self) -> bool {
+        matches!(self, Type::Char(_))
+    }
+    /// Given a type, return the maximum number of initializers for that type,
+    /// or `None` if there is no maximum (for arrays of unknown size).
+    ///
+    /// Only the first member of a union is initialized without a designator.
+    fn type_len(&self) -> Option<usize> {
         use types::ArrayType;
-        match self {
+        Some(match self {
             ty if ty.is_scalar() => 1,
             Type::Array(_, ArrayType::Fixed(size)) => *size as usize,
-            Type::Array(_, ArrayType::Unbounded) => 0,
-            Type::Struct(st) | Type::Union(st) => st.members().len(),
+            Type::Array(_, ArrayType::Unbounded) => return None,
+            Type::Struct(st) => st.initialized_members().len(),
+            Type::Union(st) => std::cmp::min(st.initialized_members().len(), 1),
             Type::Function { .. } | Type::Error => 1,
             _ => unimplemented!("type checking for {}", self),
-        }
+        })
     }
     /// Given a type and an index,
     /// return the type expected at that index in the initializer.
@@ -165,23 +451,13 @@ impl Type {
                 }
             }
             Type::Array(inner, _) => Ok((**inner).clone()),
-            Type::Struct(struct_type) => {
-                let symbols = struct_type.members();
+            Type::Struct(struct_type) | Type::Union(struct_type) => {
+                let symbols = struct_type.initialized_members();
                 symbols.get(index).map_or_else(
                     || Err(SemanticError::TooManyMembers(symbols.len(), index)),
                     |symbol| Ok(symbol.ctype.clone()),
                 )
             }
-            Type::Union(struct_type) => {
-                if index != 0 {
-                    return Err("can only initialize first member of an enum".into());
-                }
-                let members = struct_type.members();
-                Ok(members
-                    .first()
-                    .map(|m| m.ctype.clone())
-                    .unwrap_or(Type::Error))
-            }
             Type::Function { .. } | Type::Error => Ok(Type::Error),
             _ => unimplemented!("type checking for aggregate initializers of type {}", self),
         }
@@ -248,5 +524,101 @@ mod test {
             "struct { int i; float f; } s = {(int)1, (float)1.2};",
         );
         assert_errs_decls("struct s { int *p; } s = { 1.0 }", 1, 0, 1);
+        // atomic members are initialized like their non-atomic type
+        assert!(decl("struct { _Atomic _Bool b; } s = { 0 };").is_ok());
+        assert!(decl("_Atomic int a[2] = { 1, 2 };").is_ok());
+    }
+    #[test]
+    fn test_designated_initializers() {
+        assert_same("int a[] = {[1] = 2, [0] = 1};", "int a[] = {1, 2};");
+        assert_same(
+            "int a[4] = {[2] = 3, 4, [0] = 1, 2};",
+            "int a[4] = {1, 2, 3, 4};",
+        );
+        // later initializers override earlier ones
+        assert_same("int a[] = {1, 2, [0] = 3};", "int a[] = {3, 2};");
+        assert_same(
+            "struct s { int i; float f; } s = {.f = 1.5, .i = 2};",
+            "struct s { int i; float f; } s = {2, 1.5};",
+        );
+        // nested designators, followed by brace elision
+        assert_same(
+            "struct s { int a[2]; int b; } s = {.a[1] = 2, .a[0] = 1, 3};",
+            "struct s { int a[2]; int b; } s = {{1, 3}};",
+        );
+        assert_same(
+            "int a[2][2] = {[1][1] = 4, [1][0] = 3, [0] = {1, 2}};",
+            "int a[2][2] = {{1, 2}, {3, 4}};",
+        );
+        // subobjects without an initializer are zero-initialized
+        let sparse = decl("int a[] = {[3] = 1};").unwrap();
+        assert_eq!(
+            sparse.symbol.get().ctype,
+            Type::Array(Box::new(Type::Int(true)), types::ArrayType::Fixed(4))
+        );
+        match sparse.init {
+            Some(Initializer::InitializerList(list)) => {
+                assert_eq!(list.len(), 4);
+                assert!(list[..3]
+                    .iter()
+                    .all(|init| *init == Initializer::InitializerList(vec![])));
+            }
+            other => panic!("expected initializer list, got {:?}", other),
+        }
+        // only the last member of a union is initialized
+        match decl("union u { int i; float f; } u = {.i = 1, .f = 1.5};").map(|d| d.init) {
+            Ok(Some(Initializer::InitializerList(list))) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!(list[0], Initializer::InitializerList(vec![]));
+            }
+            other => panic!("expected initializer list, got {:?}", other),
+        }
+        for err in &[
+            "int a[2] = {[2] = 1};",
+            "int a[] = {[-1] = 1};",
+            "int a[] = {.x = 1};",
+            "int i = {[0] = 1};",
+            "struct s { int i; } s = {.j = 1};",
+            "struct s { int i; } s = {[0] = 1};",
+            "union u { int i; float f; } u = {1, 2};",
+        ] {
+            assert!(decl(err).is_err(), "{} should be an error", err);
+        }
+    }
+    #[test]
+    fn test_embed_initializers() {
+        const STDARG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/headers/stdarg.h");
+        let embedded = |src: &str, params: &str| {
+            src.replace("EMBED", &format!("\n#embed \"{}\" {}\n", STDARG, params))
+        };
+        // character arrays are initialized the same way as with a string literal
+        let bytes = decl(&embedded("unsigned char a[] = {EMBED};", "limit(3)")).unwrap();
+        match bytes.init {
+            Some(Initializer::Scalar(expr)) => {
+                assert_eq!(
+                    expr.expr,
+                    ExprType::Literal(LiteralValue::Str(b"#if".to_vec()))
+                )
+            }
+            other => panic!("expected string literal, got {:?}", other),
+        }
+        assert_same(
+            &embedded("char a[] = {EMBED};", "limit(2) suffix(, 0)"),
+            "char a[] = \"#i\";",
+        );
+        // everything else gets one initializer for each byte
+        assert_same(
+            &embedded("int a[] = {EMBED};", "limit(3)"),
+            "int a[] = {35, 105, 102};",
+        );
+        assert_same(
+            &embedded("char a[4] = {[1] = EMBED};", "limit(2)"),
+            "char a[4] = {[1] = 35, 105};",
+        );
+        assert_same(
+            &embedded("struct { int i; char c[2]; } s = {EMBED};", "limit(3)"),
+            "struct { int i; char c[2]; } s = {35, {105, 102}};",
+        );
+        assert!(decl(&embedded("char a[2] = {EMBED};", "limit(3)")).is_err());
     }
 }
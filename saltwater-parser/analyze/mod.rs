@@ -1,13 +1,16 @@
+mod attributes;
 mod expr;
 mod init;
 mod stmt;
 
-use std::collections::{HashSet, VecDeque};
+pub(crate) use attributes::has_c_attribute;
+
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 
 use counter::Counter;
 
-use crate::data::{error::Warning, hir::*, lex::Keyword, *};
+use crate::data::{error::Warning, hir::*, lex::Keyword, types::ArrayType, *};
 use crate::intern::InternedStr;
 use crate::parse::{Lexer, Parser};
 use crate::RecursionGuard;
@@ -72,6 +75,21 @@ pub struct PureAnalyzer {
     ///
     /// TODO: this should be a field on `FunctionAnalyzer`, not `Analyzer`
     decl_side_channel: Vec<Locatable<Declaration>>,
+    /// The number of compound literals seen so far, used to give each one a unique name
+    compound_literals: usize,
+    /// The number of variable length arrays seen so far, used to give each length a unique name
+    variable_lengths: usize,
+    /// The declarations of the lengths of VLA parameters in the last function declarator.
+    ///
+    /// For `void f(int n, double a[n][n])`, the length of `a` is evaluated on entry to the function.
+    prototype_lengths: Vec<Locatable<Declaration>>,
+    /// The function currently being analyzed, if any
+    function: Option<FunctionState>,
+    /// Whether the initializer being analyzed is for a `static` object inside a function,
+    /// which has to be constant the same as one at file scope
+    local_static_init: bool,
+    /// Whether to warn about `case` labels that are reached by falling through from the code before them
+    warn_fallthrough: bool,
 }
 
 impl<T: Lexer> Iterator for Analyzer<T> {
@@ -111,6 +129,11 @@ impl<I: Lexer> Analyzer<I> {
             inner: PureAnalyzer::new(),
         }
     }
+    /// Warn about implicit fall-through between `case` labels, like `-Wimplicit-fallthrough`.
+    pub fn implicit_fallthrough(mut self, enabled: bool) -> Self {
+        self.inner.warn_fallthrough = enabled;
+        self
+    }
 }
 
 impl Default for PureAnalyzer {
@@ -129,6 +152,12 @@ impl PureAnalyzer {
             initialized: HashSet::new(),
             recursion_guard: RecursionGuard::default(),
             decl_side_channel: Vec::new(),
+            compound_literals: 0,
+            variable_lengths: 0,
+            prototype_lengths: Vec::new(),
+            function: None,
+            local_static_init: false,
+            warn_fallthrough: false,
         }
     }
 
@@ -175,6 +204,29 @@ impl PureAnalyzer {
             ExternalDeclaration::Declaration(declaration) => {
                 self.parse_declaration(declaration, next.location)
             }
+            ExternalDeclaration::StaticAssert(assert) => {
+                self.static_assert(*assert, next.location);
+                Vec::new()
+            }
+        }
+    }
+    /// 6.7.10 Static assertions
+    ///
+    /// `_Static_assert(sizeof(int) == 4, "int must be 32 bits");`
+    fn static_assert(&mut self, assert: ast::StaticAssert, location: Location) {
+        let condition = self.expr(assert.condition);
+        if !condition.ctype.is_integral() {
+            if condition.ctype != Type::Error {
+                self.err(SemanticError::NonIntegralExpr(condition.ctype), location);
+            }
+            return;
+        }
+        match Self::const_literal(condition) {
+            Ok(value) if value.is_zero() => {
+                self.err(SemanticError::StaticAssertFailed(assert.message), location)
+            }
+            Ok(_) => {}
+            Err(err) => self.error_handler.push_back(err),
         }
     }
     /// A list of declarations: `int i, j, k;`
@@ -183,18 +235,50 @@ impl PureAnalyzer {
         declaration: ast::Declaration,
         location: Location,
     ) -> Vec<Locatable<Declaration>> {
+        // [[fallthrough]];
+        if declaration.is_attribute_declaration() {
+            self.attribute_declaration(declaration.specifiers);
+            return Vec::new();
+        }
         let original = self.parse_specifiers(declaration.specifiers, location);
 
-        if original.storage_class == Some(StorageClass::Auto) && self.scope.is_global() {
+        // `auto x = 1;` is allowed at file scope, since `auto` is only used for type inference
+        let only_infers = original.infer_type
+            && !declaration.declarators.is_empty()
+            && declaration
+                .declarators
+                .iter()
+                .all(|d| d.data.init.is_some());
+        if original.storage_class == Some(StorageClass::Auto)
+            && self.scope.is_global()
+            && !only_infers
+        {
             self.err(SemanticError::AutoAtGlobalScope, location);
         }
 
         // TODO: this is such a hack: https://github.com/jyn514/rcc/issues/371
         let sc = original.storage_class.unwrap_or(StorageClass::Auto);
         let mut decls = Vec::new();
-        for d in declaration.declarators {
-            let mut ctype =
-                self.parse_declarator(original.ctype.clone(), d.data.declarator.decl, d.location);
+        for mut d in declaration.declarators {
+            let (mut ctype, inferred_init) = match d.data.init.take() {
+                // auto x = 1;
+                Some(init) if original.infer_type => {
+                    self.infer_type(&d.data.declarator, init, d.location)
+                }
+                init => {
+                    d.data.init = init;
+                    // `auto x;` is still an implicit int
+                    if original.infer_type {
+                        self.warn(Warning::ImplicitInt, d.location);
+                    }
+                    let ctype = self.parse_declarator(
+                        original.ctype.clone(),
+                        d.data.declarator.decl,
+                        d.location,
+                    );
+                    (ctype, None)
+                }
+            };
 
             if !ctype.is_function() && original.qualifiers.func != FunctionQualifiers::default() {
                 self.err(
@@ -218,17 +302,88 @@ impl PureAnalyzer {
                 self.err(SemanticError::VoidType, location);
                 ctype = Type::Error;
             }
-            let init = if let Some(init) = d.data.init {
-                Some(self.parse_initializer(init, &ctype, d.location))
+            // > If an identifier is declared to be an object with static or thread storage duration,
+            // > it shall not have a variable length array type.
+            // > ... all identifiers declared with a variably modified type shall be ordinary identifiers with no linkage
+            // static int a[n];
+            if ctype.is_variably_modified()
+                && (sc == StorageClass::Static || sc == StorageClass::Extern)
+            {
+                self.err(SemanticError::VariablyModifiedStorage(sc), d.location);
+            }
+            if original.thread_local {
+                // _Thread_local int f();
+                if ctype.is_function() {
+                    self.err(
+                        SemanticError::ThreadLocalNotAllowed("a function"),
+                        d.location,
+                    );
+                // > In the declaration of an object with block scope, if the declaration specifiers include _Thread_local,
+                // > they shall also include either static or extern.
+                } else if !self.scope.is_global() && original.storage_class.is_none() {
+                    self.err(SemanticError::ThreadLocalAtBlockScope(id), d.location);
+                }
+            }
+            let init = match d.data.init {
+                // > The type of the entity to be initialized shall be ...
+                // > a complete object type that is not a variable length array type.
+                // int a[n] = {1};
+                Some(_) if ctype.is_vla() => {
+                    self.err(SemanticError::VlaInitializer, d.location);
+                    None
+                }
+                Some(init) => {
+                    let local_static = !self.scope.is_global() && sc == StorageClass::Static;
+                    self.local_static_init = local_static;
+                    let init = self.parse_initializer(init, &ctype, d.location);
+                    self.local_static_init = false;
+                    // int a[] = {1, 2, 3};
+                    ctype.complete_array_type(&init);
+                    let is_static = self.scope.is_global() || local_static;
+                    self.check_flexible_initializer(&ctype, &init, is_static, d.location);
+                    Some(init)
+                }
+                None => inferred_init,
+            };
+            let constexpr = if original.constexpr {
+                self.constexpr_value(id, &ctype, init.as_ref(), d.location)
             } else {
                 None
             };
-            let symbol = Variable {
+            // evaluate VLA lengths and compound assignments before the declaration that uses them
+            // int n = 5, a[n];
+            if !self.scope.is_global() {
+                decls.append(&mut self.decl_side_channel);
+            }
+            let thread_local = original.thread_local && !ctype.is_function();
+            let mut symbol = Variable {
                 ctype,
                 id,
                 qualifiers: original.qualifiers,
                 storage_class: sc,
+                bitfield: None,
+                alignment: None,
+                thread_local,
+                constexpr,
+                attributes: Attributes::default(),
             };
+            self.apply_alignment(&mut symbol, original.alignment, d.location);
+            self.apply_typedef_alignment(&mut symbol, &original);
+            // __attribute__((weak)) int i, j __attribute__((section("mine")));
+            let mut attributes = original.attributes.clone();
+            attributes.append(&mut d.data.declarator.attributes);
+            self.apply_attributes(&mut symbol, attributes, false);
+            // extern int f(void) __asm__("g");
+            if let Some(label) = d.data.asm_label {
+                if self.scope.is_global()
+                    || sc == StorageClass::Extern
+                    || symbol.ctype.is_function()
+                {
+                    symbol.attributes.asm_label = Some(label);
+                } else {
+                    self.err(SemanticError::AsmLabelWithoutLinkage(id), d.location);
+                }
+            }
             let symbol = self.declare(symbol, init.is_some(), d.location);
             if init.is_some() {
                 self.initialized.insert(symbol);
@@ -241,6 +396,121 @@ impl PureAnalyzer {
         }
         decls
     }
+    /// C23 6.7.10 Type inference: `auto x = 1;`
+    ///
+    /// The type is the type of the initializer after lvalue conversion,
+    /// so arrays decay to pointers and qualifiers are dropped.
+    /// Returns the inferred type and the converted initializer.
+    fn infer_type(
+        &mut self,
+        declarator: &ast::Declarator,
+        init: ast::Initializer,
+        location: Location,
+    ) -> (Type, Option<Initializer>) {
+        let id = declarator.id.unwrap_or_default();
+        // auto *p = &x;
+        if declarator.decl != ast::DeclaratorType::End {
+            self.err(SemanticError::AutoInferenceDeclarator(id), location);
+            return (Type::Error, None);
+        }
+        let expr = match init {
+            ast::Initializer::Scalar(expr) => Some(*expr),
+            // > The initializer for a scalar shall be a single expression, optionally enclosed in braces
+            // auto x = { 1 };
+            ast::Initializer::Aggregate(mut list) if list.len() == 1 && list[0].0.is_empty() => {
+                match list.remove(0).1 {
+                    ast::Initializer::Scalar(expr) => Some(*expr),
+                    ast::Initializer::Aggregate(_) | ast::Initializer::Embed(_) => None,
+                }
+            }
+            ast::Initializer::Aggregate(_) | ast::Initializer::Embed(_) => None,
+        };
+        let expr = match expr {
+            Some(expr) => self.expr(expr).rval(),
+            // auto a = { 1, 2 };
+            None => {
+                self.err(SemanticError::AutoInferenceList(id), location);
+                return (Type::Error, None);
+            }
+        };
+        let ctype = expr.ctype.clone();
+        let init = self.scalar_initializer(expr, &ctype);
+        (ctype, Some(init))
+    }
+    /// Check that the initializer of a `constexpr` object is a constant expression.
+    ///
+    /// Returns the value of the object if it is a scalar,
+    /// so that it can be used in integer constant expressions.
+    fn constexpr_value(
+        &mut self,
+        id: InternedStr,
+        ctype: &Type,
+        init: Option<&Initializer>,
+        location: Location,
+    ) -> Option<LiteralValue> {
+        // constexpr int f();
+        if ctype.is_function() {
+            self.err(SemanticError::ConstexprNotAllowed("a function"), location);
+            return None;
+        }
+        let init = match init {
+            Some(init) => init,
+            // constexpr int n;
+            None => {
+                self.err(SemanticError::ConstexprUninitialized(id), location);
+                return None;
+            }
+        };
+        let value = self.constant_initializer(init);
+        if ctype.is_scalar() {
+            value
+        } else {
+            None
+        }
+    }
+    /// Report an error for every part of `init` that is not a constant expression,
+    /// or that changes value when converted to the type of the object.
+    ///
+    /// Returns the value of a scalar initializer.
+    fn constant_initializer(&mut self, init: &Initializer) -> Option<LiteralValue> {
+        match init {
+            Initializer::Scalar(expr) => {
+                // pointers at file scope are wrapped in a `StaticRef`: `constexpr int *p = 0;`
+                let expr: &Expr = match &expr.expr {
+                    ExprType::StaticRef(inner) => inner,
+                    _ => expr,
+                };
+                let value = match expr.clone().constexpr() {
+                    Ok(literal) => literal.data.0,
+                    Err(err) => {
+                        self.error_handler.push_back(err);
+                        return None;
+                    }
+                };
+                // > If an object or subobject declared with storage-class specifier constexpr has
+                // > integer, real floating, complex, or pointer type, any explicit initializer value for it
+                // > shall be null, an integer constant expression, an arithmetic constant expression,
+                // > ... and the value shall be exactly representable in the corresponding type.
+                // constexpr unsigned char u = 300;
+                if let ExprType::Cast(original) = &expr.expr {
+                    if let Ok(original) = original.clone().constexpr() {
+                        if !exactly_representable(&original.data.0, &expr.ctype) {
+                            let err = SemanticError::ConstexprNotRepresentable(expr.ctype.clone());
+                            self.err(err, expr.location);
+                        }
+                    }
+                }
+                Some(value)
+            }
+            Initializer::InitializerList(list) => {
+                for init in list {
+                    self.constant_initializer(init);
+                }
+                None
+            }
+            Initializer::FunctionBody(_) => None,
+        }
+    }
     #[cfg(test)]
     #[inline(always)]
     // used only for testing, so that I can keep `parse_typename` private most of the time
@@ -254,21 +524,54 @@ impl PureAnalyzer {
     ///
     /// 6.7.7 Type names
     fn parse_typename(&mut self, ctype: ast::TypeName, location: Location) -> Type {
+        let (ctype, qualifiers) = self.parse_qualified_typename(ctype, location);
+        // const int
+        if qualifiers != Qualifiers::default() {
+            self.warn(Warning::IgnoredQualifier(qualifiers), location);
+        }
+        ctype
+    }
+    /// Like `parse_typename`, but keep the qualifiers instead of ignoring them.
+    ///
+    /// `(const int){1}` creates an object, so its qualifiers matter.
+    fn parse_qualified_typename(
+        &mut self,
+        ctype: ast::TypeName,
+        location: Location,
+    ) -> (Type, Qualifiers) {
+        let parsed = self.parse_abstract_type(ctype, location);
+        (parsed.ctype, parsed.qualifiers)
+    }
+    /// Like `parse_qualified_typename`, but keep everything `parse_type` found.
+    ///
+    /// `_Alignof(T)` needs the alignment of the typedef `T`, not just its type.
+    fn parse_abstract_type(&mut self, ctype: ast::TypeName, location: Location) -> ParsedType {
         let parsed = self.parse_type(ctype.specifiers, ctype.declarator.decl, location);
         // TODO: should these be syntax errors instead?
         // extern int
         if let Some(sc) = parsed.storage_class {
             self.err(SemanticError::IllegalStorageClass(sc), location);
         }
-        // const int
-        if parsed.qualifiers != Qualifiers::default() {
-            self.warn(Warning::IgnoredQualifier(parsed.qualifiers), location);
+        // sizeof(_Alignas(8) int)
+        if parsed.alignment.is_some() {
+            self.err(SemanticError::AlignasNotAllowed("a type name"), location);
+        }
+        // sizeof(_Thread_local int)
+        if parsed.thread_local {
+            self.err(
+                SemanticError::ThreadLocalNotAllowed("a type name"),
+                location,
+            );
+        }
+        // sizeof(constexpr int)
+        if parsed.constexpr {
+            self.err(SemanticError::ConstexprNotAllowed("a type name"), location);
         }
         // int i
         if let Some(id) = ctype.declarator.id {
             self.err(SemanticError::IdInTypeName(id), location);
         }
-        parsed.ctype
+        parsed
     }
     /// Parse a single type, given the specifiers and declarator.
     fn parse_type(
@@ -278,7 +581,12 @@ impl PureAnalyzer {
         location: Location,
     ) -> ParsedType {
         let mut specs = self.parse_specifiers(specifiers, location);
-        specs.ctype = self.parse_declarator(specs.ctype, declarator, location);
+        let ctype = self.parse_declarator(specs.ctype.clone(), declarator, location);
+        // `T *` is not aligned like `T` is
+        if ctype != specs.ctype {
+            specs.typedef_alignment = None;
+        }
+        specs.ctype = ctype;
 
         if !specs.ctype.is_function() && specs.qualifiers.func != FunctionQualifiers::default() {
             self.err(
@@ -334,29 +642,35 @@ impl PureAnalyzer {
             match long_count {
                 0 => panic!("constraint violation, should only set count if > 0"),
                 1 => {
-                    // NOTE: this is handled later by the big `for type in [...]` loop
-                    // see notes there
+                    // NOTE: `long double` is handled later by the big `for type in [...]` loop
                     if counter.get(&Double).is_none() {
                         ctype = Some(Type::Long(signed));
                     }
                 }
-                // TODO: implement `long long` as a separate type
-                2 => ctype = Some(Type::Long(signed)),
+                2 => ctype = Some(Type::LongLong(signed)),
                 _ => {
                     self.err(SemanticError::TooLong(long_count), location);
-                    ctype = Some(Type::Long(signed));
+                    ctype = Some(Type::LongLong(signed));
                 }
             }
         }
+        // C23 6.7.1: `constexpr` objects are implicitly `const`
+        let constexpr = counter.get(&Constexpr).is_some();
         // 6.7.3 Type qualifiers
-        let qualifiers = Qualifiers {
-            c_const: counter.get(&Const).is_some(),
+        let mut qualifiers = Qualifiers {
+            c_const: counter.get(&Const).is_some() || constexpr,
             volatile: counter.get(&Volatile).is_some(),
             func: FunctionQualifiers {
                 inline: counter.get(&Inline).is_some(),
                 no_return: counter.get(&NoReturn).is_some(),
             },
         };
+        // C23 6.7.10: `auto` with no type specifiers infers the type from the initializer: `auto x = 1;`
+        let infer_type = counter.get(&Auto).is_some()
+            && !counter.keys().any(|spec| spec.is_type_specifier())
+            && compounds
+                .iter()
+                .all(|spec| matches!(spec, AlignasType(_) | AlignasExpr(_) | Attribute(_)));
         // 6.7.1 Storage-class specifiers
         let mut storage_class = None;
         for (spec, sc) in &[
@@ -368,6 +682,11 @@ impl PureAnalyzer {
         ] {
             if counter.get(spec).is_some() {
                 if let Some(existing) = storage_class {
+                    // `static auto x = 1;` only uses `auto` for type inference
+                    if infer_type && existing == StorageClass::Auto {
+                        storage_class = Some(*sc);
+                        continue;
+                    }
                     self.err(
                         SemanticError::ConflictingStorageClass(existing, *sc),
                         location,
@@ -376,18 +695,50 @@ impl PureAnalyzer {
                 storage_class = Some(*sc);
             }
         }
+        // > _Thread_local may appear with static or extern.
+        let thread_local = counter.get(&ThreadLocal).is_some();
+        match storage_class {
+            Some(sc @ StorageClass::Auto)
+            | Some(sc @ StorageClass::Register)
+            | Some(sc @ StorageClass::Typedef)
+                if thread_local =>
+            {
+                self.err(SemanticError::ThreadLocalStorageClass(sc), location);
+            }
+            _ => {}
+        }
+        // > constexpr shall not appear with extern, typedef, or thread_local
+        if constexpr {
+            match storage_class {
+                Some(sc @ StorageClass::Extern) | Some(sc @ StorageClass::Typedef) => {
+                    self.err(SemanticError::ConstexprStorageClass(sc), location);
+                }
+                _ => {}
+            }
+            if thread_local {
+                self.err(
+                    SemanticError::ThreadLocalNotAllowed("a constexpr object"),
+                    location,
+                );
+            }
+        }
         // back to type specifiers
+        let double = if counter.get(&Long) == Some(&1) {
+            Type::LongDouble
+        } else {
+            Type::Double
+        };
         // TODO: maybe use `iter!` macro instead of `vec!` to avoid an allocation?
         // https://play.rust-lang.org/?gist=0535aa4f749a14cb1b28d658446f3c13
         for (spec, new_ctype) in vec![
             (Bool, Type::Bool),
             (Char, Type::Char(signed)),
             (Short, Type::Short(signed)),
+            (Int128, Type::Int128(signed)),
             // already handled `long` when we handled `long long`
             (Float, Type::Float),
-            // NOTE: if we saw `long double` before, we'll set `ctype` to `double` now
-            // TODO: make `long double` different from `double`
-            (Double, Type::Double),
+            // NOTE: if we saw `long double` before, `ctype` is still `None`
+            (Double, double),
             (Void, Type::Void),
             (VaList, Type::VaList),
         ] {
@@ -410,8 +761,8 @@ impl PureAnalyzer {
         if counter.get(&Int).is_some() {
             match ctype {
                 None => ctype = Some(Type::Int(signed)),
-                // `long int` is valid
-                Some(Type::Short(_)) | Some(Type::Long(_)) => {}
+                // `long int` and `long long int` are valid
+                Some(Type::Short(_)) | Some(Type::Long(_)) | Some(Type::LongLong(_)) => {}
                 Some(existing) => {
                     self.err(
                         SemanticError::ConflictingType(existing, Type::Int(signed)),
@@ -421,10 +772,44 @@ impl PureAnalyzer {
                 }
             }
         }
+        // 6.7.2p2: `float _Complex`, `double _Complex`, or `long double _Complex`
+        if counter.get(&Complex).is_some() {
+            ctype = match ctype {
+                Some(real) if real.is_floating() => Some(Type::Complex(Box::new(real))),
+                None => {
+                    self.warn(Warning::PlainComplex, location);
+                    Some(Type::Complex(Box::new(Type::Double)))
+                }
+                Some(other) => {
+                    self.err(SemanticError::InvalidComplex(other.clone()), location);
+                    Some(other)
+                }
+            };
+        }
+        // Annex G is optional, and imaginary types only exist in Annex G
+        if counter.get(&Imaginary).is_some() {
+            self.err(SemanticError::ImaginaryNotSupported, location);
+        }
         let mut declared_compound_type = false;
+        let mut alignment = None;
+        let mut typedef_alignment = None;
+        let mut attributes = Vec::new();
         for compound in compounds {
             let parsed = match compound {
                 Unit(_) => unreachable!("already caught"),
+                // _Alignas(double) int i;
+                AlignasType(_) | AlignasExpr(_) => {
+                    // > When multiple alignment specifiers occur in a declaration,
+                    // > the effective alignment requirement is the strictest specified alignment.
+                    alignment =
+                        std::cmp::max(alignment, self.alignment_specifier(compound, location));
+                    continue;
+                }
+                // __attribute__((noreturn)) void f();
+                Attribute(attr) => {
+                    attributes.push(attr);
+                    continue;
+                }
                 DeclarationSpecifier::Typedef(name) => {
                     let meta = self
                         .scope
@@ -432,6 +817,11 @@ impl PureAnalyzer {
                         .expect("scope of parser and analyzer should match")
                         .get();
                     assert_eq!(meta.storage_class, StorageClass::Typedef);
+                    // typedef int I __attribute__((deprecated)); I i;
+                    if let Some(message) = &meta.attributes.deprecated {
+                        self.warn(Warning::Deprecated(name, message.clone()), location);
+                    }
+                    typedef_alignment = meta.alignment;
                     meta.ctype.clone()
                 }
                 Struct(s) => self.struct_specifier(s, true, &mut declared_compound_type, location),
@@ -439,6 +829,38 @@ impl PureAnalyzer {
                 Enum { name, members } => {
                     self.enum_specifier(name, members, &mut declared_compound_type, location)
                 }
+                // _Atomic(int) i;
+                AtomicType(type_name) => {
+                    let inner = self.parse_typename(*type_name, location);
+                    // 6.7.2.4p3: the type name shall not refer to an array type, a function type,
+                    // an atomic type, or a qualified type
+                    self.atomic_type(inner, location)
+                }
+                // typeof(const int) i;
+                TypeofType { type_name, unqual } => {
+                    let parsed =
+                        self.parse_type(type_name.specifiers, type_name.declarator.decl, location);
+                    if let Some(sc) = parsed.storage_class {
+                        self.err(SemanticError::IllegalStorageClass(sc), location);
+                    }
+                    if !unqual {
+                        qualifiers.c_const |= parsed.qualifiers.c_const;
+                        qualifiers.volatile |= parsed.qualifiers.volatile;
+                    }
+                    typeof_type(parsed.ctype, unqual)
+                }
+                // typeof(x) i;
+                TypeofExpr { expr, unqual } => {
+                    // > The typeof operators do not perform lvalue conversion,
+                    // so `typeof(a)` is an array type if `a` is an array
+                    let expr = self.expr(*expr);
+                    if let (ExprType::Id(symbol), false) = (&expr.expr, unqual) {
+                        let meta = symbol.get();
+                        qualifiers.c_const |= meta.qualifiers.c_const;
+                        qualifiers.volatile |= meta.qualifiers.volatile;
+                    }
+                    typeof_type(expr.ctype, unqual)
+                }
             };
             // TODO: this should report the name of the typedef, not the type itself
             if let Some(existing) = &ctype {
@@ -455,8 +877,12 @@ impl PureAnalyzer {
         if counter.get(&Signed).is_some() || counter.get(&Unsigned).is_some() {
             match &ctype {
                 // unsigned int
-                Some(Type::Char(_)) | Some(Type::Short(_)) | Some(Type::Int(_))
-                | Some(Type::Long(_)) => {}
+                Some(Type::Char(_))
+                | Some(Type::Short(_))
+                | Some(Type::Int(_))
+                | Some(Type::Long(_))
+                | Some(Type::LongLong(_))
+                | Some(Type::Int128(_)) => {}
                 // unsigned float
                 Some(other) => {
                     let err = SemanticError::CannotBeSigned(other.clone());
@@ -467,15 +893,134 @@ impl PureAnalyzer {
             }
         }
         // `i;` or `const i;`, etc.
-        let ctype = ctype.unwrap_or_else(|| {
-            self.warn(Warning::ImplicitInt, location);
+        // for `auto x = 1;`, this is a placeholder until we see the initializer
+        let mut ctype = ctype.unwrap_or_else(|| {
+            if !infer_type {
+                self.warn(Warning::ImplicitInt, location);
+            }
             Type::Int(true)
         });
+        // `_Atomic int i;`
+        // 6.7.3p3: The type modified by the _Atomic qualifier shall not be an array type or a function type.
+        if counter.get(&UnitSpecifier::Atomic).is_some() && !ctype.is_atomic() {
+            ctype = self.atomic_type(ctype, location);
+        }
         ParsedType {
             qualifiers,
             storage_class,
             ctype,
             declared_compound_type,
+            alignment,
+            typedef_alignment,
+            thread_local,
+            attributes,
+            constexpr,
+            infer_type,
+        }
+    }
+    /// Make `inner` atomic, reporting an error if it's an array, function, or atomic type.
+    fn atomic_type(&mut self, inner: Type, location: Location) -> Type {
+        match inner {
+            Type::Array(_, _) | Type::Function(_) | Type::Atomic(_) => {
+                self.err(SemanticError::InvalidAtomic(inner.clone()), location);
+                inner
+            }
+            Type::Error => inner,
+            _ => Type::Atomic(Box::new(inner)),
+        }
+    }
+    /// 6.7.5 Alignment specifier: `_Alignas(16)` or `_Alignas(double)`
+    ///
+    /// Returns `None` if the specifier has no effect or is invalid.
+    fn alignment_specifier(
+        &mut self,
+        spec: ast::DeclarationSpecifier,
+        location: Location,
+    ) -> Option<crate::arch::SIZE_T> {
+        match spec {
+            // > The alignment specifier `_Alignas(type-name)` is equivalent to `_Alignas(_Alignof(type-name))`.
+            DeclarationSpecifier::AlignasType(type_name) => {
+                let ctype = self.parse_typename(*type_name, location);
+                match ctype.alignof() {
+                    Ok(align) => Some(align),
+                    Err(err) => {
+                        if ctype != Type::Error {
+                            self.err(err.into(), location);
+                        }
+                        None
+                    }
+                }
+            }
+            DeclarationSpecifier::AlignasExpr(expr) => {
+                let expr = self.expr(*expr);
+                match Self::const_sint(expr) {
+                    // > An alignment specification of zero has no effect.
+                    Ok(0) => None,
+                    Ok(align) if align > 0 && (align as u64).is_power_of_two() => {
+                        Some(align as u64)
+                    }
+                    Ok(align) => {
+                        self.err(SemanticError::InvalidAlignment(align), location);
+                        None
+                    }
+                    Err(err) => {
+                        self.error_handler.push_back(err);
+                        None
+                    }
+                }
+            }
+            _ => unreachable!("only alignment specifiers should be passed to alignment_specifier"),
+        }
+    }
+    /// Apply the alignment requested by `_Alignas` to `symbol`.
+    fn apply_alignment(
+        &mut self,
+        symbol: &mut Variable,
+        alignment: Option<crate::arch::SIZE_T>,
+        location: Location,
+    ) {
+        let requested = match alignment {
+            Some(align) => align,
+            None => return,
+        };
+        // > An alignment attribute shall not be specified in a declaration of a typedef, or a bit-field,
+        // > or a function, or a parameter, or an object declared with the register storage-class specifier.
+        let forbidden = if symbol.storage_class == StorageClass::Typedef {
+            Some("a typedef")
+        } else if symbol.bitfield.is_some() {
+            Some("a bit-field")
+        } else if symbol.ctype.is_function() {
+            Some("a function")
+        } else if symbol.storage_class == StorageClass::Register {
+            Some("a register variable")
+        } else {
+            None
+        };
+        if let Some(kind) = forbidden {
+            self.err(SemanticError::AlignasNotAllowed(kind), location);
+            return;
+        }
+        // > The combined effect of all alignment attributes in a declaration shall not specify an alignment
+        // > that is less strict than the alignment that would otherwise be required for the type of the object
+        match symbol.ctype.alignof() {
+            Ok(natural) if requested < natural => {
+                self.err(SemanticError::AlignasTooWeak(symbol.id), location)
+            }
+            _ => symbol.alignment = Some(requested),
+        }
+    }
+    /// Give `symbol` the alignment of the typedef it was declared with.
+    ///
+    /// `typedef int T __attribute__((aligned(8))); T i;` aligns `i` to 8 bytes, but `T *p;` does not align `p`.
+    fn apply_typedef_alignment(&mut self, symbol: &mut Variable, parsed: &ParsedType) {
+        let requested = match parsed.typedef_alignment {
+            Some(align) if symbol.ctype == parsed.ctype && symbol.bitfield.is_none() => align,
+            _ => return,
+        };
+        if let Ok(current) = symbol.alignof() {
+            if requested > current {
+                symbol.alignment = Some(requested);
+            }
         }
     }
     // 6.7.2.1 Structure and union specifiers
@@ -502,6 +1047,8 @@ impl PureAnalyzer {
                     self.error_handler.error(SemanticError::from(err), location);
                     return Type::Error;
                 };
+                // `struct s;` declares the tag, even though it has no members
+                *declared_struct = true;
                 let keyword = if is_struct {
                     Keyword::Struct
                 } else {
@@ -531,14 +1078,50 @@ impl PureAnalyzer {
                 };
             }
         };
-        let members: Vec<_> = ast_members
-            .into_iter()
-            .map(|m| self.struct_declarator_list(m, location).into_iter())
-            .flatten()
-            .collect();
+        let mut members = Vec::new();
+        for member in ast_members {
+            match member {
+                ast::StructDeclaration::Member(list) => {
+                    members.extend(self.struct_declarator_list(list, location))
+                }
+                // struct s { char c[4]; _Static_assert(sizeof(int) == 4); };
+                ast::StructDeclaration::StaticAssert(assert) => {
+                    self.static_assert(*assert.data, assert.location)
+                }
+            }
+        }
+        // struct s { char c; int i; } __attribute__((packed));
+        self.apply_struct_attributes(&mut members, struct_spec.attributes);
         if members.is_empty() {
             self.err(SemanticError::from("cannot have empty struct"), location);
             return Type::Error;
+        // struct s { int : 5; };
+        } else if members.iter().all(Variable::is_unnamed_bitfield) {
+            self.err(
+                SemanticError::from("cannot have struct with no named members"),
+                location,
+            );
+            return Type::Error;
+        }
+        // > As a special case, the last element of a structure with more than one named member
+        // > may have an incomplete array type; this is called a flexible array member.
+        let named_members = members.iter().filter(|m| !m.is_unnamed_bitfield()).count();
+        for (i, member) in members.iter().enumerate() {
+            if let Type::Array(_, ArrayType::Unbounded) = member.ctype {
+                let err = if !is_struct {
+                    // union { int i; char c[]; };
+                    SemanticError::FlexibleArrayInUnion
+                } else if i != members.len() - 1 {
+                    // struct { char c[]; int i; };
+                    SemanticError::FlexibleArrayNotLast
+                } else if named_members == 1 {
+                    // struct { char c[]; };
+                    SemanticError::FlexibleArrayOnlyMember
+                } else {
+                    continue;
+                };
+                self.err(err(member.id), location);
+            }
         }
         let constructor = if is_struct { Type::Struct } else { Type::Union };
         if let Some(id) = struct_spec.name {
@@ -580,7 +1163,7 @@ impl PureAnalyzer {
     struct_declarator_list: struct_declarator (',' struct_declarator)* ;
     struct_declarator
         : declarator
-        | ':' constant_expr  // bitfield, not supported
+        | ':' constant_expr  // unnamed bitfield
         | declarator ':' constant_expr
         ;
     */
@@ -600,55 +1183,61 @@ impl PureAnalyzer {
         let mut parsed_members = Vec::new();
         // A member of a structure or union may have any complete object type other than a variably modified type.
         for ast::StructDeclarator { decl, bitfield } in members.declarators {
-            let decl = match decl {
+            let (ctype, id, attributes) = match decl {
+                Some(decl) => {
+                    let ctype =
+                        match self.parse_declarator(parsed_type.ctype.clone(), decl.decl, location)
+                        {
+                            Type::Void => {
+                                // TODO: catch this error for types besides void?
+                                self.err(SemanticError::VoidType, location);
+                                Type::Error
+                            }
+                            other => other,
+                        };
+                    let id = decl.id.expect("struct members should have an id");
+                    (ctype, id, decl.attributes)
+                }
                 // 12 A bit-field declaration with no declarator, but only a colon and a width, indicates an unnamed bit-field.
-                // TODO: this should give an error if `bitfield` is None.
+                // struct s { int : 3; };
+                None if bitfield.is_some() => (
+                    parsed_type.ctype.clone(),
+                    InternedStr::default(),
+                    Vec::new(),
+                ),
+                // TODO: this should give an error
                 None => continue,
-                Some(d) => d,
-            };
-            let ctype = match self.parse_declarator(parsed_type.ctype.clone(), decl.decl, location)
-            {
-                Type::Void => {
-                    // TODO: catch this error for types besides void?
-                    self.err(SemanticError::VoidType, location);
-                    Type::Error
-                }
-                other => other,
             };
             let mut symbol = Variable {
                 storage_class: StorageClass::Auto,
                 qualifiers: parsed_type.qualifiers,
                 ctype,
-                id: decl.id.expect("struct members should have an id"),
+                id,
+                bitfield: None,
+                alignment: None,
+                thread_local: false,
+                constexpr: None,
+                attributes: Attributes::default(),
             };
             // struct s { int i: 5 };
             if let Some(bitfield) = bitfield {
-                let bit_size = match Self::const_uint(self.expr(bitfield)) {
-                    Ok(e) => e,
-                    Err(err) => {
-                        self.error_handler.push_back(err);
-                        1
-                    }
-                };
-                let type_size = symbol.ctype.sizeof().unwrap_or(0);
-                if bit_size == 0 {
-                    let err = SemanticError::from(format!(
-                        "C does not have zero-sized types. hint: omit the declarator {}",
-                        symbol.id
-                    ));
-                    self.err(err, location);
-                // struct s { int i: 65 }
-                } else if bit_size > type_size * u64::from(crate::arch::CHAR_BIT) {
-                    let err = SemanticError::from(format!(
-                        "cannot have bitfield {} with size {} larger than containing type {}",
-                        symbol.id, bit_size, symbol.ctype
-                    ));
-                    self.err(err, location);
+                symbol.bitfield = self.bitfield_width(&symbol, bitfield, location);
+                if symbol.bitfield.is_none() && symbol.id == InternedStr::default() {
+                    // there's nothing left to declare
+                    continue;
                 }
-                self.error_handler.warn(
-                    "bitfields are not implemented and will be ignored",
-                    location,
-                );
+            }
+            // struct s { _Alignas(16) char c; };
+            self.apply_alignment(&mut symbol, parsed_type.alignment, location);
+            self.apply_typedef_alignment(&mut symbol, &parsed_type);
+            // struct s { char c __attribute__((aligned(16))); };
+            let mut member_attributes = parsed_type.attributes.clone();
+            member_attributes.extend(attributes);
+            self.apply_attributes(&mut symbol, member_attributes, true);
+            // struct s { int a[n]; };
+            if symbol.ctype.is_variably_modified() {
+                self.err(SemanticError::VariablyModifiedMember(symbol.id), location);
+                symbol.ctype = Type::Error;
             }
             match symbol.ctype {
                 Type::Struct(StructType::Named(_, inner_members))
@@ -671,10 +1260,7 @@ impl PureAnalyzer {
             parsed_members.push(symbol);
         }
         // struct s { extern int i; };
-        if let Some(class) = parsed_type.storage_class {
-            let member = parsed_members
-                .last()
-                .expect("should have seen at least one declaration");
+        if let (Some(class), Some(member)) = (parsed_type.storage_class, parsed_members.last()) {
             self.err(
                 SemanticError::from(format!(
                     "cannot specify storage class '{}' for struct member '{}'",
@@ -683,8 +1269,80 @@ impl PureAnalyzer {
                 location,
             );
         }
+        // struct s { _Thread_local int i; };
+        if parsed_type.thread_local {
+            self.err(
+                SemanticError::ThreadLocalNotAllowed("a struct member"),
+                location,
+            );
+        }
+        // struct s { constexpr int i; };
+        if parsed_type.constexpr {
+            self.err(
+                SemanticError::ConstexprNotAllowed("a struct member"),
+                location,
+            );
+        }
         parsed_members
     }
+    /// Check the width of a bit-field, returning `None` if it was invalid.
+    // 6.7.2.1 Structure and union specifiers, constraints 4 and 5
+    fn bitfield_width(
+        &mut self,
+        symbol: &Variable,
+        width: ast::Expr,
+        location: Location,
+    ) -> Option<u8> {
+        let width = match Self::const_uint(self.expr(width)) {
+            Ok(w) => w,
+            Err(err) => {
+                self.error_handler.push_back(err);
+                return None;
+            }
+        };
+        // struct s { float f: 1; };
+        if !symbol.ctype.is_integral() {
+            if symbol.ctype != Type::Error {
+                let err = SemanticError::from(format!(
+                    "bitfield {} has non-integral type {}",
+                    symbol.id, symbol.ctype
+                ));
+                self.err(err, location);
+            }
+            return None;
+        }
+        if let Type::Int128(_) = symbol.ctype {
+            let err = SemanticError::from(format!(
+                "bitfield {} with type {} is not supported",
+                symbol.id, symbol.ctype
+            ));
+            self.err(err, location);
+            return None;
+        }
+        let max_width = match symbol.ctype {
+            Type::Bool => 1,
+            _ => symbol.ctype.sizeof().unwrap_or(0) * u64::from(crate::arch::CHAR_BIT),
+        };
+        // struct s { int i: 0; };
+        if width == 0 && symbol.id != InternedStr::default() {
+            let err = SemanticError::from(format!(
+                "C does not have zero-sized types. hint: omit the declarator {}",
+                symbol.id
+            ));
+            self.err(err, location);
+            None
+        // struct s { int i: 65 }
+        } else if width > max_width {
+            let err = SemanticError::from(format!(
+                "cannot have bitfield {} with size {} larger than containing type {}",
+                symbol.id, width, symbol.ctype
+            ));
+            self.err(err, location);
+            None
+        } else {
+            Some(width as u8)
+        }
+    }
     // 6.7.2.2 Enumeration specifiers
     fn enum_specifier(
         &mut self,
@@ -745,6 +1403,11 @@ impl PureAnalyzer {
                 },
                 storage_class: StorageClass::Register,
                 ctype: Type::Enum(None, vec![(name, discriminant)]),
+                bitfield: None,
+                alignment: None,
+                thread_local: false,
+                constexpr: None,
+                attributes: Attributes::default(),
             };
             self.declare(tmp_symbol, false, location);
             discriminant = discriminant.checked_add(1).unwrap_or_else(|| {
@@ -781,6 +1444,11 @@ impl PureAnalyzer {
                             storage_class: StorageClass::Register,
                             qualifiers: Qualifiers::NONE,
                             ctype: ctype.clone(),
+                            bitfield: None,
+                            alignment: None,
+                            thread_local: false,
+                            constexpr: None,
+                            attributes: Attributes::default(),
                         }
                         .insert(),
                     );
@@ -842,7 +1510,7 @@ impl PureAnalyzer {
         location: Location,
     ) -> Type {
         use crate::data::ast::DeclaratorType::*;
-        use crate::data::types::{ArrayType, FunctionType};
+        use crate::data::types::ArrayType;
 
         let _guard = self.recursion_check();
         match decl {
@@ -874,16 +1542,28 @@ impl PureAnalyzer {
                     // *struct s {}
                     self.err(SemanticError::NotAQualifier(spec), location);
                 }
-                Type::Pointer(Box::new(inner), qualifiers)
+                let pointer = Type::Pointer(Box::new(inner), qualifiers);
+                // int *_Atomic p;
+                if counter.get(&Atomic).is_some() {
+                    Type::Atomic(Box::new(pointer))
+                } else {
+                    pointer
+                }
             }
             Array { of, size } => {
-                // int a[5]
                 let size = if let Some(expr) = size {
-                    let size = Self::const_uint(self.expr(*expr)).unwrap_or_else(|err| {
-                        self.error_handler.push_back(err);
-                        1
-                    });
-                    ArrayType::Fixed(size)
+                    let size = self.expr(*expr);
+                    if self.is_variable_length(&size) {
+                        // int a[n]
+                        self.variable_length(size)
+                    } else {
+                        // int a[5]
+                        let size = Self::const_uint(size).unwrap_or_else(|err| {
+                            self.error_handler.push_back(err);
+                            1
+                        });
+                        ArrayType::Fixed(size)
+                    }
                 } else {
                     // int a[]
                     ArrayType::Unbounded
@@ -893,105 +1573,206 @@ impl PureAnalyzer {
                 if let Type::Function(_) = &of {
                     self.err(SemanticError::ArrayStoringFunction(of.clone()), location);
                 }
+                // struct s { int len; char data[]; } a[2];
+                if let Type::Struct(struct_type) = &of {
+                    if struct_type.flexible_array_member().is_some() {
+                        self.err(SemanticError::ArrayOfFlexibleStruct(of.clone()), location);
+                    }
+                }
                 Type::Array(Box::new(of), size)
             }
-            Function(func) => {
-                // TODO: give a warning for `const int f();` somewhere
-                let return_type = self.parse_declarator(current, *func.return_type, location);
-                match &return_type {
-                    // int a()[]
-                    Type::Array(_, _) => self.err(
-                        SemanticError::IllegalReturnType(return_type.clone()),
-                        location,
-                    ),
-                    // int a()()
-                    Type::Function(_) => self.err(
-                        SemanticError::IllegalReturnType(return_type.clone()),
-                        location,
-                    ),
-                    _ => {}
-                }
+            Function(func) => self.function_declarator(current, func, location),
+        }
+    }
+    /// Parse the declarator of a function, including its parameters.
+    ///
+    /// 6.7.6.3 Function declarators
+    fn function_declarator(
+        &mut self,
+        current: Type,
+        func: ast::FunctionDeclarator,
+        location: Location,
+    ) -> Type {
+        use crate::data::types::FunctionType;
 
-                let mut names = HashSet::new();
-                let mut params = Vec::new();
-                for param in func.params {
-                    // TODO: this location should be that of the param, not of the function
-                    let mut param_type =
-                        self.parse_type(param.specifiers, param.declarator.decl, location);
+        // TODO: give a warning for `const int f();` somewhere
+        let mut return_type = self.parse_declarator(current, *func.return_type, location);
+        // 6.7.6.3p5 (C17): `_Atomic int f()` returns a plain `int`
+        if let Type::Atomic(inner) = return_type {
+            return_type = *inner;
+        }
+        match &return_type {
+            // int a()[]
+            Type::Array(_, _) => self.err(
+                SemanticError::IllegalReturnType(return_type.clone()),
+                location,
+            ),
+            // int a()()
+            Type::Function(_) => self.err(
+                SemanticError::IllegalReturnType(return_type.clone()),
+                location,
+            ),
+            _ => {}
+        }
 
-                    // `int f(int a[])` -> `int f(int *a)`
-                    if let Type::Array(to, _) = param_type.ctype {
-                        param_type.ctype = Type::Pointer(to, Qualifiers::default());
-                    }
+        let mut names = HashSet::new();
+        let mut params = Vec::new();
+        let mut symbols = Vec::new();
+        // 6.2.1p4: parameters are in scope until the end of the function declarator,
+        // so later parameters can use them: `int f(int n, int a[n])`
+        self.scope.enter();
+        let vla_start = self.decl_side_channel.len();
+        for param in func.params {
+            // TODO: this location should be that of the param, not of the function
+            let mut param_type = self.parse_type(param.specifiers, param.declarator.decl, location);
+
+            // `int f(int a[])` -> `int f(int *a)`
+            if let Type::Array(to, _) = param_type.ctype {
+                param_type.ctype = Type::Pointer(to, Qualifiers::default());
+            }
 
-                    // C11 Standard 6.7.6.3 paragraph 8
-                    // "A declaration of a parameter as 'function returning type' shall be
-                    //  adjusted to 'pointer to function returning type', as in 6.3.2.1."
-                    // `int f(int g())` -> `int f(int (*g)())`
-                    if param_type.ctype.is_function() {
-                        param_type.ctype =
-                            Type::Pointer(Box::new(param_type.ctype), Qualifiers::default());
-                    }
+            // C11 Standard 6.7.6.3 paragraph 8
+            // "A declaration of a parameter as 'function returning type' shall be
+            //  adjusted to 'pointer to function returning type', as in 6.3.2.1."
+            // `int f(int g())` -> `int f(int (*g)())`
+            if param_type.ctype.is_function() {
+                param_type.ctype = Type::Pointer(Box::new(param_type.ctype), Qualifiers::default());
+            }
 
-                    // int a(extern int i)
-                    if let Some(sc) = param_type.storage_class {
-                        self.err(SemanticError::ParameterStorageClass(sc), location);
-                    }
-                    let id = if let Some(name) = param.declarator.id {
-                        // int f(int a, int a)
-                        if names.contains(&name) {
-                            self.err(SemanticError::DuplicateParameter(name), location)
-                        }
-                        names.insert(name);
-                        name
-                    } else {
-                        // int f(int)
-                        InternedStr::default()
-                    };
-                    let meta = Variable {
-                        ctype: param_type.ctype,
-                        id,
-                        qualifiers: param_type.qualifiers,
-                        storage_class: StorageClass::Auto,
-                    };
-                    params.push(meta);
-                }
-                // int f(void);
-                let is_void = match params.as_slice() {
-                    [Variable {
-                        ctype: Type::Void, ..
-                    }] => true,
-                    _ => false,
-                };
-                // int f(void, int) or int f(int, void) or ...
-                if !is_void
-                    && params.iter().any(|param| match param.ctype {
-                        Type::Void => true,
-                        _ => false,
-                    })
-                {
-                    self.err(SemanticError::InvalidVoidParameter, location);
-                // int f(void, ...)
-                } else if func.varargs && is_void {
-                    self.err(SemanticError::VoidVarargs, location);
-                // int f(...)
-                } else if func.varargs && params.is_empty() {
-                    self.err(SemanticError::VarargsWithoutParam, location);
+            // int a(extern int i)
+            if let Some(sc) = param_type.storage_class {
+                self.err(SemanticError::ParameterStorageClass(sc), location);
+            }
+            // int a(_Alignas(8) int i)
+            if param_type.alignment.is_some() {
+                self.err(SemanticError::AlignasNotAllowed("a parameter"), location);
+            }
+            // int a(_Thread_local int i)
+            if param_type.thread_local {
+                self.err(
+                    SemanticError::ThreadLocalNotAllowed("a parameter"),
+                    location,
+                );
+            }
+            // int a(constexpr int i)
+            if param_type.constexpr {
+                self.err(SemanticError::ConstexprNotAllowed("a parameter"), location);
+            }
+            let id = if let Some(name) = param.declarator.id {
+                // int f(int a, int a)
+                if names.contains(&name) {
+                    self.err(SemanticError::DuplicateParameter(name), location)
                 }
-                Type::Function(FunctionType {
-                    params: params.into_iter().map(|m| m.insert()).collect(),
-                    return_type: Box::new(return_type),
-                    varargs: func.varargs,
-                })
+                names.insert(name);
+                name
+            } else {
+                // int f(int)
+                InternedStr::default()
+            };
+            let meta = Variable {
+                ctype: param_type.ctype,
+                id,
+                qualifiers: param_type.qualifiers,
+                storage_class: StorageClass::Auto,
+                bitfield: None,
+                alignment: None,
+                thread_local: false,
+                constexpr: None,
+                attributes: Attributes::default(),
+            };
+            let symbol = meta.clone().insert();
+            if id != InternedStr::default() {
+                self.scope.insert(id, symbol);
             }
+            symbols.push(symbol);
+            params.push(meta);
+        }
+        self.scope.exit();
+        // the lengths of VLA parameters are only evaluated if this is a function definition
+        self.prototype_lengths = self.decl_side_channel.split_off(vla_start);
+        // int f(void);
+        let is_void = match params.as_slice() {
+            [Variable {
+                ctype: Type::Void, ..
+            }] => true,
+            _ => false,
+        };
+        // int f(void, int) or int f(int, void) or ...
+        if !is_void
+            && params.iter().any(|param| match param.ctype {
+                Type::Void => true,
+                _ => false,
+            })
+        {
+            self.err(SemanticError::InvalidVoidParameter, location);
+        // int f(void, ...)
+        } else if func.varargs && is_void {
+            self.err(SemanticError::VoidVarargs, location);
+        // int f(...)
+        } else if func.varargs && params.is_empty() {
+            self.err(SemanticError::VarargsWithoutParam, location);
+        }
+        Type::Function(FunctionType {
+            params: symbols,
+            return_type: Box::new(return_type),
+            varargs: func.varargs,
+            old_style: false,
+        })
+    }
+    /// Whether `length` is the length of a variable length array,
+    /// i.e. it is an integer that is not a constant expression.
+    ///
+    /// VLAs can only be declared at block scope or function prototype scope.
+    fn is_variable_length(&self, length: &Expr) -> bool {
+        !self.scope.is_global()
+            && length.ctype.is_integral()
+            && match length.clone().const_fold() {
+                Ok(folded) => !matches!(folded.expr, ExprType::Literal(_)),
+                Err(_) => false,
+            }
+    }
+    /// Evaluate the length of a VLA and store it in a hidden variable.
+    ///
+    /// > The size of each instance of a variable length array type does not change during its lifetime.
+    ///
+    /// so `int a[n]; n++;` doesn't change `sizeof a`.
+    /// The declaration of the hidden variable is stored in the side channel.
+    fn variable_length(&mut self, length: Expr) -> ArrayType {
+        let location = length.location;
+        self.variable_lengths += 1;
+        let symbol = Variable {
+            id: format!("vla.{}", self.variable_lengths).into(),
+            ctype: Type::Long(false),
+            qualifiers: Qualifiers::default(),
+            storage_class: StorageClass::Auto,
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Attributes::default(),
         }
+        .insert();
+        let init = length.implicit_cast(&Type::Long(false), &mut self.error_handler);
+        let decl = Declaration {
+            symbol,
+            init: Some(Initializer::Scalar(Box::new(init))),
+        };
+        self.decl_side_channel.push(Locatable::new(decl, location));
+        ArrayType::Variable(symbol)
     }
     // used for arrays like `int a[BUF_SIZE - 1];` and enums like `enum { A = 1 }`
     fn const_literal(expr: Expr) -> CompileResult<LiteralValue> {
         let location = expr.location;
-        expr.const_fold()?.into_literal().map_err(|runtime_expr| {
-            Locatable::new(SemanticError::NotConstant(runtime_expr).into(), location)
-        })
+        // `constexpr int n = 1; int a[] = { [n] = 2 };`: `n` is folded when it is loaded
+        expr.rval()
+            .const_fold()?
+            .into_literal()
+            .map_err(|runtime_expr| {
+                Locatable::new(
+                    SemanticError::NotConstant(Box::new(runtime_expr)).into(),
+                    location,
+                )
+            })
     }
     /// Return an unsigned integer that can be evaluated at compile time, or an error otherwise.
     fn const_uint(expr: Expr) -> CompileResult<crate::arch::SIZE_T> {
@@ -1011,6 +1792,22 @@ impl PureAnalyzer {
                 }
             }
             Char(c) => Ok(c.into()),
+            Int128(i) if i < 0 => Err(Locatable::new(
+                SemanticError::NegativeLength.into(),
+                location,
+            )),
+            Int128(i) => i.try_into().map_err(|_| {
+                Locatable::new(
+                    SemanticError::ConstOverflow { is_positive: true }.into(),
+                    location,
+                )
+            }),
+            UnsignedInt128(u) => u.try_into().map_err(|_| {
+                Locatable::new(
+                    SemanticError::ConstOverflow { is_positive: true }.into(),
+                    location,
+                )
+            }),
             Str(_) | Float(_) => Err(Locatable::new(
                 SemanticError::NonIntegralLength.into(),
                 location,
@@ -1032,6 +1829,20 @@ impl PureAnalyzer {
             },
             Int(i) => Ok(i),
             Char(c) => Ok(c.into()),
+            Int128(i) => match i.try_into() {
+                Ok(i) => Ok(i),
+                Err(_) => Err(Locatable::new(
+                    SemanticError::ConstOverflow { is_positive: i > 0 }.into(),
+                    location,
+                )),
+            },
+            UnsignedInt128(u) => match u.try_into() {
+                Ok(i) => Ok(i),
+                Err(_) => Err(Locatable::new(
+                    SemanticError::ConstOverflow { is_positive: true }.into(),
+                    location,
+                )),
+            },
             Str(_) | Float(_) => Err(Locatable::new(
                 SemanticError::NonIntegralLength.into(),
                 location,
@@ -1062,6 +1873,15 @@ impl PureAnalyzer {
             decl.storage_class = StorageClass::Auto;
         }
         let id = decl.id;
+        // GCC merges the attributes of all declarations of the same entity
+        if let Some(existing) = self.scope.get_immediate(&id) {
+            let existing = existing.get();
+            decl.attributes.inherit(&existing.attributes);
+            // __attribute__((noreturn)) void f(); void f() { abort(); }
+            if existing.ctype.is_function() && decl.ctype.is_function() {
+                decl.qualifiers.func.no_return |= existing.qualifiers.func.no_return;
+            }
+        }
         let symbol = decl.insert();
         if let Some(existing_ref) = self.scope.insert(id, symbol) {
             let existing = existing_ref.get();
@@ -1075,8 +1895,10 @@ impl PureAnalyzer {
             // i.e. `static int f(); int f();` is the same as `static int f(); static int f();`
             // special case redefining the same type
             if self.scope.is_global()
+                // > If _Thread_local appears in any declaration of an object, it shall be present in every declaration of that object.
+                && existing.thread_local == meta.thread_local
                 // int i; int i;
-                && (existing == meta
+                && (existing.same_declaration(&meta)
                     // `static int i; extern int i;` or `int i; extern int i;`
                     || ((existing.storage_class == StorageClass::Static
                         || existing.storage_class == StorageClass::Auto)
@@ -1138,22 +1960,46 @@ impl types::FunctionType {
 impl Type {
     #[inline]
     fn is_char(&self) -> bool {
-        match self {
-            Type::Char(true) => true,
-            _ => false,
-        }
+        matches!(self, Type::Char(true))
     }
 }
 
 /// Analyze a single function
 ///
-/// This is separate from `Analyzer` so that `metadata` does not have to be an `Option`.
+/// This is separate from `Analyzer` so that statements can assume `analyzer.function` is set.
 struct FunctionAnalyzer<'a> {
+    /// We need this for the scopes, as well as for parsing expressions
+    analyzer: &'a mut PureAnalyzer,
+}
+
+/// The state of the function currently being analyzed.
+///
+/// This is stored in `PureAnalyzer` instead of `FunctionAnalyzer`
+/// so that statement expressions can analyze statements in the middle of an expression.
+#[derive(Debug)]
+struct FunctionState {
     /// the function we are currently compiling.
     /// used for checking return types
     metadata: FunctionData,
-    /// We need this for the scopes, as well as for parsing expressions
-    analyzer: &'a mut PureAnalyzer,
+    /// Objects with a variably modified type that are currently in scope, innermost last
+    vla_scope: Vec<Symbol>,
+    /// The length of `vla_scope` when each block was entered
+    vla_scope_starts: Vec<usize>,
+    /// The VM objects and statement expressions in scope at each label
+    labels: HashMap<InternedStr, (Vec<Symbol>, Vec<usize>)>,
+    /// Each `goto`, along with the VM objects and statement expressions in scope at the `goto`
+    gotos: Vec<(InternedStr, Vec<Symbol>, Vec<usize>, Location)>,
+    /// Each label whose address was taken with `&&label`
+    label_addresses: Vec<Locatable<InternedStr>>,
+    /// The statement expressions currently being analyzed, innermost last
+    stmt_exprs: Vec<usize>,
+    /// The number of statement expressions seen so far, used to give each one a unique id
+    stmt_expr_count: usize,
+    /// The lengths of `stmt_exprs` and `vla_scope` at each `switch` currently being analyzed,
+    /// innermost last
+    switch_depths: Vec<(usize, usize)>,
+    /// Whether the next block is the body of a `switch`, which can only be entered through its labels
+    switch_body: bool,
 }
 
 #[derive(Debug)]
@@ -1166,6 +2012,8 @@ struct FunctionData {
     location: Location,
     /// the return type of the function
     return_type: Type,
+    /// whether the function was declared `_Noreturn` or `__attribute__((noreturn))`
+    no_return: bool,
 }
 
 impl FunctionAnalyzer<'_> {
@@ -1176,13 +2024,48 @@ impl FunctionAnalyzer<'_> {
         analyzer: &mut PureAnalyzer,
         location: Location,
     ) -> (Symbol, Vec<Stmt>) {
-        let parsed_func = analyzer.parse_type(func.specifiers, func.declarator.into(), location);
-        // saltwater ignores `inline` and `_Noreturn`
-        if parsed_func.qualifiers != Qualifiers::default() {
-            analyzer.error_handler.warn(
-                Warning::FunctionQualifiersIgnored(parsed_func.qualifiers),
-                location,
-            );
+        let mut parsed_func =
+            analyzer.parse_type(func.specifiers, func.declarator.into(), location);
+        // void f(int n, double a[n][n]) {}
+        let param_lengths = std::mem::take(&mut analyzer.prototype_lengths);
+        // int f(a) float a; {}
+        // Callers pass `a` as a `double`, so that's the type of the parameter.
+        // It's converted back to `float` at the start of the function.
+        let mut old_style_params = Vec::new();
+        if func.old_style {
+            analyzer.warn(Warning::OldStyleFunction, location);
+            if let Type::Function(ftype) = &mut parsed_func.ctype {
+                ftype.old_style = true;
+                for param in &mut ftype.params {
+                    let declared = param.get();
+                    let promoted = declared.ctype.clone().default_promote();
+                    if promoted != declared.ctype {
+                        let local = *param;
+                        *param = Variable {
+                            ctype: promoted,
+                            ..(*declared).clone()
+                        }
+                        .insert();
+                        old_style_params.push((*param, local));
+                    }
+                }
+            }
+        }
+        // saltwater ignores `inline`
+        let mut ignored = parsed_func.qualifiers;
+        ignored.func.no_return = false;
+        if ignored != Qualifiers::default() {
+            analyzer
+                .error_handler
+                .warn(Warning::FunctionQualifiersIgnored(ignored), location);
+        }
+        // _Thread_local int f() {}
+        if parsed_func.thread_local {
+            analyzer.err(SemanticError::ThreadLocalNotAllowed("a function"), location);
+        }
+        // constexpr int f() {}
+        if parsed_func.constexpr {
+            analyzer.err(SemanticError::ConstexprNotAllowed("a function"), location);
         }
         let sc = match parsed_func.storage_class {
             None => StorageClass::Extern,
@@ -1193,12 +2076,19 @@ impl FunctionAnalyzer<'_> {
                 StorageClass::Extern
             }
         };
-        let metadata = Variable {
+        let mut metadata = Variable {
             ctype: parsed_func.ctype.clone(),
             id: func.id,
             qualifiers: parsed_func.qualifiers,
             storage_class: sc,
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Attributes::default(),
         };
+        analyzer.apply_alignment(&mut metadata, parsed_func.alignment, location);
+        analyzer.apply_attributes(&mut metadata, parsed_func.attributes, false);
         let symbol = analyzer.declare(metadata, true, location);
         let func_type = match parsed_func.ctype {
             Type::Function(ftype) => ftype,
@@ -1209,13 +2099,12 @@ impl FunctionAnalyzer<'_> {
             location,
             id: func.id,
             return_type: *func_type.return_type,
+            no_return: symbol.get().qualifiers.func.no_return,
         };
         assert!(analyzer.scope.is_global());
         assert!(analyzer.tag_scope.is_global());
-        let mut func_analyzer = FunctionAnalyzer {
-            metadata: tmp_metadata,
-            analyzer,
-        };
+        analyzer.function = Some(FunctionState::new(tmp_metadata));
+        let mut func_analyzer = FunctionAnalyzer { analyzer };
         func_analyzer.enter_scope();
         for (i, param) in func_type.params.into_iter().enumerate() {
             let meta = param.get();
@@ -1230,26 +2119,71 @@ impl FunctionAnalyzer<'_> {
             // but that requires having a mutable `Metadata`
             func_analyzer.analyzer.scope.insert(meta.id, param);
         }
-        let stmts = func
-            .body
-            .into_iter()
-            .map(|s| func_analyzer.parse_stmt(s))
-            .collect();
+        let mut stmts = Vec::new();
+        // 6.9.1p10: the size expressions of VLA parameters are evaluated on entry to the function
+        if !param_lengths.is_empty() {
+            stmts.push(Stmt::new(StmtType::Decl(param_lengths), location));
+        }
+        if !old_style_params.is_empty() {
+            let decls = old_style_params
+                .into_iter()
+                .map(|(promoted, local)| {
+                    let ctype = local.get().ctype.clone();
+                    let init = Expr::id(promoted, location)
+                        .implicit_cast(&ctype, &mut func_analyzer.analyzer.error_handler);
+                    func_analyzer.analyzer.scope.insert(local.get().id, local);
+                    let decl = Declaration {
+                        symbol: local,
+                        init: Some(Initializer::Scalar(Box::new(init))),
+                    };
+                    Locatable::new(decl, location)
+                })
+                .collect();
+            stmts.push(Stmt::new(StmtType::Decl(decls), location));
+        }
+        stmts.extend(func.body.into_iter().map(|s| func_analyzer.parse_stmt(s)));
         // TODO: this location should be the end of the function, not the start
         func_analyzer.leave_scope(location);
+        func_analyzer.check_gotos();
+        analyzer.function = None;
         assert!(analyzer.tag_scope.is_global());
         assert!(analyzer.scope.is_global());
         (symbol, stmts)
     }
 }
 
+impl FunctionState {
+    fn new(metadata: FunctionData) -> Self {
+        Self {
+            metadata,
+            vla_scope: Vec::new(),
+            vla_scope_starts: Vec::new(),
+            labels: HashMap::new(),
+            gotos: Vec::new(),
+            label_addresses: Vec::new(),
+            stmt_exprs: Vec::new(),
+            stmt_expr_count: 0,
+            switch_depths: Vec::new(),
+            switch_body: false,
+        }
+    }
+}
+
 impl FunctionAnalyzer<'_> {
+    fn state(&mut self) -> &mut FunctionState {
+        self.analyzer
+            .function
+            .as_mut()
+            .expect("FunctionAnalyzer should only be used inside a function")
+    }
     fn err(&mut self, err: SemanticError, location: Location) {
         self.analyzer.err(err, location);
     }
     fn enter_scope(&mut self) {
         self.analyzer.scope.enter();
         self.analyzer.tag_scope.enter();
+        let state = self.state();
+        state.vla_scope_starts.push(state.vla_scope.len());
     }
     fn leave_scope(&mut self, location: Location) {
         for object in self.analyzer.scope.get_all_immediate().values() {
@@ -1274,6 +2208,39 @@ impl FunctionAnalyzer<'_> {
         }
         self.analyzer.scope.exit();
         self.analyzer.tag_scope.exit();
+        let state = self.state();
+        if let Some(start) = state.vla_scope_starts.pop() {
+            state.vla_scope.truncate(start);
+        }
+    }
+    /// Check that no `goto` jumps into the scope of a VLA or into a statement expression.
+    ///
+    /// 6.8.6.1p1: A goto statement shall not jump from outside the scope of an identifier
+    /// having a variably modified type to inside the scope of that identifier.
+    fn check_gotos(&mut self) {
+        let state = self.state();
+        let gotos = std::mem::take(&mut state.gotos);
+        let labels = std::mem::take(&mut state.labels);
+        let label_addresses = std::mem::take(&mut state.label_addresses);
+        for address in label_addresses {
+            if !labels.contains_key(&address.data) {
+                let err = SemanticError::UndeclaredLabel(address.data);
+                self.err(err, address.location);
+            }
+        }
+        for (label, goto_scope, goto_stmt_exprs, location) in gotos {
+            // scopes are nested, so everything in scope at the label
+            // must also be in scope at the `goto`
+            let (label_scope, label_stmt_exprs) = match labels.get(&label) {
+                Some(scopes) => scopes,
+                None => continue,
+            };
+            if !goto_stmt_exprs.starts_with(label_stmt_exprs) {
+                self.err(SemanticError::GotoIntoStmtExpr(label), location);
+            } else if !goto_scope.starts_with(label_scope) {
+                self.err(SemanticError::GotoIntoVlaScope(label), location);
+            }
+        }
     }
 }
 
@@ -1284,6 +2251,32 @@ struct ParsedType {
     ctype: Type,
     // TODO: this is fishy
     declared_compound_type: bool,
+    /// The strictest alignment requested by `_Alignas`
+    alignment: Option<crate::arch::SIZE_T>,
+    /// The alignment of the typedef used as the type: `typedef int T __attribute__((aligned(8)));`
+    typedef_alignment: Option<crate::arch::SIZE_T>,
+    /// `_Thread_local int i;`
+    thread_local: bool,
+    /// GNU attributes: `__attribute__((noreturn)) void f();`
+    attributes: Vec<Locatable<ast::Attribute>>,
+    /// `constexpr int n = 5;`
+    constexpr: bool,
+    /// Whether the type should be inferred from the initializer: `auto x = 1;`
+    ///
+    /// If so, `ctype` is only a placeholder.
+    infer_type: bool,
+}
+
+/// The type named by `typeof` or `typeof_unqual`.
+///
+/// `typeof_unqual` removes `_Atomic` as well as the other qualifiers,
+/// which the caller is responsible for.
+fn typeof_type(ctype: Type, unqual: bool) -> Type {
+    if unqual {
+        ctype.non_atomic().clone()
+    } else {
+        ctype
+    }
 }
 
 use ast::{DeclarationSpecifier, UnitSpecifier};
@@ -1308,8 +2301,8 @@ fn count_specifiers(
         if spec != Long && count > 1 {
             if spec.is_type() {
                 let err = SemanticError::InvalidSpecifier {
-                    existing: spec.into(),
-                    new: spec.into(),
+                    existing: Box::new(spec.into()),
+                    new: Box::new(spec.into()),
                 };
                 error_handler.error(err, location);
             } else {
@@ -1324,7 +2317,16 @@ impl UnitSpecifier {
     fn is_qualifier(self) -> bool {
         use UnitSpecifier::*;
         match self {
-            Const | Volatile | Restrict | Inline | NoReturn => true,
+            Const | Volatile | Restrict | Atomic | Inline | NoReturn => true,
+            _ => false,
+        }
+    }
+    /// Returns whether this specifies anything about the type, including modifiers like `unsigned`.
+    fn is_type_specifier(self) -> bool {
+        use UnitSpecifier::*;
+        match self {
+            Bool | Char | Short | Int | Long | Int128 | Float | Double | Void | Signed
+            | Unsigned | Complex | Imaginary | VaList => true,
             _ => false,
         }
     }
@@ -1334,12 +2336,64 @@ impl UnitSpecifier {
     fn is_type(self) -> bool {
         use UnitSpecifier::*;
         match self {
-            Bool | Char | Int | Long | Float | Double | VaList => true,
+            Bool | Char | Int | Long | Int128 | Float | Double | VaList => true,
             _ => false,
         }
     }
 }
 
+/// Whether `value` can be converted to `ctype` without changing it.
+///
+/// `300` is not representable as an `unsigned char`, and `1.5` is not representable as an `int`.
+fn exactly_representable(value: &LiteralValue, ctype: &Type) -> bool {
+    use std::convert::TryFrom;
+    // `constexpr double _Complex z = 1;` only has a real part
+    let ctype = match ctype {
+        Type::Complex(real) => real,
+        _ => ctype,
+    };
+    let integer = match *value {
+        LiteralValue::Int(i) => i128::from(i),
+        LiteralValue::UnsignedInt(u) => i128::from(u),
+        LiteralValue::Char(c) => i128::from(c),
+        LiteralValue::Int128(i) => i,
+        LiteralValue::UnsignedInt128(u) => match i128::try_from(u) {
+            Ok(i) => i,
+            Err(_) => return *ctype == Type::Int128(false),
+        },
+        LiteralValue::Float(f) if *ctype == Type::Float => {
+            return f.is_nan() || f64::from(f as f32) == f
+        }
+        LiteralValue::Float(_) if ctype.is_floating() => return true,
+        LiteralValue::Float(f) if f.fract() != 0.0 || !f.is_finite() => return false,
+        // out of range values saturate, so they're still caught below
+        LiteralValue::Float(f) => f as i128,
+        LiteralValue::Str(_) => return true,
+    };
+    if *ctype == Type::Float {
+        return integer as f32 as i128 == integer;
+    } else if ctype.is_floating() {
+        return integer as f64 as i128 == integer;
+    } else if !ctype.is_integral() {
+        // `constexpr int *p = 0;`
+        return true;
+    }
+    let bits = match ctype {
+        Type::Bool => 1,
+        _ => match ctype.sizeof() {
+            Ok(size) => size * u64::from(crate::arch::CHAR_BIT),
+            Err(_) => return true,
+        },
+    };
+    let (min, max) = match (ctype.is_signed(), bits) {
+        (true, 128) => (i128::MIN, i128::MAX),
+        (false, 128) => (0, i128::MAX),
+        (true, _) => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+        (false, _) => (0, (1 << bits) - 1),
+    };
+    min <= integer && integer <= max
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use super::{Error, *};
@@ -1357,7 +2411,20 @@ pub(crate) mod test {
         A: Fn(&mut PureAnalyzer, S) -> R,
         CompileError: From<E>,
     {
-        let mut p = parser(input);
+        analyze_with(parser(input), parse_func, analyze_func)
+    }
+
+    /// Like `analyze`, but with a parser that has already been created
+    pub(crate) fn analyze_with<'c, P, A, R, S, E>(
+        mut p: Parser<PreProcessor<'c>>,
+        parse_func: P,
+        analyze_func: A,
+    ) -> CompileResult<R>
+    where
+        P: Fn(&mut Parser<PreProcessor<'c>>) -> Result<S, E>,
+        A: Fn(&mut PureAnalyzer, S) -> R,
+        CompileError: From<E>,
+    {
         let ast = parse_func(&mut p)?;
         let mut a = PureAnalyzer::new();
         let e = analyze_func(&mut a, ast);
@@ -1381,6 +2448,19 @@ pub(crate) mod test {
             .collect()
     }
 
+    fn c23_decl(s: &str) -> CompileResult<Declaration> {
+        c23_decls(s)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| panic!("expected a declaration or error: '{}'", s))
+    }
+
+    fn c23_decls(s: &str) -> Vec<CompileResult<Declaration>> {
+        Analyzer::new(c23_parser(s), false)
+            .map(|o| o.map(|l| l.data))
+            .collect()
+    }
+
     pub(crate) fn assert_errs_decls(input: &str, errs: usize, warnings: usize, decls: usize) {
         let mut a = Analyzer::new(parser(input), false);
         let (mut a_errs, mut a_decls) = (0, 0);
@@ -1485,6 +2565,47 @@ pub(crate) mod test {
             _ => panic!("wrong error"),
         }
     }
+    #[test]
+    fn asm_label() {
+        let label = |s: &str| decl(s).unwrap().symbol.get().attributes.asm_label.clone();
+        assert_eq!(label("int f(void) __asm__(\"g\");"), Some("g".into()));
+        assert_eq!(label("static int i __asm__(\"j\");"), Some("j".into()));
+        assert_eq!(label("int i;"), None);
+        let errs = decls("int f(void) { int i __asm__(\"j\"); return i; }");
+        assert!(errs.into_iter().any(|res| matches!(
+            res.map_err(|err| err.data),
+            Err(Error::Semantic(SemanticError::AsmLabelWithoutLinkage(_)))
+        )));
+        assert!(decl("int f(void) { extern int i __asm__(\"j\"); return i; }").is_ok());
+    }
+    #[test]
+    fn thread_local() {
+        assert!(
+            decl("_Thread_local int i;")
+                .unwrap()
+                .symbol
+                .get()
+                .thread_local
+        );
+        assert!(
+            decl("extern __thread int i;")
+                .unwrap()
+                .symbol
+                .get()
+                .thread_local
+        );
+        assert!(!decl("static int i;").unwrap().symbol.get().thread_local);
+        assert_no_change("static _Thread_local int i;");
+        for s in &[
+            "_Thread_local int f();",
+            "typedef _Thread_local int i;",
+            "int f(_Thread_local int i);",
+            "struct s { _Thread_local int i; };",
+            "int *_Thread_local p;",
+        ] {
+            assert!(decl(s).is_err(), "'{}' should be an error", s);
+        }
+    }
 
     #[test]
     fn function() {
@@ -1496,6 +2617,33 @@ pub(crate) mod test {
         assert_same("int f(int g(), ...);", "int f(int (*g)(), ...);");
     }
 
+    #[test]
+    fn old_style_function() {
+        let decl = decl("int f(a, b, c) char a; float b; { return a; }").unwrap();
+        let ftype = match &decl.symbol.get().ctype {
+            Type::Function(ftype) => ftype.clone(),
+            other => panic!("expected a function, got {}", other),
+        };
+        assert!(ftype.old_style);
+        // callers see the promoted types
+        let params: Vec<_> = ftype.params.iter().map(|p| p.get().ctype.clone()).collect();
+        assert_eq!(params, vec![Int(true), Double, Int(true)]);
+        // `a` and `b` are converted back to their declared types
+        match &decl.init {
+            Some(Initializer::FunctionBody(body)) => match &body[0].data {
+                StmtType::Decl(decls) => assert_eq!(decls.len(), 2),
+                other => panic!("expected a declaration, got {}", other),
+            },
+            _ => panic!("expected a function body"),
+        }
+
+        let program = "int f(a) double a; { return a; } int main() { return f(1) + f(1.0f); }";
+        assert!(decls(program).into_iter().all(|d| d.is_ok()));
+        // wrong number of arguments
+        let program = "int f(a) int a; { return a; } int main() { return f(); }";
+        assert!(decls(program).into_iter().any(|d| d.is_err()));
+    }
+
     #[test]
     fn test_decl_specifiers() {
         assert!(match_type(decl("char i;"), Type::Char(true)));
@@ -1503,27 +2651,65 @@ pub(crate) mod test {
         assert!(match_type(decl("signed short i;"), Type::Short(true)));
         assert!(match_type(decl("unsigned short i;"), Type::Short(false)));
         assert!(match_type(decl("long i;"), Type::Long(true)));
-        assert!(match_type(decl("long long i;"), Type::Long(true)));
+        assert!(match_type(decl("long long i;"), Type::LongLong(true)));
         assert!(match_type(decl("long unsigned i;"), Type::Long(false)));
         assert!(match_type(decl("int i;"), Type::Int(true)));
         assert!(match_type(decl("signed i;"), Type::Int(true)));
         assert!(match_type(decl("unsigned i;"), Type::Int(false)));
         assert!(match_type(decl("float f;"), Type::Float));
         assert!(match_type(decl("double d;"), Type::Double));
-        assert!(match_type(decl("long double d;"), Type::Double));
+        assert!(match_type(decl("long double d;"), Type::LongDouble));
         assert!(match_type(
             decl("void f();"),
             Type::Function(FunctionType {
                 return_type: Box::new(Type::Void),
                 params: vec![],
-                varargs: false
+                varargs: false,
+                old_style: false
             })
         ));
         assert!(match_type(decl("const volatile int f;"), Type::Int(true)));
-        assert!(match_type(decl("long double d;"), Type::Double));
+        assert!(match_type(decl("long double d;"), Type::LongDouble));
         assert!(match_type(decl("short int i;"), Type::Short(true)));
         assert!(match_type(decl("long int i;"), Type::Long(true)));
-        assert!(match_type(decl("long long int i;"), Type::Long(true)));
+        assert!(match_type(decl("long long int i;"), Type::LongLong(true)));
+        assert!(match_type(
+            decl("unsigned long long int i;"),
+            Type::LongLong(false)
+        ));
+        assert!(match_type(decl("__int128 i;"), Type::Int128(true)));
+        assert!(match_type(decl("signed __int128 i;"), Type::Int128(true)));
+        assert!(match_type(
+            decl("unsigned __int128 i;"),
+            Type::Int128(false)
+        ));
+        assert!(match_type(
+            decl("float _Complex z;"),
+            Type::Complex(Box::new(Type::Float))
+        ));
+        assert!(match_type(
+            decl("_Complex double z;"),
+            Type::Complex(Box::new(Type::Double))
+        ));
+        assert!(match_type(
+            decl("long double _Complex z;"),
+            Type::Complex(Box::new(Type::LongDouble))
+        ));
+        assert!(match_type(
+            decl("_Atomic int i;"),
+            Type::Atomic(Box::new(Type::Int(true)))
+        ));
+        assert!(match_type(
+            decl("_Atomic(unsigned long) i;"),
+            Type::Atomic(Box::new(Type::Long(false)))
+        ));
+        assert!(match_type(
+            decl("int *_Atomic p;"),
+            Type::Atomic(Box::new(Type::Pointer(
+                Box::new(Type::Int(true)),
+                Qualifiers::default()
+            )))
+        ));
     }
     #[test]
     fn test_bad_decl_specs() {
@@ -1538,10 +2724,22 @@ pub(crate) mod test {
             "float char i;",
             "float double i;",
             "double double i;",
+            "long long double i;",
             "double unsigned i;",
             "short double i;",
             "int void i;",
             "void int i;",
+            "int _Complex i;",
+            "unsigned _Complex double i;",
+            "long _Complex i;",
+            "double _Imaginary i;",
+            "_Atomic(_Atomic int) i;",
+            "_Atomic(int[3]) a;",
+            "_Atomic(int()) f;",
+            "long __int128 i;",
+            "__int128 int i;",
+            "__int128 __int128 i;",
+            "struct s { __int128 i: 1; } x;",
         ] {
             assert!(decl(s).is_err(), "'{}' should be an error", s);
         }
@@ -1620,6 +2818,7 @@ pub(crate) mod test {
                 return_type: Box::new(Pointer(Box::new(Type::Void), Qualifiers::default())),
                 params: vec![],
                 varargs: false,
+                old_style: false,
             })
         ));
         // cdecl: declare i as pointer to function returning int;
@@ -1630,6 +2829,7 @@ pub(crate) mod test {
                     return_type: Box::new(Int(true)),
                     params: vec![],
                     varargs: false,
+                    old_style: false,
                 })),
                 Qualifiers::default()
             )
@@ -1648,15 +2848,22 @@ pub(crate) mod test {
                             Box::new(Function(FunctionType {
                                 return_type: Box::new(Int(true)),
                                 params: vec![],
-                                varargs: false
+                                varargs: false,
+                                old_style: false
                             })),
                             Qualifiers::default()
                         ),
                         qualifiers: Default::default(),
                         storage_class: Default::default(),
+                        bitfield: None,
+                        alignment: None,
+                        thread_local: false,
+                        constexpr: None,
+                        attributes: Attributes::default(),
                     }
                     .insert()],
                     varargs: false,
+                    old_style: false,
                 })),
                 Qualifiers::default()
             )
@@ -1669,10 +2876,16 @@ pub(crate) mod test {
                     id: Default::default(),
                     ctype: Int(true),
                     qualifiers: Default::default(),
-                    storage_class: Default::default()
+                    storage_class: Default::default(),
+                    bitfield: None,
+                    alignment: None,
+                    thread_local: false,
+                    constexpr: None,
+                    attributes: Attributes::default(),
                 }
                 .insert()],
                 varargs: true,
+                old_style: false,
             })
         ));
     }
@@ -1687,9 +2900,15 @@ pub(crate) mod test {
                     ctype: Pointer(Box::new(Int(true)), Qualifiers::default()),
                     qualifiers: Default::default(),
                     storage_class: Default::default(),
+                    bitfield: None,
+                    alignment: None,
+                    thread_local: false,
+                    constexpr: None,
+                    attributes: Attributes::default(),
                 }
                 .insert()],
-                varargs: false
+                varargs: false,
+                old_style: false
             })
         ));
 
@@ -1707,9 +2926,15 @@ pub(crate) mod test {
                     ctype: Type::Void,
                     qualifiers: Qualifiers::default(),
                     storage_class: StorageClass::default(),
+                    bitfield: None,
+                    alignment: None,
+                    thread_local: false,
+                    constexpr: None,
+                    attributes: Attributes::default(),
                 }
                 .insert()],
                 varargs: false,
+                old_style: false,
             })
         ));
 
@@ -1744,9 +2969,15 @@ pub(crate) mod test {
                                 storage_class: Default::default(),
                                 id: Default::default(),
                                 qualifiers: Qualifiers::NONE,
+                                bitfield: None,
+                                alignment: None,
+                                thread_local: false,
+                                constexpr: None,
+                                attributes: Attributes::default(),
                             }
                             .insert()],
                             varargs: false,
+                            old_style: false,
                         })),
                         Qualifiers::default()
                     )),
@@ -1772,9 +3003,15 @@ pub(crate) mod test {
                         storage_class: Default::default(),
                         id: Default::default(),
                         qualifiers: Default::default(),
+                        bitfield: None,
+                        alignment: None,
+                        thread_local: false,
+                        constexpr: None,
+                        attributes: Attributes::default(),
                     }
                     .insert()],
                     varargs: false,
+                    old_style: false,
                 })),
                 Qualifiers::default()
             )
@@ -1801,6 +3038,7 @@ pub(crate) mod test {
                                 return_type: Box::new(Char(true)),
                                 params: vec![],
                                 varargs: false,
+                                old_style: false,
                             })),
                             Qualifiers::default()
                         )),
@@ -1810,6 +3048,7 @@ pub(crate) mod test {
                 )),
                 params: vec![],
                 varargs: false,
+                old_style: false,
             })
         ));
     }
@@ -1918,6 +3157,128 @@ pub(crate) mod test {
         assert!(decl("struct { int a:5; } b;").is_ok());
         assert!(decl("struct { int a:5, b:6; } c;").is_ok());
         assert!(decl("struct { extern int a:5; } d;").is_err());
+        assert!(decl("struct { int a:5, :3, :0, b:2; } e;").is_ok());
+        assert!(decl("struct { unsigned a:32; _Bool b:1; long c:64; } f;").is_ok());
+        assert!(decl("struct { int a:0; } g;").is_err());
+        assert!(decl("struct { int a:33; } h;").is_err());
+        assert!(decl("struct { _Bool a:2; } i;").is_err());
+        assert!(decl("struct { float a:1; } j;").is_err());
+        assert!(decl("struct { int *a:1; } k;").is_err());
+        assert!(decl("struct { int a:-1; } l;").is_err());
+    }
+    #[test]
+    fn flexible_array_members() {
+        assert!(decl("struct { int len; char data[]; } a;").is_ok());
+        assert!(decl("struct { int len; int : 3; char data[]; } b;").is_ok());
+        assert!(decl("struct { char data[]; int len; } c;").is_err());
+        assert!(decl("struct { char data[]; } d;").is_err());
+        assert!(decl("struct { int : 3; char data[]; } e;").is_err());
+        assert!(decl("union { int len; char data[]; } f;").is_err());
+        assert!(decl("struct { int len; char data[]; } g[2];").is_err());
+        assert!(decls(
+            "struct s { int len; char data[]; }; _Static_assert(sizeof(struct s) == 4, \"size\");"
+        )
+        .into_iter()
+        .all(|d| d.is_ok()));
+        // GNU extension
+        assert!(decl("struct { int len; char data[]; } h = { 3, \"hi\" };").is_ok());
+        assert!(
+            decls("int main() { struct { int len; char data[]; } i = { 1, { 2 } }; }")
+                .into_iter()
+                .any(|d| d.is_err())
+        );
+        assert!(
+            decls("int main() { struct { int len; char data[]; } j = { 1 }; }")
+                .into_iter()
+                .all(|d| d.is_ok())
+        );
+    }
+    #[test]
+    fn alignas() {
+        let alignment = |s: &str| decl(s).unwrap().symbol.get().alignof().unwrap();
+        assert_eq!(alignment("_Alignas(16) int i;"), 16);
+        assert_eq!(alignment("_Alignas(double) char c;"), 8);
+        assert_eq!(alignment("_Alignas(4) _Alignas(32) _Alignas(8) int i;"), 32);
+        assert_eq!(alignment("_Alignas(0) short s;"), 2);
+        assert_eq!(alignment("struct { char c; _Alignas(64) int i; } s;"), 64);
+        assert!(decls(
+            "struct s { char c; _Alignas(16) char d; }; _Static_assert(sizeof(struct s) == 32, \"size\");"
+        )
+        .into_iter()
+        .all(|d| d.is_ok()));
+
+        assert!(decl("_Alignas(3) int i;").is_err());
+        assert!(decl("_Alignas(-4) int i;").is_err());
+        assert!(decl("_Alignas(1) int i;").is_err());
+        assert!(decl("_Alignas(8) typedef int I;").is_err());
+        assert!(decl("_Alignas(8) int f();").is_err());
+        assert!(decl("int f(_Alignas(8) int i);").is_err());
+        assert!(decl("struct { _Alignas(8) int i : 3; } s;").is_err());
+        assert!(decl("int i = sizeof(_Alignas(8) int);").is_err());
+    }
+    #[test]
+    fn variable_length_arrays() {
+        let errors = |s: &str| decls(s).into_iter().filter(Result::is_err).count();
+        assert_eq!(errors("int f(int n) { int a[n]; return sizeof a; }"), 0);
+        assert_eq!(
+            errors("int f(int n) { int a[n][3]; return sizeof(a[0]); }"),
+            0
+        );
+        assert_eq!(
+            errors("int f(int n) { int (*p)[n]; return sizeof(int[n]); }"),
+            0
+        );
+        assert_eq!(errors("void f(int n, double a[n][n]);"), 0);
+        assert_eq!(
+            errors("double f(int n, double a[n][n]) { return a[1][1]; }"),
+            0
+        );
+        // VLAs can only appear at block or prototype scope
+        assert_eq!(errors("int n; int a[n];"), 1);
+        assert_eq!(errors("int f(int n) { int a[n] = {1}; }"), 1);
+        assert_eq!(errors("int f(int n) { static int a[n]; }"), 1);
+        assert_eq!(errors("int f(int n) { extern int (*p)[n]; }"), 1);
+        assert_eq!(errors("int f(int n) { struct s { int a[n]; } s; }"), 1);
+        assert_eq!(errors("int f(int n) { (int[n]){0}; }"), 1);
+        assert_eq!(errors("int f(int n) { goto l; { int a[n]; l: n++; } }"), 1);
+        assert_eq!(
+            errors("int f(int n) { { int a[n]; l: n++; goto l; } goto m; m: n--; }"),
+            0
+        );
+        // the size is fixed when the array is declared
+        let vla = decl("int f(int n) { int a[n]; }").unwrap();
+        match &vla.init {
+            Some(Initializer::FunctionBody(body)) => match &body[0].data {
+                StmtType::Decl(decls) => {
+                    assert_eq!(decls.len(), 2);
+                    assert_eq!(decls[0].data.symbol.get().ctype, Type::Long(false));
+                    assert!(decls[1].data.symbol.get().ctype.is_vla());
+                }
+                other => panic!("expected a declaration, got {:?}", other),
+            },
+            other => panic!("expected a function body, got {:?}", other),
+        }
+    }
+    #[test]
+    fn static_assert() {
+        assert!(decls("_Static_assert(1, \"ok\");").is_empty());
+        assert!(decls("_Static_assert(sizeof(int) == 4);").is_empty());
+        match &decls("_Static_assert(sizeof(char) == 2, \"char is a byte\");")[..] {
+            [Err(err)] => assert!(err.data.to_string().contains("char is a byte")),
+            other => panic!("expected an error, got {:?}", other),
+        }
+        let errors = |s: &str| decls(s).into_iter().filter(Result::is_err).count();
+        assert_eq!(errors("int i; _Static_assert(i, \"not constant\");"), 1);
+        assert_eq!(errors("_Static_assert(1.0, \"not an integer\");"), 1);
+        assert_eq!(
+            errors("int main(void) { _Static_assert(0, \"block scope\"); }"),
+            1
+        );
+        assert_eq!(errors("struct s { int i; _Static_assert(1); };"), 0);
+        assert_eq!(
+            errors("struct s { int i; _Static_assert(0, \"member\"); };"),
+            1
+        );
     }
     #[test]
     fn lol() {
@@ -1951,4 +3312,236 @@ int main() {
             "extern int f(void) {\n    return (int)(1);\n}\n",
         );
     }
+    #[test]
+    fn typeof_specifier() {
+        let ctype = |s: &str| {
+            c23_decls(s)
+                .pop()
+                .unwrap()
+                .unwrap()
+                .symbol
+                .get()
+                .ctype
+                .clone()
+        };
+        assert_eq!(ctype("typeof(int) i;"), Type::Int(true));
+        assert_eq!(ctype("typeof(1) i;"), Type::Long(true));
+        assert_eq!(
+            ctype("int a[3]; typeof(a) b;"),
+            Array(Box::new(Int(true)), ArrayType::Fixed(3))
+        );
+        assert_eq!(ctype("_Atomic int a; typeof_unqual(a) b;"), Type::Int(true));
+        assert_eq!(
+            ctype("typeof(char *) p;"),
+            Pointer(Box::new(Char(true)), Qualifiers::default())
+        );
+        let is_const = |s: &str| {
+            c23_decls(s)
+                .pop()
+                .unwrap()
+                .unwrap()
+                .symbol
+                .get()
+                .qualifiers
+                .c_const
+        };
+        assert!(is_const("const int c = 1; typeof(c) d = 2;"));
+        assert!(is_const("typeof(const int) d = 2;"));
+        assert!(!is_const("const int c = 1; typeof_unqual(c) d;"));
+        assert!(c23_decl("typeof(1) int i;").is_err());
+        assert!(c23_decl("typeof(extern int) i;").is_err());
+    }
+    #[test]
+    fn constexpr() {
+        let symbol = c23_decl("constexpr int n = 5;").unwrap().symbol.get();
+        assert_eq!(symbol.constexpr, Some(LiteralValue::Int(5)));
+        assert!(symbol.qualifiers.c_const);
+        let last = |s: &str| c23_decls(s).pop().unwrap();
+        assert_eq!(
+            last("constexpr int n = 2 + 3; int a[n];")
+                .unwrap()
+                .symbol
+                .get()
+                .ctype,
+            Array(Box::new(Int(true)), ArrayType::Fixed(5))
+        );
+        assert!(
+            last("constexpr long n = 4; enum { A = n * 2 }; _Static_assert(A == 8, \"\");").is_ok()
+        );
+        assert!(last("constexpr double d = 1.5; static double e = d;").is_ok());
+        assert!(c23_decl("constexpr int a[] = { 1, 2 };").is_ok());
+
+        let errors = |s: &str| c23_decls(s).into_iter().filter(Result::is_err).count();
+        assert_eq!(errors("constexpr int n;"), 1);
+        assert_eq!(errors("int i; constexpr int n = i;"), 1);
+        assert_eq!(errors("int i; constexpr int a[] = { 1, i };"), 1);
+        assert_eq!(errors("constexpr int n = 1; int f() { n = 2; }"), 1);
+        // the value must be exactly representable
+        assert_eq!(errors("constexpr unsigned char u = 300;"), 1);
+        assert_eq!(errors("constexpr int f = 1.5;"), 1);
+        assert_eq!(errors("constexpr unsigned u = -1;"), 1);
+        assert_eq!(errors("constexpr float f = 0.1;"), 1);
+        assert_eq!(errors("constexpr char a[] = { 'a', 1000 };"), 1);
+        assert_eq!(
+            errors("int f() { constexpr signed char c = 200; return c; }"),
+            1
+        );
+        assert_eq!(errors("constexpr short s = 100000;"), 1);
+        assert_eq!(errors("constexpr _Bool b = 2;"), 1);
+        assert_eq!(errors("constexpr unsigned char u = 255;"), 0);
+        assert_eq!(errors("constexpr signed char c = -128;"), 0);
+        assert_eq!(errors("constexpr double d = 3;"), 0);
+        assert_eq!(errors("constexpr float f = 0.5;"), 0);
+        assert_eq!(errors("constexpr long l = 'a';"), 0);
+        for s in &[
+            "extern constexpr int n = 1;",
+            "constexpr typedef int I;",
+            "_Thread_local constexpr int n = 1;",
+            "constexpr int f();",
+            "constexpr int f() { return 1; }",
+            "int f(constexpr int i);",
+            "struct s { constexpr int i; };",
+            "int i = sizeof(constexpr int);",
+        ] {
+            assert!(c23_decl(s).is_err(), "'{}' should be an error", s);
+        }
+    }
+    #[test]
+    fn auto_type_inference() {
+        let ctype = |s: &str| decls(s).pop().unwrap().unwrap().symbol.get().ctype.clone();
+        assert_eq!(ctype("auto x = 1;"), Long(true));
+        assert_eq!(ctype("auto x = { 1.0 };"), Double);
+        assert_eq!(
+            ctype("auto s = \"hi\";"),
+            Pointer(Box::new(Char(true)), Qualifiers::default())
+        );
+        assert_eq!(ctype("_Atomic int a; auto b = a;"), Int(true));
+        assert_eq!(ctype("static auto f = (float)1;"), Float);
+        assert_eq!(
+            decl("static auto f = 1;")
+                .unwrap()
+                .symbol
+                .get()
+                .storage_class,
+            StorageClass::Static
+        );
+        assert!(decl("int f() { auto i = 1; return i; }").is_ok());
+        // `auto` is still a storage class
+        assert!(decl("int f() { auto int i = 1; return i; }").is_ok());
+        assert!(decl("auto int i = 1;").is_err());
+        assert!(decl("auto i;").is_err());
+        assert!(decl("auto *p = 0;").is_err());
+        assert!(decl("auto a[] = { 1, 2 };").is_err());
+        assert!(decl("auto a = { 1, 2 };").is_err());
+        assert!(decl("auto v = (void)0;").is_err());
+    }
+    #[test]
+    fn nullptr() {
+        let errors = |s: &str| c23_decls(s).into_iter().filter(Result::is_err).count();
+        assert_eq!(errors("int *p = nullptr;"), 0);
+        assert_eq!(errors("_Bool b = nullptr;"), 0);
+        assert_eq!(errors("typeof(nullptr) n = 0;"), 0);
+        assert_eq!(errors("typeof(nullptr) n; char *p = n;"), 0);
+        assert_eq!(errors("int f(int *p) { return p == nullptr; }"), 0);
+        assert_eq!(errors("int i = nullptr;"), 1);
+        assert_eq!(errors("typeof(nullptr) n = 1;"), 1);
+        assert_eq!(errors("int *p; typeof(nullptr) n = p;"), 1);
+        assert_eq!(
+            c23_decl("auto n = nullptr;").unwrap().symbol.get().ctype,
+            Type::NullPtr
+        );
+        assert_eq!(Type::NullPtr.sizeof(), Ok(8));
+        assert_eq!(Type::NullPtr.to_string(), "nullptr_t");
+    }
+    #[test]
+    fn statement_expressions() {
+        let errors = |s: &str| decls(s).into_iter().filter(Result::is_err).count();
+        assert_eq!(
+            errors("int f(int a) { return ({ int b = a; b * 2; }); }"),
+            0
+        );
+        assert_eq!(
+            errors("int f(int a) { __typeof__(({ a; })) b = a; return b; }"),
+            0
+        );
+        assert_eq!(
+            errors("int f(int a) { a += ({ a += 1; a; }); return a; }"),
+            0
+        );
+        assert_eq!(
+            errors("int f(int a) { while (1) ({ break; }); return a; }"),
+            0
+        );
+        assert_eq!(errors("int f(int a) { ({ goto l; }); l: return a; }"), 0);
+        assert_eq!(
+            errors("int f(int a) { return ({ switch (a) { case 1: a++; } a; }); }"),
+            0
+        );
+        // the statements have their own scope
+        assert_eq!(errors("int f() { ({ int b = 1; b; }); return b; }"), 1);
+        // the value is `void` unless the last statement is an expression
+        assert_eq!(errors("int f() { return ({ int b = 1; }); }"), 1);
+        // jumping into a statement expression is not allowed
+        assert_eq!(errors("int f() { goto l; ({ l: 1; }); }"), 1);
+        assert_eq!(
+            errors("int f(int a) { switch (a) { ({ case 1: 1; }); } }"),
+            1
+        );
+        assert!(decl("int a = ({ 1; });").is_err());
+
+        let value = decl("long f() { return ({ 1; 2; }); }").unwrap();
+        match &value.init {
+            Some(Initializer::FunctionBody(body)) => match &body[0].data {
+                StmtType::Return(Some(expr)) => {
+                    assert_eq!(expr.ctype, Type::Long(true));
+                    assert_eq!(expr.to_string(), "({ 1; 2; })");
+                }
+                other => panic!("expected a return statement, got {:?}", other),
+            },
+            other => panic!("expected a function body, got {:?}", other),
+        }
+    }
+    #[test]
+    fn gnu_extensions() {
+        // (errors, warnings)
+        let analyze = |s: &str| {
+            let mut a = Analyzer::new(parser(s).gnu_extensions(true), false);
+            let errors = (&mut a).filter(Result::is_err).count();
+            (errors, a.inner.error_handler.warnings.len())
+        };
+        // case ranges
+        assert_eq!(
+            analyze(
+                "int f(int c) { switch (c) { case 'a' ... 'z': return 1; default: return 0; } }"
+            ),
+            (0, 0)
+        );
+        assert_eq!(
+            analyze("int f(int c) { switch (c) { case -5 ... 5: return 1; } return 0; }"),
+            (0, 0)
+        );
+        assert_eq!(
+            analyze("int f(int c) { switch (c) { case 5 ... 1: return 1; } return 0; }"),
+            (0, 1)
+        );
+        // computed goto
+        assert_eq!(
+            analyze(
+                "int f(int i) { void *l[] = { &&a, &&b }; goto *l[i]; a: return 1; b: return 2; }"
+            ),
+            (0, 0)
+        );
+        assert_eq!(analyze("void f() { void *p = &&missing; }"), (1, 0));
+        assert_eq!(analyze("void f() { goto *1; }"), (1, 0));
+        assert_eq!(analyze("void *p = &&a;"), (1, 0));
+        // conditionals with an omitted operand
+        assert_eq!(analyze("int f(int a) { return a ?: 5; }"), (0, 0));
+        assert_eq!(analyze("int g(void); int f() { return g() ?: 5; }"), (0, 0));
+        assert_eq!(analyze("int x = 0 ?: 5;"), (0, 0));
+
+        // this is an error without `-std=gnu11`
+        assert!(decls("int f(int a) { return a ?: 5; }")
+            .into_iter()
+            .any(|res| res.is_err()));
+    }
 }
@@ -1,5 +1,11 @@
 use super::FunctionAnalyzer;
-use crate::data::{ast, error::SemanticError, hir::*, lex::Locatable, Location};
+use crate::data::{
+    ast,
+    error::{SemanticError, Warning},
+    hir::*,
+    lex::Locatable,
+    Location, Type,
+};
 
 impl FunctionAnalyzer<'_> {
     #[inline(always)]
@@ -10,9 +16,13 @@ impl FunctionAnalyzer<'_> {
         use ast::StmtType::*;
         use StmtType as S;
 
+        // `if (sizeof(char[n])) return;`: declarations from the condition belong before the `if`,
+        // not inside the body
+        let outer_decls = std::mem::take(&mut self.analyzer.decl_side_channel);
         // ugh so much boilerplate
         let data = match stmt.data {
             Compound(stmts) => {
+                let switch_body = std::mem::take(&mut self.state().switch_body);
                 // 6.2.1 Scopes of identifiers
                 self.enter_scope();
                 let mut parsed = Vec::new();
@@ -20,10 +30,15 @@ impl FunctionAnalyzer<'_> {
                     parsed.push(self.parse_stmt(inner));
                 }
                 self.leave_scope(stmt.location);
+                self.check_fallthrough(&parsed, switch_body);
                 S::Compound(parsed)
             }
             // 6.8.3 Expression and null statements
-            Expr(expr) => S::Expr(self.expr(expr)),
+            Expr(expr) => {
+                let expr = self.expr(expr);
+                self.check_discarded(&expr);
+                S::Expr(expr)
+            }
             // 6.8.4.1 The if statement
             If(condition, then, otherwise) => {
                 let condition = self
@@ -41,8 +56,20 @@ impl FunctionAnalyzer<'_> {
                         SemanticError::NonIntegralSwitch(value.ctype.clone()),
                         stmt.location,
                     )
+                } else if let Type::Int128(_) = value.ctype {
+                    // case labels are only 64 bits wide
+                    self.err(
+                        SemanticError::Int128Switch(value.ctype.clone()),
+                        stmt.location,
+                    )
                 }
+                let state = self.state();
+                state
+                    .switch_depths
+                    .push((state.stmt_exprs.len(), state.vla_scope.len()));
+                state.switch_body = matches!(body.data, Compound(_));
                 let body = self.parse_stmt(*body);
+                self.state().switch_depths.pop();
                 S::Switch(value, Box::new(body))
             }
             // 6.8.5.2 The do statement
@@ -75,6 +102,21 @@ impl FunctionAnalyzer<'_> {
                 let condition = condition
                     .map(|e| Box::new(self.expr(*e).truthy(&mut self.analyzer.error_handler)));
                 let post_loop = post_loop.map(|e| Box::new(self.expr(*e)));
+                // `for (int i = 0; i < n; i += 1)`: the temporaries for `i += 1` use `i`,
+                // so they have to be declared after the initializer
+                let mut decls = std::mem::take(&mut self.analyzer.decl_side_channel);
+                let initializer = match initializer.data {
+                    _ if decls.is_empty() => initializer,
+                    S::Decl(mut inner) => {
+                        inner.append(&mut decls);
+                        Stmt::new(S::Decl(inner), initializer.location)
+                    }
+                    _ => {
+                        let location = initializer.location;
+                        let decls = Stmt::new(S::Decl(decls), location);
+                        Stmt::new(S::Compound(vec![initializer, decls]), location)
+                    }
+                };
                 let body = self.parse_stmt(*body);
                 self.leave_scope(stmt.location);
                 S::For(Box::new(initializer), condition, post_loop, Box::new(body))
@@ -82,97 +124,307 @@ impl FunctionAnalyzer<'_> {
             // 6.8.1 Labeled statements
             // TODO: all of these should have semantic checking here, not in the backend
             Label(name, inner) => {
+                let state = self.state();
+                let scopes = (state.vla_scope.clone(), state.stmt_exprs.clone());
+                state.labels.insert(name, scopes);
                 let inner = self.parse_stmt(*inner);
                 S::Label(name, Box::new(inner))
             }
-            Case(expr, inner) => self.case_statement(*expr, *inner, stmt.location),
+            Case(expr, upper, inner) => {
+                self.check_case_scope(false, stmt.location);
+                self.case_statement(*expr, upper.map(|e| *e), *inner, stmt.location)
+            }
             // 6.8.1 Labeled statements
-            Default(inner) => S::Default(Box::new(self.parse_stmt(*inner))),
+            Default(inner) => {
+                self.check_case_scope(true, stmt.location);
+                S::Default(Box::new(self.parse_stmt(*inner)))
+            }
             // 6.8.6.1 The goto statement
-            Goto(label) => S::Goto(label),
+            Goto(label) => {
+                let state = self.state();
+                let (vla_scope, stmt_exprs) = (state.vla_scope.clone(), state.stmt_exprs.clone());
+                state
+                    .gotos
+                    .push((label, vla_scope, stmt_exprs, stmt.location));
+                S::Goto(label)
+            }
+            // GNU computed goto: `goto *labels[i];`
+            IndirectGoto(target) => {
+                let target = self.expr(*target).rval();
+                if !target.ctype.is_pointer() {
+                    let err = SemanticError::IndirectGotoNotPointer(target.ctype.clone());
+                    self.err(err, stmt.location);
+                }
+                S::IndirectGoto(target)
+            }
             // 6.8.6.2 The continue statement
             Continue => S::Continue,
             // 6.8.6.3 The break statement
             Break => S::Break,
             Return(value) => self.return_statement(value, stmt.location),
+            // 6.7.12.5 The fallthrough attribute
+            Decl(decls) if decls.is_attribute_declaration() => {
+                if self.analyzer.attribute_declaration(decls.specifiers) {
+                    S::Fallthrough
+                } else {
+                    S::default()
+                }
+            }
             // 6.7 Declarations
-            Decl(decls) => S::Decl(self.analyzer.parse_declaration(decls, stmt.location)),
-        };
-        let data = if !self.analyzer.decl_side_channel.is_empty() {
-            let decls = std::mem::replace(&mut self.analyzer.decl_side_channel, Vec::new());
-            // this location is wrong for the declarations, but it's _probably_ fine
-            let decl_stmt = Stmt::new(S::Decl(decls), stmt.location);
-            S::Compound(vec![decl_stmt, Stmt::new(data, stmt.location)])
-        } else {
-            data
+            Decl(decls) => {
+                let decls = self.analyzer.parse_declaration(decls, stmt.location);
+                // int a[n];
+                self.state().vla_scope.extend(
+                    decls
+                        .iter()
+                        .map(|decl| decl.data.symbol)
+                        .filter(|symbol| symbol.get().ctype.is_variably_modified()),
+                );
+                S::Decl(decls)
+            }
+            StaticAssert(assert) => {
+                self.analyzer.static_assert(*assert, stmt.location);
+                S::default()
+            }
+            // the parser already reported this
+            Error => S::default(),
         };
-        Locatable::new(data, stmt.location)
+        let decls = std::mem::replace(&mut self.analyzer.decl_side_channel, outer_decls);
+        Locatable::new(
+            Self::prepend_decls(decls, data, stmt.location),
+            stmt.location,
+        )
+    }
+    /// Declare the hidden variables in `decls` before running `data`.
+    fn prepend_decls(
+        mut decls: Vec<Locatable<Declaration>>,
+        data: StmtType,
+        location: Location,
+    ) -> StmtType {
+        match data {
+            _ if decls.is_empty() => data,
+            // `int a[n];`: a new block would free the VLA as soon as it was declared
+            StmtType::Decl(mut inner) => {
+                decls.append(&mut inner);
+                StmtType::Decl(decls)
+            }
+            _ => {
+                // this location is wrong for the declarations, but it's _probably_ fine
+                let decl_stmt = Stmt::new(StmtType::Decl(decls), location);
+                StmtType::Compound(vec![decl_stmt, Stmt::new(data, location)])
+            }
+        }
     }
     // 6.8.1 Labeled statements
     fn case_statement(
         &mut self,
         expr: ast::Expr,
+        upper: Option<ast::Expr>,
         inner: ast::Stmt,
         location: Location,
     ) -> StmtType {
+        let (lower, lower_signed) = self.case_value(expr, location);
+        // GNU case range: `case 'a' ... 'z':`
+        let upper = match upper {
+            Some(upper) => {
+                let (upper, upper_signed) = self.case_value(upper, location);
+                let empty = if lower_signed && upper_signed {
+                    (upper as i64) < (lower as i64)
+                } else {
+                    upper < lower
+                };
+                // `case 5 ... 1:` never matches, but code can still fall through to it
+                if empty {
+                    self.analyzer.warn(Warning::EmptyCaseRange, location);
+                    return self.parse_stmt(inner).data;
+                }
+                Some(upper)
+            }
+            None => None,
+        };
+        let inner = self.parse_stmt(inner);
+        StmtType::Case(lower, upper, Box::new(inner))
+    }
+    /// The value of the constant expression in a `case` label, and whether it is signed.
+    fn case_value(&mut self, expr: ast::Expr, location: Location) -> (u64, bool) {
         use super::expr::literal;
 
-        let expr = match self.expr(expr).const_fold() {
+        let expr = match self.expr(expr).rval().const_fold() {
             Ok(e) => e,
             Err(err) => {
                 self.analyzer.error_handler.push_back(err);
                 Expr::zero(location)
             }
         };
-        let int = match expr.into_literal() {
-            Ok(LiteralValue::Int(i)) => i as u64,
-            Ok(LiteralValue::UnsignedInt(u)) => u,
-            Ok(LiteralValue::Char(c)) => c.into(),
+        match expr.into_literal() {
+            Ok(LiteralValue::Int(i)) => (i as u64, true),
+            Ok(LiteralValue::UnsignedInt(u)) => (u, false),
+            Ok(LiteralValue::Char(c)) => (c.into(), true),
+            Ok(LiteralValue::Int128(i)) => (i as u64, true),
+            Ok(LiteralValue::UnsignedInt128(u)) => (u as u64, false),
             Ok(other) => {
                 let ctype = literal(other, location).ctype;
                 self.err(SemanticError::NonIntegralExpr(ctype), location);
-                0
+                (0, true)
             }
             Err(other) => {
-                self.err(SemanticError::NotConstant(other), location);
-                0
+                self.err(SemanticError::NotConstant(Box::new(other)), location);
+                (0, true)
             }
+        }
+    }
+    /// `switch (i) { ({ case 1: ; }); }`: a `case` can't jump into a statement expression
+    ///
+    /// 6.8.4.2p2: If a switch statement has an associated case or default label
+    /// within the scope of an identifier with a variably modified type,
+    /// the entire switch statement shall be within the scope of that identifier.
+    fn check_case_scope(&mut self, is_default: bool, location: Location) {
+        let state = self.state();
+        let (stmt_expr_depth, vla_depth) = match state.switch_depths.last() {
+            Some(&depths) => depths,
+            None => return,
         };
-        let inner = self.parse_stmt(inner);
-        StmtType::Case(int, Box::new(inner))
+        if state.stmt_exprs.len() > stmt_expr_depth {
+            self.err(SemanticError::CaseInStmtExpr { is_default }, location);
+        } else if state.vla_scope.len() > vla_depth {
+            self.err(SemanticError::CaseInVlaScope { is_default }, location);
+        }
+    }
+    /// GNU statement expression: `({ int x = f(); x; })`
+    ///
+    /// The statements have their own block scope.
+    /// The value is the last statement if it is an expression statement, and `void` otherwise.
+    pub(super) fn stmt_expr(
+        &mut self,
+        mut stmts: ast::CompoundStatement,
+        location: Location,
+    ) -> Expr {
+        // `a += ({ 1; })`: the temporaries for the enclosing expression are declared outside the block
+        let outer_decls = std::mem::take(&mut self.analyzer.decl_side_channel);
+        let state = self.state();
+        state.stmt_expr_count += 1;
+        let id = state.stmt_expr_count;
+        state.stmt_exprs.push(id);
+        self.enter_scope();
+
+        let value = match stmts.pop() {
+            Some(Locatable {
+                data: ast::StmtType::Expr(expr),
+                ..
+            }) => Some(expr),
+            Some(other) => {
+                stmts.push(other);
+                None
+            }
+            None => None,
+        };
+        let mut parsed: Vec<_> = stmts.into_iter().map(|s| self.parse_stmt(s)).collect();
+        self.check_fallthrough(&parsed, false);
+        let value = value.map(|expr| Box::new(self.expr(expr).rval()));
+        // `({ a += 1; })`: the temporary for `a` is only used by the value
+        if !self.analyzer.decl_side_channel.is_empty() {
+            let decls = std::mem::take(&mut self.analyzer.decl_side_channel);
+            parsed.push(Stmt::new(StmtType::Decl(decls), location));
+        }
+
+        self.leave_scope(location);
+        self.state().stmt_exprs.pop();
+        self.analyzer.decl_side_channel = outer_decls;
+        Expr {
+            ctype: value
+                .as_ref()
+                .map_or(Type::Void, |value| value.ctype.clone()),
+            lval: false,
+            location,
+            expr: ExprType::StmtExpr(parsed, value),
+        }
+    }
+    /// Warn about `case` labels that can be reached from the code before them,
+    /// and `[[fallthrough]]` that isn't followed by a label.
+    ///
+    /// `switch (i) { case 1: f(); case 2: g(); }`
+    ///
+    /// The implicit fall-through warning is only given with `-Wimplicit-fallthrough`.
+    /// If `switch_body` is true, the statements before the first label are never run.
+    fn check_fallthrough(&mut self, stmts: &[Stmt], switch_body: bool) {
+        // whether control can reach `previous`
+        let mut reachable = !switch_body;
+        for pair in stmts.windows(2) {
+            let (previous, next) = (&pair[0], &pair[1]);
+            if let StmtType::Case(..) | StmtType::Default(_) | StmtType::Label(..) = previous.data {
+                reachable = true;
+            }
+            let next_is_label = matches!(next.data, StmtType::Case(..) | StmtType::Default(_));
+            // case 1: [[fallthrough]];
+            let mut innermost = &previous.data;
+            while let StmtType::Case(_, _, inner)
+            | StmtType::Default(inner)
+            | StmtType::Label(_, inner) = innermost
+            {
+                innermost = &inner.data;
+            }
+            if *innermost == StmtType::Fallthrough {
+                if !next_is_label {
+                    self.analyzer
+                        .warn(Warning::MisplacedFallthrough, previous.location);
+                }
+            } else if next_is_label
+                && self.analyzer.warn_fallthrough
+                && reachable
+                && has_code(&previous.data)
+                && can_complete(&previous.data)
+            {
+                self.analyzer
+                    .warn(Warning::ImplicitFallthrough, next.location);
+            }
+            reachable = reachable && can_complete(&previous.data);
+        }
+    }
+    /// Warn if `expr` calls a function declared `[[nodiscard]]`.
+    ///
+    /// Casting the result to `void` silences the warning.
+    fn check_discarded(&mut self, expr: &Expr) {
+        if let ExprType::FuncCall(func, _) = &expr.expr {
+            if let ExprType::Id(symbol) = func.expr {
+                let metadata = symbol.get();
+                if let Some(message) = &metadata.attributes.nodiscard {
+                    let warning = Warning::UnusedResult(metadata.id, message.clone());
+                    self.analyzer.warn(warning, expr.location);
+                }
+            }
+        }
     }
     // 6.8.6.4 The return statement
     // A value of `None` for `expr` means `return;`
     fn return_statement(&mut self, expr: Option<ast::Expr>, location: Location) -> StmtType {
-        use crate::data::Type;
-
         let expr = expr.map(|e| self.expr(e));
-        let ret_type = &self.metadata.return_type;
-        match (expr, *ret_type != Type::Void) {
+        // > A function declared with a _Noreturn function specifier shall not return to its caller.
+        let metadata = &self.state().metadata;
+        let (id, no_return) = (metadata.id, metadata.no_return);
+        let ret_type = metadata.return_type.clone();
+        if no_return {
+            let warning = Warning::ReturnInNoreturn(id);
+            self.analyzer.warn(warning, location);
+        }
+        match (expr, ret_type != Type::Void) {
             // void f() { return ;}
             (None, false) => StmtType::Return(None),
             // int f() { return; }
             (None, true) => {
-                self.err(
-                    SemanticError::MissingReturnValue(self.metadata.id),
-                    location,
-                );
+                self.err(SemanticError::MissingReturnValue(id), location);
                 StmtType::Return(None)
             }
             // void f() { return 1; }
             (Some(expr), false) => {
-                self.err(
-                    SemanticError::ReturnFromVoid(self.metadata.id),
-                    expr.location,
-                );
+                self.err(SemanticError::ReturnFromVoid(id), expr.location);
                 StmtType::Return(None)
             }
             // int f() { return 1; }
             (Some(expr), true) => {
                 let expr = expr.rval();
-                if expr.ctype != *ret_type {
+                if expr.ctype != ret_type {
                     StmtType::Return(Some(
-                        expr.implicit_cast(ret_type, &mut self.analyzer.error_handler),
+                        expr.implicit_cast(&ret_type, &mut self.analyzer.error_handler),
                     ))
                 } else {
                     StmtType::Return(Some(expr))
@@ -182,24 +434,66 @@ impl FunctionAnalyzer<'_> {
     }
 }
 
+/// Whether `stmt` does anything other than declare labels or empty statements: `case 1: ;`
+fn has_code(stmt: &StmtType) -> bool {
+    match stmt {
+        StmtType::Case(_, _, inner) | StmtType::Default(inner) | StmtType::Label(_, inner) => {
+            has_code(&inner.data)
+        }
+        StmtType::Compound(stmts) => stmts.iter().any(|stmt| has_code(&stmt.data)),
+        StmtType::Decl(decls) => !decls.is_empty(),
+        _ => true,
+    }
+}
+
+/// Whether control can reach the end of `stmt`.
+///
+/// This is conservative: loops are always assumed to terminate,
+/// and jumping to a label in the middle of a block is ignored.
+fn can_complete(stmt: &StmtType) -> bool {
+    match stmt {
+        StmtType::Break
+        | StmtType::Continue
+        | StmtType::Return(_)
+        | StmtType::Goto(_)
+        | StmtType::IndirectGoto(_)
+        | StmtType::Fallthrough => false,
+        StmtType::Case(_, _, inner) | StmtType::Default(inner) | StmtType::Label(_, inner) => {
+            can_complete(&inner.data)
+        }
+        StmtType::Compound(stmts) => stmts.iter().all(|stmt| can_complete(&stmt.data)),
+        StmtType::If(_, then, Some(otherwise)) => {
+            can_complete(&then.data) || can_complete(&otherwise.data)
+        }
+        // exit(1);
+        StmtType::Expr(Expr {
+            expr: ExprType::FuncCall(func, _),
+            ..
+        }) => match func.expr {
+            ExprType::Id(symbol) => !symbol.get().qualifiers.func.no_return,
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::analyze::test::{analyze, analyze_expr};
-    use crate::analyze::FunctionData;
+    use crate::analyze::{FunctionData, FunctionState};
     use crate::data::*;
     use crate::Parser;
 
     fn parse_stmt(stmt: &str) -> CompileResult<Stmt> {
         analyze(stmt, Parser::statement, |a, stmt| {
-            let mut func_analyzer = FunctionAnalyzer {
-                analyzer: a,
-                metadata: FunctionData {
-                    id: "<test func>".into(),
-                    location: Location::default(),
-                    return_type: Type::Int(true),
-                },
-            };
+            a.function = Some(FunctionState::new(FunctionData {
+                id: "<test func>".into(),
+                location: Location::default(),
+                return_type: Type::Int(true),
+                no_return: false,
+            }));
+            let mut func_analyzer = FunctionAnalyzer { analyzer: a };
             func_analyzer.parse_stmt(stmt)
         })
     }
@@ -5,6 +5,7 @@ use std::cmp::max;
 use target_lexicon::Triple;
 
 use crate::data::{
+    hir::Variable,
     types::{ArrayType, StructType},
     *,
 };
@@ -28,63 +29,150 @@ pub const TARGET: Triple = Triple::host();
 mod x64;
 pub use x64::*;
 
+/// The position of a bit-field inside its storage unit.
+///
+/// The storage unit is an object with the declared type of the bit-field,
+/// located at `StructType::offset` bytes from the start of the struct.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BitField {
+    /// The number of bits between the least significant bit of the storage unit and the bit-field
+    pub shift: u8,
+    /// The width of the bit-field in bits
+    pub width: u8,
+}
+
 impl StructType {
     /// Get the offset of the given struct member.
+    ///
+    /// For bit-fields, this is the offset of the storage unit containing the member.
     #[cfg_attr(not(feature = "codegen"), allow(dead_code))]
     pub(crate) fn offset(&self, member: InternedStr) -> u64 {
+        let (bit_offset, unit_bits) = self.bit_offset(member);
+        bit_offset / unit_bits * (unit_bits / u64::from(CHAR_BIT))
+    }
+    /// Get the position of the given struct member within its storage unit,
+    /// or `None` if it is not a bit-field.
+    #[cfg_attr(not(feature = "codegen"), allow(dead_code))]
+    pub(crate) fn bitfield(&self, member: InternedStr) -> Option<BitField> {
+        let width = self
+            .members()
+            .iter()
+            .find(|formal| formal.id == member)
+            .and_then(|formal| formal.bitfield)?;
+        let (bit_offset, unit_bits) = self.bit_offset(member);
+        Some(BitField {
+            shift: (bit_offset % unit_bits) as u8,
+            width,
+        })
+    }
+    /// Get the offset of the given struct member in bits,
+    /// along with the size in bits of the unit it should be loaded from.
+    ///
+    /// Normal members are loaded a byte at a time, bit-fields are loaded with their declared type.
+    fn bit_offset(&self, member: InternedStr) -> (u64, u64) {
         let members = self.members();
         let mut current_offset = 0;
         for formal in members.iter() {
+            let start = Self::member_start(current_offset, formal)
+                .expect("structs should have valid size and alignment");
             if formal.id == member {
-                return current_offset;
+                let unit_bits = match formal.bitfield {
+                    Some(_) => formal.ctype.sizeof().unwrap() * u64::from(CHAR_BIT),
+                    None => CHAR_BIT.into(),
+                };
+                return (start, unit_bits);
             }
-            current_offset = Self::next_offset(current_offset, &formal.ctype)
-                .expect("structs should have valid size and alignment");
+            current_offset = start
+                + Self::member_bits(formal).expect("structs should have valid size and alignment");
         }
         unreachable!("cannot call struct_offset for member not in struct");
     }
-    /// Get the offset of the next struct member given the current offset.
-    fn next_offset(mut current_offset: u64, ctype: &Type) -> Result<u64, &'static str> {
-        let align = ctype.alignof()?;
-        // round up to the nearest multiple of align
-        let rem = current_offset % align;
-        if rem != 0 {
-            // for example: 7%4 == 3; 7 + ((4 - 3) = 1) == 8; 8 % 4 == 0
-            current_offset += align - rem;
+    /// Get the offset in bits where `member` starts,
+    /// given the offset in bits where the previous member ended.
+    fn member_start(current_offset: u64, member: &Variable) -> Result<u64, &'static str> {
+        let char_bit = u64::from(CHAR_BIT);
+        Ok(match member.bitfield {
+            // `int : 0;` means the next bit-field starts at a new storage unit
+            Some(0) => round_up(current_offset, member.alignof()? * char_bit),
+            // bit-fields are packed together, but can't straddle a storage unit
+            Some(width) => {
+                let unit = member.ctype.sizeof()? * char_bit;
+                let end = current_offset + u64::from(width) - 1;
+                if current_offset / unit != end / unit {
+                    round_up(current_offset, unit)
+                } else {
+                    current_offset
+                }
+            }
+            None => round_up(current_offset, member.alignof()? * char_bit),
+        })
+    }
+    /// Get the number of bits taken up by `member`.
+    ///
+    /// Flexible array members take up no space, although they still affect alignment.
+    fn member_bits(member: &Variable) -> Result<u64, &'static str> {
+        match (member.bitfield, &member.ctype) {
+            (Some(width), _) => Ok(width.into()),
+            // struct s { int len; char data[]; };
+            (None, Array(_, ArrayType::Unbounded)) => Ok(0),
+            (None, ctype) => Ok(ctype.sizeof()? * u64::from(CHAR_BIT)),
         }
-        Ok(current_offset + ctype.sizeof()?)
     }
     /// Calculate the size of a struct: the sum of all member sizes
     pub(crate) fn struct_size(&self) -> Result<SIZE_T, &'static str> {
         let symbols = &self.members();
 
-        symbols
-            .iter()
-            .try_fold(0, |offset, symbol| {
-                Ok(StructType::next_offset(offset, &symbol.ctype)?)
-            })
-            .and_then(|size_t| {
-                let align_minus_one = self.align()? - 1;
-
-                // Rounds up to the next multiple of `align`
-                Ok((size_t + align_minus_one) & !align_minus_one)
-            })
+        let bits = symbols.iter().try_fold(0, |offset, symbol| {
+            Ok::<_, &'static str>(
+                StructType::member_start(offset, symbol)? + StructType::member_bits(symbol)?,
+            )
+        })?;
+        let size_t = round_up(bits, CHAR_BIT.into()) / u64::from(CHAR_BIT);
+        // Rounds up to the next multiple of `align`
+        Ok(round_up(size_t, max(self.align()?, 1)))
     }
     /// Calculate the size of a union: the max of all member sizes
     pub(crate) fn union_size(&self) -> Result<SIZE_T, &'static str> {
         let symbols = &self.members();
-        symbols
+        let size_t = symbols
             .iter()
-            .map(|symbol| symbol.ctype.sizeof())
+            .map(|symbol| match symbol.bitfield {
+                Some(width) => Ok(round_up(width.into(), CHAR_BIT.into()) / u64::from(CHAR_BIT)),
+                None => symbol.ctype.sizeof(),
+            })
             // max of member sizes
-            .try_fold(1, |n, size| Ok(max(n, size?)))
+            .try_fold(1, |n, size| Ok::<_, &'static str>(max(n, size?)))?;
+        Ok(round_up(size_t, max(self.align()?, 1)))
     }
     /// Calculate the alignment of a struct: the max of all member alignments
+    ///
+    /// Unnamed bit-fields do not affect the alignment.
     pub(crate) fn align(&self) -> Result<SIZE_T, &'static str> {
         let members = &self.members();
-        members.iter().try_fold(0, |max, member| {
-            Ok(std::cmp::max(member.ctype.alignof()?, max))
-        })
+        members
+            .iter()
+            .filter(|member| !member.is_unnamed_bitfield())
+            .try_fold(0, |max, member| Ok(std::cmp::max(member.alignof()?, max)))
+    }
+}
+
+impl Variable {
+    /// Get the alignment of an object in bytes.
+    ///
+    /// This is the alignment of its type, unless a different alignment was requested
+    /// with `_Alignas` or `__attribute__((packed))`.
+    pub fn alignof(&self) -> Result<SIZE_T, &'static str> {
+        let natural = self.ctype.alignof()?;
+        Ok(self.alignment.unwrap_or(natural))
+    }
+}
+
+/// Round `n` up to the nearest multiple of `align`
+fn round_up(n: u64, align: u64) -> u64 {
+    // for example: 7%4 == 3; 7 + ((4 - 3) = 1) == 8; 8 % 4 == 0
+    match n % align {
+        0 => n,
+        rem => n + align - rem,
     }
 }
 
@@ -93,6 +181,9 @@ impl Type {
     pub fn can_represent(&self, other: &Type) -> bool {
         self == other
             || *self == Type::Double && *other == Type::Float
+            || *self == Type::LongDouble && other.is_floating()
+            // a complex type can represent its real part: `double _Complex z = 1.0;`
+            || self.is_complex() && self.real_type().can_represent(other.real_type())
             || (self.is_integral() && other.is_integral())
                 && (self.sizeof() > other.sizeof()
                     || self.sizeof() == other.sizeof() && self.is_signed() == other.is_signed())
@@ -108,14 +199,24 @@ impl Type {
             Short(_) => Ok(SHORT_SIZE.into()),
             Int(_) => Ok(INT_SIZE.into()),
             Long(_) => Ok(LONG_SIZE.into()),
+            LongLong(_) => Ok(LONG_LONG_SIZE.into()),
+            Int128(_) => Ok(INT128_SIZE.into()),
             Float => Ok(FLOAT_SIZE.into()),
             Double => Ok(DOUBLE_SIZE.into()),
-            Pointer(_, _) => Ok(PTR_SIZE.into()),
+            LongDouble => Ok(LONG_DOUBLE_SIZE.into()),
+            // 6.2.5p13: same as an array of two elements of the corresponding real type
+            Complex(real) => real.sizeof().map(|size| 2 * size),
+            // atomic operations use a lock, so no padding is needed
+            Atomic(inner) => inner.sizeof(),
+            Pointer(_, _) | NullPtr => Ok(PTR_SIZE.into()),
             // now for the hard ones
             Array(t, ArrayType::Fixed(l)) => t
                 .sizeof()
                 .and_then(|n| n.checked_mul(*l).ok_or("overflow in array size")),
             Array(_, ArrayType::Unbounded) => Err("cannot take sizeof variable length array"),
+            Array(_, ArrayType::Variable(_)) => {
+                Err("size of variable length array is not known at compile time")
+            }
             Enum(_, symbols) => {
                 let uchar = CHAR_BIT as usize;
                 // integer division, but taking the ceiling instead of the floor
@@ -145,11 +246,15 @@ impl Type {
             | Short(_)
             | Int(_)
             | Long(_)
+            | LongLong(_)
+            | Int128(_)
             | Float
             | Double
+            | LongDouble
             | Pointer(_, _)
+            | NullPtr
             | Enum(_, _) => self.sizeof(),
-            Array(t, _) => t.alignof(),
+            Array(t, _) | Complex(t) | Atomic(t) => t.alignof(),
             // Clang uses the largest alignment of any element as the alignment of the whole
             // Not sure why, but who am I to argue
             // Anyway, Faerie panics if the alignment isn't a power of two so it's probably for the best
@@ -192,6 +297,11 @@ mod tests {
             ctype,
             qualifiers: Qualifiers::NONE,
             storage_class: StorageClass::Auto,
+            bitfield: None,
+            alignment: None,
+            thread_local: false,
+            constexpr: None,
+            attributes: Default::default(),
         }
     }
     fn struct_for_types(types: Vec<Type>) -> Type {
@@ -266,6 +376,105 @@ mod tests {
         assert_eq!(ty.alignof(), Ok(8));
     }
 
+    fn bitfield_struct(members: Vec<(&str, Type, Option<u8>)>) -> StructType {
+        let members = members
+            .into_iter()
+            .map(|(name, ctype, bitfield)| {
+                let id = if name.is_empty() {
+                    InternedStr::default()
+                } else {
+                    InternedStr::get_or_intern(name)
+                };
+                Variable {
+                    bitfield,
+                    ..symbol_for_type(ctype, id)
+                }
+            })
+            .collect();
+        StructType::Anonymous(std::rc::Rc::new(members))
+    }
+    fn assert_bitfield(struct_type: &StructType, member: &str, offset: u64, shift: u8) {
+        let member = InternedStr::get_or_intern(member);
+        assert_eq!(struct_type.offset(member), offset);
+        let bitfield = struct_type.bitfield(member).unwrap();
+        assert_eq!(bitfield.shift, shift);
+    }
+    #[test]
+    fn bitfields() {
+        // struct { unsigned a: 3; unsigned b: 30; char c; }
+        let s = bitfield_struct(vec![
+            ("a", Int(false), Some(3)),
+            ("b", Int(false), Some(30)),
+            ("c", Char(true), None),
+        ]);
+        assert_bitfield(&s, "a", 0, 0);
+        // `b` can't straddle two `int`s
+        assert_bitfield(&s, "b", 4, 0);
+        assert_eq!(s.offset("c".into()), 8);
+        assert_eq!(s.bitfield("c".into()), None);
+        assert_eq!(s.struct_size(), Ok(12));
+
+        // struct { char a: 4; char b: 4; char c: 1; }
+        let s = bitfield_struct(vec![
+            ("a", Char(true), Some(4)),
+            ("b", Char(true), Some(4)),
+            ("c", Char(true), Some(1)),
+        ]);
+        assert_bitfield(&s, "a", 0, 0);
+        assert_bitfield(&s, "b", 0, 4);
+        assert_bitfield(&s, "c", 1, 0);
+        assert_eq!(s.struct_size(), Ok(2));
+        assert_eq!(s.align(), Ok(1));
+    }
+    #[test]
+    fn unnamed_bitfields() {
+        // struct { int a: 1; int : 0; char b; }
+        let s = bitfield_struct(vec![
+            ("a", Int(true), Some(1)),
+            ("", Int(true), Some(0)),
+            ("b", Char(true), None),
+        ]);
+        assert_eq!(s.offset("b".into()), 4);
+        assert_eq!(s.struct_size(), Ok(8));
+
+        // struct { char a; int : 3; }
+        // unnamed bit-fields don't affect alignment
+        let s = bitfield_struct(vec![("a", Char(true), None), ("", Int(true), Some(3))]);
+        assert_eq!(s.align(), Ok(1));
+        assert_eq!(s.struct_size(), Ok(2));
+
+        // union { int a: 3; char b; }
+        let u = bitfield_struct(vec![("a", Int(true), Some(3)), ("b", Char(true), None)]);
+        assert_eq!(u.union_size(), Ok(4));
+    }
+    #[test]
+    fn flexible_array_member() {
+        // struct { char len; int data[]; }
+        let s = bitfield_struct(vec![
+            ("len", Char(true), None),
+            (
+                "data",
+                Array(Box::new(Int(true)), ArrayType::Unbounded),
+                None,
+            ),
+        ]);
+        assert_eq!(s.offset("data".into()), 4);
+        assert_eq!(s.struct_size(), Ok(4));
+        assert_eq!(s.align(), Ok(4));
+
+        // struct { int len; char data[]; }
+        let s = bitfield_struct(vec![
+            ("len", Int(true), None),
+            (
+                "data",
+                Array(Box::new(Char(true)), ArrayType::Unbounded),
+                None,
+            ),
+        ]);
+        assert_eq!(s.offset("data".into()), 4);
+        assert_eq!(s.struct_size(), Ok(4));
+    }
+
     proptest! {
         // https://github.com/jyn514/rcc/pull/325#issuecomment-596297785
         // prop_assert_eq!(discriminant(&t.sizeof()), discriminant(&t.alignof()));
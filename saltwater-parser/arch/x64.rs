@@ -8,12 +8,19 @@ pub const SIZE_MAX: SIZE_T = SIZE_T::max_value();
 
 pub const FLOAT_SIZE: u16 = 4;
 pub const DOUBLE_SIZE: u16 = 8;
+/// x87 extended precision is 10 bytes, padded to 16
+pub const LONG_DOUBLE_SIZE: u16 = 16;
 
 pub const LONG_SIZE: u16 = 8;
+pub const LONG_LONG_SIZE: u16 = 8;
+pub const INT128_SIZE: u16 = 16;
 pub const INT_SIZE: u16 = 4;
 pub const SHORT_SIZE: u16 = 2;
 pub const BOOL_SIZE: u16 = 1;
 
 pub const PTR_SIZE: u16 = 8;
 
+/// The stack pointer is aligned to 16 bytes at each function call
+pub const STACK_ALIGN: u16 = 16;
+
 pub const CHAR_BIT: u16 = 8; // number of bits in a byte
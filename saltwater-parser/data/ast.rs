@@ -2,7 +2,7 @@ use std::fmt::{self, Display};
 
 use super::{joined, joined_locatable};
 use crate::data::hir::LiteralValue;
-use crate::data::lex::{AssignmentToken, ComparisonToken, Locatable};
+use crate::data::lex::{AssignmentToken, AtomicOp, ComparisonToken, Locatable};
 use crate::intern::InternedStr;
 
 pub type Program = Vec<Declaration>;
@@ -11,6 +11,15 @@ pub type Program = Vec<Declaration>;
 pub enum ExternalDeclaration {
     Function(FunctionDefinition),
     Declaration(Declaration),
+    StaticAssert(Box<StaticAssert>),
+}
+
+/// `_Static_assert(sizeof(int) == 4, "int must be 32 bits");`
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticAssert {
+    pub condition: Expr,
+    /// The message is optional starting in C2x: `static_assert(sizeof(int) == 4);`
+    pub message: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -19,6 +28,10 @@ pub struct FunctionDefinition {
     pub id: InternedStr,
     pub declarator: FunctionDeclarator,
     pub body: CompoundStatement,
+    /// Whether this is an old-style (K&R) definition: `int f(a, b) int a; double b; { ... }`
+    ///
+    /// The parser has already moved the types from the declaration list into `declarator.params`.
+    pub old_style: bool,
 }
 
 impl FunctionDefinition {
@@ -28,6 +41,7 @@ impl FunctionDefinition {
             declarator: Declarator {
                 decl: DeclaratorType::Function(self.declarator.clone()),
                 id: Some(self.id),
+                attributes: Vec::new(),
             },
         }
     }
@@ -52,6 +66,49 @@ pub enum DeclarationSpecifier {
     // NOTE: _not_ the same as UnitSpecifier::Typedef
     // that represents the `typedef` keyword, this represents a name that has been typedef-ed
     Typedef(InternedStr),
+    // _Alignas(double)
+    AlignasType(Box<TypeName>),
+    // _Alignas(16)
+    AlignasExpr(Box<Expr>),
+    // _Atomic(int)
+    AtomicType(Box<TypeName>),
+    // typeof(int) or typeof_unqual(const int)
+    TypeofType {
+        type_name: Box<TypeName>,
+        unqual: bool,
+    },
+    // typeof(x) or typeof_unqual(x)
+    TypeofExpr {
+        expr: Box<Expr>,
+        unqual: bool,
+    },
+    // __attribute__((noreturn)) or [[noreturn]]
+    Attribute(Locatable<Attribute>),
+}
+
+/// A single attribute: the `aligned(16)` in `__attribute__((aligned(16)))`,
+/// or the `nodiscard` in `[[nodiscard]]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    /// The name of the attribute, with any surrounding underscores removed.
+    ///
+    /// `__aligned__` and `aligned` are the same attribute, so both are stored as `aligned`.
+    /// `[[gnu::aligned]]` is also stored as `aligned`,
+    /// but attributes in other namespaces keep their prefix: `clang::noescape`.
+    pub name: InternedStr,
+    /// The arguments, if any. Identifiers such as the `printf` in `format(printf, 1, 2)`
+    /// are parsed as expressions and resolved by the analyzer.
+    pub args: Vec<Expr>,
+}
+
+impl Attribute {
+    /// Remove the double underscores around an attribute name: `__aligned__` is the same as `aligned`.
+    pub fn normalize_name(name: &str) -> &str {
+        match name.strip_prefix("__").and_then(|n| n.strip_suffix("__")) {
+            Some(stripped) if !stripped.is_empty() => stripped,
+            _ => name,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -61,6 +118,7 @@ pub enum UnitSpecifier {
     Short,
     Int,
     Long,
+    Int128,
     Float,
     Double,
     Void,
@@ -90,6 +148,8 @@ pub enum UnitSpecifier {
     Static,
     Extern,
     Typedef,
+    // C23: a `const` object whose value is known at compile time
+    Constexpr,
 }
 
 impl From<UnitSpecifier> for DeclarationSpecifier {
@@ -103,7 +163,17 @@ pub struct StructSpecifier {
     pub name: Option<InternedStr>,
     /// Some([]): `struct s {}`
     /// None: `struct s;`
-    pub members: Option<Vec<StructDeclarationList>>,
+    pub members: Option<Vec<StructDeclaration>>,
+    /// `struct __attribute__((packed)) s { ... }` or `struct s { ... } __attribute__((packed))`
+    pub attributes: Vec<Locatable<Attribute>>,
+}
+
+/// 6.7.2.1 struct-declaration
+#[derive(Clone, Debug, PartialEq)]
+pub enum StructDeclaration {
+    Member(StructDeclarationList),
+    /// `struct s { int i; _Static_assert(sizeof(int) == 4); };`
+    StaticAssert(Locatable<Box<StaticAssert>>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -125,29 +195,57 @@ pub struct Declaration {
     pub declarators: Vec<Locatable<InitDeclarator>>,
 }
 
+impl Declaration {
+    /// Whether this declaration only has attributes: `[[fallthrough]];`
+    pub fn is_attribute_declaration(&self) -> bool {
+        self.declarators.is_empty()
+            && !self.specifiers.is_empty()
+            && self
+                .specifiers
+                .iter()
+                .all(|spec| matches!(spec, DeclarationSpecifier::Attribute(_)))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct InitDeclarator {
     pub init: Option<Initializer>,
     pub declarator: Declarator,
+    /// The name of the symbol in the object file, if given with a GNU asm label:
+    /// `int f(void) __asm__("g");`
+    pub asm_label: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Initializer {
     Scalar(Box<Expr>),
-    Aggregate(Vec<Initializer>),
+    // { 1, .a = 2, [3].b = 4 }
+    Aggregate(Vec<(Vec<Designator>, Initializer)>),
+    /// The bytes from an `#embed` directive in an initializer list,
+    /// one initializer for each byte: `{ #embed "data.bin" }`
+    Embed(Locatable<Vec<u8>>),
+}
+
+/// A single designator in a designated initializer: `.member` or `[index]`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Designator {
+    Index(Expr),
+    Member(Locatable<InternedStr>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Declarator {
     pub decl: DeclaratorType,
     pub id: Option<InternedStr>,
+    /// GNU attributes that appeared anywhere in the declarator: `int *__attribute__((unused)) p`
+    pub attributes: Vec<Locatable<Attribute>>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FunctionDeclarator {
     pub return_type: Box<DeclaratorType>,
-    // TODO: maybe support K&R C?
-    //DeclarationList
+    /// NOTE: for an identifier list (`int f(a, b)`),
+    /// each parameter is a `TypeName` with no specifiers and only an id
     pub params: Vec<TypeName>,
     pub varargs: bool,
 }
@@ -193,14 +291,21 @@ pub enum StmtType {
     },
     Switch(Expr, Box<Stmt>),
     Label(InternedStr, Box<Stmt>),
-    Case(Box<Expr>, Box<Stmt>),
+    // case 1:
+    // case 'a' ... 'z':
+    Case(Box<Expr>, Option<Box<Expr>>, Box<Stmt>),
     Default(Box<Stmt>),
     Expr(Expr),
     Goto(InternedStr),
+    // goto *labels[i];
+    IndirectGoto(Box<Expr>),
     Continue,
     Break,
     Return(Option<Expr>),
     Decl(Declaration),
+    StaticAssert(Box<StaticAssert>),
+    // a statement with a syntax error, which has already been reported
+    Error,
 }
 
 pub type Expr = Locatable<ExprType>;
@@ -210,6 +315,12 @@ pub enum ExprType {
     // primary
     Id(InternedStr),
     Literal(LiteralValue),
+    /// `1LL` or `1.5L`: a constant whose suffix makes it a `long long` or a `long double`
+    LongLiteral(LiteralValue),
+    // C23 null pointer constant
+    Nullptr,
+    // GNU address of a label: &&label
+    LabelAddress(InternedStr),
 
     // postfix
     FuncCall(Box<Expr>, Vec<Expr>),
@@ -219,6 +330,8 @@ pub enum ExprType {
     PostIncrement(Box<Expr>, bool),
     // a[i]
     Index(Box<Expr>, Box<Expr>),
+    // (struct point){ .x = 1, .y = 2 }
+    CompoundLiteral(Box<TypeName>, Box<Initializer>),
 
     // prefix
     PreIncrement(Box<Expr>, bool),
@@ -233,6 +346,10 @@ pub enum ExprType {
     Negate(Box<Expr>),
     BitwiseNot(Box<Expr>),
     LogicalNot(Box<Expr>),
+    // __real__ z
+    Real(Box<Expr>),
+    // __imag__ z
+    Imag(Box<Expr>),
 
     // binary
     LogicalOr(Box<Expr>, Box<Expr>),
@@ -255,7 +372,25 @@ pub enum ExprType {
     // misfits
     // Ternary: if ? then : else
     Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    // GNU conditional with an omitted operand: if ?: else
+    Elvis(Box<Expr>, Box<Expr>),
     Comma(Box<Expr>, Box<Expr>),
+    // _Generic(x, int: a, default: b)
+    Generic(Box<Expr>, Vec<GenericAssociation>),
+    // __builtin_complex(re, im)
+    BuiltinComplex(Box<Expr>, Box<Expr>),
+    // __atomic_load_n(p, __ATOMIC_SEQ_CST)
+    AtomicBuiltin(AtomicOp, Vec<Expr>),
+    // GNU statement expression: ({ int x = f(); x; })
+    StmtExpr(CompoundStatement),
+}
+
+/// A single `type: expr` pair in a `_Generic` selection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenericAssociation {
+    /// `None` for the `default` association
+    pub ctype: Option<Locatable<TypeName>>,
+    pub expr: Expr,
 }
 
 impl Default for StmtType {
@@ -266,8 +401,11 @@ impl Default for StmtType {
 
 impl Display for StructSpecifier {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for attr in &self.attributes {
+            write!(f, "{} ", attr.data)?;
+        }
         if let Some(ident) = self.name {
-            write!(f, "{} ", ident)
+            write!(f, "{}", ident)
         } else if let Some(body) = &self.members {
             writeln!(f, "{{")?;
             for decl in body {
@@ -281,6 +419,15 @@ impl Display for StructSpecifier {
     }
 }
 
+impl Display for StructDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StructDeclaration::Member(member) => write!(f, "{}", member),
+            StructDeclaration::StaticAssert(assert) => write!(f, "{}", assert.data),
+        }
+    }
+}
+
 impl Display for StructDeclarationList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} ", joined(&self.specifiers, " "))?;
@@ -305,10 +452,21 @@ impl Display for ExternalDeclaration {
         match self {
             ExternalDeclaration::Declaration(decl) => write!(f, "{}", decl),
             ExternalDeclaration::Function(func) => write!(f, "{}", func),
+            ExternalDeclaration::StaticAssert(assert) => write!(f, "{}", assert),
         }
     }
 }
 
+impl Display for StaticAssert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "_Static_assert({}", self.condition)?;
+        if let Some(message) = &self.message {
+            write!(f, ", {:?}", message)?;
+        }
+        write!(f, ");")
+    }
+}
+
 impl Display for FunctionDefinition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for spec in &self.specifiers {
@@ -333,6 +491,9 @@ impl Display for Declaration {
 impl Display for InitDeclarator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.declarator)?;
+        if let Some(label) = &self.asm_label {
+            write!(f, " __asm__(\"{}\")", label)?;
+        }
         if let Some(init) = &self.init {
             write!(f, " = {}", init)?;
         }
@@ -346,9 +507,27 @@ impl Display for Initializer {
             Initializer::Scalar(expr) => write!(f, "{}", expr),
             Initializer::Aggregate(items) => {
                 write!(f, "{{ ")?;
-                write!(f, "{}", joined(items, ", "))?;
+                for (i, (designators, init)) in items.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    if !designators.is_empty() {
+                        write!(f, "{} = ", joined(designators, ""))?;
+                    }
+                    write!(f, "{}", init)?;
+                }
                 write!(f, " }}")
             }
+            Initializer::Embed(bytes) => write!(f, "{}", joined(&bytes.data, ", ")),
+        }
+    }
+}
+
+impl Display for Designator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Designator::Index(index) => write!(f, "[{}]", index),
+            Designator::Member(member) => write!(f, ".{}", member.data),
         }
     }
 }
@@ -384,10 +563,36 @@ impl Display for DeclarationSpecifier {
             Union(spec) => write!(f, "union {}", spec),
             Struct(spec) => write!(f, "struct {}", spec),
             Typedef(name) => write!(f, "{}", name),
+            AlignasType(ty) => write!(f, "_Alignas({})", ty),
+            AlignasExpr(expr) => write!(f, "_Alignas({})", expr),
+            AtomicType(ty) => write!(f, "_Atomic({})", ty),
+            TypeofType { type_name, unqual } => {
+                write!(f, "{}({})", typeof_keyword(*unqual), type_name)
+            }
+            TypeofExpr { expr, unqual } => write!(f, "{}({})", typeof_keyword(*unqual), expr),
+            Attribute(attr) => write!(f, "{}", attr.data),
         }
     }
 }
 
+fn typeof_keyword(unqual: bool) -> &'static str {
+    if unqual {
+        "typeof_unqual"
+    } else {
+        "typeof"
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "__attribute__(({}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "({})", joined(&self.args, ", "))?;
+        }
+        write!(f, "))")
+    }
+}
+
 impl Display for UnitSpecifier {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use UnitSpecifier::*;
@@ -398,6 +603,7 @@ impl Display for UnitSpecifier {
             Register => write!(f, "register"),
             Auto => write!(f, "auto"),
             Typedef => write!(f, "typedef"),
+            Constexpr => write!(f, "constexpr"),
 
             Const => write!(f, "const"),
             Volatile => write!(f, "volatile"),
@@ -414,6 +620,7 @@ impl Display for UnitSpecifier {
             Short => write!(f, "short"),
             Int => write!(f, "int"),
             Long => write!(f, "long"),
+            Int128 => write!(f, "__int128"),
             Float => write!(f, "float"),
             Double => write!(f, "double"),
             Signed => write!(f, "signed"),
@@ -500,7 +707,11 @@ impl DeclaratorType {
 
 impl Display for Declarator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.decl.pretty_print(self.id, f)
+        self.decl.pretty_print(self.id, f)?;
+        for attr in &self.attributes {
+            write!(f, " {}", attr.data)?;
+        }
+        Ok(())
     }
 }
 
@@ -511,6 +722,15 @@ impl Display for FunctionDeclarator {
 }
 
 impl FunctionDeclarator {
+    /// Whether the parameters are an old-style identifier list: `int f(a, b)`
+    pub(crate) fn is_identifier_list(&self) -> bool {
+        !self.params.is_empty()
+            && self.params.iter().all(|param| {
+                param.specifiers.is_empty()
+                    && param.declarator.decl == DeclaratorType::End
+                    && param.declarator.id.is_some()
+            })
+    }
     fn pretty_print(&self, name: Option<InternedStr>, f: &mut fmt::Formatter) -> fmt::Result {
         // TODO: maybe factor out some of the repeated code?
         // print_pre
@@ -537,9 +757,14 @@ impl Display for DeclaratorType {
 
 impl Display for TypeName {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", joined(&self.specifiers, " "))?;
+        let specs = joined(&self.specifiers, " ");
+        write!(f, "{}", specs)?;
         if self.declarator.is_nonempty() {
-            write!(f, " {}", self.declarator)?;
+            // don't print a leading space for identifier lists: `int f(a)`
+            if !specs.is_empty() {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", self.declarator)?;
         }
         Ok(())
     }
@@ -576,11 +801,16 @@ impl StmtType {
                 writeln!(f, "default:")?;
                 stmt.data.pretty_print(f, depth + 1)
             }
-            StmtType::Case(expr, stmt) => {
+            StmtType::Case(expr, None, stmt) => {
                 writeln!(f, "case {}:", expr)?;
                 stmt.data.pretty_print(f, depth + 1)
             }
+            StmtType::Case(lower, Some(upper), stmt) => {
+                writeln!(f, "case {} ... {}:", lower, upper)?;
+                stmt.data.pretty_print(f, depth + 1)
+            }
             StmtType::Goto(id) => write!(f, "goto {};", id),
+            StmtType::IndirectGoto(target) => write!(f, "goto *{};", target),
             StmtType::Label(id, inner) => write!(f, "{}: {}", id, inner.data),
             StmtType::While(condition, body) => write!(f, "while ({}) {}", condition, body.data),
             StmtType::If(condition, body, None) => write!(f, "if ({}) {}", condition, body.data),
@@ -615,6 +845,8 @@ impl StmtType {
                 body.data.pretty_print(f, depth)
             }
             StmtType::Decl(decls) => write!(f, "{}", decls),
+            StmtType::StaticAssert(assert) => write!(f, "{}", assert),
+            StmtType::Error => write!(f, "<syntax error>;"),
             StmtType::Compound(stmts) => pretty_print_compound(f, stmts, depth),
             StmtType::Switch(condition, body) => write!(f, "switch ({}) {}", condition, body.data),
         }
@@ -626,7 +858,11 @@ impl Display for Expr {
         match &self.data {
             ExprType::Comma(left, right) => write!(f, "{}, {}", *left, *right),
             ExprType::Literal(token) => write!(f, "{}", token),
+            ExprType::LongLiteral(token @ LiteralValue::Float(_)) => write!(f, "{}L", token),
+            ExprType::LongLiteral(token) => write!(f, "{}LL", token),
             ExprType::Id(symbol) => write!(f, "{}", symbol),
+            ExprType::Nullptr => write!(f, "nullptr"),
+            ExprType::LabelAddress(label) => write!(f, "&&{}", label),
             ExprType::Add(left, right) => write!(f, "({}) + ({})", left, right),
             ExprType::Sub(left, right) => write!(f, "({}) - ({})", left, right),
             ExprType::Mul(left, right) => write!(f, "({}) * ({})", left, right),
@@ -640,6 +876,8 @@ impl Display for Expr {
             ExprType::Negate(expr) => write!(f, "-({})", expr),
             ExprType::UnaryPlus(expr) => write!(f, "+({})", expr),
             ExprType::LogicalNot(expr) => write!(f, "!({})", expr),
+            ExprType::Real(expr) => write!(f, "__real__({})", expr),
+            ExprType::Imag(expr) => write!(f, "__imag__({})", expr),
             ExprType::LogicalOr(left, right) => write!(f, "({}) || ({})", left, right),
             ExprType::LogicalAnd(left, right) => write!(f, "({}) && ({})", left, right),
             ExprType::Shift(val, by, left) => {
@@ -650,6 +888,7 @@ impl Display for Expr {
             ExprType::Ternary(cond, left, right) => {
                 write!(f, "({}) ? ({}) : ({})", cond, left, right)
             }
+            ExprType::Elvis(cond, right) => write!(f, "({}) ?: ({})", cond, right),
             ExprType::FuncCall(left, params) => write!(f, "({})({})", left, joined(params, ", ")),
             ExprType::Cast(ctype, expr) => write!(f, "({})({})", ctype, expr),
             ExprType::Member(compound, id) => write!(f, "({}).{}", compound, id),
@@ -661,12 +900,40 @@ impl Display for Expr {
                 write!(f, "({}){}", expr, if *inc { "++" } else { "--" })
             }
             ExprType::Index(array, index) => write!(f, "({})[{}]", array, index),
+            ExprType::CompoundLiteral(ctype, init) => write!(f, "({}){}", ctype, init),
             // intrinsics
             ExprType::AddressOf(expr) => write!(f, "&({})", expr),
             ExprType::SizeofExpr(expr) => write!(f, "sizeof({})", expr),
             ExprType::SizeofType(ty) => write!(f, "sizeof({})", ty),
             ExprType::AlignofExpr(expr) => write!(f, "alignof({})", expr),
             ExprType::AlignofType(ty) => write!(f, "alignof({})", ty),
+            ExprType::Generic(controlling, associations) => {
+                write!(f, "_Generic({}", controlling)?;
+                for assoc in associations {
+                    write!(f, ", {}", assoc)?;
+                }
+                write!(f, ")")
+            }
+            ExprType::BuiltinComplex(real, imag) => {
+                write!(f, "__builtin_complex({}, {})", real, imag)
+            }
+            ExprType::AtomicBuiltin(op, args) => write!(f, "{}({})", op, joined(args, ", ")),
+            ExprType::StmtExpr(stmts) => {
+                write!(f, "({{ ")?;
+                for stmt in stmts {
+                    write!(f, "{} ", stmt.data)?;
+                }
+                write!(f, "}})")
+            }
+        }
+    }
+}
+
+impl Display for GenericAssociation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.ctype {
+            Some(ctype) => write!(f, "{}: {}", ctype.data, self.expr),
+            None => write!(f, "default: {}", self.expr),
         }
     }
 }
@@ -62,11 +62,6 @@ impl<T> ErrorHandler<T> {
         self.errors.push_back(location.with(error.into()));
     }
 
-    /// Add an iterator of errors to the error queue
-    pub(crate) fn extend<E: Into<Locatable<T>>>(&mut self, iter: impl Iterator<Item = E>) {
-        self.errors.extend(iter.map(Into::into));
-    }
-
     /// Move another `ErrorHandler`'s errors and warnings into this one.
     pub(crate) fn append<S>(&mut self, other: &mut ErrorHandler<S>)
     where
@@ -111,8 +106,8 @@ pub enum SemanticError {
     // Declaration specifier errors
     #[error("cannot combine '{new}' specifier with previous '{existing}' type specifier")]
     InvalidSpecifier {
-        existing: ast::DeclarationSpecifier,
-        new: ast::DeclarationSpecifier,
+        existing: Box<ast::DeclarationSpecifier>,
+        new: Box<ast::DeclarationSpecifier>,
     },
 
     #[error("'{0}' is not a qualifier and cannot be used for pointers")]
@@ -130,6 +125,32 @@ pub enum SemanticError {
     #[error("'{0}' cannot be signed or unsigned")]
     CannotBeSigned(Type),
 
+    #[error("'{0}' cannot be complex, only floating types can")]
+    InvalidComplex(Type),
+
+    #[error("_Imaginary types are not supported")]
+    ImaginaryNotSupported,
+
+    #[error("'{0}' is not a complex or floating type")]
+    NotComplex(Type),
+
+    #[error(
+        "arguments to __builtin_complex must have the same floating type, got '{0}' and '{1}'"
+    )]
+    BuiltinComplexArgs(Type, Type),
+
+    #[error("'{0}' cannot be atomic")]
+    InvalidAtomic(Type),
+
+    #[error("'{0}' takes {} arguments, got {1}", .0.arity())]
+    AtomicArguments(lex::AtomicOp, usize),
+
+    #[error("the first argument to an atomic builtin must be a pointer to an integer, floating, or pointer type, got '{0}'")]
+    AtomicPointer(Type),
+
+    #[error("'{0}' cannot be used on an object of type '{1}'")]
+    InvalidAtomicOperation(lex::AtomicOp, Type),
+
     #[error("types cannot be both signed and unsigned")]
     ConflictingSigned,
 
@@ -263,7 +284,7 @@ pub enum SemanticError {
     },
 
     #[error("not a constant expression: {0}")]
-    NotConstant(Expr),
+    NotConstant(Box<Expr>),
 
     #[error("cannot dereference NULL pointer")]
     NullPointerDereference,
@@ -284,6 +305,21 @@ pub enum SemanticError {
     #[error("cannot cast structs to any type")]
     StructCast,
 
+    #[error("static assertion failed{}", .0.as_ref().map_or_else(String::new, |msg| format!(": {}", msg)))]
+    StaticAssertFailed(Option<String>),
+
+    #[error("type '{0}' in generic association compatible with previously specified type")]
+    DuplicateGenericType(Type),
+
+    #[error("duplicate default generic association")]
+    DuplicateGenericDefault,
+
+    #[error("type '{0}' in generic association is not a complete object type")]
+    InvalidGenericType(Type),
+
+    #[error("controlling expression type '{0}' not compatible with any generic association type")]
+    NoGenericMatch(Type),
+
     // Control flow errors
     #[error("unreachable statement")]
     UnreachableStatement,
@@ -313,6 +349,68 @@ pub enum SemanticError {
     #[error("too many initializers (declared with {0} elements, found {1})")]
     TooManyMembers(usize, usize),
 
+    #[error("compound literal has incomplete type '{0}'")]
+    IncompleteCompoundLiteral(Type),
+
+    #[error("variable length array cannot be initialized")]
+    VlaInitializer,
+
+    #[error("variably modified type cannot be declared '{0}'")]
+    VariablyModifiedStorage(StorageClass),
+
+    #[error("struct member '{0}' cannot have a variably modified type")]
+    VariablyModifiedMember(InternedStr),
+
+    #[error("compound literal cannot have variable length array type '{0}'")]
+    VlaCompoundLiteral(Type),
+
+    #[error("cannot jump to label '{0}' from outside the scope of a variably modified type")]
+    GotoIntoVlaScope(InternedStr),
+
+    #[error("cannot jump to label '{0}' from outside the statement expression containing it")]
+    GotoIntoStmtExpr(InternedStr),
+
+    #[error("{}case cannot be inside a statement expression unless its switch is too",
+            if *(.is_default) { "default " } else { "" })]
+    CaseInStmtExpr { is_default: bool },
+
+    #[error("{}case cannot be in the scope of a variably modified type unless its switch is too",
+            if *(.is_default) { "default " } else { "" })]
+    CaseInVlaScope { is_default: bool },
+
+    #[error("statement expressions are only allowed inside functions")]
+    StmtExprOutsideFunction,
+
+    #[error("label addresses are only allowed inside functions")]
+    LabelAddressOutsideFunction,
+
+    #[error("computed goto must have a pointer type, got '{0}'")]
+    IndirectGotoNotPointer(Type),
+
+    #[error("flexible array member '{0}' must be the last member of a struct")]
+    FlexibleArrayNotLast(InternedStr),
+
+    #[error("flexible array member '{0}' cannot be the only named member of a struct")]
+    FlexibleArrayOnlyMember(InternedStr),
+
+    #[error("flexible array member '{0}' is not allowed in a union")]
+    FlexibleArrayInUnion(InternedStr),
+
+    #[error("cannot have an array of '{0}', which has a flexible array member")]
+    ArrayOfFlexibleStruct(Type),
+
+    #[error("flexible array members can only be initialized at file scope")]
+    LocalFlexibleInitializer,
+
+    #[error("array designator cannot initialize non-array type '{0}'")]
+    ArrayDesignator(Type),
+
+    #[error("field designator cannot initialize non-struct, non-union type '{0}'")]
+    FieldDesignator(Type),
+
+    #[error("array designator index {0} exceeds array bounds (array has {1} elements)")]
+    DesignatorOutOfBounds(u64, u64),
+
     // Function definition errors
     #[error("illegal storage class {0} for function (only `static` and `extern` are allowed)")]
     InvalidFuncStorageClass(StorageClass),
@@ -336,11 +434,64 @@ pub enum SemanticError {
     #[error("'{0}' can only appear on functions")]
     FuncQualifiersNotAllowed(hir::FunctionQualifiers),
 
+    #[error("requested alignment {0} is not a positive power of 2")]
+    InvalidAlignment(i64),
+
+    #[error("_Alignas cannot be used on {0}")]
+    AlignasNotAllowed(&'static str),
+
+    #[error("_Alignas cannot reduce the alignment of '{0}'")]
+    AlignasTooWeak(InternedStr),
+
+    #[error("_Thread_local cannot be used on {0}")]
+    ThreadLocalNotAllowed(&'static str),
+
+    #[error("_Thread_local cannot be combined with '{0}'")]
+    ThreadLocalStorageClass(StorageClass),
+
+    #[error(
+        "_Thread_local variable '{0}' at block scope must also be declared 'static' or 'extern'"
+    )]
+    ThreadLocalAtBlockScope(InternedStr),
+
+    #[error("constexpr cannot be used on {0}")]
+    ConstexprNotAllowed(&'static str),
+
+    #[error("constexpr cannot be combined with '{0}'")]
+    ConstexprStorageClass(StorageClass),
+
+    #[error("constexpr object '{0}' must be initialized")]
+    ConstexprUninitialized(InternedStr),
+
+    #[error("constexpr initializer is not exactly representable in type '{0}'")]
+    ConstexprNotRepresentable(Type),
+
+    #[error("cannot infer the type of '{0}': 'auto' can only be used with a plain identifier")]
+    AutoInferenceDeclarator(InternedStr),
+
+    #[error("cannot infer the type of '{0}' from an initializer list")]
+    AutoInferenceList(InternedStr),
+
+    #[error("invalid argument to attribute '{0}': expected {1}")]
+    BadAttributeArgument(InternedStr, &'static str),
+
+    #[error("cleanup function '{0}' must take a single argument of type '{1}'")]
+    BadCleanupFunction(InternedStr, Type),
+
+    #[error("attribute '{0}' is not supported by the JIT, compile to an object file instead")]
+    AttributeNotSupportedByJit(InternedStr),
+
     // stmt errors
     // new with the new parser
+    #[error("cannot rename '{0}' with an asm label, since it has no linkage")]
+    AsmLabelWithoutLinkage(InternedStr),
+
     #[error("switch expressions must have an integer type (got {0})")]
     NonIntegralSwitch(Type),
 
+    #[error("switch expressions with type '{0}' are not yet supported")]
+    Int128Switch(Type),
+
     #[error("function '{0}' does not return a value")]
     MissingReturnValue(InternedStr),
 
@@ -383,13 +534,16 @@ pub enum SyntaxError {
     ExpectedDeclaratorStart(Token),
 
     #[error("only functions can have a function body (got {0})")]
-    NotAFunction(ast::InitDeclarator),
+    NotAFunction(Box<ast::InitDeclarator>),
 
     #[error("functions cannot be initialized (got {0})")]
-    FunctionInitializer(ast::Initializer),
+    FunctionInitializer(Box<ast::Initializer>),
+
+    #[error("asm labels are not allowed on function definitions")]
+    AsmLabelOnDefinition,
 
     #[error("function not allowed in this context (got {})", .0.as_type())]
-    FunctionNotAllowed(ast::FunctionDefinition),
+    FunctionNotAllowed(Box<ast::FunctionDefinition>),
 
     #[error("function definitions must have a name")]
     MissingFunctionName,
@@ -411,6 +565,9 @@ pub enum SyntaxError {
 
     #[error("{0}")]
     InvalidHexFloat(#[from] hexponent::ParseError),
+
+    #[error("{0} are a GNU extension; use -std=gnu11 to enable them")]
+    GnuExtension(&'static str),
 }
 
 /// Preprocessing errors are non-exhaustive and may have new variants added at any time
@@ -506,6 +663,22 @@ pub enum CppError {
     /// '#' in a function macro not followed by function parameter
     #[error("'#' is not followed by a macro parameter")]
     HashMissingParameter,
+
+    /// '##' at the start or end of a macro body, such as `#define f(a) a ##`
+    #[error("'##' cannot appear at either end of a macro expansion")]
+    HashHashAtEdge,
+
+    /// Token pasting produced something that isn't a single token, such as `+ ## -`
+    #[error("pasting \"{0}\" and \"{1}\" does not give a valid preprocessing token")]
+    InvalidPaste(Token, Token),
+
+    /// An `#embed` parameter other than `limit`, `prefix`, `suffix`, or `if_empty` was present.
+    #[error("unsupported #embed parameter '{0}'")]
+    UnknownEmbedParameter(String),
+
+    /// The argument to `limit()` in an `#embed` directive was negative or not an integer.
+    #[error("#embed limit must be a non-negative integer")]
+    InvalidEmbedLimit,
 }
 
 /// Lex errors are non-exhaustive and may have new variants added at any time
@@ -587,11 +760,57 @@ pub enum Warning {
     #[error("variadic macros are not yet supported")]
     IgnoredVariadic,
 
+    #[error("'{0}' is a keyword, ignoring its definition as a macro")]
+    KeywordDefinition(InternedStr),
+
     #[error("implicit int is deprecated and may be removed in a future release")]
     ImplicitInt,
 
     #[error("this is a definition, not a declaration, the 'extern' keyword has no effect")]
     ExtraneousExtern,
+
+    #[error("old-style function definitions are obsolescent, use a prototype instead")]
+    OldStyleFunction,
+
+    #[error("initializing a flexible array member is a GNU extension")]
+    FlexibleArrayInitializer,
+
+    #[error("'_Complex' without a real type is a GNU extension, assuming 'double _Complex'")]
+    PlainComplex,
+
+    #[error("{0} are a C23 extension")]
+    C23Extension(&'static str),
+
+    #[error("unknown attribute '{0}' ignored")]
+    UnknownAttribute(InternedStr),
+
+    #[error("attribute '{0}' is ignored on {1}")]
+    IgnoredAttribute(InternedStr, &'static str),
+
+    #[error("'{0}' is deprecated{}", if .1.is_empty() { String::new() } else { format!(": {}", .1) })]
+    Deprecated(InternedStr, String),
+
+    #[error("function '{0}' is declared 'noreturn' but returns")]
+    ReturnInNoreturn(InternedStr),
+
+    #[error("ignoring return value of '{0}', which is declared 'nodiscard'{}", if .1.is_empty() { String::new() } else { format!(": {}", .1) })]
+    UnusedResult(InternedStr, String),
+
+    #[error("unannotated fall-through between switch labels; use '[[fallthrough]];' if this is intentional")]
+    ImplicitFallthrough,
+
+    #[error("'fallthrough' attribute is not followed by a 'case' or 'default' label")]
+    MisplacedFallthrough,
+
+    #[error("format string expects {0} argument{}, but {1} {} given",
+            if *.0 == 1 { "" } else { "s" }, if *.1 == 1 { "was" } else { "were" })]
+    FormatArgumentCount(usize, usize),
+
+    #[error("format '%{0}' expects {1}, but the argument has type '{2}'")]
+    FormatArgumentType(String, &'static str, Type),
+
+    #[error("empty case range will never be matched")]
+    EmptyCaseRange,
 }
 
 impl<T: Into<String>> From<T> for Warning {
@@ -8,7 +8,7 @@ use std::rc::Rc;
 #[cfg(test)]
 use proptest_derive::Arbitrary;
 
-use super::lex::{ComparisonToken, Keyword, Locatable};
+use super::lex::{AtomicOp, ComparisonToken, Keyword, Locatable};
 use super::types::Type;
 use super::*;
 use crate::intern::InternedStr;
@@ -27,14 +27,21 @@ pub enum StmtType {
     For(Box<Stmt>, Option<Box<Expr>>, Option<Box<Expr>>, Box<Stmt>),
     Switch(Expr, Box<Stmt>),
     Label(InternedStr, Box<Stmt>),
-    Case(u64, Box<Stmt>),
+    // case 1:
+    // case 'a' ... 'z': (the range is inclusive)
+    Case(u64, Option<u64>, Box<Stmt>),
     Default(Box<Stmt>),
     Expr(Expr),
     Goto(InternedStr),
+    // goto *labels[i];
+    // The target is always a pointer.
+    IndirectGoto(Expr),
     Continue,
     Break,
     Return(Option<Expr>),
     Decl(Vec<Locatable<Declaration>>),
+    // [[fallthrough]];
+    Fallthrough,
 }
 
 impl Default for StmtType {
@@ -118,6 +125,35 @@ impl Variable {
     pub fn insert(self) -> Symbol {
         SYMBOL_TABLE.with(|store| store.borrow_mut().insert(self))
     }
+    /// Whether this is an unnamed bit-field, e.g. `int : 3;`.
+    ///
+    /// Unnamed bit-fields are only padding: they cannot be accessed or initialized.
+    pub fn is_unnamed_bitfield(&self) -> bool {
+        self.bitfield.is_some() && self.id == InternedStr::default()
+    }
+    /// Whether `self` and `other` declare the same entity in the same way.
+    ///
+    /// This ignores attributes, since later declarations can add attributes to earlier ones.
+    pub(crate) fn same_declaration(&self, other: &Variable) -> bool {
+        let Variable {
+            ctype,
+            storage_class,
+            qualifiers,
+            id,
+            bitfield,
+            alignment,
+            thread_local,
+            constexpr: _,
+            attributes: _,
+        } = self;
+        *ctype == other.ctype
+            && *storage_class == other.storage_class
+            && *qualifiers == other.qualifiers
+            && *id == other.id
+            && *bitfield == other.bitfield
+            && *alignment == other.alignment
+            && *thread_local == other.thread_local
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -129,6 +165,10 @@ pub enum ExprType {
     Literal(LiteralValue),
     FuncCall(Box<Expr>, Vec<Expr>),
     Member(Box<Expr>, InternedStr),
+    // (int){1}: an unnamed object with automatic storage duration.
+    // The symbol is not declared anywhere else: the object is initialized every time this is evaluated.
+    // Compound literals at file scope are static and are declared as a normal `Id` instead.
+    CompoundLiteral(Symbol, Box<Initializer>),
 
     // unary expressions
     // post increment/decrement
@@ -138,6 +178,15 @@ pub enum ExprType {
     Deref(Box<Expr>),
     Negate(Box<Expr>),
     BitwiseNot(Box<Expr>),
+    // `__real__ z` and `__imag__ z`: a part of the complex number `z`.
+    // Like `Member`, this is an lvalue even if `z` is not, so it may need to be wrapped in a `Deref`.
+    Real(Box<Expr>),
+    Imag(Box<Expr>),
+    // __builtin_complex(re, im)
+    Complex(Box<Expr>, Box<Expr>),
+    // __atomic_fetch_add(p, 1, __ATOMIC_SEQ_CST)
+    // The arguments have already been converted to the type of the atomic object.
+    AtomicBuiltin(AtomicOp, Vec<Expr>),
 
     // binary expressions
     Binary(BinaryOp, Box<Expr>, Box<Expr>),
@@ -151,6 +200,14 @@ pub enum ExprType {
     StaticRef(Box<Expr>),
     // used to work around various bugs, see places this is constructed for details
     Noop(Box<Expr>),
+    // `a += 1` where `a` is atomic: the whole expression is evaluated as a single atomic operation
+    AtomicUpdate(Box<Expr>),
+    // GNU statement expression: `({ int x = f(); x; })`
+    // The value is the last expression statement, or `None` if the last statement was anything else.
+    StmtExpr(Vec<Stmt>, Option<Box<Expr>>),
+    // GNU address of a label: `&&label`
+    // The label is guaranteed to exist in the current function.
+    LabelAddress(InternedStr),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -162,6 +219,9 @@ pub enum LiteralValue {
     Float(f64),
     Str(Vec<u8>),
     Char(u8),
+    // the result of folding an expression with type `__int128` or `unsigned __int128`
+    Int128(i128),
+    UnsignedInt128(u128),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -218,6 +278,98 @@ pub struct Variable {
     pub storage_class: StorageClass,
     pub qualifiers: Qualifiers,
     pub id: InternedStr,
+    /// For struct and union members declared as bit-fields, the width in bits.
+    ///
+    /// Unnamed bit-fields (`int : 3;`) are stored as members with an empty `id`.
+    pub bitfield: Option<u8>,
+    /// The alignment requested with `_Alignas` or `__attribute__((aligned))`, in bytes: `_Alignas(16) int i;`
+    ///
+    /// This is never less strict than the alignment of `ctype`,
+    /// except for members of a struct with `__attribute__((packed))`.
+    pub alignment: Option<u64>,
+    /// Whether this has thread storage duration: `_Thread_local int i;`
+    pub thread_local: bool,
+    /// For scalar `constexpr` objects, the value of the initializer: `constexpr int n = 5;`
+    ///
+    /// This lets the object be used in integer constant expressions.
+    pub constexpr: Option<LiteralValue>,
+    /// Attributes that affect code generation or diagnostics: `__attribute__((weak)) int i;`
+    pub attributes: Attributes,
+}
+
+/// The attributes that are stored on a variable or function.
+///
+/// These can come from either GNU attributes (`__attribute__((weak))`) or standard attributes (`[[nodiscard]]`).
+///
+/// Attributes which only change the type or alignment, like `aligned` or `noreturn`,
+/// are applied by the analyzer and not stored here.
+/// Attributes which have no effect, like `unused`, are not stored at all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Attributes {
+    /// The section to place this object in: `__attribute__((section(".data.mine")))`
+    pub section: Option<String>,
+    /// The name of the symbol in the object file, if different from the name in C:
+    /// `int f(void) __asm__("g");`
+    pub asm_label: Option<String>,
+    /// Whether this can be overridden by another definition at link time: `__attribute__((weak))`
+    pub weak: bool,
+    /// `__attribute__((visibility("hidden")))`
+    pub visibility: Visibility,
+    /// If set, this function should be called before `main`: `__attribute__((constructor(101)))`
+    ///
+    /// Constructors with a lower priority run first.
+    /// Constructors without a priority have a priority of 65535 and run last.
+    pub constructor: Option<u16>,
+    /// If set, this function should be called after `main` returns: `__attribute__((destructor))`
+    ///
+    /// Destructors with a lower priority run last.
+    pub destructor: Option<u16>,
+    /// A function to call with the address of this variable when it goes out of scope:
+    /// `__attribute__((cleanup(free_ptr))) char *p = malloc(1);`
+    pub cleanup: Option<Symbol>,
+    /// If set, warn whenever this is used. The message may be empty.
+    pub deprecated: Option<String>,
+    /// The arguments to this function should be checked against a format string:
+    /// `__attribute__((format(printf, 1, 2)))`
+    pub format: Option<FormatAttribute>,
+    /// If set, warn when the result of a call to this function is discarded. The message may be empty.
+    ///
+    /// `[[nodiscard("check for errors")]]` or `__attribute__((warn_unused_result))`
+    pub nodiscard: Option<String>,
+    /// `[[reproducible]]`: two calls with the same arguments return the same result,
+    /// as long as no memory the function can see was modified in between.
+    pub reproducible: bool,
+    /// `[[unsequenced]]`: a `reproducible` function which also has no side effects
+    /// and doesn't depend on any state other than its arguments.
+    pub unsequenced: bool,
+}
+
+/// The ELF visibility of a symbol: `__attribute__((visibility("hidden")))`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    #[default]
+    Default,
+    Hidden,
+    Internal,
+    Protected,
+}
+
+/// `__attribute__((format(printf, 1, 2)))`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FormatAttribute {
+    pub kind: FormatKind,
+    /// The index of the format string parameter, starting from 1.
+    pub format_index: usize,
+    /// The index of the first argument to check, starting from 1.
+    ///
+    /// This is 0 for functions which take a `va_list`, like `vprintf`.
+    pub first_arg: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FormatKind {
+    Printf,
+    Scanf,
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
@@ -373,6 +525,10 @@ impl Display for Expr {
             ExprType::BitwiseNot(expr) => write!(f, "(~{})", expr),
             ExprType::Deref(expr) => write!(f, "*({})", expr),
             ExprType::Negate(expr) => write!(f, "-({})", expr),
+            ExprType::Real(expr) => write!(f, "__real__({})", expr),
+            ExprType::Imag(expr) => write!(f, "__imag__({})", expr),
+            ExprType::Complex(real, imag) => write!(f, "__builtin_complex({}, {})", real, imag),
+            ExprType::AtomicBuiltin(op, args) => write!(f, "{}({})", op, joined(args, ", ")),
 
             ExprType::Ternary(cond, left, right) => {
                 write!(f, "({}) ? ({}) : ({})", cond, left, right)
@@ -384,8 +540,22 @@ impl Display for Expr {
             ExprType::PostIncrement(expr, inc) => {
                 write!(f, "({}){}", expr, if *inc { "++" } else { "--" })
             }
+            ExprType::CompoundLiteral(_, init) => write!(f, "({}){}", self.ctype, init),
             ExprType::StaticRef(expr) => write!(f, "&{}", expr),
             ExprType::Noop(expr) => write!(f, "{}", expr),
+            ExprType::AtomicUpdate(expr) => write!(f, "{}", expr),
+            ExprType::StmtExpr(stmts, value) => {
+                write!(f, "({{ ")?;
+                for stmt in stmts {
+                    // statements end with a newline, which would split up the expression
+                    write!(f, "{} ", stmt.data.to_string().trim_end())?;
+                }
+                if let Some(value) = value {
+                    write!(f, "{}; ", value)?;
+                }
+                write!(f, "}})")
+            }
+            ExprType::LabelAddress(label) => write!(f, "&&{}", label),
         }
     }
 }
@@ -425,9 +595,14 @@ impl StmtType {
             StmtType::Return(Some(expr)) => write!(f, "return {};", expr),
             StmtType::Break => write!(f, "break;"),
             StmtType::Continue => write!(f, "continue;"),
+            StmtType::Fallthrough => write!(f, "[[fallthrough]];"),
             StmtType::Default(stmt) => write!(f, "default:\n{}", stmt.data),
-            StmtType::Case(expr, stmt) => write!(f, "case {}:\n{}", expr, stmt.data),
+            StmtType::Case(expr, None, stmt) => write!(f, "case {}:\n{}", expr, stmt.data),
+            StmtType::Case(lower, Some(upper), stmt) => {
+                write!(f, "case {} ... {}:\n{}", lower, upper, stmt.data)
+            }
             StmtType::Goto(id) => write!(f, "goto {};", id),
+            StmtType::IndirectGoto(target) => write!(f, "goto *{};", target),
             StmtType::Label(id, inner) => write!(f, "{}: {}", id, inner.data),
             StmtType::While(condition, body) => write!(f, "while ({}) {}", condition, body.data),
             StmtType::If(condition, body, None) => write!(f, "if ({}) {}", condition, body.data),
@@ -491,7 +666,14 @@ impl Display for Variable {
         if self.storage_class != StorageClass::default() {
             write!(f, "{} ", self.storage_class)?;
         }
-        super::types::print_type(&self.ctype, Some(self.id), f)
+        if self.thread_local {
+            write!(f, "_Thread_local ")?;
+        }
+        super::types::print_type(&self.ctype, Some(self.id), f)?;
+        if let Some(width) = self.bitfield {
+            write!(f, ": {}", width)?;
+        }
+        Ok(())
     }
 }
 
@@ -525,6 +707,8 @@ impl LiteralValue {
             LiteralValue::Int(i) => i == 0,
             LiteralValue::UnsignedInt(u) => u == 0,
             LiteralValue::Char(c) => c == 0,
+            LiteralValue::Int128(i) => i == 0,
+            LiteralValue::UnsignedInt128(u) => u == 0,
             _ => false,
         }
     }
@@ -536,6 +720,8 @@ impl std::fmt::Display for LiteralValue {
         match self {
             Int(i) => write!(f, "{}", i),
             UnsignedInt(u) => write!(f, "{}", u),
+            Int128(i) => write!(f, "{}", i),
+            UnsignedInt128(u) => write!(f, "{}", u),
             Float(n) => write!(f, "{}", n),
             Str(s) => {
                 let mut escaped = s
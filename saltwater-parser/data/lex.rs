@@ -91,6 +91,7 @@ pub enum Keyword {
     Short,
     Int,
     Long,
+    Int128,
     Float,
     Double,
     Void,
@@ -134,6 +135,48 @@ pub enum Keyword {
     StaticAssert,
     Alignas,
     Alignof,
+    Real,
+    Imag,
+    BuiltinComplex,
+    AtomicBuiltin(AtomicOp),
+    Attribute,
+    // GNU asm label: `int f(void) __asm__("g");`
+    Asm,
+
+    // C23
+    Nullptr,
+    Typeof,
+    TypeofUnqual,
+    Constexpr,
+}
+
+/// The GNU builtins used to implement `<stdatomic.h>`, e.g. `__atomic_fetch_add`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum AtomicOp {
+    Load,
+    Store,
+    Exchange,
+    CompareExchange,
+    FetchAdd,
+    FetchSub,
+    FetchAnd,
+    FetchOr,
+    FetchXor,
+    ThreadFence,
+}
+
+impl AtomicOp {
+    /// The number of arguments this builtin takes, including memory orders.
+    pub fn arity(self) -> usize {
+        use AtomicOp::*;
+        match self {
+            ThreadFence => 1,
+            Load => 2,
+            Store | Exchange | FetchAdd | FetchSub | FetchAnd | FetchOr | FetchXor => 3,
+            CompareExchange => 6,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -226,6 +269,10 @@ pub enum Token {
     Keyword(Keyword),
     Literal(LiteralToken),
     Id(InternedStr),
+    /// The contents of a resource included with `#embed`.
+    ///
+    /// This stands for a comma-separated list of integer constants, one for each byte.
+    Embed(Vec<u8>),
 
     Whitespace(String),
 
@@ -235,6 +282,46 @@ pub enum Token {
     Hash,        // #, used for preprocessing
 }
 
+/// Source text which has no meaning to the parser, but which is needed to reproduce the original file.
+///
+/// These are only created by `LosslessLexer`; the normal `Lexer` throws them away.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    /// The exact text from the original source
+    pub text: Substr,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces, tabs, and escaped newlines (`\` at the end of a line)
+    Whitespace,
+    /// A single `\n` or `\r\n`
+    Newline,
+    /// `// comment`, not including the newline at the end
+    LineComment,
+    /// `/* comment */`
+    BlockComment,
+    /// Text which could not be lexed, for example `@` or an unterminated string
+    Skipped,
+}
+
+/// A token together with all the trivia around it.
+///
+/// Trailing trivia is everything after the token up to and including the end of the line.
+/// Leading trivia is everything else between the previous token and this one.
+/// Any trivia at the end of the file is attached to the last token.
+///
+/// Concatenating the `Display` of every token in a file gives back exactly the original file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LosslessToken {
+    pub token: Locatable<Token>,
+    /// The exact text of the token, including escaped newlines
+    pub text: Substr,
+    pub leading: Vec<Locatable<Trivia>>,
+    pub trailing: Vec<Locatable<Trivia>>,
+}
+
 /* impls */
 impl PartialOrd for Location {
     /// NOTE: this only compares the start of the spans, it ignores the end
@@ -345,11 +432,38 @@ impl std::fmt::Display for Keyword {
             Keyword::ThreadLocal => write!(f, "_Thread_local"),
             Keyword::StaticAssert => write!(f, "_Static_assert"),
             Keyword::VaList => write!(f, "va_list"),
+            Keyword::Int128 => write!(f, "__int128"),
+            Keyword::Real => write!(f, "__real__"),
+            Keyword::Imag => write!(f, "__imag__"),
+            Keyword::BuiltinComplex => write!(f, "__builtin_complex"),
+            Keyword::AtomicBuiltin(op) => write!(f, "{}", op),
+            Keyword::Attribute => write!(f, "__attribute__"),
+            Keyword::Asm => write!(f, "__asm__"),
+            Keyword::TypeofUnqual => write!(f, "typeof_unqual"),
             _ => write!(f, "{}", &format!("{:?}", self).to_lowercase()),
         }
     }
 }
 
+impl std::fmt::Display for AtomicOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use AtomicOp::*;
+        let name = match self {
+            Load => "load_n",
+            Store => "store_n",
+            Exchange => "exchange_n",
+            CompareExchange => "compare_exchange_n",
+            FetchAdd => "fetch_add",
+            FetchSub => "fetch_sub",
+            FetchAnd => "fetch_and",
+            FetchOr => "fetch_or",
+            FetchXor => "fetch_xor",
+            ThreadFence => "thread_fence",
+        };
+        write!(f, "__atomic_{}", name)
+    }
+}
+
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Token::*;
@@ -387,6 +501,15 @@ impl std::fmt::Display for Token {
             Literal(lit) => write!(f, "{}", lit),
             Id(id) => write!(f, "{}", id),
             Keyword(k) => write!(f, "{}", k),
+            Embed(bytes) => {
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", byte)?;
+                }
+                Ok(())
+            }
 
             Whitespace(s) => write!(f, "{}", s),
 
@@ -397,6 +520,25 @@ impl std::fmt::Display for Token {
     }
 }
 
+impl std::fmt::Display for Trivia {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl std::fmt::Display for LosslessToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia.data)?;
+        }
+        write!(f, "{}", self.text)?;
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia.data)?;
+        }
+        Ok(())
+    }
+}
+
 impl std::fmt::Display for LiteralToken {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use LiteralToken::*;
@@ -498,6 +640,13 @@ pub(crate) mod test {
     pub(crate) fn cpp_no_newline(s: &str) -> PreProcessor {
         PreProcessorBuilder::new(s).build()
     }
+    /// Create a new preprocessor with `s` as the input that follows C23
+    pub(crate) fn cpp_c23(s: &str) -> PreProcessor<'_> {
+        let newline = format!("{}\n", s).into_boxed_str();
+        PreProcessorBuilder::new(Box::leak(newline))
+            .standard(Standard::C23)
+            .build()
+    }
 
     #[test]
     fn assignment_display() {
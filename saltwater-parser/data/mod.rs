@@ -9,7 +9,7 @@ pub use error::{
     CompileError, CompileResult, CompileWarning, Error, ErrorHandler, SemanticError, SyntaxError,
 };
 pub use hir::LiteralValue;
-pub use lex::{LiteralToken, Locatable, Location, Token};
+pub use lex::{LiteralToken, Locatable, Location, LosslessToken, Token, Trivia, TriviaKind};
 pub use types::Type;
 pub use types::{StructRef, StructType};
 
@@ -127,7 +127,7 @@ mod codegen_impls {
     use cranelift_codegen::ir::{
         condcodes::{FloatCC, IntCC},
         types::{self, Type as IrType},
-        AbiParam, ArgumentPurpose,
+        AbiParam, ArgumentLoc, ArgumentPurpose,
     };
     use cranelift_codegen::isa::{CallConv, TargetIsa};
 
@@ -157,7 +157,15 @@ mod codegen_impls {
             match self {
                 // Integers
                 Bool => types::B1,
-                Char(_) | Short(_) | Int(_) | Long(_) | Pointer(_, _) | Enum(_, _) => {
+                Char(_)
+                | Short(_)
+                | Int(_)
+                | Long(_)
+                | LongLong(_)
+                | Int128(_)
+                | Pointer(_, _)
+                | NullPtr
+                | Enum(_, _) => {
                     let int_size = SIZE_T::from(CHAR_BIT)
                         * self
                             .sizeof()
@@ -175,15 +183,43 @@ mod codegen_impls {
                 // TODO: this is hard-coded for x64
                 Float => types::F32,
                 Double => types::F64,
+                // computed with the precision of a double, converted to x87 format in memory
+                LongDouble => types::F64,
 
                 // Aggregates
-                // arrays and functions decay to pointers
-                Function(_) | Array(_, _) => IrType::int(PTR_SIZE * CHAR_BIT)
+                // arrays and functions decay to pointers,
+                // complex numbers are passed around as the address of a temporary
+                Function(_) | Array(_, _) | Complex(_) => IrType::int(PTR_SIZE * CHAR_BIT)
                     .unwrap_or_else(|| panic!("unsupported size of IR: {}", PTR_SIZE)),
+                Atomic(inner) => inner.as_ir_type(),
                 // void cannot be loaded or stored
                 _ => types::INVALID,
             }
         }
+        /// Return the IR types used to pass this type to or return it from a function.
+        ///
+        /// Following the System V ABI, a `float _Complex` is packed into a single SSE register
+        /// and the parts of any other complex type are passed separately.
+        pub fn abi_types(&self) -> Vec<IrType> {
+            match self {
+                Type::Complex(real) if **real == Type::Float => vec![types::F64],
+                Type::Complex(real) => vec![real.as_ir_type(); 2],
+                Type::Atomic(inner) => inner.abi_types(),
+                _ => vec![self.as_ir_type()],
+            }
+        }
+        /// Like `abi_types`, but a `long double`, `long double _Complex`, or `__int128`
+        /// is split into the `I64` eightbytes that hold it in memory.
+        ///
+        /// These are the types of the parameters of a function that uses `long double`.
+        pub fn eightbytes(&self) -> Vec<IrType> {
+            match self {
+                Type::LongDouble | Type::Int128(_) => vec![types::I64; 2],
+                Type::Complex(real) if **real == Type::LongDouble => vec![types::I64; 4],
+                Type::Atomic(inner) => inner.eightbytes(),
+                _ => self.abi_types(),
+            }
+        }
         pub fn member_offset(&self, member: InternedStr) -> Result<u64, ()> {
             match self {
                 Type::Struct(stype) => Ok(stype.offset(member)),
@@ -191,6 +227,21 @@ mod codegen_impls {
                 _ => Err(()),
             }
         }
+        /// If `member` is a bit-field, return its position within the storage unit.
+        ///
+        /// The storage unit is located at `member_offset(member)`.
+        pub fn member_bitfield(&self, member: InternedStr) -> Option<BitField> {
+            match self {
+                Type::Struct(stype) => stype.bitfield(member),
+                Type::Union(stype) => stype
+                    .members()
+                    .iter()
+                    .find(|formal| formal.id == member)
+                    .and_then(|formal| formal.bitfield)
+                    .map(|width| BitField { shift: 0, width }),
+                _ => None,
+            }
+        }
     }
 
     impl CompileError {
@@ -204,15 +255,31 @@ mod codegen_impls {
             !(self.params.len() == 1 && self.params[0].get().ctype == Type::Void)
         }
 
+        /// Whether this function takes or returns a `long double` (or `long double _Complex`).
+        ///
+        /// The System V ABI passes these in memory and returns them on the x87 stack.
+        /// Cranelift can do neither, so `signature` gives every parameter of such a function
+        /// an explicit location, and the return value is moved to and from the x87 stack by hand.
+        pub fn uses_long_double(&self) -> bool {
+            is_long_double(&self.return_type)
+                || self
+                    .params
+                    .iter()
+                    .any(|param| is_long_double(&param.get().ctype))
+        }
+
         /// Generate the IR function signature for `self`
         pub fn signature(&self, isa: &dyn TargetIsa) -> Signature {
-            let mut params = if self.params.len() == 1 && self.params[0].get().ctype == Type::Void {
+            let mut params = if !self.has_params() {
                 // no arguments
                 Vec::new()
+            } else if self.uses_long_double() {
+                self.explicit_params(isa)
             } else {
                 self.params
                     .iter()
-                    .map(|param| AbiParam::new(param.get().ctype.as_ir_type()))
+                    .flat_map(|param| param.get().ctype.abi_types())
+                    .map(AbiParam::new)
                     .collect()
             };
             if self.varargs {
@@ -226,10 +293,15 @@ mod codegen_impls {
                     al,
                 ));
             }
-            let return_type = if !self.should_return() {
+            // a `long double` is returned in `st0`, which cranelift doesn't know about
+            let return_type = if !self.should_return() || is_long_double(&self.return_type) {
                 vec![]
             } else {
-                vec![AbiParam::new(self.return_type.as_ir_type())]
+                self.return_type
+                    .abi_types()
+                    .into_iter()
+                    .map(AbiParam::new)
+                    .collect()
             };
             Signature {
                 call_conv: *CALLING_CONVENTION,
@@ -237,6 +309,59 @@ mod codegen_impls {
                 returns: return_type,
             }
         }
+
+        /// Assign each parameter to a register or a stack offset, following the System V ABI.
+        ///
+        /// A parameter is passed in registers only if all of its eightbytes fit;
+        /// otherwise it goes on the stack, aligned to at least 8 bytes.
+        /// `long double` is always passed on the stack.
+        fn explicit_params(&self, isa: &dyn TargetIsa) -> Vec<AbiParam> {
+            const INT_REGS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+            const FLOAT_REGS: usize = 8;
+            let reg = |name: &str| {
+                isa.register_info()
+                    .parse_regunit(name)
+                    .unwrap_or_else(|| panic!("x86 should have a {} register", name))
+            };
+            let (mut ints, mut floats, mut offset) = (0, 0, 0);
+            let mut params = Vec::new();
+            for param in &self.params {
+                let ctype = &param.get().ctype;
+                let eightbytes = ctype.eightbytes();
+                let needed_floats = eightbytes.iter().filter(|ty| ty.is_float()).count();
+                let needed_ints = eightbytes.len() - needed_floats;
+                if !is_long_double(ctype)
+                    && ints + needed_ints <= INT_REGS.len()
+                    && floats + needed_floats <= FLOAT_REGS
+                {
+                    for ty in eightbytes {
+                        let unit = if ty.is_float() {
+                            floats += 1;
+                            reg(&format!("xmm{}", floats - 1))
+                        } else {
+                            ints += 1;
+                            reg(INT_REGS[ints - 1])
+                        };
+                        params.push(AbiParam::special_reg(ty, ArgumentPurpose::Normal, unit));
+                    }
+                } else {
+                    let align = ctype.alignof().unwrap_or(8).max(8) as i32;
+                    offset = (offset + align - 1) / align * align;
+                    for ty in eightbytes {
+                        params.push(AbiParam {
+                            location: ArgumentLoc::Stack(offset),
+                            ..AbiParam::new(ty)
+                        });
+                        offset += 8;
+                    }
+                }
+            }
+            params
+        }
+    }
+
+    fn is_long_double(ctype: &Type) -> bool {
+        *ctype.non_atomic().real_type() == Type::LongDouble
     }
 
     impl ComparisonToken {
@@ -1,4 +1,4 @@
-use super::hir::{Symbol, Variable};
+use super::hir::{ExprType, Initializer, LiteralValue, Symbol, Variable};
 use crate::intern::InternedStr;
 #[cfg(test)]
 use proptest_derive::Arbitrary;
@@ -117,6 +117,29 @@ mod struct_ref {
                 StructType::Named(_, struct_ref) => struct_ref.get(),
             }
         }
+        /// Get the members which take part in initialization,
+        /// i.e. all members except unnamed bit-fields.
+        pub fn initialized_members(&self) -> Vec<Variable> {
+            self.members()
+                .iter()
+                .filter(|member| !member.is_unnamed_bitfield())
+                .cloned()
+                .collect()
+        }
+        /// Get the flexible array member of a struct, if it has one.
+        ///
+        /// e.g. `data` in `struct packet { int len; char data[]; }`
+        pub fn flexible_array_member(&self) -> Option<Variable> {
+            self.members()
+                .last()
+                .filter(|member| {
+                    matches!(
+                        member.ctype,
+                        super::Type::Array(_, super::ArrayType::Unbounded)
+                    )
+                })
+                .cloned()
+        }
         /// Return whether the struct has no members.
         ///
         /// For `Named` structs, this occurs whenever we have seen
@@ -141,8 +164,22 @@ pub enum Type {
     Short(bool),
     Int(bool),
     Long(bool),
+    LongLong(bool),
+    /// `__int128` or `unsigned __int128`
+    Int128(bool),
     Float,
     Double,
+    /// Stored in the x87 extended precision format, but computed with the precision of a `double`.
+    LongDouble,
+    /// `float _Complex`, `double _Complex`, or `long double _Complex`.
+    ///
+    /// Stored as the real part followed by the imaginary part, each with the given type.
+    Complex(Box<Type>),
+    /// `_Atomic int` or `_Atomic(int)`.
+    ///
+    /// Has the same size and representation as the inner type,
+    /// which is never an array, function, or another atomic type.
+    Atomic(Box<Type>),
     // TODO: separate Qualifiers into LvalQualifiers and FunctionQualifiers
     Pointer(Box<Type>, super::hir::Qualifiers),
     Array(Box<Type>, ArrayType),
@@ -153,6 +190,10 @@ pub enum Type {
     Enum(Option<InternedStr>, Vec<(InternedStr, i64)>),
     /// This is the type used for variadic arguments.
     VaList,
+    /// `nullptr_t`, the type of `nullptr` (C23).
+    ///
+    /// Has the same size and representation as `void *`, but its only value is a null pointer.
+    NullPtr,
     /// A semantic error occured while parsing this type.
     Error,
 }
@@ -162,9 +203,29 @@ pub enum Type {
 pub enum ArrayType {
     Fixed(u64),
     Unbounded,
+    /// A variable length array, whose length is only known at runtime: `int a[n]`.
+    ///
+    /// The length is evaluated once, when the array type is declared,
+    /// and stored in the hidden variable `Symbol`.
+    #[cfg_attr(test, proptest(skip))]
+    Variable(Symbol),
+}
+
+impl ArrayType {
+    /// 6.7.6.2p6: For two array types to be compatible, [...] if both size specifiers
+    /// are present, and are integer constant expressions, then both size specifiers
+    /// shall have the same constant value.
+    ///
+    /// The length of a VLA isn't known until runtime, so it's compatible with any array that has a length.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        use ArrayType::*;
+        match (self, other) {
+            (Variable(_), Fixed(_)) | (Fixed(_), Variable(_)) | (Variable(_), Variable(_)) => true,
+            _ => self == other,
+        }
+    }
 }
 
-// NOTE: K&R declarations are not supported at this time
 #[derive(Clone, Debug)]
 pub struct FunctionType {
     // TODO: allow FunctionQualifiers as well
@@ -178,6 +239,12 @@ pub struct FunctionType {
     //    this is as good a place to store them as any.
     pub params: Vec<Symbol>,
     pub varargs: bool,
+    /// Whether this function was defined with an identifier list (`int f(a) int a; {}`).
+    ///
+    /// Like `int f()`, this type has no prototype:
+    /// arguments undergo the default argument promotions instead of being converted to the parameter types.
+    /// `params` holds the promoted types, which is what callers actually pass.
+    pub old_style: bool,
 }
 
 impl Type {
@@ -186,47 +253,98 @@ impl Type {
     pub fn is_scalar(&self) -> bool {
         use Type::*;
         match self {
-            Enum(_, _) => true,
+            Enum(_, _) | NullPtr => true,
             k if k.is_arithmetic() || k.is_pointer() => true,
             _ => false,
         }
     }
     #[inline]
     pub(crate) fn is_bool(&self) -> bool {
-        match self {
-            Type::Bool => true,
-            _ => false,
-        }
+        matches!(self, Type::Bool)
     }
     #[inline]
     // returns whether `self` is a signed integer type
     pub fn is_signed(&self) -> bool {
         use Type::*;
-        match self {
-            Bool | Char(true) | Short(true) | Int(true) | Long(true) | Enum(_, _) => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            Bool | Char(true)
+                | Short(true)
+                | Int(true)
+                | Long(true)
+                | LongLong(true)
+                | Int128(true)
+                | Enum(_, _)
+        )
     }
     #[inline]
     pub fn is_integral(&self) -> bool {
         use Type::*;
         match self {
-            Bool | Char(_) | Short(_) | Int(_) | Long(_) | Enum(_, _) => true,
+            Bool | Char(_) | Short(_) | Int(_) | Long(_) | LongLong(_) | Int128(_) | Enum(_, _) => {
+                true
+            }
             _ => false,
         }
     }
     #[inline]
     pub fn is_floating(&self) -> bool {
         match self {
-            Type::Float | Type::Double => true,
+            Type::Float | Type::Double | Type::LongDouble => true,
             _ => false,
         }
     }
     #[inline]
-    pub(crate) fn is_arithmetic(&self) -> bool {
+    pub fn is_complex(&self) -> bool {
+        match self {
+            Type::Complex(_) => true,
+            _ => false,
+        }
+    }
+    /// Returns the type of the real and imaginary parts of a complex type,
+    /// or the type itself for real types (6.2.5p18).
+    #[inline]
+    pub fn real_type(&self) -> &Type {
+        match self {
+            Type::Complex(real) => real,
+            _ => self,
+        }
+    }
+    #[inline]
+    pub fn is_atomic(&self) -> bool {
+        match self {
+            Type::Atomic(_) => true,
+            _ => false,
+        }
+    }
+    /// Returns the type that an lvalue of this type has after lvalue conversion,
+    /// i.e. `int` for `_Atomic int` (6.3.2.1p2).
+    #[inline]
+    pub fn non_atomic(&self) -> &Type {
+        match self {
+            Type::Atomic(inner) => inner,
+            _ => self,
+        }
+    }
+    /// Returns this type without its top-level qualifiers, i.e. `int *` for `int *const`.
+    ///
+    /// Lvalue conversion drops these qualifiers, so they never affect compatibility of values.
+    pub fn unqualified(&self) -> Type {
+        match self {
+            Type::Pointer(inner, _) => Type::Pointer(inner.clone(), Default::default()),
+            other => other.clone(),
+        }
+    }
+    /// Whether this is an integer or real floating type (6.2.5p17).
+    #[inline]
+    pub(crate) fn is_real(&self) -> bool {
         self.is_integral() || self.is_floating()
     }
     #[inline]
+    pub(crate) fn is_arithmetic(&self) -> bool {
+        self.is_real() || self.is_complex()
+    }
+    #[inline]
     pub fn is_pointer(&self) -> bool {
         match self {
             Type::Pointer(_, _) => true,
@@ -240,6 +358,61 @@ impl Type {
             _ => false,
         }
     }
+    /// Whether the size of this type is only known at runtime, e.g. `int [n]` or `int [n][3]`.
+    pub fn is_vla(&self) -> bool {
+        match self {
+            Type::Array(_, ArrayType::Variable(_)) => true,
+            Type::Array(inner, _) => inner.is_vla(),
+            _ => false,
+        }
+    }
+    /// Whether two types are compatible (6.2.7).
+    ///
+    /// This is the same as `==`, except that arrays with a variable length
+    /// are compatible with any other array that has a length.
+    pub fn is_compatible(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Array(a, a_len), Type::Array(b, b_len)) => {
+                a_len.is_compatible(b_len) && a.is_compatible(b)
+            }
+            (Type::Pointer(a, a_quals), Type::Pointer(b, b_quals)) => {
+                a_quals == b_quals && a.is_compatible(b)
+            }
+            _ => self == other,
+        }
+    }
+    /// Whether this type depends on the length of a VLA, e.g. `int [n]` or `int (*)[n]`.
+    ///
+    /// 6.7.6p3: A full declarator is a declarator that is not part of another declarator.
+    /// If, in the nested sequence of declarators in a full declarator, there is a declarator
+    /// specifying a variable length array type, the type specified by the full declarator is
+    /// said to be variably modified.
+    pub fn is_variably_modified(&self) -> bool {
+        match self {
+            Type::Array(_, ArrayType::Variable(_)) => true,
+            Type::Array(inner, _) | Type::Pointer(inner, _) => inner.is_variably_modified(),
+            Type::Function(func) => func.return_type.is_variably_modified(),
+            _ => false,
+        }
+    }
+    /// Complete the type of an array of unknown size from its initializer.
+    ///
+    /// > If an array of unknown size is initialized,
+    /// > its size is determined by the largest indexed element with an explicit initializer.
+    pub fn complete_array_type(&mut self, init: &Initializer) {
+        if let Type::Array(_, size @ ArrayType::Unbounded) = self {
+            let len = match init {
+                Initializer::InitializerList(list) => list.len(),
+                // char s[] = "hi";
+                Initializer::Scalar(expr) => match &expr.expr {
+                    ExprType::Literal(LiteralValue::Str(s)) => s.len(),
+                    _ => return,
+                },
+                Initializer::FunctionBody(_) => return,
+            };
+            *size = ArrayType::Fixed(len as crate::arch::SIZE_T);
+        }
+    }
 }
 
 impl PartialEq for FunctionType {
@@ -257,7 +430,7 @@ impl PartialEq for FunctionType {
                 .zip(other.params.iter())
                 .all(|(a, b)| {
                     let (this_param, other_param) = (a.get(), b.get());
-                    this_param.ctype == other_param.ctype
+                    this_param.ctype.is_compatible(&other_param.ctype)
                         && this_param.qualifiers == other_param.qualifiers
                 })
     }
@@ -322,6 +495,7 @@ pub(super) fn print_type(
                 postfixes.push(match array_type {
                     ArrayType::Fixed(length) => format!("[{}]", length),
                     ArrayType::Unbounded => "[]".to_string(),
+                    ArrayType::Variable(_) => "[*]".to_string(),
                 });
             }
             Type::Function(function_type) => {
@@ -373,7 +547,8 @@ pub(super) fn print_type(
 
     let final_type = unrolled_type[unrolled_type.len() - 1];
     match final_type {
-        Char(signed) | Short(signed) | Int(signed) | Long(signed) => {
+        Char(signed) | Short(signed) | Int(signed) | Long(signed) | LongLong(signed)
+        | Int128(signed) => {
             write!(
                 f,
                 "{}{}",
@@ -383,6 +558,8 @@ pub(super) fn print_type(
                     Short(_) => "short",
                     Int(_) => "int",
                     Long(_) => "long",
+                    LongLong(_) => "long long",
+                    Int128(_) => "__int128",
                     _ => unreachable!(),
                 }
             )?;
@@ -390,6 +567,9 @@ pub(super) fn print_type(
         Bool => write!(f, "_Bool")?,
         Float => write!(f, "float")?,
         Double => write!(f, "double")?,
+        LongDouble => write!(f, "long double")?,
+        Complex(real) => write!(f, "{} _Complex", real)?,
+        Atomic(inner) => write!(f, "_Atomic({})", inner)?,
         Void => write!(f, "void")?,
         Enum(Some(ident), _) => write!(f, "enum {}", ident)?,
         Enum(None, _) => write!(f, "<anonymous enum>")?,
@@ -402,6 +582,7 @@ pub(super) fn print_type(
             write_struct_type(struct_type, f)?;
         }
         VaList => write!(f, "va_list")?,
+        NullPtr => write!(f, "nullptr_t")?,
         Error => write!(f, "<type error>")?,
         // These are unreachable because if they were part of the type, the
         // would have been unrolled. Only specifier types are valid final types
@@ -443,8 +624,17 @@ pub(crate) mod tests {
             any::<bool>().prop_map(Type::Short),
             any::<bool>().prop_map(Type::Int),
             any::<bool>().prop_map(Type::Long),
+            any::<bool>().prop_map(Type::LongLong),
+            any::<bool>().prop_map(Type::Int128),
             Just(Type::Float),
             Just(Type::Double),
+            Just(Type::LongDouble),
+            prop_oneof![
+                Just(Type::Float),
+                Just(Type::Double),
+                Just(Type::LongDouble)
+            ]
+            .prop_map(|real| Type::Complex(Box::new(real))),
             // enum
             any::<(Option<InternedStr>, Vec<(InternedStr, i64)>)>()
                 .prop_map(|(name, members)| Type::Enum(name, members)),
@@ -2,6 +2,7 @@ use crate::arch::CHAR_BIT;
 use crate::data::hir::LiteralValue::*;
 use crate::data::hir::*;
 use crate::data::*;
+use std::convert::TryFrom;
 use std::ops::{Add, Div, Mul, Sub};
 
 macro_rules! fold_int_bin_op {
@@ -10,6 +11,8 @@ macro_rules! fold_int_bin_op {
             (Int(a), Int(b)) => Ok(Some(Int(a $op b))),
             (UnsignedInt(a), UnsignedInt(b)) => Ok(Some(UnsignedInt(a $op b))),
             (Char(a), Char(b)) => Ok(Some(Char(a $op b))),
+            (Int128(a), Int128(b)) => Ok(Some(Int128(a $op b))),
+            (UnsignedInt128(a), UnsignedInt128(b)) => Ok(Some(UnsignedInt128(a $op b))),
             (_, _) => Ok(None),
         }
     }
@@ -21,6 +24,8 @@ fn fold_scalar_bin_op(
     overflowing: fn(i64, i64) -> (i64, bool),
     wrapping: fn(u64, u64) -> u64,
     wrapping_byte: fn(u8, u8) -> u8,
+    overflowing_128: fn(i128, i128) -> (i128, bool),
+    wrapping_128: fn(u128, u128) -> u128,
 ) -> impl Fn(&LiteralValue, &LiteralValue, &Type) -> Result<Option<LiteralValue>, SemanticError> {
     move |a: &LiteralValue, b: &LiteralValue, _ctype| match (a, b) {
         (Int(a), Int(b)) => {
@@ -37,6 +42,17 @@ fn fold_scalar_bin_op(
         }
         (UnsignedInt(a), UnsignedInt(b)) => Ok(Some(UnsignedInt(wrapping(*a, *b)))),
         (Char(a), Char(b)) => Ok(Some(Char(wrapping_byte(*a, *b)))),
+        (Int128(a), Int128(b)) => {
+            let (value, overflowed) = overflowing_128(*a, *b);
+            if overflowed {
+                Err(SemanticError::ConstOverflow {
+                    is_positive: value.is_negative(),
+                })
+            } else {
+                Ok(Some(Int128(value)))
+            }
+        }
+        (UnsignedInt128(a), UnsignedInt128(b)) => Ok(Some(UnsignedInt128(wrapping_128(*a, *b)))),
         (Float(a), Float(b)) => Ok(Some(Float(simple(*a, *b)))),
         // TODO: find a way to do this that allows `"hello" + 2 - 1`
         //(Str(s), Int(i)) | (Int(i), Str(s)) => {
@@ -55,6 +71,8 @@ macro_rules! fold_compare_op {
                     #[allow(clippy::float_cmp)]
                     (Float(a), Float(b)) => ExprType::Literal(Int((a $op b) as i64)),
                     (Char(a), Char(b)) => ExprType::Literal(Int((a $op b) as i64)),
+                    (Int128(a), Int128(b)) => ExprType::Literal(Int((a $op b) as i64)),
+                    (UnsignedInt128(a), UnsignedInt128(b)) => ExprType::Literal(Int((a $op b) as i64)),
                     (_, _) => ExprType::Binary(BinaryOp::Compare($compare), Box::new(left), Box::new(right)),
                 }
             }
@@ -71,6 +89,8 @@ impl Expr {
                 UnsignedInt(u) => u == 0,
                 Float(f) => f == 0.0,
                 Char(c) => c == 0,
+                Int128(i) => i == 0,
+                UnsignedInt128(u) => u == 0,
                 _ => false,
             }
         } else {
@@ -102,7 +122,9 @@ impl Expr {
                 data: (token, folded.ctype),
                 location: folded.location,
             }),
-            _ => Err(folded.location.error(SemanticError::NotConstant(folded))),
+            _ => Err(folded
+                .location
+                .error(SemanticError::NotConstant(Box::new(folded)))),
         }
     }
     pub fn const_fold(self) -> CompileResult<Expr> {
@@ -116,9 +138,11 @@ impl Expr {
                         _ => self.expr,
                     }
                 }
-                // TODO: if a variable were const, could we const fold Ids?
+                // `constexpr` variables are folded when they are loaded, see `Deref` below
                 _ => self.expr,
             },
+            // the size of a VLA is only known at runtime
+            ExprType::Sizeof(ref ctype) if ctype.is_vla() => self.expr,
             ExprType::Sizeof(ctype) => {
                 let sizeof = ctype.sizeof().map_err(|data| Locatable {
                     data: data.to_string(),
@@ -126,32 +150,55 @@ impl Expr {
                 })?;
                 ExprType::Literal(UnsignedInt(sizeof))
             }
-            ExprType::Negate(expr) => expr.const_fold()?.map_literal(
-                &location,
-                |token| match token {
-                    Int(i) => {
-                        let (value, overflowed) = i.overflowing_neg();
-                        if overflowed {
-                            Err(SemanticError::ConstOverflow {
-                                is_positive: value.is_negative(),
-                            })
-                        } else {
-                            Ok(Int(value))
-                        }
-                    }
-                    UnsignedInt(u) => Ok(UnsignedInt(u.wrapping_neg())),
-                    Char(c) => Ok(Char(c.wrapping_neg())),
-                    Float(f) => Ok(Float(-f)),
-                    _ => Ok(token),
+            ExprType::Negate(expr) => match expr.const_fold()? {
+                // `-I`
+                expr @ Expr {
+                    expr: ExprType::Complex(..),
+                    ..
+                } => match complex_parts(&expr) {
+                    Some((re, im)) => complex_literal(-re, -im, &self.ctype, location),
+                    None => ExprType::Negate(Box::new(expr)),
                 },
-                ExprType::Negate,
-            )?,
+                expr => expr.map_literal(
+                    &location,
+                    |token| match token {
+                        Int(i) => {
+                            let (value, overflowed) = i.overflowing_neg();
+                            if overflowed {
+                                Err(SemanticError::ConstOverflow {
+                                    is_positive: value.is_negative(),
+                                })
+                            } else {
+                                Ok(Int(value))
+                            }
+                        }
+                        UnsignedInt(u) => Ok(UnsignedInt(u.wrapping_neg())),
+                        Char(c) => Ok(Char(c.wrapping_neg())),
+                        Int128(i) => {
+                            let (value, overflowed) = i.overflowing_neg();
+                            if overflowed {
+                                Err(SemanticError::ConstOverflow {
+                                    is_positive: value.is_negative(),
+                                })
+                            } else {
+                                Ok(Int128(value))
+                            }
+                        }
+                        UnsignedInt128(u) => Ok(UnsignedInt128(u.wrapping_neg())),
+                        Float(f) => Ok(Float(-f)),
+                        _ => Ok(token),
+                    },
+                    ExprType::Negate,
+                )?,
+            },
             ExprType::BitwiseNot(expr) => expr.const_fold()?.map_literal(
                 &location,
                 |token| match token {
                     Int(i) => Ok(Int(!i)),
                     UnsignedInt(u) => Ok(UnsignedInt(!u)),
                     Char(c) => Ok(Char(!c)),
+                    Int128(i) => Ok(Int128(!i)),
+                    UnsignedInt128(u) => Ok(UnsignedInt128(!u)),
                     _ => Ok(token),
                 },
                 ExprType::BitwiseNot,
@@ -173,6 +220,16 @@ impl Expr {
                 ExprType::Noop(Box::new(inner))
             }
             ExprType::Deref(expr) => {
+                // `constexpr int n = 5; int a[n];`
+                if let ExprType::Id(symbol) = expr.expr {
+                    if let (false, Some(value)) = (self.lval, symbol.get().constexpr.clone()) {
+                        return Ok(Expr {
+                            expr: ExprType::Literal(value),
+                            location,
+                            ..self
+                        });
+                    }
+                }
                 let folded = expr.const_fold()?;
                 if let ExprType::Literal(Int(0)) = folded.expr {
                     return Err(Locatable::new(
@@ -212,6 +269,22 @@ impl Expr {
                 let expr = expr.const_fold()?;
                 ExprType::Member(Box::new(expr), member)
             }
+            // these designate an object, so they can't be replaced with a literal
+            ExprType::Real(expr) => ExprType::Real(Box::new(expr.const_fold()?)),
+            ExprType::Imag(expr) => ExprType::Imag(Box::new(expr.const_fold()?)),
+            ExprType::Complex(real, imag) => {
+                ExprType::Complex(Box::new(real.const_fold()?), Box::new(imag.const_fold()?))
+            }
+            // atomic operations have side effects
+            ExprType::AtomicBuiltin(op, args) => {
+                #[rustfmt::skip]
+                let args: Vec<Expr> = args
+                    .into_iter()
+                    .map(Self::const_fold)
+                    .collect::<CompileResult<_>>()?;
+                ExprType::AtomicBuiltin(op, args)
+            }
+            ExprType::AtomicUpdate(expr) => ExprType::AtomicUpdate(Box::new(expr.const_fold()?)),
             ExprType::PostIncrement(expr, increase) => {
                 let expr = expr.const_fold()?;
                 // this isn't constant for the same reason assignment isn't constant
@@ -219,6 +292,13 @@ impl Expr {
             }
             ExprType::Cast(expr) => cast(*expr, &self.ctype)?,
             ExprType::StaticRef(inner) => ExprType::StaticRef(Box::new(inner.const_fold()?)),
+            // the initializer is folded when the compound literal is evaluated
+            ExprType::CompoundLiteral(symbol, init) => ExprType::CompoundLiteral(symbol, init),
+            // statement expressions are never constant;
+            // the statements are folded when they are compiled
+            ExprType::StmtExpr(stmts, value) => ExprType::StmtExpr(stmts, value),
+            // the address of a label is only known once the function is compiled
+            ExprType::LabelAddress(label) => ExprType::LabelAddress(label),
         };
         Ok(Expr {
             expr: folded,
@@ -279,6 +359,55 @@ impl Expr {
     }
 }
 
+/// The real and imaginary parts of a complex constant,
+/// like `__builtin_complex(1.0, 2.0)` or `(double _Complex)1.0`.
+fn complex_parts(expr: &Expr) -> Option<(f64, f64)> {
+    match &expr.expr {
+        ExprType::Literal(Float(re)) if expr.ctype.is_complex() => Some((*re, 0.0)),
+        ExprType::Complex(re, im) => match (&re.expr, &im.expr) {
+            (ExprType::Literal(Float(re)), ExprType::Literal(Float(im))) => Some((*re, *im)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Fold arithmetic on complex constants with an imaginary part, like `1.0 + 2.0 * I`.
+///
+/// Constants without an imaginary part are folded like any other float.
+fn fold_complex(
+    left: &Expr,
+    right: &Expr,
+    op: BinaryOp,
+    parent_type: &Type,
+    location: Location,
+) -> Option<ExprType> {
+    let is_imaginary = |expr: &Expr| matches!(expr.expr, ExprType::Complex(..));
+    if !parent_type.is_complex() || !(is_imaginary(left) || is_imaginary(right)) {
+        return None;
+    }
+    let ((a, b), (c, d)) = (complex_parts(left)?, complex_parts(right)?);
+    let (re, im) = match op {
+        BinaryOp::Add => (a + c, b + d),
+        BinaryOp::Sub => (a - c, b - d),
+        BinaryOp::Mul => (a * c - b * d, a * d + b * c),
+        _ => return None,
+    };
+    Some(complex_literal(re, im, parent_type, location))
+}
+
+fn complex_literal(re: f64, im: f64, ctype: &Type, location: Location) -> ExprType {
+    let part = |value| {
+        Box::new(Expr {
+            expr: ExprType::Literal(Float(value)),
+            ctype: ctype.real_type().clone(),
+            lval: false,
+            location,
+        })
+    };
+    ExprType::Complex(part(re), part(im))
+}
+
 fn fold_binary(
     left: Expr,
     right: Expr,
@@ -292,6 +421,9 @@ fn fold_binary(
     let left = left.const_fold()?;
     let right = right.const_fold()?;
 
+    if let Some(folded) = fold_complex(&left, &right, op, parent_type, location) {
+        return Ok(folded);
+    }
     match op {
         Add => left.literal_bin_op(
             right,
@@ -301,6 +433,8 @@ fn fold_binary(
                 i64::overflowing_add,
                 u64::wrapping_add,
                 u8::wrapping_add,
+                i128::overflowing_add,
+                u128::wrapping_add,
             ),
             Add,
         ),
@@ -312,6 +446,8 @@ fn fold_binary(
                 i64::overflowing_sub,
                 u64::wrapping_sub,
                 u8::wrapping_sub,
+                i128::overflowing_sub,
+                u128::wrapping_sub,
             ),
             Sub,
         ),
@@ -323,6 +459,8 @@ fn fold_binary(
                 i64::overflowing_mul,
                 u64::wrapping_mul,
                 u8::wrapping_mul,
+                i128::overflowing_mul,
+                u128::wrapping_mul,
             ),
             Mul,
         ),
@@ -338,6 +476,8 @@ fn fold_binary(
                     i64::overflowing_div,
                     u64::wrapping_div,
                     u8::wrapping_div,
+                    i128::overflowing_div,
+                    u128::wrapping_div,
                 ),
                 Div,
             )
@@ -362,6 +502,20 @@ fn fold_binary(
                         }
                     }
                     (UnsignedInt(a), UnsignedInt(b)) => Ok(Some(UnsignedInt(a.wrapping_rem(*b)))),
+                    (Int128(a), Int128(b)) => {
+                        let (value, overflowed) = a.overflowing_rem(*b);
+
+                        if overflowed {
+                            Err(SemanticError::ConstOverflow {
+                                is_positive: value.is_negative(),
+                            })
+                        } else {
+                            Ok(Some(Int128(value)))
+                        }
+                    }
+                    (UnsignedInt128(a), UnsignedInt128(b)) => {
+                        Ok(Some(UnsignedInt128(a.wrapping_rem(*b))))
+                    }
                     (_, _) => Ok(None),
                 },
                 Mod,
@@ -416,6 +570,8 @@ impl LiteralValue {
             Int(i) if i >= 0 => Ok(i as u64),
             UnsignedInt(u) => Ok(u),
             Char(c) => Ok(u64::from(c)),
+            Int128(i) => u64::try_from(i).map_err(|_| ()),
+            UnsignedInt128(u) => u64::try_from(u).map_err(|_| ()),
             _ => Err(()),
         }
     }
@@ -438,28 +594,61 @@ fn cast(expr: Expr, ctype: &Type) -> CompileResult<ExprType> {
 /// all this does is make sure the folded value is in a valid range
 /// TODO: when we add suffix literals, that will have type information
 /// and we can use that to store the new type
+///
+/// A `Float` literal with a complex type has an imaginary part of 0.
 fn const_cast(token: &LiteralValue, ctype: &Type) -> Option<LiteralValue> {
     let token = match (token, ctype) {
+        (_, Type::Complex(real)) => return const_cast(token, real),
+        // 128-bit integers are the only literals that keep their width
+        (_, Type::Int128(signed)) => {
+            let value = match *token {
+                Int(i) => i.into(),
+                UnsignedInt(u) => u.into(),
+                Char(c) => c.into(),
+                Float(f) if *signed => f as i128,
+                Float(f) => f as u128 as i128,
+                Int128(i) => i,
+                UnsignedInt128(u) => u as i128,
+                Str(_) => return None,
+            };
+            if *signed {
+                Int128(value)
+            } else {
+                UnsignedInt128(value as u128)
+            }
+        }
+        (&Int128(i), Type::Bool) => Int((i != 0).into()),
+        (&Int128(i), Type::Char(_)) => Char(i as u8),
+        (&Int128(i), ty) if ty.is_floating() => Float(i as f64),
+        (&Int128(i), ty) if ty.is_integral() && ty.is_signed() => Int(i as i64),
+        (&Int128(i), ty) if ty.is_integral() || ty.is_pointer() => UnsignedInt(i as u64),
+
+        (&UnsignedInt128(u), Type::Bool) => Int((u != 0).into()),
+        (&UnsignedInt128(u), Type::Char(_)) => Char(u as u8),
+        (&UnsignedInt128(u), ty) if ty.is_floating() => Float(u as f64),
+        (&UnsignedInt128(u), ty) if ty.is_integral() && ty.is_signed() => Int(u as i64),
+        (&UnsignedInt128(u), ty) if ty.is_integral() || ty.is_pointer() => UnsignedInt(u as u64),
+
         (Int(i), Type::Bool) => Int((*i != 0).into()),
         (Int(i), Type::Char(_)) => Char(*i as u8),
-        (Int(i), Type::Double) | (Int(i), Type::Float) => Float(*i as f64),
+        (Int(i), ty) if ty.is_floating() => Float(*i as f64),
         (Int(i), ty) if ty.is_integral() && ty.is_signed() => Int(*i),
         (Int(i), ty) if ty.is_integral() => UnsignedInt(*i as u64),
 
         (UnsignedInt(u), Type::Bool) => Int((*u != 0).into()),
         (UnsignedInt(u), Type::Char(_)) => Char(*u as u8),
-        (UnsignedInt(u), Type::Double) | (UnsignedInt(u), Type::Float) => Float(*u as f64),
+        (UnsignedInt(u), ty) if ty.is_floating() => Float(*u as f64),
         (UnsignedInt(u), ty) if ty.is_integral() && ty.is_signed() => Int(*u as i64),
         (UnsignedInt(u), ty) if ty.is_integral() => UnsignedInt(*u),
 
         (Float(f), Type::Bool) => Int((*f != 0.0) as i64),
         (Float(f), Type::Char(_)) => Char(*f as u8),
-        (Float(f), Type::Double) | (Float(f), Type::Float) => Float(*f),
+        (Float(f), ty) if ty.is_floating() => Float(*f),
         (Float(f), ty) if ty.is_integral() && ty.is_signed() => Int(*f as i64),
         (Float(f), ty) if ty.is_integral() => UnsignedInt(*f as u64),
 
         (&Char(c), Type::Bool) => Int((c != 0).into()),
-        (&Char(c), Type::Double) | (&Char(c), Type::Float) => Float(c.into()),
+        (&Char(c), ty) if ty.is_floating() => Float(c.into()),
         (&Char(c), ty) if ty.is_integral() && ty.is_signed() => Int(c.into()),
         (&Char(c), ty) if ty.is_integral() => UnsignedInt(c.into()),
 
@@ -490,17 +679,22 @@ fn shift_right(
             location: *location,
         })?;
         // Rust panics if the shift is greater than the size of the type
-        if shift >= sizeof {
-            return Ok(ExprType::Literal(if ctype.is_signed() {
-                Int(0)
-            } else {
-                UnsignedInt(0)
+        if shift >= u64::from(CHAR_BIT) * sizeof {
+            return Ok(ExprType::Literal(match (ctype, ctype.is_signed()) {
+                (Type::Int128(_), true) => Int128(0),
+                (Type::Int128(_), false) => UnsignedInt128(0),
+                (_, true) => Int(0),
+                (_, false) => UnsignedInt(0),
             }));
         }
         if let ExprType::Literal(token) = left.expr {
             Ok(match token {
                 Int(i) => ExprType::Literal(Int(i.wrapping_shr(shift as u32))),
                 UnsignedInt(u) => ExprType::Literal(UnsignedInt(u.wrapping_shr(shift as u32))),
+                Int128(i) => ExprType::Literal(Int128(i.wrapping_shr(shift as u32))),
+                UnsignedInt128(u) => {
+                    ExprType::Literal(UnsignedInt128(u.wrapping_shr(shift as u32)))
+                }
                 _ => unreachable!("only ints and unsigned ints can be right shifted"),
             })
         } else {
@@ -568,6 +762,16 @@ fn shift_left(
             ExprType::Literal(UnsignedInt(u)) => {
                 ExprType::Literal(UnsignedInt(u.wrapping_shl(shift as u32)))
             }
+            ExprType::Literal(Int128(i)) => {
+                let (result, overflow) = i.overflowing_shl(shift as u32);
+                if overflow {
+                    return Err(location.error(SemanticError::ConstOverflow { is_positive: true }));
+                }
+                ExprType::Literal(Int128(result))
+            }
+            ExprType::Literal(UnsignedInt128(u)) => {
+                ExprType::Literal(UnsignedInt128(u.wrapping_shl(shift as u32)))
+            }
             _ => ExprType::Binary(
                 BinaryOp::Shl,
                 Box::new(left),
@@ -771,4 +975,26 @@ mod tests {
         assert_fold("(long)'0'", "48");
         assert_fold("(unsigned short)'0'", "48u");
     }
+    #[test]
+    fn test_int128() {
+        assert_fold("(__int128)1 << 100 >> 99", "(__int128)2");
+        assert_fold(
+            "((__int128)0x7fffffffffffffff * 0x7fffffffffffffff) >> 64",
+            "(__int128)0x3fffffffffffffff",
+        );
+        assert_fold("(unsigned __int128)0 - 1 > 0", "1");
+        assert_fold("(long)((unsigned __int128)-1 >> 64)", "-1");
+        assert_fold("(__int128)-7 % 2", "(__int128)-1");
+        assert_fold("(double)((__int128)1 << 64)", "18446744073709551616.0");
+        assert_eq!(
+            test_const_fold("(__int128)1 / 0").unwrap_err().data,
+            SemanticError::DivideByZero.into()
+        );
+        assert_eq!(
+            test_const_fold("((__int128)1 << 126) * 2")
+                .unwrap_err()
+                .data,
+            SemanticError::ConstOverflow { is_positive: true }.into()
+        );
+    }
 }
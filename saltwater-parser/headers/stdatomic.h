@@ -0,0 +1,112 @@
+// http://port70.net/~nsz/c/c11/n1570.html#7.17
+
+#ifndef __STDC_STDATOMIC_H
+#define __STDC_STDATOMIC_H
+#include <stddef.h>
+
+// 7.17.2 Initialization
+#define ATOMIC_VAR_INIT(value) (value)
+#define atomic_init(obj, value) ((void)(*(obj) = (value)))
+
+// 7.17.3 Order and consistency
+// saltwater uses a lock for all atomic operations, so they are all sequentially consistent
+typedef enum memory_order {
+    memory_order_relaxed = __ATOMIC_RELAXED,
+    memory_order_consume = __ATOMIC_CONSUME,
+    memory_order_acquire = __ATOMIC_ACQUIRE,
+    memory_order_release = __ATOMIC_RELEASE,
+    memory_order_acq_rel = __ATOMIC_ACQ_REL,
+    memory_order_seq_cst = __ATOMIC_SEQ_CST
+} memory_order;
+#define kill_dependency(y) (y)
+
+// 7.17.4 Fences
+#define atomic_thread_fence(order) __atomic_thread_fence(order)
+#define atomic_signal_fence(order) ((void)(order))
+
+// 7.17.5 Lock-free property
+#define ATOMIC_BOOL_LOCK_FREE 0
+#define ATOMIC_CHAR_LOCK_FREE 0
+#define ATOMIC_CHAR16_T_LOCK_FREE 0
+#define ATOMIC_CHAR32_T_LOCK_FREE 0
+#define ATOMIC_WCHAR_T_LOCK_FREE 0
+#define ATOMIC_SHORT_LOCK_FREE 0
+#define ATOMIC_INT_LOCK_FREE 0
+#define ATOMIC_LONG_LOCK_FREE 0
+#define ATOMIC_LLONG_LOCK_FREE 0
+#define ATOMIC_POINTER_LOCK_FREE 0
+#define atomic_is_lock_free(obj) ((void)(obj), (_Bool)0)
+
+// 7.17.6 Atomic integer types
+// the integer types are the same as in glibc's <stdint.h> on x86_64
+typedef _Atomic _Bool atomic_bool;
+typedef _Atomic char atomic_char;
+typedef _Atomic signed char atomic_schar;
+typedef _Atomic unsigned char atomic_uchar;
+typedef _Atomic short atomic_short;
+typedef _Atomic unsigned short atomic_ushort;
+typedef _Atomic int atomic_int;
+typedef _Atomic unsigned int atomic_uint;
+typedef _Atomic long atomic_long;
+typedef _Atomic unsigned long atomic_ulong;
+typedef _Atomic long long atomic_llong;
+typedef _Atomic unsigned long long atomic_ullong;
+typedef _Atomic unsigned short atomic_char16_t;
+typedef _Atomic unsigned int atomic_char32_t;
+typedef _Atomic wchar_t atomic_wchar_t;
+typedef _Atomic signed char atomic_int_least8_t;
+typedef _Atomic unsigned char atomic_uint_least8_t;
+typedef _Atomic short atomic_int_least16_t;
+typedef _Atomic unsigned short atomic_uint_least16_t;
+typedef _Atomic int atomic_int_least32_t;
+typedef _Atomic unsigned int atomic_uint_least32_t;
+typedef _Atomic long atomic_int_least64_t;
+typedef _Atomic unsigned long atomic_uint_least64_t;
+typedef _Atomic signed char atomic_int_fast8_t;
+typedef _Atomic unsigned char atomic_uint_fast8_t;
+typedef _Atomic long atomic_int_fast16_t;
+typedef _Atomic unsigned long atomic_uint_fast16_t;
+typedef _Atomic long atomic_int_fast32_t;
+typedef _Atomic unsigned long atomic_uint_fast32_t;
+typedef _Atomic long atomic_int_fast64_t;
+typedef _Atomic unsigned long atomic_uint_fast64_t;
+typedef _Atomic long atomic_intptr_t;
+typedef _Atomic unsigned long atomic_uintptr_t;
+typedef _Atomic size_t atomic_size_t;
+typedef _Atomic ptrdiff_t atomic_ptrdiff_t;
+typedef _Atomic long atomic_intmax_t;
+typedef _Atomic unsigned long atomic_uintmax_t;
+
+// 7.17.7 Operations on atomic types
+#define atomic_store(object, desired) __atomic_store_n(object, desired, __ATOMIC_SEQ_CST)
+#define atomic_store_explicit(object, desired, order) __atomic_store_n(object, desired, order)
+#define atomic_load(object) __atomic_load_n(object, __ATOMIC_SEQ_CST)
+#define atomic_load_explicit(object, order) __atomic_load_n(object, order)
+#define atomic_exchange(object, desired) __atomic_exchange_n(object, desired, __ATOMIC_SEQ_CST)
+#define atomic_exchange_explicit(object, desired, order) __atomic_exchange_n(object, desired, order)
+#define atomic_compare_exchange_strong(object, expected, desired) __atomic_compare_exchange_n(object, expected, desired, 0, __ATOMIC_SEQ_CST, __ATOMIC_SEQ_CST)
+#define atomic_compare_exchange_strong_explicit(object, expected, desired, success, failure) __atomic_compare_exchange_n(object, expected, desired, 0, success, failure)
+#define atomic_compare_exchange_weak(object, expected, desired) __atomic_compare_exchange_n(object, expected, desired, 1, __ATOMIC_SEQ_CST, __ATOMIC_SEQ_CST)
+#define atomic_compare_exchange_weak_explicit(object, expected, desired, success, failure) __atomic_compare_exchange_n(object, expected, desired, 1, success, failure)
+#define atomic_fetch_add(object, operand) __atomic_fetch_add(object, operand, __ATOMIC_SEQ_CST)
+#define atomic_fetch_add_explicit(object, operand, order) __atomic_fetch_add(object, operand, order)
+#define atomic_fetch_sub(object, operand) __atomic_fetch_sub(object, operand, __ATOMIC_SEQ_CST)
+#define atomic_fetch_sub_explicit(object, operand, order) __atomic_fetch_sub(object, operand, order)
+#define atomic_fetch_or(object, operand) __atomic_fetch_or(object, operand, __ATOMIC_SEQ_CST)
+#define atomic_fetch_or_explicit(object, operand, order) __atomic_fetch_or(object, operand, order)
+#define atomic_fetch_xor(object, operand) __atomic_fetch_xor(object, operand, __ATOMIC_SEQ_CST)
+#define atomic_fetch_xor_explicit(object, operand, order) __atomic_fetch_xor(object, operand, order)
+#define atomic_fetch_and(object, operand) __atomic_fetch_and(object, operand, __ATOMIC_SEQ_CST)
+#define atomic_fetch_and_explicit(object, operand, order) __atomic_fetch_and(object, operand, order)
+
+// 7.17.8 Atomic flag type and operations
+typedef struct atomic_flag {
+    atomic_bool __value;
+} atomic_flag;
+#define ATOMIC_FLAG_INIT { 0 }
+#define atomic_flag_test_and_set(object) __atomic_exchange_n(&(object)->__value, 1, __ATOMIC_SEQ_CST)
+#define atomic_flag_test_and_set_explicit(object, order) __atomic_exchange_n(&(object)->__value, 1, order)
+#define atomic_flag_clear(object) __atomic_store_n(&(object)->__value, 0, __ATOMIC_SEQ_CST)
+#define atomic_flag_clear_explicit(object, order) __atomic_store_n(&(object)->__value, 0, order)
+
+#endif
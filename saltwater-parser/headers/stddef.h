@@ -4,9 +4,16 @@
 #define __STDC_STDDEF_H
 typedef long ptrdiff_t;
 typedef unsigned long size_t;
-typedef long max_align_t;
+// the fundamental types with the strictest alignment
+typedef struct {
+    long long __max_align_ll;
+    long double __max_align_ld;
+} max_align_t;
 // Unicode is at most 32-bits per character
 typedef int wchar_t;
+#if __STDC_VERSION__ >= 202311L
+typedef typeof(nullptr) nullptr_t;
+#endif
 
 #define NULL 0
 #define offsetof(type, member) (offsetof not_currently_supported)
@@ -36,10 +36,10 @@ use super::replace::{replace, replace_iter, Definition, Definitions};
 use super::{Lexer, LiteralParser, Token};
 use crate::arch::TARGET;
 use crate::data::error::CppError;
-use crate::data::lex::{Keyword, LiteralToken};
+use crate::data::lex::{AtomicOp, Keyword, LiteralToken};
 use crate::data::*;
 use crate::get_str;
-use crate::Files;
+use crate::{Files, Standard};
 
 /// An easier interface for constructing a preprocessor.
 ///
@@ -63,6 +63,10 @@ pub struct PreProcessorBuilder<'a> {
     search_path: Vec<Cow<'a, Path>>,
     /// The user-defined macros that should be defined at startup
     definitions: Definitions,
+    /// The version of the C standard to follow
+    standard: Standard,
+    /// Whether to accept GNU extensions
+    gnu: bool,
 }
 
 impl<'a> PreProcessorBuilder<'a> {
@@ -73,6 +77,8 @@ impl<'a> PreProcessorBuilder<'a> {
             buf: buf.into(),
             search_path: Vec::new(),
             definitions: Definitions::new(),
+            standard: Standard::default(),
+            gnu: false,
         }
     }
     pub fn filename<P: Into<PathBuf>>(mut self, name: P) -> Self {
@@ -91,6 +97,14 @@ impl<'a> PreProcessorBuilder<'a> {
         self.definitions.insert(name, def.into());
         self
     }
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+    pub fn gnu_extensions(mut self, enabled: bool) -> Self {
+        self.gnu = enabled;
+        self
+    }
     pub fn build(self) -> PreProcessor<'a> {
         PreProcessor::new(
             self.buf,
@@ -98,7 +112,9 @@ impl<'a> PreProcessorBuilder<'a> {
             self.debug,
             self.search_path,
             self.definitions,
+            self.standard,
         )
+        .gnu_extensions(self.gnu)
     }
 }
 
@@ -123,7 +139,7 @@ impl<'a> PreProcessorBuilder<'a> {
 /// ```
 /// use saltwater_parser::PreProcessor;
 ///
-/// let cpp = PreProcessor::new("int main(void) { char *hello = \"hi\"; }\n", "example.c", false, vec![], Default::default());
+/// let cpp = PreProcessor::new("int main(void) { char *hello = \"hi\"; }\n", "example.c", false, vec![], Default::default(), Default::default());
 /// for token in cpp {
 ///     assert!(token.is_ok());
 /// }
@@ -140,6 +156,10 @@ pub struct PreProcessor<'a> {
     definitions: Definitions,
     /// Handles reading from files
     file_processor: FileProcessor,
+    /// The version of the C standard to follow
+    standard: Standard,
+    /// Whether to accept GNU extensions
+    gnu: bool,
 }
 
 enum PendingToken {
@@ -179,7 +199,7 @@ impl TryFrom<&str> for Definition {
 /// Keeps track of the state of a conditional inclusion directive.
 ///
 /// `If` means we are currently processing an `#if`,
-/// `Elif` means an `#elif`, and `Else` means an `#else`.
+/// `Elif` means an `#elif`, `#elifdef`, or `#elifndef`, and `Else` means an `#else`.
 ///
 /// There are more states, but they are tracked internally to `consume_directive()`.
 /// The state diagram looks like this (pipe to `xdot -` for visualization):
@@ -197,8 +217,8 @@ impl TryFrom<&str> for Definition {
 ///    consume_all -> END [label="#endif"]
 ///
 ///    start -> consume_if [label="#if 0"]
-///    consume_if -> consume_if [label="#elif 0"]
-///    consume_if -> ELIF [label="#elif 1"]
+///    consume_if -> consume_if [label="#elif 0 / #elifdef undefined"]
+///    consume_if -> ELIF [label="#elif 1 / #elifdef defined"]
 ///    consume_if -> ELSE [label="#else"]
 ///    consume_if -> END  [label="#endif"]
 ///
@@ -267,7 +287,7 @@ impl Iterator for PreProcessor<'_> {
 impl<'a> PreProcessor<'a> {
     /// Possibly recursively replace tokens. This also handles turning identifiers into keywords.
     ///
-    /// If `token` was defined to an empty token list, this will return `None`.
+    /// If `token` was defined to an empty token list or is `__extension__`, this will return `None`.
     fn handle_token(
         &mut self,
         token: PendingToken,
@@ -300,8 +320,28 @@ impl<'a> PreProcessor<'a> {
         })) = &mut token
         {
             if let Token::Id(name) = &data {
-                if let Some(keyword) = KEYWORDS.get(get_str!(name)) {
-                    *data = Token::Keyword(*keyword);
+                // `__extension__` only silences pedantic warnings in GCC, and saltwater doesn't give those
+                if get_str!(name) == "__extension__" {
+                    return None;
+                }
+                let keyword = match get_str!(name) {
+                    // before C23, `static_assert` is a macro defined by <assert.h>
+                    "static_assert" if self.standard >= Standard::C23 => {
+                        Some(Keyword::StaticAssert)
+                    }
+                    // GCC accepts `typeof` in all its `gnu` modes
+                    "typeof" if self.gnu => Some(Keyword::Typeof),
+                    // before C23, these are ordinary identifiers
+                    // (`__typeof__` and `__typeof` are keywords in every version)
+                    "nullptr" | "typeof" | "typeof_unqual" | "constexpr"
+                        if self.standard < Standard::C23 =>
+                    {
+                        None
+                    }
+                    name => KEYWORDS.get(name).copied(),
+                };
+                if let Some(keyword) = keyword {
+                    *data = Token::Keyword(keyword);
                 }
             }
         }
@@ -314,12 +354,15 @@ impl<'a> PreProcessor<'a> {
     /// but will never delete a file.
     ///
     /// The `debug` parameter specifies whether to print out tokens before replacement.
+    /// The `standard` parameter controls which features give a warning
+    /// and the value of `__STDC_VERSION__`.
     pub fn new<'search: 'a, I: IntoIterator<Item = Cow<'search, Path>>, S: Into<ArcStr>>(
         chars: S,
         filename: impl Into<std::ffi::OsString>,
         debug: bool,
         user_search_path: I,
         user_definitions: HashMap<InternedStr, Definition>,
+        standard: Standard,
     ) -> Self {
         let system_path = format!(
             "{}-{}-{}",
@@ -328,17 +371,21 @@ impl<'a> PreProcessor<'a> {
 
         let now = time::OffsetDateTime::now_local();
 
-        #[allow(clippy::inconsistent_digit_grouping)]
         let mut definitions = map! {
             format!("__{}__", TARGET.architecture).into() => int_def(1),
             format!("__{}__", TARGET.operating_system).into() => int_def(1),
             "__STDC__".into() => int_def(1),
             "__STDC_HOSTED__".into() => int_def(1),
-            "__STDC_VERSION__".into() => int_def(2011_12),
-            "__STDC_NO_ATOMICS__".into() => int_def(1),
-            "__STDC_NO_COMPLEX__".into() => int_def(1),
+            "__STDC_VERSION__".into() => int_def(standard.version()),
             "__STDC_NO_THREADS__".into() => int_def(1),
             "__STDC_NO_VLA__".into() => int_def(1),
+            // memory orders for the `__atomic_*` builtins, the same as GCC
+            "__ATOMIC_RELAXED".into() => int_def(0),
+            "__ATOMIC_CONSUME".into() => int_def(1),
+            "__ATOMIC_ACQUIRE".into() => int_def(2),
+            "__ATOMIC_RELEASE".into() => int_def(3),
+            "__ATOMIC_ACQ_REL".into() => int_def(4),
+            "__ATOMIC_SEQ_CST".into() => int_def(5),
             "__DATE__".into() => str_def(&now.format("%b %_d %Y")),
             "__TIME__".into() => str_def(&now.format("%H:%M:%S")),
         };
@@ -351,7 +398,7 @@ impl<'a> PreProcessor<'a> {
         ];
         search_path.extend(user_search_path.into_iter());
 
-        let file_processor = FileProcessor::new(chars, filename, debug);
+        let file_processor = FileProcessor::new(chars, filename, debug, standard);
 
         let mut new_cpp = Self {
             error_handler: Default::default(),
@@ -360,11 +407,19 @@ impl<'a> PreProcessor<'a> {
             search_path,
             definitions,
             file_processor,
+            standard,
+            gnu: false,
         };
         new_cpp.update_builtin_definitions(); // So they are defined from the start
         new_cpp
     }
 
+    /// Accept GNU extensions, such as `typeof` before C23.
+    pub fn gnu_extensions(mut self, enabled: bool) -> Self {
+        self.gnu = enabled;
+        self
+    }
+
     /// Return all warnings found so far.
     ///
     /// These warnings are consumed and will not be returned if you call
@@ -479,6 +534,9 @@ impl<'a> PreProcessor<'a> {
     fn directive(&mut self, kind: DirectiveKind, start: u32) -> Result<(), CompileError> {
         use crate::data::error::Warning as WarningDiagnostic;
         use DirectiveKind::*;
+        if kind == ElifDef || kind == ElifNDef {
+            self.warn_c23_extension("`#elifdef` directives", self.span(start));
+        }
         match kind {
             If => {
                 let condition = self.boolean_expr()?;
@@ -487,24 +545,27 @@ impl<'a> PreProcessor<'a> {
             IfNDef => {
                 self.consume_whitespace_oneline(start, CppError::ExpectedMacroId)?;
                 let name = self.expect_id()?;
-                self.if_directive(!self.definitions.contains_key(&name.data), start)
+                self.if_directive(!is_defined(&self.definitions, name.data), start)
             }
             IfDef => {
                 self.consume_whitespace_oneline(start, CppError::ExpectedMacroId)?;
                 let name = self.expect_id()?;
-                self.if_directive(self.definitions.contains_key(&name.data), start)
+                self.if_directive(is_defined(&self.definitions, name.data), start)
             }
             // No matter what happens here, we will not read the tokens from this `#elif`.
             // Either we have been reading an `#if` or an `#elif` or an `#else`;
             // in any case, this `#elif` will be ignored.
-            Elif => match self.nested_ifs.last() {
+            // `#elifdef` and `#elifndef` behave exactly the same way.
+            Elif | ElifDef | ElifNDef => match self.nested_ifs.last() {
                 None => Err(CompileError::new(
                     CppError::UnexpectedElif { early: true }.into(),
                     self.span(start),
                 )),
                 // saw a previous #if or #elif, consume all following directives
                 // `If` / `Elif` -> `consume_all`
-                Some(IfState::If) | Some(IfState::Elif) => self.consume_directive(start, false),
+                Some(IfState::If) | Some(IfState::Elif) => {
+                    self.consume_directive(start, false, false)
+                }
                 Some(IfState::Else) => Err(CompileError::new(
                     CppError::UnexpectedElif { early: false }.into(),
                     self.span(start),
@@ -518,7 +579,9 @@ impl<'a> PreProcessor<'a> {
                 // we already took the `#if` condition,
                 // `#else` should just be ignored
                 // `Else` -> `consume_all`
-                Some(IfState::If) | Some(IfState::Elif) => self.consume_directive(start, false),
+                Some(IfState::If) | Some(IfState::Elif) => {
+                    self.consume_directive(start, false, true)
+                }
                 // we saw an `#else` before, seeing it again is an error
                 Some(IfState::Else) => Err(CompileError::new(
                     CppError::UnexpectedElse.into(),
@@ -548,8 +611,9 @@ impl<'a> PreProcessor<'a> {
                 drop(self.tokens_until_newline(false));
                 Ok(())
             }
-            // NOTE: #warning is a non-standard extension, but is implemented
-            // by most major compilers including clang and gcc.
+            // NOTE: #warning was only standardized in C23, but it has been implemented
+            // by most major compilers including clang and gcc for a long time,
+            // so it doesn't give a C23 extension warning.
             Warning => {
                 let tokens: Vec<_> = self
                     .tokens_until_newline(false)
@@ -579,6 +643,7 @@ impl<'a> PreProcessor<'a> {
                 Ok(())
             }
             Include => self.include(start),
+            Embed => self.embed(start),
         }
     }
     // convienience function around cpp_expr
@@ -589,6 +654,7 @@ impl<'a> PreProcessor<'a> {
             .into_iter()
             .collect::<Result<_, CompileError>>()?;
         let location = self.span(start);
+        let lex_tokens = self.replace_has_embed(lex_tokens, start)?;
 
         self.update_builtin_definitions();
         // TODO: is this unwrap safe? there should only be scalar types in a cpp directive...
@@ -670,6 +736,56 @@ impl<'a> PreProcessor<'a> {
             };
         }
     }
+    /// `#if __has_c_attribute(nodiscard)` or `#if __has_c_attribute(gnu::packed)`
+    fn has_c_attribute(
+        lex_tokens: impl Iterator<Item = Locatable<Token>>,
+        location: Location,
+    ) -> Result<u32, CompileError> {
+        fn unexpected<T>(
+            expected: &'static str,
+            token: Locatable<Token>,
+        ) -> Result<T, CompileError> {
+            Err(CompileError::new(
+                CppError::UnexpectedToken(expected, token.data).into(),
+                token.location,
+            ))
+        }
+        let mut lex_tokens = lex_tokens.filter(|token| !matches!(token.data, Token::Whitespace(_)));
+        let mut next = |expected| {
+            lex_tokens
+                .next()
+                .ok_or_else(|| CompileError::new(CppError::EndOfFile(expected).into(), location))
+        };
+        let attribute_name = |token: Locatable<Token>| match token.data {
+            Token::Id(name) => Ok(name.resolve_and_clone()),
+            Token::Keyword(keyword) => Ok(keyword.to_string()),
+            _ => unexpected("attribute name", token),
+        };
+
+        let token = next("__has_c_attribute(attribute)")?;
+        if token.data != Token::LeftParen {
+            return unexpected("left paren", token);
+        }
+        let mut prefix = None;
+        let mut name = attribute_name(next("attribute name")?)?;
+        let mut token = next("right paren")?;
+        // __has_c_attribute(gnu::packed)
+        if token.data == Token::Colon {
+            let colon = next("':'")?;
+            if colon.data != Token::Colon {
+                return unexpected("':'", colon);
+            }
+            prefix = Some(name);
+            name = attribute_name(next("attribute name")?)?;
+            token = next("right paren")?;
+        }
+        if token.data != Token::RightParen {
+            return unexpected("right paren", token);
+        }
+        let prefix = prefix.as_deref().map(ast::Attribute::normalize_name);
+        let name = ast::Attribute::normalize_name(&name);
+        Ok(crate::analyze::has_c_attribute(prefix, name))
+    }
     /// A C expression on a single line. Used for `#if` directives.
     ///
     /// Note that identifiers are replaced with a constant 0,
@@ -687,6 +803,7 @@ impl<'a> PreProcessor<'a> {
 
         let mut cpp_tokens = Vec::with_capacity(lex_tokens.size_hint().1.unwrap_or_default());
         let defined = "defined".into();
+        let has_c_attribute = "__has_c_attribute".into();
 
         while let Some(token) = lex_tokens.next() {
             let token = match token {
@@ -696,13 +813,22 @@ impl<'a> PreProcessor<'a> {
                     location,
                 } if name == defined => {
                     let def = Self::defined(&mut lex_tokens, location)?;
-                    let literal = if definitions.contains_key(&def) {
+                    let literal = if is_defined(definitions, def) {
                         ONE
                     } else {
                         ZERO
                     };
                     location.with(Token::Literal(literal))
                 }
+                // #if __has_c_attribute(nodiscard)
+                Locatable {
+                    data: Token::Id(name),
+                    location,
+                } if name == has_c_attribute => {
+                    let value = Self::has_c_attribute(&mut lex_tokens, location)?;
+                    let literal = LiteralToken::Int(Substr::from(value.to_string()));
+                    location.with(Token::Literal(literal))
+                }
                 _ => token,
             };
             cpp_tokens.push(token);
@@ -742,6 +868,20 @@ impl<'a> PreProcessor<'a> {
         // TODO: can semantic errors happen here? should we check?
         Ok(PureAnalyzer::new().expr(expr))
     }
+    /// `#elifdef name` is the same as `#elif defined(name)`,
+    /// and `#elifndef name` is the same as `#elif !defined(name)`.
+    fn elifdef_condition(&mut self, kind: DirectiveKind) -> Result<bool, CompileError> {
+        let start = self.file_processor.offset();
+        self.warn_c23_extension("`#elifdef` directives", self.span(start));
+        self.consume_whitespace_oneline(start, CppError::ExpectedMacroId)?;
+        let name = self.expect_id()?;
+        let defined = is_defined(&self.definitions, name.data);
+        Ok(if kind == DirectiveKind::ElifDef {
+            defined
+        } else {
+            !defined
+        })
+    }
     /// We saw an `#if`, `#ifdef`, or `#ifndef` token at the start of the line
     /// and want to either take the branch or ignore the tokens within the directive.
     fn if_directive(&mut self, condition: bool, start: u32) -> Result<(), CompileError> {
@@ -751,13 +891,15 @@ impl<'a> PreProcessor<'a> {
             Ok(())
         } else {
             // `start` -> `consume_if`
-            self.consume_directive(start, true)
+            self.consume_directive(start, true, false)
         }
     }
     /// Keep consuming tokens until `#endif`.
     /// This has to take into account nesting of #if directives.
     ///
     /// `consume_if` indicates whether this is the `consume_if` or `consume_all` state.
+    /// `after_else` indicates that the group being consumed follows an `#else`,
+    /// so any further `#elif` or `#else` at the same depth is an error.
     ///
     /// Example:
     /// ```c
@@ -770,14 +912,19 @@ impl<'a> PreProcessor<'a> {
     /// int g() { return 0; }
     /// ```
     /// should yield `int` as the next token, not `void`.
-    fn consume_directive(&mut self, start: u32, consume_if: bool) -> Result<(), CompileError> {
+    fn consume_directive(
+        &mut self,
+        start: u32,
+        consume_if: bool,
+        mut after_else: bool,
+    ) -> Result<(), CompileError> {
         let mut depth = 1;
         while depth > 0 {
-            let directive = match self.next_cpp_token() {
+            let (directive, location) = match self.next_cpp_token() {
                 Some(Ok(Locatable {
                     data: CppToken::Directive(d),
-                    ..
-                })) => d,
+                    location,
+                })) => (d, location),
                 Some(_) => continue,
                 None => {
                     return Err(Locatable::new(CppError::UnterminatedIf, self.span(start)).into())
@@ -790,13 +937,19 @@ impl<'a> PreProcessor<'a> {
                 depth += 1;
             } else if directive == DirectiveKind::EndIf {
                 depth -= 1;
-            // Note the only directives left are #elif and #else.
+            // Note the only directives left are #elif, #elifdef, #elifndef, and #else.
             // If depth >= 2, they are just ignored.
             } else if depth == 1 {
                 // `consume_if` from the state diagram
                 if consume_if {
-                    if directive == DirectiveKind::Elif {
-                        let condition = self.boolean_expr()?;
+                    let condition = match directive {
+                        DirectiveKind::Elif => Some(self.boolean_expr()?),
+                        DirectiveKind::ElifDef | DirectiveKind::ElifNDef => {
+                            Some(self.elifdef_condition(directive)?)
+                        }
+                        _ => None,
+                    };
+                    if let Some(condition) = condition {
                         if !condition {
                             // stay in the same `consume_if` state
                             continue;
@@ -811,8 +964,25 @@ impl<'a> PreProcessor<'a> {
                         return Ok(());
                     }
                     // otherwise, keep consuming tokens
+                } else if directive == DirectiveKind::Else {
+                    // `#if 1 #else #else #endif`
+                    if after_else {
+                        return Err(CompileError::new(CppError::UnexpectedElse.into(), location));
+                    }
+                    after_else = true;
+                } else if after_else
+                    && matches!(
+                        directive,
+                        DirectiveKind::Elif | DirectiveKind::ElifDef | DirectiveKind::ElifNDef
+                    )
+                {
+                    // `#if 1 #else #elif 1 #endif`
+                    return Err(CompileError::new(
+                        CppError::UnexpectedElif { early: false }.into(),
+                        location,
+                    ));
                 }
-                // `consume_all` from the state diagram: all directives ignored
+                // `consume_all` from the state diagram: all other directives ignored
             }
         }
         Ok(())
@@ -902,6 +1072,17 @@ impl<'a> PreProcessor<'a> {
 
         self.consume_whitespace_oneline(start, CppError::EmptyDefine)?;
         let id = self.expect_id()?;
+        // glibc's <sys/cdefs.h> defines `__attribute__(xyz)` away for compilers other than GCC,
+        // but saltwater implements the GNU spellings of keywords itself
+        let name = id.data.resolve_and_clone();
+        if name == "__extension__" || (name.starts_with("__") && KEYWORDS.contains_key(&*name)) {
+            self.tokens_until_newline(false);
+            self.error_handler.warn(
+                crate::data::error::Warning::KeywordDefinition(id.data),
+                id.location,
+            );
+            return Ok(());
+        }
         // NOTE: does _not_ discard whitespace
         if self.lexer_mut().match_next('(') {
             // function macro
@@ -1111,6 +1292,249 @@ impl<'a> PreProcessor<'a> {
         self.file_processor.add_file(filename, source);
         Ok(())
     }
+    // C23 6.10.4: `#embed "file.bin" limit(4) prefix(0xff,) suffix(, 0) if_empty(0)`
+    //
+    // The contents of the file become a single `Token::Embed`,
+    // so large resources don't need a separate token for each byte.
+    fn embed(&mut self, start: u32) -> Result<(), Locatable<Error>> {
+        use std::io::Read;
+
+        self.warn_c23_extension("`#embed` directives", self.span(start));
+        self.consume_whitespace_oneline(start, CppError::EmptyInclude)?;
+        let lexer = self.lexer_mut();
+        let local = if lexer.match_next('"') {
+            true
+        } else if lexer.match_next('<') {
+            false
+        } else {
+            return Err(match self.file_processor.next() {
+                Some(Err(err)) => err,
+                Some(Ok(other)) => {
+                    other.map(|tok| CppError::UnexpectedToken("embed file", tok).into())
+                }
+                None => self.span(start).error(CppError::EndOfFile("embed file")),
+            });
+        };
+        let end = if local { '"' } else { '>' };
+        let filename = PathBuf::from(self.chars_until(end).to_owned());
+
+        let lex_tokens: Vec<_> = self
+            .tokens_until_newline(false)
+            .into_iter()
+            .collect::<Result<_, CompileError>>()?;
+        let location = self.span(start);
+        self.update_builtin_definitions();
+        let params = self.embed_parameters(lex_tokens, location)?;
+
+        let path = self.find_include_path(&filename, local, start)?;
+        let mut bytes = Vec::new();
+        std::fs::File::open(&path)
+            .and_then(|file| {
+                file.take(params.limit.unwrap_or(u64::MAX))
+                    .read_to_end(&mut bytes)
+            })
+            .map_err(|err| location.error(CppError::IO(err.to_string())))?;
+
+        let replacement = |tokens: Vec<Locatable<Token>>| {
+            tokens
+                .into_iter()
+                .map(|token| token.map(PendingToken::NeedsReplacement))
+        };
+        if bytes.is_empty() {
+            self.pending.extend(replacement(params.if_empty));
+        } else {
+            self.pending.extend(replacement(params.prefix));
+            let embedded = Token::Embed(bytes);
+            self.pending
+                .push_back(location.with(PendingToken::Replaced(embedded)));
+            self.pending.extend(replacement(params.suffix));
+        }
+        Ok(())
+    }
+    /// Parse the parameters following the resource name of `#embed` or `__has_embed`.
+    fn embed_parameters(
+        &self,
+        lex_tokens: Vec<Locatable<Token>>,
+        location: Location,
+    ) -> Result<EmbedParameters, CompileError> {
+        let mut params = EmbedParameters::default();
+        let mut lex_tokens = lex_tokens.into_iter().peekable();
+        loop {
+            let token = match next_non_whitespace_token(&mut lex_tokens) {
+                Some(token) => token,
+                None => return Ok(params),
+            };
+            let mut name = match token.data {
+                Token::Id(name) => name.resolve_and_clone(),
+                other => {
+                    return Err(CompileError::new(
+                        CppError::UnexpectedToken("embed parameter", other).into(),
+                        token.location,
+                    ))
+                }
+            };
+            // vendor-specific parameters: `gnu::offset(2)`
+            if lex_tokens.peek().map(|token| &token.data) == Some(&Token::Colon) {
+                lex_tokens.next();
+                let suffix = match lex_tokens.next() {
+                    Some(Locatable {
+                        data: Token::Colon, ..
+                    }) => lex_tokens.next(),
+                    other => other,
+                };
+                match suffix {
+                    Some(Locatable {
+                        data: Token::Id(suffix),
+                        ..
+                    }) => name = format!("{}::{}", name, suffix),
+                    Some(other) => {
+                        return Err(CompileError::new(
+                            CppError::UnexpectedToken("embed parameter", other.data).into(),
+                            other.location,
+                        ))
+                    }
+                    None => {
+                        return Err(CompileError::new(
+                            CppError::EndOfFile("embed parameter").into(),
+                            location,
+                        ))
+                    }
+                }
+            }
+            // `__limit__(4)` is the same as `limit(4)`
+            let name = match name.strip_prefix("__").and_then(|n| n.strip_suffix("__")) {
+                Some(stripped) => stripped.to_owned(),
+                None => name,
+            };
+            let clause = balanced_clause(&mut lex_tokens, location)?;
+            match name.as_str() {
+                "limit" => {
+                    let limit = Self::cpp_expr(&self.definitions, clause.into_iter(), location)?
+                        .constexpr()?;
+                    params.limit = Some(match limit.data {
+                        (LiteralValue::Int(i), _) if i >= 0 => i as u64,
+                        (LiteralValue::UnsignedInt(u), _) => u,
+                        (LiteralValue::Char(c), _) => c.into(),
+                        _ => {
+                            return Err(CompileError::new(
+                                CppError::InvalidEmbedLimit.into(),
+                                limit.location,
+                            ))
+                        }
+                    });
+                }
+                "prefix" => params.prefix = clause,
+                "suffix" => params.suffix = clause,
+                "if_empty" => params.if_empty = clause,
+                _ => {
+                    return Err(CompileError::new(
+                        CppError::UnknownEmbedParameter(name).into(),
+                        token.location,
+                    ))
+                }
+            }
+        }
+    }
+    /// Replace `__has_embed("file.bin" limit(1))` with an integer constant.
+    ///
+    /// This is not part of `cpp_expr` because it has to look at the include search path.
+    /// As per C23 6.10.1, the result is 0 if the resource is not found
+    /// or uses an unsupported parameter, 1 if it is not empty, and 2 if it is empty.
+    fn replace_has_embed(
+        &mut self,
+        lex_tokens: Vec<Locatable<Token>>,
+        start: u32,
+    ) -> Result<Vec<Locatable<Token>>, CompileError> {
+        use crate::data::lex::ComparisonToken;
+
+        let has_embed = Token::Id("__has_embed".into());
+        if !lex_tokens.iter().any(|token| token.data == has_embed) {
+            return Ok(lex_tokens);
+        }
+        let mut replaced = Vec::with_capacity(lex_tokens.len());
+        let mut lex_tokens = lex_tokens.into_iter();
+        while let Some(token) = lex_tokens.next() {
+            if token.data != has_embed {
+                replaced.push(token);
+                continue;
+            }
+            let location = token.location;
+            let mut args = balanced_clause(&mut lex_tokens, location)?.into_iter();
+            let (filename, local) = match next_non_whitespace_token(&mut args) {
+                // __has_embed("file.bin")
+                Some(Locatable {
+                    data: Token::Literal(LiteralToken::Str(strs)),
+                    ..
+                }) => {
+                    let filename: String = strs
+                        .iter()
+                        .map(|s| s.trim_start_matches('"').trim_end_matches('"'))
+                        .collect();
+                    (filename, true)
+                }
+                // __has_embed(<file.bin>)
+                Some(Locatable {
+                    data: Token::Comparison(ComparisonToken::Less),
+                    ..
+                }) => {
+                    let mut filename = String::new();
+                    loop {
+                        match args.next() {
+                            Some(Locatable {
+                                data: Token::Comparison(ComparisonToken::Greater),
+                                ..
+                            }) => break,
+                            Some(token) => filename.push_str(&token.data.to_string()),
+                            None => {
+                                return Err(CompileError::new(
+                                    CppError::EndOfFile("'>'").into(),
+                                    location,
+                                ))
+                            }
+                        }
+                    }
+                    (filename, false)
+                }
+                Some(other) => {
+                    return Err(CompileError::new(
+                        CppError::UnexpectedToken("embed file", other.data).into(),
+                        other.location,
+                    ))
+                }
+                None => {
+                    return Err(CompileError::new(
+                        CppError::EndOfFile("embed file").into(),
+                        location,
+                    ))
+                }
+            };
+            let params = match self.embed_parameters(args.collect(), location) {
+                Ok(params) => Some(params),
+                Err(Locatable {
+                    data: Error::PreProcessor(CppError::UnknownEmbedParameter(_)),
+                    ..
+                }) => None,
+                Err(err) => return Err(err),
+            };
+            let value = match params {
+                None => 0,
+                Some(params) => match self.find_include_path(Path::new(&filename), local, start) {
+                    Err(_) => 0,
+                    Ok(path) => {
+                        let len = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+                        if params.limit.map_or(len, |limit| limit.min(len)) == 0 {
+                            2
+                        } else {
+                            1
+                        }
+                    }
+                },
+            };
+            let literal = LiteralToken::Int(Substr::from(value.to_string()));
+            replaced.push(location.with(Token::Literal(literal)));
+        }
+        Ok(replaced)
+    }
     /// Returns every char between the current position and the next `end`.
     /// Consumes and does not return the final `end`.
     fn chars_until(&mut self, end: char) -> &str {
@@ -1159,6 +1583,14 @@ impl<'a> PreProcessor<'a> {
         Ok(ret)
     }
 
+    /// Warn that `feature` is only part of C23, unless we're following C23.
+    fn warn_c23_extension(&mut self, feature: &'static str, location: Location) {
+        if self.standard < Standard::C23 {
+            self.error_handler
+                .warn(crate::data::error::Warning::C23Extension(feature), location);
+        }
+    }
+
     fn update_builtin_definitions(&mut self) {
         self.definitions.extend(map! {
             "__LINE__".into() => int_def((self.line() + 1) as i32),
@@ -1167,6 +1599,57 @@ impl<'a> PreProcessor<'a> {
     }
 }
 
+/// Whether `name` is a macro, for `#ifdef` and `defined(name)`.
+fn is_defined(definitions: &Definitions, name: InternedStr) -> bool {
+    // `__has_c_attribute` and `__has_embed` are operators, not macros, but they still count as defined
+    definitions.contains_key(&name)
+        || name == "__has_c_attribute".into()
+        || name == "__has_embed".into()
+}
+fn next_non_whitespace_token(
+    lex_tokens: &mut impl Iterator<Item = Locatable<Token>>,
+) -> Option<Locatable<Token>> {
+    lex_tokens.find(|token| !matches!(token.data, Token::Whitespace(_)))
+}
+/// Collect the tokens of a parenthesized clause like `prefix(0xff, )` or `__has_embed("file")`,
+/// not including the outer parentheses.
+fn balanced_clause(
+    lex_tokens: &mut impl Iterator<Item = Locatable<Token>>,
+    location: Location,
+) -> Result<Vec<Locatable<Token>>, CompileError> {
+    match next_non_whitespace_token(lex_tokens) {
+        Some(Locatable {
+            data: Token::LeftParen,
+            ..
+        }) => {}
+        Some(other) => {
+            return Err(CompileError::new(
+                CppError::UnexpectedToken("left paren", other.data).into(),
+                other.location,
+            ))
+        }
+        None => {
+            return Err(CompileError::new(
+                CppError::EndOfFile("left paren").into(),
+                location,
+            ))
+        }
+    }
+    let mut clause = Vec::new();
+    let mut depth = 0;
+    loop {
+        let token = lex_tokens.next().ok_or_else(|| {
+            CompileError::new(CppError::EndOfFile("right paren").into(), location)
+        })?;
+        match token.data {
+            Token::LeftParen => depth += 1,
+            Token::RightParen if depth == 0 => return Ok(clause),
+            Token::RightParen => depth -= 1,
+            _ => {}
+        }
+        clause.push(token);
+    }
+}
 fn int_def(i: i32) -> Definition {
     Definition::Object(vec![LiteralToken::Int(Substr::from(i.to_string())).into()])
 }
@@ -1187,8 +1670,9 @@ macro_rules! built_in_headers {
 // [(filename, contents)]
 // TODO: this could probably use a perfect-hashmap,
 // but it's so small that it's not worth it
-const PRECOMPILED_HEADERS: [(&str, &str); 2] = built_in_headers! {
+const PRECOMPILED_HEADERS: [(&str, &str); 3] = built_in_headers! {
     "stdarg.h",
+    "stdatomic.h",
     "stddef.h",
 };
 
@@ -1199,15 +1683,31 @@ fn get_builtin_header(expected: impl AsRef<str>) -> Option<&'static str> {
         .map(|x| x.1)
 }
 
+/// The parameters of an `#embed` directive or `__has_embed` expression.
+#[derive(Default)]
+struct EmbedParameters {
+    /// The maximum number of bytes to embed: `limit(4)`
+    limit: Option<u64>,
+    /// Tokens that go before the bytes if the resource is not empty: `prefix(0xff, )`
+    prefix: Vec<Locatable<Token>>,
+    /// Tokens that go after the bytes if the resource is not empty: `suffix(, 0)`
+    suffix: Vec<Locatable<Token>>,
+    /// Tokens that replace the directive if the resource is empty: `if_empty(0)`
+    if_empty: Vec<Locatable<Token>>,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum DirectiveKind {
     If,
     IfDef,
     IfNDef,
     Elif,
+    ElifDef,
+    ElifNDef,
     Else,
     EndIf,
     Include,
+    Embed,
     Define,
     Undef,
     Line,
@@ -1235,11 +1735,14 @@ impl TryFrom<&str> for DirectiveKind {
         Ok(match s {
             "if" => If,
             "elif" => Elif,
+            "elifdef" => ElifDef,
+            "elifndef" => ElifNDef,
             "endif" => EndIf,
             "else" => Else,
             "ifdef" => IfDef,
             "ifndef" => IfNDef,
             "include" => Include,
+            "embed" => Embed,
             "define" => Define,
             "undef" => Undef,
             "line" => Line,
@@ -1274,6 +1777,8 @@ lazy_static! {
         "short" => Keyword::Short,
         "int" => Keyword::Int,
         "long" => Keyword::Long,
+        // GNU extension: a 128-bit integer
+        "__int128" => Keyword::Int128,
         "float" => Keyword::Float,
         "double" => Keyword::Double,
         "_Complex" => Keyword::Complex,
@@ -1281,6 +1786,9 @@ lazy_static! {
         "void" => Keyword::Void,
         "signed" => Keyword::Signed,
         "unsigned" => Keyword::Unsigned,
+        "__signed" => Keyword::Signed,
+        "__signed__" => Keyword::Signed,
+        "__complex__" => Keyword::Complex,
         "typedef" => Keyword::Typedef,
         "enum" => Keyword::Enum,
         "union" => Keyword::Union,
@@ -1290,12 +1798,23 @@ lazy_static! {
         "const" => Keyword::Const,
         "volatile" => Keyword::Volatile,
         "restrict" => Keyword::Restrict,
+        // GNU spellings, used by glibc headers
+        "__const" => Keyword::Const,
+        "__const__" => Keyword::Const,
+        "__volatile" => Keyword::Volatile,
+        "__volatile__" => Keyword::Volatile,
+        "__restrict" => Keyword::Restrict,
+        "__restrict__" => Keyword::Restrict,
         "_Atomic" => Keyword::Atomic,
         "_Thread_local" => Keyword::ThreadLocal,
+        // GNU spelling
+        "__thread" => Keyword::ThreadLocal,
 
         // function qualifiers
         "inline" => Keyword::Inline,
         "_Noreturn" => Keyword::NoReturn,
+        "__inline" => Keyword::Inline,
+        "__inline__" => Keyword::Inline,
 
         // storage classes
         "auto" => Keyword::Auto,
@@ -1307,8 +1826,40 @@ lazy_static! {
         "sizeof" => Keyword::Sizeof,
         "_Alignof" => Keyword::Alignof,
         "_Alignas" => Keyword::Alignas,
+        "__alignof" => Keyword::Alignof,
+        "__alignof__" => Keyword::Alignof,
         "_Generic" => Keyword::Generic,
+        // GNU extensions for complex numbers
+        "__real__" => Keyword::Real,
+        "__imag__" => Keyword::Imag,
+        "__builtin_complex" => Keyword::BuiltinComplex,
+        // GNU extensions for atomics, used by <stdatomic.h>
+        "__atomic_load_n" => Keyword::AtomicBuiltin(AtomicOp::Load),
+        "__atomic_store_n" => Keyword::AtomicBuiltin(AtomicOp::Store),
+        "__atomic_exchange_n" => Keyword::AtomicBuiltin(AtomicOp::Exchange),
+        "__atomic_compare_exchange_n" => Keyword::AtomicBuiltin(AtomicOp::CompareExchange),
+        "__atomic_fetch_add" => Keyword::AtomicBuiltin(AtomicOp::FetchAdd),
+        "__atomic_fetch_sub" => Keyword::AtomicBuiltin(AtomicOp::FetchSub),
+        "__atomic_fetch_and" => Keyword::AtomicBuiltin(AtomicOp::FetchAnd),
+        "__atomic_fetch_or" => Keyword::AtomicBuiltin(AtomicOp::FetchOr),
+        "__atomic_fetch_xor" => Keyword::AtomicBuiltin(AtomicOp::FetchXor),
+        "__atomic_thread_fence" => Keyword::AtomicBuiltin(AtomicOp::ThreadFence),
+        // GNU attributes: `__attribute__((noreturn))`
+        "__attribute__" => Keyword::Attribute,
+        "__attribute" => Keyword::Attribute,
+        // GNU asm labels: `int f(void) __asm__("g");`
+        "__asm__" => Keyword::Asm,
+        "__asm" => Keyword::Asm,
         "_Static_assert" => Keyword::StaticAssert,
+
+        // C23
+        "nullptr" => Keyword::Nullptr,
+        "typeof" => Keyword::Typeof,
+        // GNU spellings, available even in strict modes
+        "__typeof__" => Keyword::Typeof,
+        "__typeof" => Keyword::Typeof,
+        "typeof_unqual" => Keyword::TypeofUnqual,
+        "constexpr" => Keyword::Constexpr,
     };
 }
 
@@ -1366,11 +1917,43 @@ mod tests {
             // and making it a keyword messes up parsing
             if *keyword != Keyword::VaList {
                 println!("{}", keyword);
-                assert_keyword(cpp(&keyword.to_string()).next(), *keyword);
+                let mut cpp = PreProcessorBuilder::new(keyword.to_string())
+                    .standard(Standard::C23)
+                    .build();
+                assert_keyword(cpp.next(), *keyword);
             }
         }
     }
     #[test]
+    fn static_assert_keyword() {
+        let c23 = |src| {
+            PreProcessorBuilder::new(src)
+                .standard(Standard::C23)
+                .build()
+        };
+        assert_keyword(c23("static_assert\n").next(), Keyword::StaticAssert);
+        // before C23, <assert.h> defines it as a macro
+        assert!(matches!(
+            cpp("static_assert").next(),
+            Some(Ok(Locatable {
+                data: Token::Id(_),
+                ..
+            }))
+        ));
+    }
+    #[test]
+    fn gnu_keywords() {
+        assert_same(
+            "__const __volatile__ __restrict int",
+            "const volatile restrict int",
+        );
+        assert_same(
+            "__inline__ __signed__ __alignof__",
+            "inline signed _Alignof",
+        );
+        assert_same("__extension__ int i;", "int i;");
+    }
+    #[test]
     fn if_directive() {
         assert_same(
             "
@@ -1596,6 +2179,12 @@ d
 #endif
 ";
         assert_err!(src, CppError::UnexpectedElse, "duplicate else",);
+        assert_err!(
+            "#if 1\n#else\n#else\n#endif",
+            CppError::UnexpectedElse,
+            "duplicate else after a taken #if",
+        );
+        assert_same("#if 1\na\n#else\n#define b\n#endif", "a");
     }
     #[test]
     fn elif() {
@@ -1612,6 +2201,56 @@ d
         assert_same(src, "right");
     }
     #[test]
+    fn elifdef() {
+        let src = "
+            #define a
+            #if 0
+                wrong1
+            #elifdef b
+                wrong2
+            #elifndef a
+                wrong3
+            #elifdef a
+                right
+            #elifndef b
+                wrong4
+            #else
+                wrong5
+            #endif
+        ";
+        assert_same(src, "right");
+        assert_same("#if 0\n#elifndef a\nright\n#endif", "right");
+        assert_same("#if 1\nright\n#elifdef\n#endif", "right");
+        // only the outermost #elifdef is looked at
+        let src = "
+            #define a
+            #if 0
+                #if 1
+                #elifdef a
+                    wrong1
+                #endif
+            #elifdef a
+                right
+            #endif
+        ";
+        assert_same(src, "right");
+        assert_err!(
+            "#elifdef a",
+            CppError::UnexpectedElif { early: true },
+            "#elifdef without #if"
+        );
+        assert_err!(
+            "#if 1\n#else\n#elifndef a\n#endif",
+            CppError::UnexpectedElif { early: false },
+            "#elifndef after #else"
+        );
+        assert_err!(
+            "#if 0\n#elifdef\n#endif",
+            CppError::ExpectedMacroId,
+            "#elifdef without a macro"
+        );
+    }
+    #[test]
     fn function_body_replacement() {
         let src = "#define a b
         #define f(c) a
@@ -1637,6 +2276,19 @@ d
         assert!(cpp.warnings().pop_front().is_some());
     }
     #[test]
+    fn define_keyword() {
+        // from glibc's <sys/cdefs.h>
+        let src = "#define __attribute__(xyz)
+        #define __extension__
+        struct __attribute__((packed)) s;";
+        assert_same(src, "struct __attribute__((packed)) s;");
+        let mut cpp = cpp(src);
+        while cpp.next_non_whitespace().is_some() {}
+        assert_eq!(cpp.warnings().len(), 2);
+        // only GNU spellings are protected
+        assert_same("#define inline\n inline int i;", "int i;");
+    }
+    #[test]
     fn warning() {
         let src = "#warning your pants are on file";
         let mut cpp = cpp(src);
@@ -1695,6 +2347,98 @@ int main(){}
         );
     }
     #[test]
+    fn test_has_c_attribute() {
+        assert_same(
+            "#if __has_c_attribute(nodiscard) == 202003\nyes\n#endif",
+            "yes",
+        );
+        assert_same("#if __has_c_attribute(__fallthrough__)\nyes\n#endif", "yes");
+        assert_same("#if __has_c_attribute(gnu::packed)\nyes\n#endif", "yes");
+        assert_same("#if __has_c_attribute(not_an_attribute)\nyes\n#endif", "");
+        assert_same("#if __has_c_attribute(clang::noescape)\nyes\n#endif", "");
+        assert_same("#ifdef __has_c_attribute\nyes\n#endif", "yes");
+        assert_same("#if defined(__has_c_attribute)\nyes\n#endif", "yes");
+        assert_err!(
+            "#if __has_c_attribute(nodiscard",
+            CppError::EndOfFile(_),
+            "unterminated __has_c_attribute"
+        );
+        assert_err!(
+            "#if __has_c_attribute nodiscard",
+            CppError::UnexpectedToken(_, _),
+            "missing parenthesis"
+        );
+    }
+    #[test]
+    fn test_embed() {
+        const STDARG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/headers/stdarg.h");
+        let embed = |params: &str| {
+            cpp(&format!("#embed \"{}\" {}", STDARG, params))
+                .filter_map(|res| res.ok().map(|token| token.data))
+                .filter(|token| !matches!(token, Token::Whitespace(_)))
+                .collect::<Vec<_>>()
+        };
+        let contents = std::fs::read(STDARG).unwrap();
+        assert_eq!(embed(""), vec![Token::Embed(contents)]);
+        assert_eq!(embed("limit(3)"), vec![Token::Embed(b"#if".to_vec())]);
+        assert_eq!(
+            embed("__limit__(1 + 1)"),
+            vec![Token::Embed(b"#i".to_vec())]
+        );
+        assert_eq!(
+            embed("limit(1) prefix(-) suffix(, 0) if_empty(1)"),
+            vec![
+                Token::Minus,
+                Token::Embed(b"#".to_vec()),
+                Token::Comma,
+                Token::Literal(LiteralToken::Int(arcstr::literal_substr!("0")))
+            ]
+        );
+        assert_eq!(
+            embed("limit(0) prefix(-) if_empty(1)"),
+            vec![Token::Literal(LiteralToken::Int(arcstr::literal_substr!(
+                "1"
+            )))]
+        );
+        assert_same_exact(&format!("#embed \"{}\" limit(2)", STDARG), "35,105");
+        assert_err!(
+            "#embed \"not a file.bin\"",
+            CppError::FileNotFound(_),
+            "missing embed file"
+        );
+        assert_err!(
+            &format!("#embed \"{}\" offset(1)", STDARG),
+            CppError::UnknownEmbedParameter(_),
+            "unknown embed parameter"
+        );
+        assert_err!(
+            &format!("#embed \"{}\" limit(-1)", STDARG),
+            CppError::InvalidEmbedLimit,
+            "negative embed limit"
+        );
+        assert_err!(
+            &format!("#embed \"{}\" limit(1", STDARG),
+            CppError::EndOfFile(_),
+            "unterminated embed parameter"
+        );
+    }
+    #[test]
+    fn test_has_embed() {
+        const STDARG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/headers/stdarg.h");
+        let has_embed =
+            |condition: &str| assert_same(&format!("#if {}\nyes\n#endif", condition), "yes");
+        has_embed(&format!("__has_embed(\"{}\") == 1", STDARG));
+        has_embed(&format!("__has_embed(\"{}\" limit(0)) == 2", STDARG));
+        has_embed(&format!("__has_embed(\"{}\" gnu::offset(1)) == 0", STDARG));
+        has_embed("__has_embed(\"not a file.bin\") == 0");
+        assert_same("#ifdef __has_embed\nyes\n#endif", "yes");
+        assert_err!(
+            "#if __has_embed(\"file.bin\"",
+            CppError::EndOfFile(_),
+            "unterminated __has_embed"
+        );
+    }
+    #[test]
     fn cycle_detection() {
         let src = "
         #define sa_handler   __sa_handler.sa_handler
@@ -1732,6 +2476,44 @@ int main(){}
         assert_same(original, "a(1)");
     }
     #[test]
+    fn repeated_macro() {
+        assert_same("#define A 5\n#define f(x) x + A + A\nf(1)", "1 + 5 + 5");
+        assert_same("#define A 5\n#define f(x, y) x + y\nf(A, A)", "5 + 5");
+        assert_same("#define a a + a\na", "a + a");
+    }
+    #[test]
+    fn token_pasting() {
+        assert_same("#define cat(a, b) a ## b\ncat(x, y)", "xy");
+        assert_same(
+            "#define cat(a, b) a##b\ncat(1, 2) cat(+, =) cat(, y) cat(x,)",
+            "12 += y x",
+        );
+        assert_same("#define xy 5\n#define cat(a, b) a ## b\ncat(x, y)", "5");
+        assert_same("#define obj x ## 1\nobj", "x1");
+        // glibc's <features.h>
+        let src = "#define __GLIBC_USE(F) __GLIBC_USE_ ## F\n#define __GLIBC_USE_ISOC2X 0\n
+            #if __GLIBC_USE (ISOC2X)\nyes\n#else\nno\n#endif";
+        assert_same(src, "no");
+        assert!(cpp("#define cat(a, b) a ## b\ncat(+, -)").any(|x| x.is_err()));
+        assert!(cpp("#define f(a) ## a\nf(1)").any(|x| x.is_err()));
+    }
+    #[test]
+    fn argument_expansion() {
+        // arguments are replaced before they are substituted
+        let src =
+            "#define cat(a, b) a ## b\n#define f(x) cat(x, 1)\n#define y z\nf(y) f(cat(y, y))";
+        assert_same(src, "z1 yy1");
+        // but not when they're next to `#` or `##`
+        assert_same("#define str(x) #x\n#define y z\nstr(y)", "\"y\"");
+        assert_same("#define cat(a, b) a ## b\n#define y z\ncat(y, y)", "yy");
+        // <complex.h>
+        let src = "#define __CONCAT(x, y) x ## y
+            #define PRECNAME(name) name ## f
+            #define DECL(function) PRECNAME(function)
+            DECL(__CONCAT(__, cacos))";
+        assert_same(src, "__cacosf");
+    }
+    #[test]
     // https://github.com/jyn514/rcc/issues/356
     fn preprocess_only() {
         let assert_unchanged = |s| assert_same_exact(s, s);
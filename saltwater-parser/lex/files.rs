@@ -3,7 +3,7 @@ use crate::{
     data::{CompileResult, Locatable, Token},
     ErrorHandler, Location,
 };
-use crate::{Files, Source};
+use crate::{Files, Source, Standard};
 use arcstr::ArcStr;
 use std::path::{Path, PathBuf};
 
@@ -52,6 +52,7 @@ impl FileProcessor {
         chars: impl Into<ArcStr>,
         filename: impl Into<std::ffi::OsString>,
         debug: bool,
+        standard: Standard,
     ) -> Self {
         let mut files = Files::new();
         let chars = chars.into();
@@ -63,7 +64,7 @@ impl FileProcessor {
         let file = files.add(filename, source);
         Self {
             error_handler: ErrorHandler::default(),
-            first_lexer: Lexer::new(file, chars, debug),
+            first_lexer: Lexer::new(file, chars, debug).standard(standard),
             files,
             includes: Default::default(),
             current: None,
@@ -90,7 +91,7 @@ impl FileProcessor {
         let code = ArcStr::clone(&source.code);
         let id = self.files.add(filename, source);
         self.includes
-            .push(Lexer::new(id, code, self.first_lexer.debug));
+            .push(Lexer::new(id, code, self.first_lexer.debug).standard(self.first_lexer.standard));
     }
 
     /// Return a `Location` representing the end of the first file.
@@ -0,0 +1,347 @@
+use std::collections::VecDeque;
+
+use codespan::FileId;
+
+use super::{LexResult, Lexer};
+use crate::data::error::LexError;
+use crate::data::lex::*;
+use arcstr::ArcStr;
+
+/// A lexer which keeps whitespace and comments instead of throwing them away.
+///
+/// Each token is returned together with its surrounding trivia (see `LosslessToken`),
+/// so that the original file can be reproduced exactly.
+/// This is meant for tools like formatters, not for compiling code:
+/// the tokens are not preprocessed and `LosslessLexer` cannot be passed to a `Parser`.
+///
+/// Lex errors are returned in order, after the token preceding the error.
+/// The text of the invalid token is kept as `TriviaKind::Skipped`.
+///
+/// A file with no tokens at all has nothing to attach its trivia to;
+/// use `trailing_trivia` to get it once the lexer has finished.
+#[derive(Debug)]
+pub struct LosslessLexer {
+    inner: Lexer,
+    /// The end of the last token we saw
+    offset: u32,
+    /// The last token we saw, waiting for its trailing trivia
+    last: Option<LosslessToken>,
+    /// Trivia after the end of the line, which belongs to the next token
+    leading: Vec<Locatable<Trivia>>,
+    /// Errors seen after `last`, which are returned once `last` has been returned
+    errors: VecDeque<Locatable<LexError>>,
+    /// Whether we've already reached the end of the file
+    finished: bool,
+}
+
+impl LosslessLexer {
+    /// Creates a LosslessLexer from a filename and the contents of a file
+    pub fn new<S: Into<ArcStr>>(file: FileId, chars: S, debug: bool) -> LosslessLexer {
+        LosslessLexer {
+            inner: Lexer::new(file, chars, debug),
+            offset: 0,
+            last: None,
+            leading: Vec::new(),
+            errors: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Trivia at the end of the file which wasn't attached to any token.
+    ///
+    /// This is only ever non-empty for a file without any tokens,
+    /// after the lexer has returned `None`.
+    pub fn trailing_trivia(&self) -> &[Locatable<Trivia>] {
+        &self.leading
+    }
+
+    /// Add the text between the last token and `end` as trivia.
+    fn add_trivia(&mut self, end: u32) {
+        let file = self.inner.location.file;
+        let start = self.offset;
+        let mut trivia = split_trivia(&self.inner.chars, start, end, file);
+        self.offset = end;
+
+        if let Some(last) = &mut self.last {
+            // everything up to (and including) the end of the line is trailing trivia
+            if self.leading.is_empty() {
+                let has_newline =
+                    |t: &[Locatable<Trivia>]| t.iter().any(|t| t.data.kind == TriviaKind::Newline);
+                if !has_newline(&last.trailing) {
+                    let split = trivia
+                        .iter()
+                        .position(|t| t.data.kind == TriviaKind::Newline)
+                        .map_or(trivia.len(), |i| i + 1);
+                    let rest = trivia.split_off(split);
+                    last.trailing.extend(trivia);
+                    trivia = rest;
+                }
+            }
+        }
+        self.leading.extend(trivia);
+    }
+}
+
+impl Iterator for LosslessLexer {
+    type Item = LexResult<LosslessToken>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // errors which came after the token we returned last time
+        if let Some(err) = self.errors.pop_front() {
+            return Some(Err(err));
+        }
+        loop {
+            if self.finished {
+                return None;
+            }
+            let token = match self.inner.next() {
+                // whitespace is handled by `add_trivia`
+                Some(Ok(Locatable {
+                    data: Token::Whitespace(_),
+                    ..
+                })) => continue,
+                Some(Ok(token)) => token,
+                // `int i = @;`: wait until `=` has its trailing trivia before reporting `@`
+                Some(Err(err)) if self.last.is_some() => {
+                    self.errors.push_back(err);
+                    continue;
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.finished = true;
+                    self.add_trivia(self.inner.chars.len() as u32);
+                    // a file with only comments or whitespace keeps its trivia in `leading`
+                    return self.last.take().map(|mut last| {
+                        last.trailing.append(&mut self.leading);
+                        Ok(last)
+                    });
+                }
+            };
+            let span = token.location.span;
+            self.add_trivia(span.start);
+            self.offset = span.end;
+            let current = LosslessToken {
+                token,
+                text: self
+                    .inner
+                    .chars
+                    .substr(span.start as usize..span.end as usize),
+                leading: std::mem::take(&mut self.leading),
+                trailing: Vec::new(),
+            };
+            if let Some(previous) = self.last.replace(current) {
+                return Some(Ok(previous));
+            }
+        }
+    }
+}
+
+/// Break the text in `chars[start..end]` into whitespace, newlines, and comments.
+///
+/// Anything else (e.g. the text of an invalid token) is `TriviaKind::Skipped`.
+fn split_trivia(chars: &ArcStr, start: u32, end: u32, file: FileId) -> Vec<Locatable<Trivia>> {
+    let text = &chars[start as usize..end as usize];
+    let mut trivia = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let (kind, len) = if rest.starts_with('\n') {
+            (TriviaKind::Newline, 1)
+        } else if rest.starts_with("\r\n") {
+            (TriviaKind::Newline, 2)
+        } else if rest.starts_with("//") {
+            (TriviaKind::LineComment, line_comment_len(rest))
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let len = body.find("*/").map_or(rest.len(), |j| j + 4);
+            (TriviaKind::BlockComment, len)
+        } else if let Some(len) = whitespace_len(rest) {
+            (TriviaKind::Whitespace, len)
+        } else {
+            (TriviaKind::Skipped, skipped_len(rest))
+        };
+        let (lo, hi) = (start as usize + i, start as usize + i + len);
+        let location = Location {
+            span: (lo as u32..hi as u32).into(),
+            file,
+        };
+        trivia.push(location.with(Trivia {
+            kind,
+            text: chars.substr(lo..hi),
+        }));
+        i += len;
+    }
+    trivia
+}
+
+/// The length of an escaped newline at the start of `s`, if there is one.
+fn escaped_newline_len(s: &str) -> Option<usize> {
+    if s.starts_with("\\\n") {
+        Some(2)
+    } else if s.starts_with("\\\r\n") {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// The length of a `//` comment, stopping before the newline.
+/// Escaped newlines continue the comment onto the next line.
+fn line_comment_len(s: &str) -> usize {
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        if let Some(len) = escaped_newline_len(rest) {
+            i += len;
+        } else if rest.starts_with('\n') || rest.starts_with("\r\n") {
+            break;
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    i
+}
+
+/// The length of the whitespace at the start of `s`, not including newlines.
+fn whitespace_len(s: &str) -> Option<usize> {
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        if let Some(len) = escaped_newline_len(rest) {
+            i += len;
+        } else if rest.starts_with('\n') || rest.starts_with("\r\n") {
+            break;
+        } else if rest.starts_with(|c: char| c.is_ascii_whitespace()) {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    if i == 0 {
+        None
+    } else {
+        Some(i)
+    }
+}
+
+/// The length of text that isn't trivia, stopping at the next whitespace or comment.
+fn skipped_len(s: &str) -> usize {
+    let mut chars = s.char_indices().peekable();
+    // always skip at least one character, so we make progress
+    chars.next();
+    while let Some(&(i, c)) = chars.peek() {
+        let rest = &s[i..];
+        if c.is_ascii_whitespace()
+            || rest.starts_with("//")
+            || rest.starts_with("/*")
+            || escaped_newline_len(rest).is_some()
+        {
+            return i;
+        }
+        chars.next();
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lexer(input: &str) -> LosslessLexer {
+        let mut files: codespan::Files<String> = codespan::Files::new();
+        let id = files.add("<test suite>", String::new());
+        LosslessLexer::new(id, input, false)
+    }
+    fn lossless(input: &str) -> Vec<LosslessToken> {
+        lexer(input).filter_map(Result::ok).collect()
+    }
+    fn assert_round_trip(input: &str) {
+        let mut lexer = lexer(input);
+        let mut output: String = lexer
+            .by_ref()
+            .filter_map(Result::ok)
+            .map(|token| token.to_string())
+            .collect();
+        for trivia in lexer.trailing_trivia() {
+            output.push_str(&trivia.data.text);
+        }
+        assert_eq!(input, output);
+    }
+    fn kinds(trivia: &[Locatable<Trivia>]) -> Vec<TriviaKind> {
+        trivia.iter().map(|t| t.data.kind).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_round_trip("int main() { return 0; }\n");
+        assert_round_trip("  int i ;\t// comment\n\n/* block\n comment */ int j;\n");
+        assert_round_trip("int a = 1 +\\\n 2;\r\n");
+        assert_round_trip("#define f(x) x /* c */\nf(1)\n");
+        assert_round_trip("// just a comment\n");
+        assert_round_trip("int i = @;\n");
+        assert_round_trip("int i; /* unterminated");
+        assert_round_trip("char *s = \"a\\\nb\";\n");
+        assert_round_trip("");
+    }
+
+    #[test]
+    fn attach_trivia() {
+        use TriviaKind::*;
+        let tokens = lossless("int /* a */ i; // b\n\n  /* c */\nint j;\n");
+        assert_eq!(tokens.len(), 6);
+        // keywords are only recognized by the preprocessor
+        assert_eq!(tokens[0].token.data, Token::Id("int".into()));
+        assert!(tokens[0].leading.is_empty());
+        assert_eq!(
+            kinds(&tokens[0].trailing),
+            [Whitespace, BlockComment, Whitespace]
+        );
+        assert_eq!(&*tokens[0].trailing[1].data.text, "/* a */");
+        assert_eq!(
+            kinds(&tokens[2].trailing),
+            [Whitespace, LineComment, Newline]
+        );
+        assert_eq!(
+            kinds(&tokens[3].leading),
+            [Newline, Whitespace, BlockComment, Newline]
+        );
+        assert_eq!(kinds(&tokens[5].trailing), [Newline]);
+    }
+
+    #[test]
+    fn skipped_text() {
+        let tokens = lossless("a @ b\n");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            kinds(&tokens[0].trailing),
+            [
+                TriviaKind::Whitespace,
+                TriviaKind::Skipped,
+                TriviaKind::Whitespace
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_in_order() {
+        let results: Vec<_> = lexer("a @ b\n").collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[0], Ok(token) if token.token.data == Token::Id("a".into())));
+        assert!(results[1].is_err());
+        assert!(matches!(&results[2], Ok(token) if token.token.data == Token::Id("b".into())));
+        // an error after the last token
+        let results: Vec<_> = lexer("a @").collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn only_trivia() {
+        let mut lexer = lexer("/* nothing */\n");
+        assert!(lexer.next().is_none());
+        assert_eq!(
+            kinds(lexer.trailing_trivia()),
+            [TriviaKind::BlockComment, TriviaKind::Newline]
+        );
+    }
+}
@@ -8,10 +8,12 @@ use super::data::{
     *,
 };
 use super::intern::InternedStr;
+use super::Standard;
 use arcstr::{ArcStr, Substr};
 
 mod cpp;
 mod files;
+mod lossless;
 pub mod replace;
 #[cfg(test)]
 mod tests;
@@ -19,6 +21,8 @@ mod tests;
 #[allow(unreachable_pub)]
 pub use cpp::{PreProcessor, PreProcessorBuilder};
 #[allow(unreachable_pub)]
+pub use lossless::LosslessLexer;
+#[allow(unreachable_pub)]
 pub use replace::{Definition, Peekable};
 
 type LexResult<T = Token> = Result<T, Locatable<LexError>>;
@@ -50,6 +54,8 @@ pub struct Lexer {
     error_handler: ErrorHandler<LexError>,
     /// Whether or not to display each token as it is processed
     debug: bool,
+    /// Features newer than this standard give a warning
+    standard: Standard,
     given_newline_error: bool,
 }
 
@@ -81,6 +87,7 @@ impl Lexer {
         Lexer {
             given_newline_error: false,
             debug,
+            standard: Standard::default(),
             location: SingleLocation { offset: 0, file },
             chars: chars.into(),
             seen_line_token: false,
@@ -91,6 +98,12 @@ impl Lexer {
         }
     }
 
+    /// Set the version of the C standard to follow.
+    pub fn standard(mut self, standard: Standard) -> Lexer {
+        self.standard = standard;
+        self
+    }
+
     // Internal use only, use `next_char()` instead.
     // This gets the next token from the buffer
     // and updates the current offset and relevant fields.
@@ -129,7 +142,8 @@ impl Lexer {
     ///
     /// A number matches the following regex:
     /// `({digits}\.{digits}|{digits}|\.{digits})([eE]-?{digits})?`
-    /// where {digits} is the regex `([0-9]*|0x[0-9a-f]+)`
+    /// where {digits} is the regex `([0-9]*|0x[0-9a-f]+|0b[01]+)`.
+    /// As of C23, digits may be separated by `'`, e.g. `1'000'000`.
     ///
     /// TODO: return an error enum instead of Strings
     ///
@@ -141,12 +155,19 @@ impl Lexer {
             "main loop should only pass [0-9] as start to parse_num"
         );
         let span_start = self.get_location().offset - 1; // -1 for `start`
+        let num = self.parse_num_raw(start, span_start)?;
+        if self.slice(span_start).contains('\'') {
+            self.c23_extension("digit separators", span_start);
+        }
+        Ok(num)
+    }
+    fn parse_num_raw(&mut self, start: char, span_start: u32) -> Result<Token, LexError> {
         let float_literal = |f| Token::Literal(LiteralToken::Float(f));
         let mut buf = String::new();
         buf.push(start as char);
         // check for radix other than 10 - but if we see '.', use 10
         let radix = if start == '0' {
-            if self.match_next('b') {
+            if self.match_next('b') || self.match_next('B') {
                 Radix::Binary
             } else if self.match_next('x') {
                 buf.push('x');
@@ -178,20 +199,36 @@ impl Lexer {
             self.consume_float_suffix();
             return Ok(self.slice(span_start)).map(float_literal);
         }
-        let literal = if self.match_next('u') || self.match_next('U') {
+        let mut unsigned = self.match_next('u') || self.match_next('U');
+        // `_BitInt` isn't supported, so `wb` has the same type as no suffix at all
+        let bit_precise = self.match_bit_precise_suffix();
+        if bit_precise {
+            unsigned |= self.match_next('u') || self.match_next('U');
+            self.c23_extension("'wb' suffixes", span_start);
+        }
+        // keep 'l' and 'll' in the token, so that `1LL` can be given the type `long long`
+        if !bit_precise {
+            let long = if self.match_next('l') {
+                self.match_next('l');
+                true
+            } else if self.match_next('L') {
+                self.match_next('L');
+                true
+            } else {
+                false
+            };
+            // `1LLU`
+            if long && !unsigned {
+                unsigned = self.match_next('u') || self.match_next('U');
+            }
+        }
+        let literal = if unsigned {
             LiteralToken::UnsignedInt(self.slice(span_start))
         } else {
             LiteralToken::Int(self.slice(span_start))
         };
-        // get rid of 'l' and 'll' suffixes, we don't handle them
-        if self.match_next('l') {
-            self.match_next('l');
-        } else if self.match_next('L') {
-            self.match_next('L');
-        }
         if radix == Radix::Binary {
-            let span = self.span(span_start);
-            self.warn_loc("binary number literals are an extension", span);
+            self.c23_extension("binary integer constants", span_start);
         }
         Ok(Token::Literal(literal))
     }
@@ -200,7 +237,7 @@ impl Lexer {
         // parse fraction: second {digits} in regex
         while let Some(c) = self.peek() {
             let c = c as char;
-            if c.is_digit(radix.as_u8().into()) {
+            if c.is_digit(radix.as_u8().into()) || self.match_digit_separator(radix) {
                 self.next_char();
             } else {
                 break;
@@ -211,10 +248,18 @@ impl Lexer {
         Ok(self.slice(span_start))
     }
     fn consume_float_suffix(&mut self) {
+        // GNU imaginary constants: `2.0i`, `1.0iF`, or `1.0fi`
+        let imaginary = self.match_imaginary_suffix();
         // Ignored for compatibility reasons
         if !(self.match_next('f') || self.match_next('F') || self.match_next('l')) {
             self.match_next('L');
         }
+        if !imaginary {
+            self.match_imaginary_suffix();
+        }
+    }
+    fn match_imaginary_suffix(&mut self) -> bool {
+        self.match_next('i') || self.match_next('I') || self.match_next('j') || self.match_next('J')
     }
     // should only be called at the end of a number. mostly error handling
     fn parse_exponent(&mut self, hex: bool) -> Result<(), LexError> {
@@ -235,13 +280,47 @@ impl Lexer {
         }
         while let Some(c) = self.peek() {
             let c = c as char;
-            if !(c).is_digit(10) {
+            if !(c.is_ascii_digit() || self.match_digit_separator(Radix::Decimal)) {
                 break;
             }
             self.next_char();
         }
         Ok(())
     }
+    /// If the next character is a digit separator between two digits, consume it.
+    ///
+    /// Note that this does not consume the digit after the separator.
+    fn match_digit_separator(&mut self, radix: Radix) -> bool {
+        let is_digit = |c: char| c.is_digit(radix.as_u8().into());
+        let after_digit = self.chars[..self.location.offset as usize]
+            .chars()
+            .next_back()
+            .is_some_and(is_digit);
+        if after_digit && self.peek() == Some('\'') && self.peek_next().is_some_and(is_digit) {
+            self.next_char();
+            true
+        } else {
+            false
+        }
+    }
+    /// If the next characters are the `wb` or `WB` suffix for `_BitInt`, consume them.
+    fn match_bit_precise_suffix(&mut self) -> bool {
+        match (self.peek(), self.peek_next()) {
+            (Some('w'), Some('b')) | (Some('W'), Some('B')) => {
+                self.next_char();
+                self.next_char();
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Warn that a feature is only standard as of C23, unless we are following C23.
+    fn c23_extension(&mut self, feature: &'static str, span_start: u32) {
+        if self.standard < Standard::C23 {
+            let span = self.span(span_start);
+            self.warn_loc(Warning::C23Extension(feature), span);
+        }
+    }
     // returns None if there are no digits at the current position
     fn parse_int(&mut self, radix: Radix) -> Result<Option<()>, LexError> {
         let parse_digit = |c: char| match c.to_digit(16) {
@@ -254,6 +333,9 @@ impl Lexer {
         };
         let mut saw_digit = false;
         while let Some(c) = self.peek() {
+            if self.match_digit_separator(radix) {
+                continue;
+            }
             match parse_digit(c as char)? {
                 Some(_) => {
                     self.next_char();
@@ -275,6 +357,13 @@ impl Lexer {
         self.parse_char_raw(false)?;
         Ok(LiteralToken::Char(self.slice(start)).into())
     }
+    /// Parse a UTF-8 character constant, given the start of the `u8` prefix.
+    fn parse_utf8_char(&mut self, start: u32) -> Result<Token, LexError> {
+        assert!(self.match_next('8') && self.match_next('\''));
+        self.parse_char_raw(false)?;
+        self.c23_extension("UTF-8 character constants", start);
+        Ok(LiteralToken::Char(self.slice(start)).into())
+    }
     fn parse_string(&mut self) -> Result<Token, LexError> {
         let start = self.get_location().offset - '"'.len_utf8() as u32;
         let raw_str = self.parse_string_raw(false);
@@ -493,7 +582,12 @@ impl Iterator for Lexer {
                 '.' => match self.peek() {
                     Some(c) if c.is_ascii_digit() => {
                         match self.parse_float(Radix::Decimal, span_start) {
-                            Ok(f) => LiteralToken::Float(f).into(),
+                            Ok(f) => {
+                                if f.contains('\'') {
+                                    self.c23_extension("digit separators", span_start);
+                                }
+                                LiteralToken::Float(f).into()
+                            }
                             Err(err) => {
                                 return Err(Locatable {
                                     data: err,
@@ -521,13 +615,21 @@ impl Iterator for Lexer {
                         return Err(span.with(err));
                     }
                 },
-                'a'..='z' | 'A'..='Z' | '_' => match self.parse_id(c) {
-                    Ok(id) => id,
-                    Err(err) => {
-                        let span = self.span(span_start);
-                        return Err(span.with(err));
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    let token =
+                        if c == 'u' && self.peek() == Some('8') && self.peek_next() == Some('\'') {
+                            self.parse_utf8_char(span_start)
+                        } else {
+                            self.parse_id(c)
+                        };
+                    match token {
+                        Ok(token) => token,
+                        Err(err) => {
+                            let span = self.span(span_start);
+                            return Err(span.with(err));
+                        }
                     }
-                },
+                }
                 '\'' => match self.parse_char() {
                     Ok(id) => id,
                     Err(err) => {
@@ -976,8 +1078,8 @@ impl<T: Iterator<Item = char>> LiteralParser for PseudoLexer<T> {
 }
 
 fn parse_int_raw(buf: &str) -> Result<u64, SyntaxError> {
-    let (radix, buf) = if buf.starts_with("0b") {
-        (Radix::Binary, buf.trim_start_matches("0b"))
+    let (radix, buf) = if buf.starts_with("0b") || buf.starts_with("0B") {
+        (Radix::Binary, &buf[2..])
     } else if buf.starts_with("0x") {
         (Radix::Hexadecimal, buf.trim_start_matches("0x"))
     } else if buf.starts_with('0') {
@@ -988,6 +1090,9 @@ fn parse_int_raw(buf: &str) -> Result<u64, SyntaxError> {
     };
     let mut acc: u64 = 0;
     for c in buf.chars() {
+        if c == '\'' {
+            continue;
+        }
         let digit = c.to_digit(radix.as_u8().into());
         let digit = match digit {
             Some(digit) => digit,
@@ -1003,6 +1108,32 @@ fn parse_int_raw(buf: &str) -> Result<u64, SyntaxError> {
 }
 
 impl LiteralToken {
+    /// Whether this is a GNU imaginary constant, like `2.0i`.
+    ///
+    /// `parse` returns the value of the imaginary part.
+    pub fn is_imaginary(&self) -> bool {
+        match self {
+            LiteralToken::Float(rcstr) => rcstr
+                .as_str()
+                .trim_end_matches(|c| "fFlL".contains(c))
+                .ends_with(|c| "iIjJ".contains(c)),
+            _ => false,
+        }
+    }
+    /// Whether the suffix of this constant makes it a `long long` or a `long double`,
+    /// like `1LL` or `1.5L`.
+    pub fn is_long_long_or_double(&self) -> bool {
+        match self {
+            LiteralToken::Int(rcstr) | LiteralToken::UnsignedInt(rcstr) => {
+                rcstr.as_str().contains("ll") || rcstr.as_str().contains("LL")
+            }
+            LiteralToken::Float(rcstr) => rcstr
+                .as_str()
+                .trim_end_matches(|c| "iIjJ".contains(c))
+                .ends_with(&['l', 'L'][..]),
+            _ => false,
+        }
+    }
     pub fn parse(self) -> Result<LiteralValue, SyntaxError> {
         match self {
             LiteralToken::Int(rcstr) => Ok(LiteralValue::Int(
@@ -1016,9 +1147,9 @@ impl LiteralToken {
                 Ok(LiteralValue::UnsignedInt(parse_int_raw(rcstr.as_str())?))
             }
             LiteralToken::Float(rcstr) => {
-                let buf = rcstr.as_str();
+                let buf = rcstr.as_str().replace('\'', "");
                 let hex = buf.starts_with("0x");
-                let buf = buf.trim_end_matches(|c| "fFlL".contains(c));
+                let buf = buf.trim_end_matches(|c| "fFlLiIjJ".contains(c));
                 let float: f64 = if hex {
                     let float_literal: hexponent::FloatLiteral = buf.parse()?;
                     float_literal.into()
@@ -1052,7 +1183,7 @@ impl LiteralToken {
                 ))
             }
             LiteralToken::Char(rcstr) => Ok(LiteralValue::Char(
-                PseudoLexer::new(rcstr.as_str())
+                PseudoLexer::new(rcstr.as_str().trim_start_matches("u8"))
                     .parse_char_raw(true)
                     .unwrap(),
             )),
@@ -8,6 +8,8 @@ use crate::{
     Token,
 };
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::rc::Rc;
 
 use arcstr::Substr;
 
@@ -74,6 +76,11 @@ pub enum Definition {
     },
 }
 
+/// An empty macro argument next to `##`, which pastes to nothing: `#define f(a) a ## 1` `f()`
+///
+/// Ordinary whitespace is never empty, so this can't be confused with a real token.
+const PLACEMARKER: Token = Token::Whitespace(String::new());
+
 pub struct Replace<'a, I: Iterator> {
     iter: std::iter::Peekable<I>,
     definitions: &'a Definitions,
@@ -160,61 +167,73 @@ pub fn replace(
     mut inner: impl Iterator<Item = CppResult<Token>> + Peekable,
     location: Location,
 ) -> Vec<CompileResult<Locatable<Token>>> {
-    // The ids seen while replacing the current token.
+    // The macros that were replaced to produce each pending token (its "hide set").
     //
-    // This allows cycle detection. It should be reset after every replacement list
-    // - _not_ after every token, since otherwise that won't catch some mutual recursion
+    // A macro is never replaced again inside its own replacement list,
+    // which detects cycles. This is tracked per token and not for the whole replacement,
+    // so that `#define f(a) a + A + A` replaces both `A`s,
+    // but mutual recursion is still caught.
     // See https://github.com/jyn514/rcc/issues/427 for examples.
-    let mut ids_seen = HashSet::new();
     let mut replacements = Vec::new();
     let mut pending = VecDeque::new();
+    let mut hide_sets: VecDeque<Rc<HashSet<InternedStr>>> = VecDeque::new();
     pending.push_back(Ok(location.with(token)));
+    hide_sets.push_back(Rc::default());
 
     // outer loop: replace all tokens in the replacement list
     while let Some(token) = pending.pop_front() {
+        let hidden = hide_sets.pop_front().unwrap_or_default();
         // first step: perform (recursive) substitution on the ID
         if let Ok(Locatable {
             data: Token::Id(id),
             ..
         }) = token
         {
-            if !ids_seen.contains(&id) {
-                match definitions.get(&id) {
+            if !hidden.contains(&id) {
+                let mut new_tokens: VecDeque<_> = match definitions.get(&id) {
+                    // we need a `clone()` because `self.definitions` needs to keep its copy of the definition
                     Some(Definition::Object(replacement_list)) => {
-                        ids_seen.insert(id);
-                        // prepend the new tokens to the pending tokens
-                        // They need to go before, not after. For instance:
-                        // ```c
-                        // #define a b c d
-                        // #define b 1 + 2
-                        // a
-                        // ```
-                        // should replace to `1 + 2 c d`, not `c d 1 + 2`
-                        let mut new_pending = VecDeque::new();
-                        // we need a `clone()` because `self.definitions` needs to keep its copy of the definition
-                        new_pending.extend(
-                            replacement_list
-                                .iter()
-                                .cloned()
-                                .map(|t| Ok(location.with(t))),
-                        );
-                        new_pending.append(&mut pending);
-                        pending = new_pending;
-                        continue;
+                        match paste_tokens(replacement_list.clone(), location) {
+                            Ok(tokens) => {
+                                tokens.into_iter().map(|t| Ok(location.with(t))).collect()
+                            }
+                            Err(err) => {
+                                replacements.push(Err(err));
+                                continue;
+                            }
+                        }
                     }
                     // TODO: so many allocations :(
                     Some(Definition::Function { .. }) => {
-                        ids_seen.insert(id);
+                        let before = pending.len();
                         let func_replacements =
                             replace_function(definitions, id, location, &mut pending, &mut inner);
-                        let mut func_replacements: VecDeque<_> =
-                            func_replacements.into_iter().collect();
-                        func_replacements.append(&mut pending);
-                        pending = func_replacements;
+                        // the arguments were taken from the pending tokens
+                        hide_sets.drain(..before - pending.len());
+                        func_replacements.into_iter().collect()
+                    }
+                    None => {
+                        replacements.push(token);
                         continue;
                     }
-                    None => {}
+                };
+                let mut hidden = (*hidden).clone();
+                hidden.insert(id);
+                let hidden = Rc::new(hidden);
+                // prepend the new tokens to the pending tokens
+                // They need to go before, not after. For instance:
+                // ```c
+                // #define a b c d
+                // #define b 1 + 2
+                // a
+                // ```
+                // should replace to `1 + 2 c d`, not `c d 1 + 2`
+                for _ in 0..new_tokens.len() {
+                    hide_sets.push_front(Rc::clone(&hidden));
                 }
+                new_tokens.append(&mut pending);
+                pending = new_tokens;
+                continue;
             }
         }
         replacements.push(token);
@@ -374,13 +393,38 @@ fn replace_function(
     }
 
     let mut pending_hash = false; // Seen a hash?
-    for token in body {
+    let mut pending_paste = false; // Seen `##`?
+    let mut body = body.iter().peekable();
+    while let Some(token) = body.next() {
         match *token {
+            // #define cat(a, b) a ## b
+            // this is pasted below, after all the parameters are replaced
+            Token::Hash if !pending_hash && body.peek() == Some(&&Token::Hash) => {
+                body.next();
+                replacements.push(Token::Hash);
+                replacements.push(Token::Hash);
+                pending_paste = true;
+                continue;
+            }
             Token::Id(id) => {
                 // #define f(a) { a + 1 } \n f(b) => b + 1
                 if let Some(index) = params.iter().position(|&param| param == id) {
-                    let replacement = args[index].clone();
+                    // > A parameter in the replacement list, unless preceded by a # or ## preprocessing token
+                    // > or followed by a ## preprocessing token, is replaced by the corresponding argument
+                    // > after all macros contained therein have been expanded.
+                    let mut rest = body.clone();
+                    let paste_next = rest.find(|t| !matches!(t, Token::Whitespace(_)))
+                        == Some(&Token::Hash)
+                        && rest.next() == Some(&Token::Hash);
+                    let replacement = if pending_hash || pending_paste || paste_next {
+                        args[index].clone()
+                    } else {
+                        expand_argument(definitions, &args[index], location, &mut errors)
+                    };
                     if !pending_hash {
+                        if replacement.is_empty() {
+                            replacements.push(PLACEMARKER);
+                        }
                         replacements.extend(replacement);
                     } else {
                         // #define str(a) #a
@@ -400,6 +444,7 @@ fn replace_function(
                 if !pending_hash {
                     replacements.push(Token::Whitespace(String::from(" ")));
                 }
+                continue;
             }
             _ => {
                 if pending_hash {
@@ -409,7 +454,12 @@ fn replace_function(
                 }
             }
         }
+        pending_paste = false;
     }
+    let replacements = match paste_tokens(replacements, location) {
+        Ok(replacements) => replacements,
+        Err(err) => return vec![Err(err)],
+    };
     // TODO: this collect is useless
     errors
         .into_iter()
@@ -417,6 +467,78 @@ fn replace_function(
         .collect()
 }
 
+/// Replace all the macros in an argument to a function macro, as if it were the rest of the file.
+///
+/// `#define f(a) a` `f(g(1))` replaces `g(1)` before substituting it into the body of `f`.
+fn expand_argument(
+    definitions: &Definitions,
+    arg: &[Token],
+    location: Location,
+    errors: &mut Vec<CompileResult<Locatable<Token>>>,
+) -> Vec<Token> {
+    let tokens = arg.iter().cloned().map(|token| Ok(location.with(token)));
+    let mut expanded = Vec::with_capacity(arg.len());
+    for token in replace_iter(tokens, definitions).flatten() {
+        match token {
+            Ok(token) => expanded.push(token.data),
+            Err(err) => errors.push(Err(err)),
+        }
+    }
+    expanded
+}
+
+/// Paste together the tokens on either side of each `##`: `a ## b` becomes `ab`.
+///
+/// This also removes all placemarkers, whether or not they were next to a `##`.
+/// 6.10.3.3 The ## operator
+fn paste_tokens(tokens: Vec<Token>, location: Location) -> CompileResult<Vec<Token>> {
+    let is_space = |token: &Token| matches!(token, Token::Whitespace(s) if !s.is_empty());
+    let mut pasted = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if token != Token::Hash || tokens.peek() != Some(&Token::Hash) {
+            pasted.push(token);
+            continue;
+        }
+        tokens.next();
+        // `a ## b` is the same as `a##b`
+        while pasted.last().is_some_and(is_space) {
+            pasted.pop();
+        }
+        while tokens.peek().is_some_and(is_space) {
+            tokens.next();
+        }
+        match (pasted.pop(), tokens.next()) {
+            (Some(left), Some(right)) => pasted.push(paste(left, right, location)?),
+            _ => return Err(location.with(CppError::HashHashAtEdge.into())),
+        }
+    }
+    pasted.retain(|token| *token != PLACEMARKER);
+    Ok(pasted)
+}
+
+/// Concatenate two tokens into one: `__GLIBC_USE_ ## ISOC2X` becomes `__GLIBC_USE_ISOC2X`
+fn paste(left: Token, right: Token, location: Location) -> CompileResult<Token> {
+    if left == PLACEMARKER {
+        return Ok(right);
+    } else if right == PLACEMARKER {
+        return Ok(left);
+    }
+    let mut tokens = match Definition::try_from(format!("{}{}", left, right).as_str()) {
+        Ok(Definition::Object(tokens)) => tokens,
+        _ => Vec::new(),
+    };
+    // ignore the trailing newline
+    while matches!(tokens.last(), Some(Token::Whitespace(_))) {
+        tokens.pop();
+    }
+    if tokens.len() == 1 {
+        Ok(tokens.remove(0))
+    } else {
+        Err(location.with(CppError::InvalidPaste(left, right).into()))
+    }
+}
+
 fn stringify(args: Vec<Token>) -> Token {
     let escape = |s: &str| s.replace('\\', r#"\\"#).replace('"', r#"\""#);
     let ret: String = args
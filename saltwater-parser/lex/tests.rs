@@ -165,6 +165,26 @@ fn test_int_literals() {
     assert_eq!(lex_all("1a").len(), 2);
 }
 #[test]
+fn test_long_long_literals() {
+    for long_long in &["1LL", "1ll", "0x1LL", "1llu", "1ULL"] {
+        let lexed = lex(long_long).unwrap().unwrap().data;
+        assert!(
+            matches!(lexed, Token::Literal(lit) if lit.is_long_long_or_double()),
+            "{} is not long long",
+            long_long
+        );
+        assert_eq!(lex_all(long_long).len(), 1);
+    }
+    assert_int("1LL", 1);
+    assert_int("0x10ll", 16);
+    for not_long_long in &["1L", "1lu", "1.5", "1.5f", "1.5i"] {
+        let lexed = lex(not_long_long).unwrap().unwrap().data;
+        assert!(!matches!(lexed, Token::Literal(lit) if lit.is_long_long_or_double()));
+    }
+    let lexed = lex("1.5L").unwrap().unwrap().data;
+    assert!(matches!(lexed, Token::Literal(lit) if lit.is_long_long_or_double()));
+}
+#[test]
 fn test_float_literals() {
     assert_float("0.1", 0.1);
     assert_float(".1", 0.1);
@@ -208,6 +228,21 @@ fn test_float_literals() {
     assert_float("2.225073858507201383e-308L", 2.225_073_858_507_201_4e-308);
 }
 
+#[test]
+fn test_imaginary_literals() {
+    for imaginary in &["2.5i", "2.5j", "2.5iF", "2.5fi", "2.5Li", "25e-1J"] {
+        assert_float(imaginary, 2.5);
+        let lexed = lex(imaginary).unwrap().unwrap().data;
+        assert!(
+            matches!(lexed, Token::Literal(lit) if lit.is_imaginary()),
+            "{} is not imaginary",
+            imaginary
+        );
+    }
+    let lexed = lex("2.5f").unwrap().unwrap().data;
+    assert!(!matches!(lexed, Token::Literal(lit) if lit.is_imaginary()));
+}
+
 #[test]
 fn test_num_errors() {
     assert_err("1e");
@@ -215,6 +250,104 @@ fn test_num_errors() {
     assert_eq!(lex_all("1e1.0").len(), 2);
 }
 
+#[test]
+fn test_c23_literals() {
+    assert_int("1'000'000", 1_000_000);
+    assert_int("0x7fff'ffff", 0x7fff_ffff);
+    assert_int("0b1010", 10);
+    assert_int("0B1'0", 2);
+    assert_int("0'17", 0o17);
+    assert_int("10wb", 10);
+    assert_int("10WB", 10);
+    assert_float("1'0.2'5e1'0", 10.25e10);
+    for unsigned in &["10uwb", "10wbU", "1'0Uwb"] {
+        assert!(match_data(lex(unsigned), |lexed| match_data_eq(
+            lexed.unwrap(),
+            &LiteralToken::UnsignedInt(Substr::from("10")).into()
+        )));
+    }
+    assert!(match_char(lex("u8'a'"), b'a'));
+    assert!(match_char(lex("u8'\\xff'"), b'\xff'));
+    // separators have to be between two digits
+    assert_eq!(lex_all("1'a'").len(), 2);
+    assert_err("1'");
+    assert_err("0x'1");
+    // mixed case suffixes aren't allowed
+    assert_eq!(lex_all("1wB").len(), 2);
+    assert_eq!(lex_all("u8").len(), 1);
+    assert_eq!(lex_all("u8 'a'").len(), 2);
+}
+
+#[test]
+fn test_c23_literal_warnings() {
+    use crate::{PreProcessorBuilder, Standard};
+
+    let src = "1'000 0b1 1wb u8'a'\n";
+    let mut cpp = PreProcessorBuilder::new(src).build();
+    assert!(cpp.by_ref().all(|token| token.is_ok()));
+    assert_eq!(cpp.warnings().len(), 4);
+
+    let mut cpp = PreProcessorBuilder::new(src)
+        .standard(Standard::C23)
+        .build();
+    assert!(cpp.by_ref().all(|token| token.is_ok()));
+    assert!(cpp.warnings().is_empty());
+
+    let mut cpp = PreProcessorBuilder::new("__STDC_VERSION__\n")
+        .standard(Standard::C23)
+        .build();
+    let version = cpp.next().unwrap().unwrap().data;
+    assert_eq!(version.to_string(), "202311");
+}
+
+#[test]
+fn test_c23_keywords() {
+    use crate::data::lex::Keyword;
+    use crate::{PreProcessorBuilder, Standard};
+
+    let tokens = |src, standard| {
+        PreProcessorBuilder::new(src)
+            .standard(standard)
+            .build()
+            .map(|token| token.unwrap().data)
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect::<Vec<_>>()
+    };
+    let src = "nullptr typeof typeof_unqual constexpr\n";
+    // before C23, these are ordinary identifiers
+    assert!(tokens(src, Standard::C11)
+        .iter()
+        .all(|token| matches!(token, Token::Id(_))));
+    assert_eq!(
+        tokens(src, Standard::C23),
+        vec![
+            Token::Keyword(Keyword::Nullptr),
+            Token::Keyword(Keyword::Typeof),
+            Token::Keyword(Keyword::TypeofUnqual),
+            Token::Keyword(Keyword::Constexpr),
+        ]
+    );
+    // GNU spellings are keywords in every version
+    assert_eq!(
+        tokens("__typeof__ __typeof\n", Standard::C11),
+        vec![Token::Keyword(Keyword::Typeof); 2]
+    );
+
+    // `-std=gnu11` has `typeof`, without a warning
+    let mut cpp = PreProcessorBuilder::new("typeof typeof_unqual\n")
+        .gnu_extensions(true)
+        .build();
+    let tokens = cpp
+        .by_ref()
+        .map(|token| token.unwrap().data)
+        .filter(|token| !matches!(token, Token::Whitespace(_)));
+    assert!(matches!(
+        tokens.collect::<Vec<_>>().as_slice(),
+        [Token::Keyword(Keyword::Typeof), Token::Id(_)]
+    ));
+    assert!(cpp.warnings().is_empty());
+}
+
 fn lots_of(c: char) -> String {
     let mut buf = Vec::new();
     buf.resize(8096, c);
@@ -63,7 +63,7 @@ pub use analyze::{Analyzer, PureAnalyzer};
 pub use data::*;
 // https://github.com/rust-lang/rust/issues/64762
 #[allow(unreachable_pub)]
-pub use lex::{Definition, Lexer, PreProcessor, PreProcessorBuilder};
+pub use lex::{Definition, Lexer, LosslessLexer, PreProcessor, PreProcessorBuilder};
 pub use parse::Parser;
 
 #[macro_use]
@@ -168,6 +168,29 @@ pub struct Opt {
     /// If set, compile and assemble but do not link. Object file is machine-dependent.
     pub no_link: bool,
 
+    /// If set, complex multiplication and division handle infinities and NaNs
+    /// as described in Annex G of the C standard.
+    ///
+    /// This calls `__muldc3` and `__divdc3` from the runtime library (libgcc or compiler-rt),
+    /// which is slower than the textbook formulas.
+    pub annex_g: bool,
+
+    /// The version of the C standard to follow.
+    ///
+    /// Features from newer standards are still accepted, but give a warning.
+    pub standard: Standard,
+
+    /// If set, accept the GNU extensions enabled by `-std=gnu11`.
+    ///
+    /// These are case ranges, the Elvis operator `x ?: y`,
+    /// and computed goto (`&&label` and `goto *ptr`).
+    /// Without this, they are a syntax error.
+    pub gnu_extensions: bool,
+
+    /// If set, warn when a `case` or `default` label can be reached
+    /// from the statements before it without `[[fallthrough]];`.
+    pub implicit_fallthrough: bool,
+
     #[cfg(feature = "jit")]
     /// If set, compile and emit JIT code, and do not emit object files and binaries.
     pub jit: bool,
@@ -190,10 +213,50 @@ pub struct Opt {
     pub filename: PathBuf,
 }
 
+/// A version of the C standard.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Standard {
+    /// ISO/IEC 9899:2011. C17 only fixed defects in C11, so it is treated the same.
+    #[default]
+    C11,
+    /// ISO/IEC 9899:2024, also known as C2x.
+    C23,
+}
+
+impl Standard {
+    /// The value of `__STDC_VERSION__` for this standard.
+    #[allow(clippy::inconsistent_digit_grouping)]
+    pub fn version(self) -> i32 {
+        match self {
+            Standard::C11 => 2011_12,
+            Standard::C23 => 2023_11,
+        }
+    }
+}
+
+impl std::str::FromStr for Standard {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Standard, &'static str> {
+        match s {
+            "c11" | "c17" | "c18" => Ok(Standard::C11),
+            "c23" | "c2x" => Ok(Standard::C23),
+            _ => Err("unsupported standard, expected one of c11, c17, c18, c23, or c2x"),
+        }
+    }
+}
+
 /// Preprocess the source and return the tokens.
 pub fn preprocess(buf: &str, opt: Opt) -> Program<VecDeque<Locatable<Token>>> {
     let path = opt.search_path.iter().map(|p| p.into());
-    let mut cpp = PreProcessor::new(buf, opt.filename, opt.debug_lex, path, opt.definitions);
+    let mut cpp = PreProcessor::new(
+        buf,
+        opt.filename,
+        opt.debug_lex,
+        path,
+        opt.definitions,
+        opt.standard,
+    )
+    .gnu_extensions(opt.gnu_extensions);
 
     let mut tokens = VecDeque::new();
     let mut errs = VecDeque::new();
@@ -218,12 +281,22 @@ pub fn preprocess(buf: &str, opt: Opt) -> Program<VecDeque<Locatable<Token>>> {
 /// Perform semantic analysis, including type checking and constant folding.
 pub fn check_semantics(buf: &str, opt: Opt) -> Program<Vec<Locatable<hir::Declaration>>> {
     let path = opt.search_path.iter().map(|p| p.into());
-    let mut cpp = PreProcessor::new(buf, opt.filename, opt.debug_lex, path, opt.definitions);
+    let mut cpp = PreProcessor::new(
+        buf,
+        opt.filename,
+        opt.debug_lex,
+        path,
+        opt.definitions,
+        opt.standard,
+    )
+    .gnu_extensions(opt.gnu_extensions);
 
     let mut errs = VecDeque::new();
 
     let mut hir = vec![];
-    let mut parser = Analyzer::new(Parser::new(&mut cpp, opt.debug_ast), opt.debug_hir);
+    let parser = Parser::new(&mut cpp, opt.debug_ast).gnu_extensions(opt.gnu_extensions);
+    let mut parser =
+        Analyzer::new(parser, opt.debug_hir).implicit_fallthrough(opt.implicit_fallthrough);
     for res in &mut parser {
         match res {
             Ok(decl) => hir.push(decl),
@@ -1,10 +1,11 @@
 use super::*;
 use crate::data::ast::{
-    self, Declaration, DeclarationSpecifier, Declarator, Expr, ExternalDeclaration, Initializer,
-    TypeName,
+    self, Declaration, DeclarationSpecifier, Declarator, Designator, Expr, ExternalDeclaration,
+    Initializer, TypeName,
 };
 use crate::data::error::Warning;
 use crate::data::*;
+use crate::get_str;
 use std::convert::{TryFrom, TryInto};
 
 #[derive(Debug)]
@@ -26,6 +27,7 @@ enum InternalDeclaratorType {
 struct InternalDeclarator {
     current: InternalDeclaratorType,
     next: Option<Box<InternalDeclarator>>,
+    attributes: Vec<Locatable<ast::Attribute>>,
 }
 
 impl<I: Lexer> Parser<I> {
@@ -42,32 +44,58 @@ impl<I: Lexer> Parser<I> {
     /// ```
     /// <http://www.quut.com/c/ANSI-C-grammar-y.html#external_declaration>
     pub fn external_declaration(&mut self) -> SyntaxResult<Locatable<ExternalDeclaration>> {
+        if self.peek_token() == Some(&Token::Keyword(Keyword::StaticAssert)) {
+            return self.static_assert_declaration();
+        }
         let (specifiers, specifier_locations) = self.specifiers()?;
 
         // allow `int;`
         if let Some(token) = self.match_next(&Token::Semicolon) {
             let location = token.location.maybe_merge(specifier_locations);
-            self.error_handler.warn(Warning::EmptyDeclaration, location);
-            let empty_decl = ExternalDeclaration::Declaration(Declaration {
+            let empty_decl = Declaration {
                 specifiers,
                 declarators: Vec::new(),
-            });
+            };
+            // `[[fallthrough]];` is an attribute declaration, which is checked by the analyzer
+            if !empty_decl.is_attribute_declaration() {
+                self.error_handler.warn(Warning::EmptyDeclaration, location);
+            }
+            let empty_decl = ExternalDeclaration::Declaration(empty_decl);
             return Ok(Locatable::new(empty_decl, location));
         }
 
-        let declarator = self.init_declarator()?;
+        let mut declarator = self.init_declarator()?;
         let mut location = declarator.location.maybe_merge(specifier_locations);
+        // int f(a, b) int a; double b; { ... }
+        let mut old_style = false;
+        if let ast::DeclaratorType::Function(func) = &mut declarator.data.declarator.decl {
+            if func.is_identifier_list()
+                && declarator.data.init.is_none()
+                && self
+                    .peek_token()
+                    .is_some_and(|t| *t == Token::LeftBrace || t.is_decl_specifier())
+            {
+                self.old_style_declarations(func)?;
+                old_style = true;
+            }
+        }
         if self.peek_token() == Some(&Token::LeftBrace) {
             use crate::data::ast::{DeclaratorType, FunctionDefinition};
 
             // int i = 1 {}
             let func = match declarator.data.declarator.decl {
                 DeclaratorType::Function(func) => func,
-                _ => return Err(location.with(SyntaxError::NotAFunction(declarator.data))),
+                _ => {
+                    return Err(location.with(SyntaxError::NotAFunction(Box::new(declarator.data))))
+                }
             };
             // int f() = 1 { }
             if let Some(init) = declarator.data.init {
-                return Err(location.with(SyntaxError::FunctionInitializer(init)));
+                return Err(location.with(SyntaxError::FunctionInitializer(Box::new(init))));
+            }
+            // int f() __asm__("g") { }
+            if declarator.data.asm_label.is_some() {
+                return Err(location.with(SyntaxError::AsmLabelOnDefinition));
             }
 
             let body = self.compound_statement()?;
@@ -75,20 +103,40 @@ impl<I: Lexer> Parser<I> {
             // int () {}
             let err = location.with(SyntaxError::MissingFunctionName);
             let id = declarator.data.declarator.id.ok_or(err)?;
+            // `void __attribute__((noreturn)) f() {}` means the same as `__attribute__((noreturn)) void f() {}`
+            let mut specifiers = specifiers;
+            specifiers.extend(
+                declarator
+                    .data
+                    .declarator
+                    .attributes
+                    .into_iter()
+                    .map(DeclarationSpecifier::Attribute),
+            );
             let def = FunctionDefinition {
                 id,
                 body: body.data,
                 specifiers,
                 declarator: func,
+                old_style,
             };
             return Ok(Locatable::new(ExternalDeclaration::Function(def), location));
+        } else if old_style {
+            // int f(a) int a;
+            return Err(self.next_location().with(SyntaxError::Generic(
+                "expected '{' after parameter declarations".into(),
+            )));
         }
         let mut decls = vec![declarator];
         let has_typedef = specifiers
             .iter()
             .any(|s| *s == DeclarationSpecifier::Unit(crate::data::ast::UnitSpecifier::Typedef));
         while self.match_next(&Token::Semicolon).is_none() {
-            self.expect(Token::Comma)?;
+            if self.match_next(&Token::Comma).is_none() {
+                // `int i = 1 int j;` is missing a semicolon, not a comma
+                self.expect(Token::Semicolon)?;
+                break;
+            }
             let decl = self.init_declarator()?;
             location = location.merge(decl.location);
             decls.push(decl);
@@ -108,6 +156,115 @@ impl<I: Lexer> Parser<I> {
             location,
         ))
     }
+    /// ```yacc
+    /// static_assert_declaration
+    /// : STATIC_ASSERT '(' constant_expr ',' STRING_LITERAL ')' ';'
+    /// | STATIC_ASSERT '(' constant_expr ')' ';'  // C2x
+    /// ;
+    /// ```
+    fn static_assert_declaration(&mut self) -> SyntaxResult<Locatable<ExternalDeclaration>> {
+        let assert = self.static_assert()?;
+        Ok(assert.map(|assert| ExternalDeclaration::StaticAssert(Box::new(assert))))
+    }
+    /// A static assertion, which can appear anywhere a declaration can,
+    /// including in a struct (6.7.2.1).
+    fn static_assert(&mut self) -> SyntaxResult<Locatable<ast::StaticAssert>> {
+        let start = self.expect(Token::Keyword(Keyword::StaticAssert))?;
+        self.expect(Token::LeftParen)?;
+        let condition = self.ternary_expr()?;
+        let message = if self.match_next(&Token::Comma).is_some() {
+            let expected_string = || SyntaxError::Generic("expected string literal".into());
+            let literal = match self.match_literal() {
+                Some(literal) => literal,
+                None => return Err(self.next_location().with(expected_string())),
+            };
+            match literal.data.parse() {
+                Ok(LiteralValue::Str(mut message)) => {
+                    // remove the null terminator
+                    message.pop();
+                    Some(String::from_utf8_lossy(&message).into_owned())
+                }
+                Ok(_) => return Err(literal.location.with(expected_string())),
+                Err(err) => return Err(literal.location.with(err)),
+            }
+        } else {
+            None
+        };
+        self.expect(Token::RightParen)?;
+        let end = self.expect(Token::Semicolon)?;
+        let assert = ast::StaticAssert { condition, message };
+        Ok(Locatable::new(assert, start.location.merge(end.location)))
+    }
+    /// The declaration list of an old-style (K&R) function definition.
+    ///
+    /// Each declaration gives the type of one of the identifiers in the identifier list;
+    /// identifiers without a declaration are left alone and will default to `int`.
+    ///
+    /// ```yacc
+    /// function_definition
+    /// : declaration_specifiers declarator declaration_list compound_statement
+    /// ;
+    ///
+    /// declaration_list
+    /// : declaration
+    /// | declaration_list declaration
+    /// ;
+    /// ```
+    fn old_style_declarations(&mut self, func: &mut ast::FunctionDeclarator) -> SyntaxResult<()> {
+        while self.peek_token().is_some_and(Token::is_decl_specifier) {
+            let decl = self.external_declaration()?;
+            let location = decl.location;
+            let decl = decl
+                .data
+                .into_declaration()
+                .map_err(|err| location.with(err))?;
+            for declarator in decl.declarators {
+                let location = declarator.location;
+                let declarator = declarator.data;
+                let id = match declarator.declarator.id {
+                    Some(id) => id,
+                    None => {
+                        self.error_handler
+                            .push_back(location.with(SyntaxError::ExpectedDeclarator));
+                        continue;
+                    }
+                };
+                // int f(a) int a = 1; {}
+                if declarator.init.is_some() {
+                    let err =
+                        SyntaxError::Generic(format!("parameter '{}' cannot be initialized", id));
+                    self.error_handler.push_back(location.with(err));
+                }
+                let param = func
+                    .params
+                    .iter_mut()
+                    .find(|param| param.declarator.id == Some(id));
+                match param {
+                    Some(param) if param.specifiers.is_empty() => {
+                        *param = TypeName {
+                            specifiers: decl.specifiers.clone(),
+                            declarator: declarator.declarator,
+                        };
+                    }
+                    // int f(a) int a; int a; {}
+                    Some(_) => {
+                        let err =
+                            SyntaxError::Generic(format!("redeclaration of parameter '{}'", id));
+                        self.error_handler.push_back(location.with(err));
+                    }
+                    // int f(a) int b; {}
+                    None => {
+                        let err = SyntaxError::Generic(format!(
+                            "declaration for '{}', which is not a parameter",
+                            id
+                        ));
+                        self.error_handler.push_back(location.with(err));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
     pub fn type_name(&mut self) -> SyntaxResult<Locatable<TypeName>> {
         use crate::ast::DeclaratorType;
 
@@ -119,6 +276,7 @@ impl<I: Lexer> Parser<I> {
                 Declarator {
                     decl: DeclaratorType::End,
                     id: None,
+                    attributes: Vec::new(),
                 },
             ),
             Some(decl) => (
@@ -143,12 +301,43 @@ impl<I: Lexer> Parser<I> {
         let mut specifiers = Vec::new();
         let mut all_locs = None;
         let mut seen_typedef = false;
-        while let Some(&Token::Keyword(keyword)) = self.peek_token() {
+        loop {
+            // [[nodiscard]] int f();
+            if self.is_standard_attribute() {
+                for attr in self.standard_attribute_list()? {
+                    all_locs = Some(attr.location.maybe_merge(all_locs));
+                    specifiers.push(DeclarationSpecifier::Attribute(attr));
+                }
+                continue;
+            }
+            let keyword = match self.peek_token() {
+                Some(&Token::Keyword(keyword)) => keyword,
+                _ => break,
+            };
             let location = self.next_token().unwrap().location;
             let spec = match keyword {
                 Keyword::Struct => self.struct_specifier(true, location)?,
                 Keyword::Union => self.struct_specifier(false, location)?,
                 Keyword::Enum => self.enum_specifier(location)?,
+                Keyword::Alignas => self.alignas_specifier(location)?,
+                Keyword::Typeof | Keyword::TypeofUnqual => {
+                    // `typedef int I; typeof(1) I;` declares a variable named `I`
+                    seen_typedef = true;
+                    self.typeof_specifier(keyword == Keyword::TypeofUnqual, location)?
+                }
+                // > If the _Atomic keyword is immediately followed by a left parenthesis,
+                // > it is interpreted as a type specifier (with a type name), not as a type qualifier.
+                Keyword::Atomic if self.peek_token() == Some(&Token::LeftParen) => {
+                    self.atomic_specifier(location)?
+                }
+                // __attribute__((noreturn)) void f();
+                Keyword::Attribute => {
+                    for attr in self.attribute_list()? {
+                        all_locs = Some(attr.location.maybe_merge(all_locs));
+                        specifiers.push(DeclarationSpecifier::Attribute(attr));
+                    }
+                    continue;
+                }
                 Keyword::UserTypedef(name) => {
                     // absolute hack: allow awful code like `typedef int I; { I I; }`
                     if !seen_typedef {
@@ -187,6 +376,8 @@ impl<I: Lexer> Parser<I> {
     ) -> SyntaxResult<Locatable<DeclarationSpecifier>> {
         use crate::data::ast::StructSpecifier;
 
+        // struct __attribute__((packed)) s { ... }
+        let mut attributes = self.attributes()?;
         let name = self.match_id().map(|id| {
             start = start.merge(id.location);
             id.data
@@ -206,15 +397,41 @@ impl<I: Lexer> Parser<I> {
                     );
                     continue;
                 }
-                let decl = self.struct_declaration_list()?;
-                start = start.merge(decl.location);
-                members.push(decl.data);
+                let member = if self.peek_token() == Some(&Token::Keyword(Keyword::StaticAssert)) {
+                    self.static_assert().map(|assert| {
+                        let location = assert.location;
+                        location.with(ast::StructDeclaration::StaticAssert(assert.map(Box::new)))
+                    })
+                } else {
+                    self.struct_declaration_list()
+                        .map(|decl| decl.map(ast::StructDeclaration::Member))
+                };
+                match member {
+                    Ok(decl) => {
+                        start = start.merge(decl.location);
+                        members.push(decl.data);
+                    }
+                    // keep going, so that errors in later members are also reported
+                    Err(err) => {
+                        self.panic();
+                        if self.peek_token().is_none() {
+                            return Err(err);
+                        }
+                        self.error_handler.push_back(err);
+                    }
+                }
             }
+            // struct s { ... } __attribute__((packed))
+            attributes.append(&mut self.attributes()?);
             Some(members)
         } else {
             None
         };
-        let spec = StructSpecifier { name, members };
+        let spec = StructSpecifier {
+            name,
+            members,
+            attributes,
+        };
         let spec = if is_struct {
             DeclarationSpecifier::Struct(spec)
         } else {
@@ -230,7 +447,7 @@ impl<I: Lexer> Parser<I> {
     ///
     /// struct_declarator
     /// : declarator
-    /// | ':' constant_expr  // bitfield, not supported
+    /// | ':' constant_expr  // unnamed bitfield
     /// | declarator ':' constant_expr
     /// ;
     /// ```
@@ -309,6 +526,8 @@ impl<I: Lexer> Parser<I> {
         &mut self,
         mut location: Location,
     ) -> SyntaxResult<Locatable<DeclarationSpecifier>> {
+        // enum __attribute__((packed)) e { ... }
+        let mut attributes = self.attributes()?;
         let name = self.match_id().map(|id| {
             location = location.merge(id.location);
             id.data
@@ -322,6 +541,8 @@ impl<I: Lexer> Parser<I> {
                     break;
                 }
                 let enumerator = self.expect_id()?;
+                // enum e { A __attribute__((deprecated)) }
+                attributes.append(&mut self.attributes()?);
                 let value = if self.match_next(&Token::EQUAL).is_some() {
                     Some(self.ternary_expr()?)
                 } else {
@@ -334,10 +555,17 @@ impl<I: Lexer> Parser<I> {
                     break;
                 }
             }
+            // enum e { ... } __attribute__((packed))
+            attributes.append(&mut self.attributes()?);
             Some(body)
         } else {
             None
         };
+        // enums always have the same representation, and enumerators can't be referenced by symbol
+        for attr in attributes {
+            let warning = Warning::IgnoredAttribute(attr.data.name, "an enum");
+            self.error_handler.warn(warning, attr.location);
+        }
         let decl = DeclarationSpecifier::Enum {
             name,
             members: body,
@@ -345,10 +573,319 @@ impl<I: Lexer> Parser<I> {
         Ok(Locatable::new(decl, location))
     }
 
+    /// ```yacc
+    /// alignment_specifier
+    /// : '_Alignas' '(' type_name ')'
+    /// | '_Alignas' '(' constant_expression ')'
+    /// ;
+    /// ```
+    ///
+    /// we've already seen an `_Alignas` token, `location` is where we saw it
+    fn alignas_specifier(
+        &mut self,
+        location: Location,
+    ) -> SyntaxResult<Locatable<DeclarationSpecifier>> {
+        self.expect(Token::LeftParen)?;
+        let spec = if self.peek_token().is_some_and(Token::is_decl_specifier) {
+            DeclarationSpecifier::AlignasType(Box::new(self.type_name()?.data))
+        } else {
+            DeclarationSpecifier::AlignasExpr(Box::new(self.ternary_expr()?))
+        };
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(Locatable::new(spec, location.merge(end)))
+    }
+
+    /// ```yacc
+    /// typeof_specifier
+    /// : ('typeof' | 'typeof_unqual') '(' type_name ')'
+    /// | ('typeof' | 'typeof_unqual') '(' expression ')'
+    /// ;
+    /// ```
+    ///
+    /// we've already seen a `typeof` or `typeof_unqual` token, `location` is where we saw it
+    fn typeof_specifier(
+        &mut self,
+        unqual: bool,
+        location: Location,
+    ) -> SyntaxResult<Locatable<DeclarationSpecifier>> {
+        self.expect(Token::LeftParen)?;
+        let spec = if self.peek_token().is_some_and(Token::is_decl_specifier) {
+            DeclarationSpecifier::TypeofType {
+                type_name: Box::new(self.type_name()?.data),
+                unqual,
+            }
+        } else {
+            DeclarationSpecifier::TypeofExpr {
+                expr: Box::new(self.expr()?),
+                unqual,
+            }
+        };
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(Locatable::new(spec, location.merge(end)))
+    }
+
+    /// ```yacc
+    /// atomic_type_specifier
+    /// : '_Atomic' '(' type_name ')'
+    /// ;
+    /// ```
+    ///
+    /// we've already seen an `_Atomic` token, `location` is where we saw it
+    fn atomic_specifier(
+        &mut self,
+        location: Location,
+    ) -> SyntaxResult<Locatable<DeclarationSpecifier>> {
+        self.expect(Token::LeftParen)?;
+        let spec = DeclarationSpecifier::AtomicType(Box::new(self.type_name()?.data));
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(Locatable::new(spec, location.merge(end)))
+    }
+
+    /// Any number of GNU or standard attribute specifiers, possibly none.
+    pub(super) fn attributes(&mut self) -> SyntaxResult<Vec<Locatable<ast::Attribute>>> {
+        let mut attributes = Vec::new();
+        loop {
+            if self
+                .match_next(&Token::Keyword(Keyword::Attribute))
+                .is_some()
+            {
+                attributes.append(&mut self.attribute_list()?);
+            } else if self.is_standard_attribute() {
+                attributes.append(&mut self.standard_attribute_list()?);
+            } else {
+                break Ok(attributes);
+            }
+        }
+    }
+
+    /// Whether the next tokens are `[[`, which always starts a standard attribute specifier.
+    pub(super) fn is_standard_attribute(&mut self) -> bool {
+        self.peek_token() == Some(&Token::LeftBracket)
+            && self.peek_next_token() == Some(&Token::LeftBracket)
+    }
+
+    /// ```yacc
+    /// attribute_specifier
+    /// : '__attribute__' '(' '(' attribute_list ')' ')'
+    /// ;
+    ///
+    /// attribute_list
+    /// : attribute?
+    /// | attribute_list ',' attribute?
+    /// ;
+    /// ```
+    /// <https://gcc.gnu.org/onlinedocs/gcc/Attribute-Syntax.html>
+    ///
+    /// we've already seen an `__attribute__` token
+    fn attribute_list(&mut self) -> SyntaxResult<Vec<Locatable<ast::Attribute>>> {
+        self.expect(Token::LeftParen)?;
+        self.expect(Token::LeftParen)?;
+        let mut attributes = Vec::new();
+        loop {
+            // empty attributes are allowed: `__attribute__((unused,))`
+            if self.match_next(&Token::Comma).is_some() {
+                continue;
+            }
+            if self.match_next(&Token::RightParen).is_some() {
+                break;
+            }
+            attributes.push(self.attribute()?);
+            if self.match_next(&Token::Comma).is_none() {
+                self.expect(Token::RightParen)?;
+                break;
+            }
+        }
+        self.expect(Token::RightParen)?;
+        Ok(attributes)
+    }
+
+    /// ```yacc
+    /// attribute
+    /// : attribute_name
+    /// | attribute_name '(' ')'
+    /// | attribute_name '(' assignment_expr (',' assignment_expr)* ')'
+    /// ;
+    /// ```
+    fn attribute(&mut self) -> SyntaxResult<Locatable<ast::Attribute>> {
+        let (name, mut location) = self.attribute_name()?;
+        let args = self.attribute_arguments(&mut location)?;
+        Ok(Locatable::new(ast::Attribute { name, args }, location))
+    }
+
+    /// ```yacc
+    /// standard_attribute_specifier
+    /// : '[' '[' standard_attribute_list ']' ']'
+    /// ;
+    ///
+    /// standard_attribute_list
+    /// : standard_attribute?
+    /// | standard_attribute_list ',' standard_attribute?
+    /// ;
+    /// ```
+    /// <http://www.open-std.org/jtc1/sc22/wg14/www/docs/n2731.pdf#section.6.7.12.1>
+    fn standard_attribute_list(&mut self) -> SyntaxResult<Vec<Locatable<ast::Attribute>>> {
+        self.expect(Token::LeftBracket)?;
+        self.expect(Token::LeftBracket)?;
+        let mut attributes = Vec::new();
+        loop {
+            // empty attributes are allowed: `[[nodiscard,]]`
+            if self.match_next(&Token::Comma).is_some() {
+                continue;
+            }
+            if self.match_next(&Token::RightBracket).is_some() {
+                break;
+            }
+            attributes.push(self.standard_attribute()?);
+            if self.match_next(&Token::Comma).is_none() {
+                self.expect(Token::RightBracket)?;
+                break;
+            }
+        }
+        self.expect(Token::RightBracket)?;
+        Ok(attributes)
+    }
+
+    /// ```yacc
+    /// standard_attribute
+    /// : attribute_token
+    /// | attribute_token '(' balanced_token* ')'
+    /// ;
+    ///
+    /// attribute_token
+    /// : attribute_name
+    /// | attribute_name ':' ':' attribute_name
+    /// ;
+    /// ```
+    ///
+    /// The arguments of attributes in the `gnu` namespace, or without a namespace,
+    /// are parsed the same as GNU attributes.
+    /// Attributes in other namespaces can have any balanced sequence of tokens as arguments,
+    /// which are discarded.
+    fn standard_attribute(&mut self) -> SyntaxResult<Locatable<ast::Attribute>> {
+        let (mut name, mut location) = self.attribute_name()?;
+        if self.peek_token() == Some(&Token::Colon) && self.peek_next_token() == Some(&Token::Colon)
+        {
+            self.next_token();
+            self.next_token();
+            let (suffix, suffix_location) = self.attribute_name()?;
+            location = location.merge(suffix_location);
+            // [[gnu::aligned(16)]] is the same as __attribute__((aligned(16)))
+            if name != "gnu".into() {
+                // [[clang::availability(macos, introduced=10.4)]]
+                if self.peek_token() == Some(&Token::LeftParen) {
+                    location = location.merge(self.balanced_tokens()?);
+                }
+                let name = format!("{}::{}", name, suffix).into();
+                let attr = ast::Attribute {
+                    name,
+                    args: Vec::new(),
+                };
+                return Ok(Locatable::new(attr, location));
+            }
+            name = suffix;
+        }
+        let args = self.attribute_arguments(&mut location)?;
+        Ok(Locatable::new(ast::Attribute { name, args }, location))
+    }
+
+    /// Skip a parenthesized sequence of tokens, returning the location of the closing `)`.
+    ///
+    /// Parentheses, brackets, and braces in the sequence must be balanced.
+    fn balanced_tokens(&mut self) -> SyntaxResult<Location> {
+        let mut depth = 0_usize;
+        loop {
+            let token = match self.next_token() {
+                Some(token) => token,
+                None => {
+                    let err = SyntaxError::EndOfFile("attribute arguments");
+                    return Err(self.last_location.with(err));
+                }
+            };
+            match token.data {
+                Token::LeftParen | Token::LeftBracket | Token::LeftBrace => depth += 1,
+                Token::RightParen | Token::RightBracket | Token::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(token.location);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// ```yacc
+    /// attribute_name: IDENTIFIER | keyword ;
+    /// ```
+    ///
+    /// Surrounding double underscores are removed.
+    fn attribute_name(&mut self) -> SyntaxResult<(InternedStr, Location)> {
+        let (name, location) = match self.next_token() {
+            Some(Locatable {
+                data: Token::Id(name),
+                location,
+            })
+            | Some(Locatable {
+                data: Token::Keyword(Keyword::UserTypedef(name)),
+                location,
+            }) => (name, location),
+            // __attribute__((const))
+            Some(Locatable {
+                data: Token::Keyword(keyword),
+                location,
+            }) => (keyword.to_string().into(), location),
+            Some(other) => {
+                let err = format!("expected attribute name, got '{}'", other.data);
+                return Err(other.location.with(SyntaxError::Generic(err)));
+            }
+            None => {
+                let err = SyntaxError::EndOfFile("attribute name");
+                return Err(self.last_location.with(err));
+            }
+        };
+        let name = ast::Attribute::normalize_name(&name.resolve_and_clone()).into();
+        Ok((name, location))
+    }
+
+    /// The optional arguments to an attribute: the `(16)` in `aligned(16)`.
+    fn attribute_arguments(&mut self, location: &mut Location) -> SyntaxResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.match_next(&Token::LeftParen).is_some() {
+            if self.peek_token() != Some(&Token::RightParen) {
+                loop {
+                    args.push(self.assignment_expr()?);
+                    if self.match_next(&Token::Comma).is_none() {
+                        break;
+                    }
+                }
+            }
+            *location = location.merge(self.expect(Token::RightParen)?.location);
+        }
+        Ok(args)
+    }
+
     fn init_declarator(&mut self) -> SyntaxResult<Locatable<ast::InitDeclarator>> {
-        let decl = self.declarator(false)?;
+        let mut decl = self.declarator(false)?;
+        let asm_label = self.asm_label()?;
+        if asm_label.is_some() {
+            // int f(void) __asm__("g") __attribute__((nothrow));
+            let mut attributes = self.attributes()?;
+            if let Some(decl) = &mut decl {
+                decl.data.attributes.append(&mut attributes);
+            }
+        }
         let init = if self.match_next(&Token::EQUAL).is_some() {
-            Some(self.initializer()?)
+            match self.initializer() {
+                Ok(init) => Some(init),
+                // `int i = 1 +;` still declares `i`, so later uses of it aren't errors too
+                Err(err) => {
+                    if !self.skip_initializer() {
+                        return Err(err);
+                    }
+                    self.error_handler.push_back(err);
+                    None
+                }
+            }
         } else {
             None
         };
@@ -358,8 +895,66 @@ impl<I: Lexer> Parser<I> {
         Ok(decl.map(|d| ast::InitDeclarator {
             declarator: InternalDeclarator::parse_declarator(d),
             init,
+            asm_label,
         }))
     }
+    /// Skip the rest of an initializer that had a syntax error.
+    ///
+    /// Returns whether this found the `,` or `;` that ends the initializer,
+    /// which is left as the next token.
+    fn skip_initializer(&mut self) -> bool {
+        let mut depth = 0_usize;
+        while let Some(token) = self.peek_token() {
+            match token {
+                Token::Comma | Token::Semicolon if depth == 0 => return true,
+                Token::LeftParen | Token::LeftBracket | Token::LeftBrace => depth += 1,
+                // this closes something that was opened before the error
+                Token::RightParen | Token::RightBracket | Token::RightBrace if depth == 0 => {
+                    return false
+                }
+                Token::RightParen | Token::RightBracket | Token::RightBrace => depth -= 1,
+                _ => {}
+            }
+            self.next_token();
+        }
+        false
+    }
+    /// ```yacc
+    /// asm_label
+    /// : '__asm__' '(' STRING_LITERAL ')'
+    /// ;
+    /// ```
+    ///
+    /// Plain `asm` is only a keyword with `-std=gnu11`.
+    fn asm_label(&mut self) -> SyntaxResult<Option<String>> {
+        let gnu = self.gnu;
+        let is_asm = match self.peek_token() {
+            Some(Token::Keyword(Keyword::Asm)) => true,
+            Some(Token::Id(id)) => gnu && get_str!(id) == "asm",
+            _ => false,
+        };
+        if !is_asm {
+            return Ok(None);
+        }
+        self.next_token();
+        self.expect(Token::LeftParen)?;
+        let expected_string = || SyntaxError::Generic("expected string literal".into());
+        let literal = match self.match_literal() {
+            Some(literal) => literal,
+            None => return Err(self.next_location().with(expected_string())),
+        };
+        let label = match literal.data.parse() {
+            Ok(LiteralValue::Str(mut label)) => {
+                // remove the null terminator
+                label.pop();
+                String::from_utf8_lossy(&label).into_owned()
+            }
+            Ok(_) => return Err(literal.location.with(expected_string())),
+            Err(err) => return Err(literal.location.with(err)),
+        };
+        self.expect(Token::RightParen)?;
+        Ok(Some(label))
+    }
 
     fn merge_decls(
         current: Locatable<InternalDeclaratorType>,
@@ -370,12 +965,14 @@ impl<I: Lexer> Parser<I> {
             let decl = InternalDeclarator {
                 current: current.data,
                 next: Some(Box::new(next.data)),
+                attributes: Vec::new(),
             };
             Locatable::new(decl, location)
         } else {
             current.map(|data| InternalDeclarator {
                 current: data,
                 next: None,
+                attributes: Vec::new(),
             })
         }
     }
@@ -391,21 +988,32 @@ impl<I: Lexer> Parser<I> {
         // anything to the left of a `Function` represents the return type
         // anything to the right represents a declarator with higher precedence
         // the `Id` should always be the last declarator in the Vec
+        //
+        // GNU attributes can appear anywhere in the declarator; they all apply to the declared entity.
+        // int i, __attribute__((unused)) j;
+        let mut attributes = self.attributes()?;
         while let Some(Locatable { mut location, .. }) = self.match_next(&Token::Star) {
             let mut qualifiers = Vec::new();
             // *const volatile p
-            while let Some(Locatable {
-                location: keyword_loc,
-                data: Token::Keyword(keyword),
-            }) = self.match_any(&[
-                &Token::Keyword(Keyword::Const),
-                &Token::Keyword(Keyword::Volatile),
-                &Token::Keyword(Keyword::Restrict),
-                &Token::Keyword(Keyword::Atomic),
-                &Token::Keyword(Keyword::ThreadLocal),
-            ]) {
-                location = location.merge(keyword_loc);
-                qualifiers.push(keyword.try_into().unwrap());
+            loop {
+                // *__attribute__((unused)) p
+                attributes.append(&mut self.attributes()?);
+                match self.match_any(&[
+                    &Token::Keyword(Keyword::Const),
+                    &Token::Keyword(Keyword::Volatile),
+                    &Token::Keyword(Keyword::Restrict),
+                    &Token::Keyword(Keyword::Atomic),
+                    &Token::Keyword(Keyword::ThreadLocal),
+                ]) {
+                    Some(Locatable {
+                        location: keyword_loc,
+                        data: Token::Keyword(keyword),
+                    }) => {
+                        location = location.merge(keyword_loc);
+                        qualifiers.push(keyword.try_into().unwrap());
+                    }
+                    _ => break,
+                }
             }
             let current = Locatable::new(InternalDeclaratorType::Pointer { qualifiers }, location);
             pointer_decls.push(current);
@@ -414,6 +1022,13 @@ impl<I: Lexer> Parser<I> {
         while let Some(pointer) = pointer_decls.pop() {
             decl = Some(Self::merge_decls(pointer, decl));
         }
+        // int i __attribute__((aligned(16)));
+        attributes.append(&mut self.attributes()?);
+        // abstract declarators don't declare anything, so there's nothing to apply these to:
+        // `int f(int __attribute__((unused)));`
+        if let Some(decl) = &mut decl {
+            decl.data.attributes.append(&mut attributes);
+        }
         Ok(decl)
     }
     /*
@@ -474,6 +1089,7 @@ impl<I: Lexer> Parser<I> {
                 Token::Id(id) => InternalDeclarator {
                     current: InternalDeclaratorType::Id(id),
                     next: None,
+                    attributes: Vec::new(),
                 },
                 _ => panic!("peek() should always return the same thing as next()"),
             })),
@@ -500,6 +1116,7 @@ impl<I: Lexer> Parser<I> {
                                 varargs: false,
                             },
                             next: None,
+                            attributes: Vec::new(),
                         };
                         Some(Locatable::new(decl, left_paren.merge(right_paren)))
                     }
@@ -521,12 +1138,10 @@ impl<I: Lexer> Parser<I> {
             }
             _ if allow_abstract => None,
             Some(x) => {
-                let err = Err(Locatable::new(
+                return Err(Locatable::new(
                     SyntaxError::Generic(format!("expected variable name or '(', got '{}'", x)),
                     self.next_location(),
                 ));
-                self.panic();
-                return err;
             }
             None => {
                 return Err(self.next_location().with(SyntaxError::Generic(
@@ -554,10 +1169,19 @@ impl<I: Lexer> Parser<I> {
         mut prefix: Option<Locatable<InternalDeclarator>>,
         allow_abstract: bool,
     ) -> SyntaxResult<Option<Locatable<InternalDeclarator>>> {
-        while let Some(data) = self.peek_token() {
-            let current = match data {
+        loop {
+            // int f(void) [[unsequenced]];
+            if self.is_standard_attribute() {
+                let mut attributes = self.standard_attribute_list()?;
+                // abstract declarators don't declare anything, so there's nothing to apply these to
+                if let Some(prefix) = &mut prefix {
+                    prefix.data.attributes.append(&mut attributes);
+                }
+                continue;
+            }
+            let current = match self.peek_token() {
                 // Array; Specified in section 6.7.6.2 of the C11 spec
-                Token::LeftBracket => {
+                Some(Token::LeftBracket) => {
                     self.expect(Token::LeftBracket).unwrap();
                     if let Some(token) = self.match_next(&Token::Keyword(Keyword::Static)) {
                         if !allow_abstract {
@@ -576,7 +1200,7 @@ impl<I: Lexer> Parser<I> {
                         };
                     Locatable::new(InternalDeclaratorType::Array { size }, location)
                 }
-                Token::LeftParen => self.parameter_type_list()?,
+                Some(Token::LeftParen) => self.parameter_type_list()?,
                 _ => break,
             };
             prefix = Some(Self::merge_decls(current, prefix))
@@ -658,17 +1282,21 @@ impl<I: Lexer> Parser<I> {
 
     // handle char[][3] = {{1,2,3}}, but also = {1,2,3} and {{1}, 2, 3}
     // NOTE: this does NOT consume {} except for sub-elements
-    fn aggregate_initializer(&mut self) -> SyntaxResult<Initializer> {
+    pub(super) fn aggregate_initializer(&mut self) -> SyntaxResult<Initializer> {
         let _guard = self.recursion_check();
         let mut elems = vec![];
         while self.match_next(&Token::RightBrace).is_none() {
-            let next = if self.match_next(&Token::LeftBrace).is_some() {
+            let designators = self.designation()?;
+            let next = if let Some(bytes) = self.match_embed() {
+                // unsigned char data[] = { #embed "data.bin" };
+                Initializer::Embed(bytes)
+            } else if self.match_next(&Token::LeftBrace).is_some() {
                 self.aggregate_initializer()?
             } else {
                 // scalar
                 self.initializer()?
             };
-            elems.push(next);
+            elems.push((designators, next));
             // NOTE: this allows trailing commas
             if self.match_next(&Token::Comma).is_none() {
                 self.expect(Token::RightBrace)?;
@@ -677,6 +1305,51 @@ impl<I: Lexer> Parser<I> {
         }
         Ok(Initializer::Aggregate(elems))
     }
+
+    /// Match the bytes from an `#embed` directive if they make up whole initializers,
+    /// i.e. they are followed by `,` or `}`.
+    fn match_embed(&mut self) -> Option<Locatable<Vec<u8>>> {
+        if !matches!(self.peek_token(), Some(Token::Embed(_)))
+            || !matches!(
+                self.peek_next_token(),
+                Some(Token::Comma) | Some(Token::RightBrace)
+            )
+        {
+            return None;
+        }
+        match self.next_token() {
+            Some(Locatable {
+                data: Token::Embed(bytes),
+                location,
+            }) => Some(location.with(bytes)),
+            _ => unreachable!("peek_token should match next_token"),
+        }
+    }
+
+    // designation: designator_list '='
+    // designator_list: designator+
+    // designator: '[' constant_expression ']' | '.' IDENTIFIER
+    // <http://www.quut.com/c/ANSI-C-grammar-y.html#designation>
+    //
+    // returns an empty list if there is no designation
+    fn designation(&mut self) -> SyntaxResult<Vec<Designator>> {
+        let mut designators = vec![];
+        loop {
+            if self.match_next(&Token::LeftBracket).is_some() {
+                let index = self.ternary_expr()?;
+                self.expect(Token::RightBracket)?;
+                designators.push(Designator::Index(index));
+            } else if self.match_next(&Token::Dot).is_some() {
+                designators.push(Designator::Member(self.expect_id()?));
+            } else {
+                break;
+            }
+        }
+        if !designators.is_empty() {
+            self.expect(Token::EQUAL)?;
+        }
+        Ok(designators)
+    }
 }
 
 impl InternalDeclarator {
@@ -686,8 +1359,10 @@ impl InternalDeclarator {
 
         let mut id = None;
         let mut current = DeclaratorType::End;
+        let mut attributes = Vec::new();
         let mut declarator = Some(self);
-        while let Some(decl) = declarator {
+        while let Some(mut decl) = declarator {
+            attributes.append(&mut decl.attributes);
             current = match decl.current {
                 Id(i) => {
                     id = Some(i);
@@ -709,7 +1384,11 @@ impl InternalDeclarator {
             };
             declarator = decl.next.map(|x| *x);
         }
-        Declarator { decl: current, id }
+        Declarator {
+            decl: current,
+            id,
+            attributes,
+        }
     }
 }
 
@@ -732,9 +1411,9 @@ impl TryFrom<Keyword> for DeclarationSpecifier {
         change_enum!(k, Keyword, DeclarationSpecifier,
             Const, Volatile, Restrict, Atomic, ThreadLocal,
             Unsigned, Signed,
-            Bool, Char, Short, Int, Long, Float, Double, Void,
+            Bool, Char, Short, Int, Long, Int128, Float, Double, Void,
             Complex, Imaginary, VaList,
-            Extern, Static, Auto, Register, Typedef,
+            Extern, Static, Auto, Register, Typedef, Constexpr,
             Inline, NoReturn,
         )
     }
@@ -755,17 +1434,23 @@ impl Keyword {
         use Keyword::*;
         match self {
             // type specifier
-            Unsigned | Signed | Bool | Char | Short | Int | Long | Float | Double | Void
+            Unsigned | Signed | Bool | Char | Short | Int | Long | Int128 | Float | Double | Void
             // complex type specifier
             | Struct | Union | Enum | VaList | Complex | Imaginary
             // user-defined type
             | UserTypedef(_)
             // storage class
-            | Extern | Static | Auto | Register | Typedef
+            | Extern | Static | Auto | Register | Typedef | Constexpr
             // qualifier
             | Const | Volatile | Restrict | Atomic | ThreadLocal
             // function qualifier
-            | Inline | NoReturn => true,
+            | Inline | NoReturn
+            // alignment specifier
+            | Alignas
+            // C23 typeof specifier
+            | Typeof | TypeofUnqual
+            // GNU attributes
+            | Attribute => true,
             _ => false,
         }
     }
@@ -775,10 +1460,17 @@ impl Keyword {
 pub(crate) mod test {
     use crate::data::ast::*;
     use crate::data::*;
+    use crate::lex::PreProcessor;
     use crate::parse::test::*;
+    use crate::Parser;
 
     fn decl(decl: &str) -> CompileResult<Locatable<ExternalDeclaration>> {
-        let mut p = parser(decl);
+        decl_with(parser(decl))
+    }
+    fn c23_decl(decl: &str) -> CompileResult<Locatable<ExternalDeclaration>> {
+        decl_with(c23_parser(decl))
+    }
+    fn decl_with(mut p: Parser<PreProcessor>) -> CompileResult<Locatable<ExternalDeclaration>> {
         let exp = p.external_declaration();
         if let Some(err) = p.error_handler.pop_front() {
             Err(err)
@@ -821,6 +1513,123 @@ pub(crate) mod test {
         assert_same("int a[10 + 1] = 1;", "int a[(10) + (1)] = 1;");
     }
     #[test]
+    fn test_designated_initializers() {
+        assert_no_change("int a[] = { [1] = 2, 3, [0] = 1 };");
+        assert_no_change("struct s s = { .a = 1, .b.c[(1) + (2)] = { 3 } };");
+        assert_same("int a[] = {[1]=2,};", "int a[] = { [1] = 2 };");
+        assert!(decl("int a[] = { [1] 2 };").is_err());
+        assert!(decl("int a[] = { [] = 2 };").is_err());
+        assert!(decl("struct s s = { .a. = 2 };").is_err());
+    }
+    #[test]
+    fn test_alignas() {
+        assert_no_change("_Alignas(16) int i;");
+        assert_no_change("char _Alignas((4) * (2)) c;");
+        match decl("_Alignas(double) char c;").unwrap().data {
+            ExternalDeclaration::Declaration(decl) => assert!(matches!(
+                decl.specifiers[0],
+                DeclarationSpecifier::AlignasType(_)
+            )),
+            other => panic!("expected a declaration, got {:?}", other),
+        }
+        assert!(decl("_Alignas int i;").is_err());
+    }
+    #[test]
+    fn test_atomic() {
+        assert_no_change("_Atomic(int) i;");
+        assert_no_change("_Atomic(unsigned long) u;");
+        assert_no_change("_Atomic int i;");
+        match decl("_Atomic(long) l;").unwrap().data {
+            ExternalDeclaration::Declaration(decl) => assert!(matches!(
+                decl.specifiers[0],
+                DeclarationSpecifier::AtomicType(_)
+            )),
+            other => panic!("expected a declaration, got {:?}", other),
+        }
+        assert!(decl("_Atomic() i;").is_err());
+    }
+    #[test]
+    fn test_typeof() {
+        let assert_no_change = |s: &str| assert_eq!(c23_decl(s).unwrap().data.to_string(), s);
+        assert_no_change("typeof(int) i;");
+        assert_no_change("typeof_unqual(const int (*)) p;");
+        assert_no_change("typeof(1) i;");
+        assert_no_change("typeof(nullptr) n;");
+        assert_no_change("constexpr int i = 1;");
+        match c23_decl("typeof(i) j;").unwrap().data {
+            ExternalDeclaration::Declaration(decl) => assert!(matches!(
+                decl.specifiers[0],
+                DeclarationSpecifier::TypeofExpr { unqual: false, .. }
+            )),
+            other => panic!("expected a declaration, got {:?}", other),
+        }
+        assert!(c23_decl("typeof() i;").is_err());
+        assert!(c23_decl("typeof int i;").is_err());
+    }
+    #[test]
+    fn test_attributes() {
+        assert_no_change("__attribute__((noreturn)) void f();");
+        assert_no_change("int i __attribute__((aligned(16)));");
+        assert_no_change("int i __attribute__((unused)), j;");
+        assert_no_change("char (*s) __attribute__((section(\"data\")));");
+        // attributes in the specifiers apply to every declarator, so they stay there
+        assert_no_change("int __attribute__((unused)) i;");
+        assert_same(
+            "int *__attribute__((unused)) const p;",
+            "int *const p __attribute__((unused));",
+        );
+        assert_same(
+            "int i __attribute__((__aligned__(16)));",
+            "int i __attribute__((aligned(16)));",
+        );
+        assert_same(
+            "int i __attribute__((unused, aligned(16)));",
+            "int i __attribute__((unused)) __attribute__((aligned(16)));",
+        );
+        assert_same(
+            "int f(const char *, ...) __attribute__((format(printf, 1, 2)));",
+            "int f(const char *, ...) __attribute__((format(printf, 1, 2)));",
+        );
+        assert!(
+            display("struct __attribute__((packed)) s { char c; int i; } s;")
+                .contains("__attribute__((packed))")
+        );
+        assert!(decl("int i __attribute__((aligned(16));").is_err());
+        assert!(decl("int i __attribute__((1));").is_err());
+        assert!(decl("int i __attribute__;").is_err());
+    }
+    #[test]
+    fn test_standard_attributes() {
+        assert_same(
+            "[[nodiscard]] int f();",
+            "__attribute__((nodiscard)) int f();",
+        );
+        assert_same(
+            "int i [[maybe_unused]];",
+            "int i __attribute__((maybe_unused));",
+        );
+        assert_same(
+            "int f(void) [[unsequenced]];",
+            "int f(void) __attribute__((unsequenced));",
+        );
+        assert_same(
+            "int a[2] [[maybe_unused]] [3];",
+            "int a[2][3] __attribute__((maybe_unused));",
+        );
+        assert_same(
+            "[[gnu::aligned(16), __deprecated__(\"no\")]] int i;",
+            "__attribute__((aligned(16), deprecated(\"no\"))) int i;",
+        );
+        assert_same("[[]] int i;", "int i;");
+        // the arguments of attributes in other namespaces are ignored
+        assert!(
+            display("[[clang::availability(macos, introduced=10.4, [x])]] int i;")
+                .contains("clang::availability))")
+        );
+        assert!(decl("[[nodiscard] int f();").is_err());
+        assert!(decl("[[gnu::]] int i;").is_err());
+    }
+    #[test]
     fn test_enum() {
         assert!(display("enum { A, B = 2, C };").contains("enum { A, B = 2, C }"));
         assert!(display("enum E { A, B = 2, C };").contains("enum E "));
@@ -832,6 +1641,55 @@ pub(crate) mod test {
         assert!(decl("struct s { int *; };").is_err());
     }
     #[test]
+    fn test_old_style_definition() {
+        assert_eq!(
+            display("int f(a, b) int a; double b; {}"),
+            display("int f(int a, double b) {}")
+        );
+        assert_eq!(
+            display("int f(a, b, c) double b, *c; {}"),
+            display("int f(a, double b, double *c) {}")
+        );
+        // no declaration list
+        assert!(display("int f(a) {}").starts_with("int f(a)"));
+        assert!(decl("int f(a) int b; {}").is_err());
+        assert!(decl("int f(a) int a; int a; {}").is_err());
+        assert!(decl("int f(a) int a = 1; {}").is_err());
+        assert!(decl("int f(a) int a;").is_err());
+    }
+    #[test]
+    fn test_static_assert() {
+        assert_no_change("_Static_assert(1, \"message\");");
+        assert_no_change("_Static_assert(1);");
+        assert_display(
+            "_Static_assert(1, \"split \" \"message\");",
+            "_Static_assert(1, \"split message\");",
+        );
+        assert!(decl("_Static_assert(1, 2);").is_err());
+        assert!(decl("_Static_assert(1, );").is_err());
+        assert!(decl("_Static_assert(1, \"no semicolon\")").is_err());
+        assert!(decl("struct s { _Static_assert(1); int i; };").is_ok());
+        assert!(decl("struct s { int i; _Static_assert(1) };").is_err());
+    }
+    #[test]
+    fn test_asm_label() {
+        assert_no_change("int f() __asm__(\"g\");");
+        assert_no_change("extern int i __asm__(\"j\"), k;");
+        assert_display("int f() __asm(\"g\");", "int f() __asm__(\"g\");");
+        assert_same(
+            "int f() __asm__(\"g\") __attribute__((noreturn));",
+            "int f() __attribute__((noreturn)) __asm__(\"g\");",
+        );
+        assert!(decl("int f() __asm__(\"g\") { return 0; }").is_err());
+        assert!(decl("int f() __asm__(g);").is_err());
+        assert!(decl("int f() __asm__(\"g\";").is_err());
+        // plain `asm` is only reserved by GNU C
+        assert!(decl("int f() asm(\"g\");").is_err());
+        let mut p = parser("int f() asm(\"g\");").gnu_extensions(true);
+        let decl = p.external_declaration().unwrap();
+        assert_eq!(decl.data.to_string(), "int f() __asm__(\"g\");");
+    }
+    #[test]
     fn test_cursed_function_declarator() {
         let decl = parser("f(())")
             .declarator(false)
@@ -1,8 +1,8 @@
 use std::convert::TryFrom;
 
 use super::*;
-use crate::data::ast::{Expr, ExprType, TypeName};
-use crate::data::lex::{AssignmentToken, Keyword};
+use crate::data::ast::{Expr, ExprType, GenericAssociation, TypeName};
+use crate::data::lex::{AssignmentToken, AtomicOp, Keyword};
 use crate::data::*;
 
 trait UnaryExprFn: FnOnce(Expr) -> ExprType {}
@@ -156,6 +156,15 @@ impl<I: Lexer> Parser<I> {
                 // | logical_or_expression '?' expression ':' conditional_expression
                 // ;
                 // <http://www.quut.com/c/ANSI-C-grammar-y.html#conditional_expression>
+                // GNU extension: `x ?: y` means `x ? x : y`, but only evaluates `x` once
+                if let Some(colon) = self.match_next(&Token::Colon) {
+                    self.gnu_extension("conditionals with an omitted operand", colon.location);
+                    let right_start = self.unary_expr()?;
+                    let right = self.binary_expr(right_start, BinaryPrecedence::Ternary.prec())?;
+                    let location = left.location.merge(right.location);
+                    left = Expr::new(ExprType::Elvis(Box::new(left), Box::new(right)), location);
+                    continue;
+                }
                 let inner = self.expr()?;
                 self.expect(Token::Colon)?;
                 let right_start = self.unary_expr()?;
@@ -197,10 +206,21 @@ impl<I: Lexer> Parser<I> {
     // this takes the place of `unary_expr` in the yacc grammar
     fn unary_expr(&mut self) -> SyntaxResult<Expr> {
         // prefix expressions
-        let mut prefixes = Vec::new();
+        let mut prefixes: Vec<(Box<dyn UnaryExprFn>, Location)> = Vec::new();
         // hack: `sizeof` can be either a unary or primary expression, so we special-case it
         let mut inner = loop {
-            if let Some(Locatable {
+            if let Some(ctype) = self.parenthesized_type()? {
+                // `(int){1}` is a postfix expression, not a cast
+                if self.match_next(&Token::LeftBrace).is_some() {
+                    break self.compound_literal(ctype)?;
+                }
+                // (int)x
+                let loc = ctype.location;
+                prefixes.push((
+                    Box::new(move |expr| ExprType::Cast(ctype.data, Box::new(expr))),
+                    loc,
+                ));
+            } else if let Some(Locatable {
                 data: constructor,
                 location,
             }) = self.match_prefix_operator()
@@ -211,6 +231,17 @@ impl<I: Lexer> Parser<I> {
             {
                 // `sizeof(int)` is a primary expr
                 if let Some(mut ctype) = self.parenthesized_type()? {
+                    // `sizeof (int[]){1, 2}` is the size of a compound literal
+                    if self.match_next(&Token::LeftBrace).is_some() {
+                        let constructor = if keyword.data == Keyword::Sizeof {
+                            ExprType::SizeofExpr
+                        } else {
+                            ExprType::AlignofExpr
+                        };
+                        let literal = self.compound_literal(ctype)?;
+                        let location = keyword.location.merge(literal.location);
+                        break location.with(constructor(Box::new(literal)));
+                    }
                     ctype.location = keyword.location.merge(ctype.location);
                     let constructor = if keyword.data == Keyword::Sizeof {
                         ExprType::SizeofType
@@ -249,20 +280,51 @@ impl<I: Lexer> Parser<I> {
     fn primary_expr(&mut self) -> SyntaxResult<Expr> {
         // primary expression
         // this must be an expression since we already consumed all the prefix expressions
+        // `return (\n#embed "answer.bin"\n);`
+        if let Some(Token::Embed(_)) = self.peek_token() {
+            self.expand_embed();
+        }
         let primary = if let Some(paren) = self.match_next(&Token::LeftParen) {
             // take out lots of guards since there's a lot of indirection
             let _guard = self.recursion_check();
             let _guard2 = self.recursion_check();
+            if self.peek_token() == Some(&Token::LeftBrace) {
+                let expr = self.statement_expr(paren.location)?;
+                return self.postfix_expr(expr);
+            }
             let mut inner = self.expr()?;
             let end_loc = self.expect(Token::RightParen)?.location;
             inner.location = paren.location.merge(&end_loc);
             inner
+        } else if let Some(kw) = self.match_keywords(&[Keyword::Generic]) {
+            self.generic_selection(kw.location)?
+        } else if let Some(kw) = self.match_keywords(&[Keyword::BuiltinComplex]) {
+            self.builtin_complex(kw.location)?
+        } else if let Some(kw) = self.match_keywords(&[Keyword::Nullptr]) {
+            kw.location.with(ExprType::Nullptr)
+        } else if let Some(and) = self.match_next(&Token::LogicalAnd) {
+            self.label_address(and.location)?
+        } else if let Some(&Token::Keyword(Keyword::AtomicBuiltin(op))) = self.peek_token() {
+            let start = self.next_token().unwrap().location;
+            self.atomic_builtin(op, start)?
         } else if let Some(loc) = self.match_id() {
             loc.map(ExprType::Id)
         } else if let Some(literal) = self.match_literal() {
             let loc = literal.location;
+            let imaginary = literal.data.is_imaginary();
+            let wrap = if literal.data.is_long_long_or_double() {
+                ExprType::LongLiteral
+            } else {
+                ExprType::Literal
+            };
             match literal.data.parse() {
-                Ok(literal) => loc.with(literal).map(ExprType::Literal),
+                // `2.0i` is the same as `__builtin_complex(0.0, 2.0)`
+                Ok(literal) if imaginary => {
+                    let zero = loc.with(wrap(LiteralValue::Float(0.0)));
+                    let imag = loc.with(wrap(literal));
+                    loc.with(ExprType::BuiltinComplex(Box::new(zero), Box::new(imag)))
+                }
+                Ok(literal) => loc.with(wrap(literal)),
                 Err(err) => return Err(loc.with(err)),
             }
         } else {
@@ -271,6 +333,98 @@ impl<I: Lexer> Parser<I> {
         self.postfix_expr(primary)
     }
 
+    // GNU statement expression: `({ int x = f(); x * x; })`
+    //
+    // `(` has already been consumed, `start` is where we saw it
+    fn statement_expr(&mut self, start: Location) -> SyntaxResult<Expr> {
+        let stmts = self.compound_statement()?.data;
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(start.merge(end).with(ExprType::StmtExpr(stmts)))
+    }
+
+    // GNU address of a label: `&&label`
+    //
+    // `&&` has already been consumed, `start` is where we saw it
+    fn label_address(&mut self, start: Location) -> SyntaxResult<Expr> {
+        self.gnu_extension("label addresses", start);
+        let label = self.expect_id()?;
+        Ok(start
+            .merge(label.location)
+            .with(ExprType::LabelAddress(label.data)))
+    }
+
+    // generic_selection: GENERIC '(' assignment_expression ',' generic_assoc_list ')'
+    // generic_assoc_list: generic_association (',' generic_association)*
+    // generic_association: type_name ':' assignment_expression
+    //                    | DEFAULT ':' assignment_expression
+    // <http://www.quut.com/c/ANSI-C-grammar-y.html#generic_selection>
+    //
+    // `_Generic` has already been consumed
+    fn generic_selection(&mut self, start: Location) -> SyntaxResult<Expr> {
+        self.expect(Token::LeftParen)?;
+        let controlling = self.assignment_expr()?;
+        self.expect(Token::Comma)?;
+        let mut associations = Vec::new();
+        loop {
+            let ctype = if self.match_keywords(&[Keyword::Default]).is_some() {
+                None
+            } else {
+                Some(self.type_name()?)
+            };
+            self.expect(Token::Colon)?;
+            let expr = self.assignment_expr()?;
+            associations.push(GenericAssociation { ctype, expr });
+            if self.match_next(&Token::Comma).is_none() {
+                break;
+            }
+        }
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(start
+            .merge(end)
+            .with(ExprType::Generic(Box::new(controlling), associations)))
+    }
+
+    // builtin_complex: BUILTIN_COMPLEX '(' assignment_expression ',' assignment_expression ')'
+    //
+    // `__builtin_complex` has already been consumed
+    fn builtin_complex(&mut self, start: Location) -> SyntaxResult<Expr> {
+        self.expect(Token::LeftParen)?;
+        let real = self.assignment_expr()?;
+        self.expect(Token::Comma)?;
+        let imag = self.assignment_expr()?;
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(start
+            .merge(end)
+            .with(ExprType::BuiltinComplex(Box::new(real), Box::new(imag))))
+    }
+
+    // atomic_builtin: ATOMIC_BUILTIN '(' assignment_expression (',' assignment_expression)* ')'
+    //
+    // the builtin has already been consumed; the analyzer checks the number of arguments
+    fn atomic_builtin(&mut self, op: AtomicOp, start: Location) -> SyntaxResult<Expr> {
+        self.expect(Token::LeftParen)?;
+        let mut args = vec![self.assignment_expr()?];
+        while self.match_next(&Token::Comma).is_some() {
+            args.push(self.assignment_expr()?);
+        }
+        let end = self.expect(Token::RightParen)?.location;
+        Ok(start.merge(end).with(ExprType::AtomicBuiltin(op, args)))
+    }
+
+    // postfix_expression: '(' type_name ')' '{' initializer_list ','? '}'
+    // <http://www.quut.com/c/ANSI-C-grammar-y.html#postfix_expression>
+    //
+    // `(type_name) {` has already been consumed
+    fn compound_literal(&mut self, ctype: Locatable<TypeName>) -> SyntaxResult<Expr> {
+        let init = self.aggregate_initializer()?;
+        let location = ctype.location.merge(self.last_location);
+        let literal = location.with(ExprType::CompoundLiteral(
+            Box::new(ctype.data),
+            Box::new(init),
+        ));
+        self.postfix_expr(literal)
+    }
+
     // `expr` should be a primary expression
     fn postfix_expr(&mut self, mut expr: Expr) -> SyntaxResult<Expr> {
         // fortunately, all postfix expressions have the same precedence
@@ -285,19 +439,9 @@ impl<I: Lexer> Parser<I> {
         Ok(expr)
     }
 
-    // '(' TYPE_NAME ')' | '*' | '~' | '!' | '+' | '-' | '&' | '++' | '--'
+    // '*' | '~' | '!' | '+' | '-' | '&' | '++' | '--'
+    // NOTE: casts are handled separately by `unary_expr` since they could also be compound literals
     fn match_prefix_operator(&mut self) -> Option<Locatable<Box<dyn UnaryExprFn>>> {
-        let maybe_type = self.parenthesized_type().unwrap_or_else(|err| {
-            self.error_handler.push_back(err);
-            None
-        });
-        if let Some(cast) = maybe_type {
-            let loc = cast.location;
-            return Some(Locatable::new(
-                Box::new(move |expr| ExprType::Cast(cast.data, Box::new(expr))),
-                loc,
-            ));
-        }
         // prefix operator
         let func = match self.peek_token()? {
             Token::Star => ExprType::Deref,
@@ -308,6 +452,8 @@ impl<I: Lexer> Parser<I> {
             Token::Ampersand => ExprType::AddressOf,
             Token::PlusPlus => |e| ExprType::PreIncrement(e, true),
             Token::MinusMinus => |e| ExprType::PreIncrement(e, false),
+            Token::Keyword(Keyword::Real) => ExprType::Real,
+            Token::Keyword(Keyword::Imag) => ExprType::Imag,
             _ => return None,
         };
         let loc = self.next_token().unwrap().location;
@@ -467,4 +613,78 @@ pub(crate) mod test {
         assert_same("sizeof(int)++", "(sizeof(int))++");
         assert_same("++sizeof(int)", "++(sizeof(int))");
     }
+    #[test]
+    fn compound_literal() {
+        assert_expr_display("(int){1}", "(int){ 1 }");
+        assert_same("(int){1}[0]", "((int){1})[0]");
+        assert_same("-(long){.x = 1}.x++", "-((((long){.x = 1}).x)++)");
+        assert_same("(int)(int){1}", "(int)((int){1})");
+        assert!(match expr("sizeof (int[]){1, 2}").unwrap().data {
+            ExprType::SizeofExpr(inner) => matches!(inner.data, ExprType::CompoundLiteral(..)),
+            _ => false,
+        });
+        assert!(expr("(int){1").is_err());
+    }
+    #[test]
+    fn generic() {
+        assert_expr_display(
+            "_Generic(x, int: 1, char *: 2, default: 3)",
+            "_Generic(x, int: 1, char (*): 2, default: 3)",
+        );
+        assert_expr_display("_Generic(1, int: f)(2)", "(_Generic(1, int: f))(2)");
+        assert_expr_display("_Generic(a = 1, int: b)", "_Generic((a) = (1), int: b)");
+        assert!(expr("_Generic(x)").is_err());
+        assert!(expr("_Generic(x, int 1)").is_err());
+        assert!(expr("_Generic(x, )").is_err());
+    }
+    #[test]
+    fn complex() {
+        assert_same("__real__ z + 1", "(__real__(z)) + 1");
+        assert_same("__imag__ z++", "__imag__(z++)");
+        assert_expr_display("__builtin_complex(1, x)", "__builtin_complex(1, x)");
+        assert!(expr("__builtin_complex(1)").is_err());
+    }
+    #[test]
+    fn embed() {
+        const STDARG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/headers/stdarg.h");
+        // outside of an initializer list, `#embed` is a comma-separated list of integers
+        let embedded = |params| format!("\n#embed \"{}\" {}\n", STDARG, params);
+        assert_same(&format!("({})", embedded("limit(3)")), "(35, 105, 102)");
+        assert_same(&format!("f({})", embedded("limit(2)")), "f(35, 105)");
+        assert_same(&format!("1 + {}", embedded("limit(1)")), "1 + 35");
+    }
+    #[test]
+    fn statement_expression() {
+        assert_expr_display("({ int x = 1; x; })", "({ int x = 1; x; })");
+        assert_expr_display("({ f(); }) + 1", "(({ (f)(); })) + (1)");
+        assert_expr_display("({})", "({ })");
+        assert_same("({ 1; }).x", "(({ 1; })).x");
+        assert!(expr("({ 1; }").is_err());
+        // errors inside the block are recovered from, so they go to the error handler
+        let mut p = parser("({ 1 })");
+        let _ = p.expr();
+        assert!(p.error_handler.pop_front().is_some());
+    }
+    #[test]
+    fn gnu_extensions() {
+        let gnu_expr = |e: &str| {
+            let mut p = parser(e).gnu_extensions(true);
+            let expr = p.expr().unwrap();
+            assert!(p.error_handler.pop_front().is_none());
+            expr.to_string()
+        };
+        assert_eq!(gnu_expr("x ?: y"), "(x) ?: (y)");
+        assert_eq!(gnu_expr("a ?: b ?: c"), "(a) ?: ((b) ?: (c))");
+        assert_eq!(gnu_expr("a || b ?: c"), "((a) || (b)) ?: (c)");
+        assert_eq!(gnu_expr("&&done"), "&&done");
+        assert_eq!(gnu_expr("a && &&done"), "(a) && (&&done)");
+        assert!(parser("&&1").gnu_extensions(true).expr().is_err());
+
+        // without `-std=gnu11`, these still parse but give an error
+        for input in &["x ?: y", "&&done"] {
+            let mut p = parser(input);
+            assert!(p.expr().is_ok());
+            assert!(p.error_handler.pop_front().is_some());
+        }
+    }
 }
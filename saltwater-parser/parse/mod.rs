@@ -37,8 +37,12 @@ pub struct Parser<I: Lexer> {
     /// this was put here for declarations, so we know the difference between
     /// int (*x) and int (int), but there's probably a workaround
     next: Option<Locatable<Token>>,
+    /// tokens from an `#embed` directive that were split up by `expand_embed`
+    embedded: VecDeque<Locatable<Token>>,
     /// whether to debug each declaration
     debug: bool,
+    /// whether to accept GNU extensions (`-std=gnu11`)
+    gnu: bool,
     /// Internal API which makes it easier to return errors lazily
     error_handler: ErrorHandler,
     /// Internal API which prevents segfaults due to stack overflow
@@ -58,11 +62,18 @@ impl<I: Lexer> Parser<I> {
             last_location: Location::default(),
             current: None,
             next: None,
+            embedded: VecDeque::new(),
             debug,
+            gnu: false,
             error_handler: ErrorHandler::new(),
             recursion_guard: Default::default(),
         }
     }
+    /// Accept GNU extensions, such as case ranges and computed goto.
+    pub fn gnu_extensions(mut self, enabled: bool) -> Self {
+        self.gnu = enabled;
+        self
+    }
     /// Return whether this parser has fully finished parsing.
     ///
     /// This can be used if, for example, you call `parser.expr()`
@@ -124,6 +135,9 @@ impl<I: Lexer> Iterator for Parser<I> {
                     // there could be semantic errors that were reported in the meantime,
                     // so we can't just return this error (it might be in the wrong order)
                     self.error_handler.push_back(err);
+                    self.panic();
+                    // there are no blocks at the top level, so this `}` can't close anything
+                    self.match_next(&Token::RightBrace);
                 }
             }
         }
@@ -138,6 +152,9 @@ impl<I: Lexer> Parser<I> {
     // don't use this, use next_token instead
     // WARNING: this _cannot_ read or modify `self.current` or `self.next`
     fn __impl_next_token(&mut self) -> Option<Locatable<Token>> {
+        if let Some(token) = self.embedded.pop_front() {
+            return Some(token);
+        }
         loop {
             match self.tokens.next() {
                 Some(Ok(Locatable {
@@ -218,6 +235,36 @@ impl<I: Lexer> Parser<I> {
         }
         self.next.as_ref().map(|x| &x.data)
     }
+    /// If the next token is from an `#embed` directive,
+    /// split it up into the integer constants and commas it stands for.
+    ///
+    /// Initializer lists use `Token::Embed` directly,
+    /// but anywhere else it behaves the same as the expanded tokens.
+    fn expand_embed(&mut self) {
+        let (bytes, location) = match self.current.take() {
+            Some(Locatable {
+                data: Token::Embed(bytes),
+                location,
+            }) => (bytes, location),
+            other => {
+                self.current = other;
+                return;
+            }
+        };
+        let mut tokens = VecDeque::with_capacity(bytes.len() * 2);
+        for (i, byte) in bytes.into_iter().enumerate() {
+            if i != 0 {
+                tokens.push_back(location.with(Token::Comma));
+            }
+            let literal = LiteralToken::Int(arcstr::Substr::from(byte.to_string()));
+            tokens.push_back(location.with(Token::Literal(literal)));
+        }
+        self.current = tokens.pop_front();
+        // the expanded tokens come before any tokens that have already been looked at
+        tokens.extend(self.next.take());
+        tokens.append(&mut self.embedded);
+        self.embedded = tokens;
+    }
     fn next_location(&self) -> Location {
         if let Some(token) = &self.current {
             token.location
@@ -273,55 +320,92 @@ impl<I: Lexer> Parser<I> {
         }
         None
     }
-    /*
-     * If we're in an invalid state, try to recover.
-     * Consume tokens until the end of a statement - either ';' or '}'
-     */
+    /// If we're in an invalid state, try to recover.
+    ///
+    /// Consume tokens until the end of the current statement or declaration:
+    /// either a `;` or a `}` that closes a block opened after the error.
+    /// This stops early before a `}` that closes an enclosing block,
+    /// so that the rest of the block can still be parsed,
+    /// and before a keyword that starts a new declaration or statement.
+    ///
+    /// At least one token is always consumed, unless the next token closes a block.
     fn panic(&mut self) {
-        while let Some(token) = self.next_token() {
-            match token.data {
-                Token::Semicolon => break,
+        let mut depth = 0_usize;
+        let mut skipped = false;
+        while let Some(token) = self.peek_token() {
+            match token {
+                Token::Semicolon if depth == 0 => {
+                    self.next_token();
+                    return;
+                }
+                Token::Keyword(keyword)
+                    if depth == 0 && skipped && keyword.can_start_statement() =>
+                {
+                    return;
+                }
+                Token::LeftParen | Token::LeftBracket | Token::LeftBrace => depth += 1,
+                Token::RightParen | Token::RightBracket => depth = depth.saturating_sub(1),
                 Token::RightBrace => {
-                    break;
+                    // `int a[] = { 1 2 };` and `struct s { ... } s;` don't end a block
+                    let ends_block = !matches!(
+                        self.peek_next_token(),
+                        Some(Token::Semicolon) | Some(Token::Comma)
+                    );
+                    if depth == 0 && ends_block {
+                        return;
+                    }
+                    depth = depth.saturating_sub(1);
+                    // if (x y) { ... }
+                    if depth == 0 && ends_block {
+                        self.next_token();
+                        return;
+                    }
                 }
-                _ => continue,
-            };
+                _ => {}
+            }
+            self.next_token();
+            skipped = true;
         }
     }
     fn expect_id(&mut self) -> SyntaxResult<Locatable<InternedStr>> {
         if let Some(id) = self.match_id() {
             Ok(id)
         } else {
-            let err = Err(Locatable {
+            Err(Locatable {
                 data: SyntaxError::ExpectedId(self.peek_token().cloned()),
                 location: self.next_location(),
-            });
-            self.panic();
-            err
+            })
         }
     }
+    /// Consume the next token if it is the same kind as `next`, or return an error.
+    ///
+    /// If it's unambiguous that `next` was left out, like in `return 0 }`,
+    /// this reports the error and continues as if `next` had been there.
     fn expect(&mut self, next: Token) -> SyntaxResult<Locatable<Token>> {
         let token = match self.peek_token() {
             Some(t) => t,
             None => {
-                let err = Err(Locatable {
+                return Err(Locatable {
                     data: SyntaxError::Generic(format!("expected '{}', got '<end-of-file>'", next)),
                     // TODO: we don't actually want this, we want the end of the file
                     location: self.last_location,
                 });
-                self.panic();
-                return err;
             }
         };
         if token.same_kind(&next) {
-            Ok(self.next_token().unwrap())
+            return Ok(self.next_token().unwrap());
+        }
+        let missing = next.is_missing_before(token);
+        let err = Locatable {
+            data: SyntaxError::Generic(format!("expected '{}', got '{}'", next, token)),
+            location: self.next_location(),
+        };
+        if missing {
+            let location = err.location;
+            self.error_handler.push_back(err);
+            Ok(location.with(next))
         } else {
-            let err = Err(Locatable {
-                data: SyntaxError::Generic(format!("expected '{}', got '{}'", next, token)),
-                location: self.next_location(),
-            });
-            self.panic();
-            err
+            Err(err)
         }
     }
     /// - replace `self.current` with `item`
@@ -331,6 +415,15 @@ impl<I: Lexer> Parser<I> {
         assert!(self.next.is_none());
         self.next = mem::replace(&mut self.current, item);
     }
+    /// Report an error for `feature` unless GNU extensions are enabled.
+    ///
+    /// Parsing continues as if the extension were enabled.
+    fn gnu_extension(&mut self, feature: &'static str, location: Location) {
+        if !self.gnu {
+            let err = location.with(SyntaxError::GnuExtension(feature));
+            self.error_handler.push_back(err);
+        }
+    }
     fn lex_error(&mut self, err: CompileError) {
         self.error_handler.push_back(err);
     }
@@ -365,13 +458,36 @@ impl Token {
             _ => mem::discriminant(self) == mem::discriminant(other),
         }
     }
+    /// Whether `self` was unambiguously left out when `actual` was seen instead.
+    fn is_missing_before(&self, actual: &Self) -> bool {
+        match (self, actual) {
+            // `return 0 }` or `int i = 1 int j;`
+            (Token::Semicolon, Token::RightBrace) => true,
+            (Token::Semicolon, Token::Keyword(keyword)) => keyword.can_start_statement(),
+            // `f(1;` or `if (x {`
+            (Token::RightParen, Token::Semicolon) | (Token::RightParen, Token::LeftBrace) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Keyword {
+    /// Whether a declaration or statement can start with this keyword.
+    fn can_start_statement(self) -> bool {
+        use Keyword::*;
+        match self {
+            If | Switch | While | Do | For | Goto | Continue | Break | Return | Case | Default
+            | StaticAssert => true,
+            _ => self.is_decl_specifier(),
+        }
+    }
 }
 
 #[cfg(test)]
 pub(crate) mod test {
     use super::Parser;
     use crate::data::ast::ExternalDeclaration;
-    use crate::data::lex::test::cpp;
+    use crate::data::lex::test::{cpp, cpp_c23};
     use crate::data::*;
     use crate::lex::PreProcessor;
     use proptest::prelude::*;
@@ -385,6 +501,9 @@ pub(crate) mod test {
     pub(crate) fn parser(input: &str) -> Parser<PreProcessor> {
         Parser::new(cpp(input), false)
     }
+    pub(crate) fn c23_parser(input: &str) -> Parser<PreProcessor<'_>> {
+        Parser::new(cpp_c23(input), false)
+    }
 
     prop_compose! {
         fn arb_vec_result_locatable_token()(tokens in any::<Vec<Token>>()) -> Vec<CompileResult<Locatable<Token>>> {
@@ -419,6 +538,39 @@ pub(crate) mod test {
         }
     }
 
+    fn assert_recovers(input: &str, errors: usize, decls: usize) {
+        let (mut actual_errors, mut actual_decls) = (0, 0);
+        for result in parse_all(input) {
+            match result {
+                Ok(_) => actual_decls += 1,
+                Err(_) => actual_errors += 1,
+            }
+        }
+        assert_eq!(
+            (actual_errors, actual_decls),
+            (errors, decls),
+            "wrong number of (errors, declarations) for '{}'",
+            input
+        );
+    }
+
+    #[test]
+    fn test_recovery() {
+        // missing tokens are inserted
+        assert_recovers("int f() { return 0 } int g;", 1, 2);
+        assert_recovers("int i = 1 int j;", 1, 2);
+        assert_recovers("int f() { if (1 { return 1; } return 0; }", 1, 1);
+        // the rest of the block is still parsed
+        assert_recovers("int f() { x = a b; return 0; } int g;", 1, 2);
+        assert_recovers("int f() { int x = 1 +; x = ; return x; } int g;", 2, 2);
+        assert_recovers("int f() { if (x y) { a; b; } c d; } int g;", 2, 2);
+        assert_recovers("struct s { int a b; int c; } s; int g;", 1, 2);
+        // braces that were opened before the error are skipped
+        assert_recovers("int a[] = { 1 2 }, b; int g;", 1, 1);
+        assert_recovers("int i; } int j;", 1, 2);
+        assert_recovers("int f() { return 0;", 2, 0);
+    }
+
     #[test]
     fn test_strings() {
         let assert_str = |s, expected: &str| {
@@ -1,7 +1,11 @@
 use super::{Lexer, Parser, SyntaxResult};
 use crate::data::*;
 use crate::data::{
-    ast::{CompoundStatement, Declaration, Expr, ExternalDeclaration, Stmt, StmtType},
+    ast::{
+        CompoundStatement, Declaration, DeclarationSpecifier, Expr, ExternalDeclaration, Stmt,
+        StmtType,
+    },
+    error::Warning,
     lex::Keyword,
 };
 
@@ -14,16 +18,17 @@ impl<I: Lexer> Parser<I> {
             .expect("compound_statement should be called with '{' as the next token")
             .location;
         let mut stmts = vec![];
-        let mut pending_errs = vec![];
         while self.peek_token() != Some(&Token::RightBrace) {
             match self.statement() {
                 Ok(stmt) => {
                     location = location.merge(stmt.location);
                     stmts.push(stmt);
                 }
+                // keep going, so that errors later in the block are also reported
                 Err(err) => {
+                    stmts.push(Stmt::new(StmtType::Error, err.location));
+                    self.error_handler.push_back(err);
                     self.panic();
-                    pending_errs.push(err);
                     // prevent infinite loops if there's a syntax error at EOF
                     if self.peek_token().is_none() {
                         break;
@@ -33,20 +38,17 @@ impl<I: Lexer> Parser<I> {
         }
         if self.expect(Token::RightBrace).is_err() {
             assert!(self.peek_token().is_none()); // from the 'break' above
-            let actual_err = self.last_location.with(SyntaxError::Generic(
+            return Err(self.last_location.with(SyntaxError::Generic(
                 "unclosed '{' delimeter at end of file".into(),
-            ));
-            pending_errs.push(actual_err);
-        }
-        if let Some(err) = pending_errs.pop() {
-            self.error_handler.extend(pending_errs.into_iter());
-            Err(err)
-        } else {
-            Ok(Locatable::new(stmts, location))
+            )));
         }
+        Ok(Locatable::new(stmts, location))
     }
     fn declaration(&mut self) -> SyntaxResult<Stmt> {
         let decl = self.external_declaration()?;
+        if let ExternalDeclaration::StaticAssert(assert) = decl.data {
+            return Ok(Stmt::new(StmtType::StaticAssert(assert), decl.location));
+        }
         match decl.data.into_declaration() {
             Err(err) => Err(decl.location.with(err)),
             Ok(declaration) => Ok(Stmt::new(StmtType::Decl(declaration), decl.location)),
@@ -72,6 +74,9 @@ impl<I: Lexer> Parser<I> {
         let _guard = self.recursion_check();
         // take out 2 guards since this goes through `compound_statement` before calling itself again
         let _guard2 = self.recursion_check();
+        if self.is_standard_attribute() {
+            return self.attributed_statement();
+        }
         match self.peek_token() {
             Some(Token::LeftBrace) => Ok(self.compound_statement()?.map(StmtType::Compound)),
             Some(Token::Keyword(k)) => match k {
@@ -79,11 +84,22 @@ impl<I: Lexer> Parser<I> {
                 Keyword::Case => {
                     let kw = self.next_token().unwrap();
                     let expr = self.expr()?;
+                    // GNU case ranges: `case 'a' ... 'z':`
+                    let upper = if let Some(ellipsis) = self.match_next(&Token::Ellipsis) {
+                        self.gnu_extension("case ranges", ellipsis.location);
+                        Some(Box::new(self.expr()?))
+                    } else {
+                        None
+                    };
                     self.expect(Token::Colon)?;
                     let inner = Box::new(self.statement()?);
+                    let location = match &upper {
+                        Some(upper) => kw.location.merge(upper.location),
+                        None => kw.location.merge(expr.location),
+                    };
                     Ok(Stmt {
-                        location: kw.location.merge(expr.location),
-                        data: StmtType::Case(Box::new(expr), inner),
+                        location,
+                        data: StmtType::Case(Box::new(expr), upper, inner),
                     })
                 }
                 Keyword::Default => {
@@ -126,11 +142,15 @@ impl<I: Lexer> Parser<I> {
                 Keyword::Return => self.return_statement(),
 
                 // start of an expression statement
+                Keyword::StaticAssert => self.declaration(),
                 Keyword::Sizeof
-                | Keyword::StaticAssert
-                | Keyword::Alignas
                 | Keyword::Alignof
-                | Keyword::Generic => self.expression_statement(),
+                | Keyword::Generic
+                | Keyword::Real
+                | Keyword::Imag
+                | Keyword::BuiltinComplex
+                | Keyword::AtomicBuiltin(_)
+                | Keyword::Nullptr => self.expression_statement(),
                 decl if decl.is_decl_specifier() => self.declaration(),
                 other => {
                     let err = SyntaxError::NotAStatement(*other);
@@ -173,6 +193,52 @@ impl<I: Lexer> Parser<I> {
             _ => self.expression_statement(),
         }
     }
+    /// A statement or declaration that starts with standard attributes.
+    ///
+    /// ```yacc
+    /// attributed_statement
+    /// : attribute_specifier+ ';'
+    /// | attribute_specifier+ declaration
+    /// | attribute_specifier+ statement
+    /// ;
+    /// ```
+    fn attributed_statement(&mut self) -> StmtResult {
+        let attributes = self.attributes()?;
+        // [[fallthrough]];
+        if let Some(token) = self.match_next(&Token::Semicolon) {
+            let location = attributes.iter().fold(token.location, |location, attr| {
+                location.merge(attr.location)
+            });
+            let decl = Declaration {
+                specifiers: attributes
+                    .into_iter()
+                    .map(DeclarationSpecifier::Attribute)
+                    .collect(),
+                declarators: Vec::new(),
+            };
+            return Ok(Stmt::new(StmtType::Decl(decl), location));
+        }
+        let is_declaration = match self.peek_token() {
+            Some(&Token::Id(id)) => self.typedefs.get(&id).is_some(),
+            Some(token) => token.is_decl_specifier(),
+            None => false,
+        };
+        // [[maybe_unused]] int i;
+        if is_declaration {
+            let mut stmt = self.declaration()?;
+            if let StmtType::Decl(decl) = &mut stmt.data {
+                let attributes = attributes.into_iter().map(DeclarationSpecifier::Attribute);
+                decl.specifiers.extend(attributes);
+            }
+            return Ok(stmt);
+        }
+        // there are no standard attributes that apply to statements other than `fallthrough`
+        for attr in attributes {
+            let warning = Warning::IgnoredAttribute(attr.data.name, "a statement");
+            self.error_handler.warn(warning, attr.location);
+        }
+        self.statement()
+    }
     // expr ;
     fn expression_statement(&mut self) -> SyntaxResult<Stmt> {
         let expr = self.expr()?;
@@ -331,11 +397,22 @@ impl<I: Lexer> Parser<I> {
             location: start.location,
         })
     }
-    /// `goto_statement: GOTO identifier ';'`
+    /// `goto_statement: GOTO identifier ';' | GOTO '*' expr ';'`
+    ///
+    /// The second form is the GNU computed goto.
     ///
     /// <http://www.quut.com/c/ANSI-C-grammar-y.html#jump_statement>
     fn goto_statement(&mut self) -> StmtResult {
         let start = self.expect(Token::Keyword(Keyword::Goto)).unwrap();
+        if let Some(star) = self.match_next(&Token::Star) {
+            self.gnu_extension("computed gotos", star.location);
+            let target = self.expr()?;
+            self.expect(Token::Semicolon)?;
+            return Ok(Stmt {
+                location: start.location.merge(target.location),
+                data: StmtType::IndirectGoto(Box::new(target)),
+            });
+        }
         let id = self.expect_id()?;
         self.expect(Token::Semicolon)?;
         Ok(Stmt {
@@ -348,10 +425,15 @@ impl<I: Lexer> Parser<I> {
 impl ExternalDeclaration {
     /// If this is a `Declaration`, return all declarations seen.
     /// Otherwise, return the declarator for the function definition.
-    fn into_declaration(self) -> Result<Declaration, SyntaxError> {
+    pub(super) fn into_declaration(self) -> Result<Declaration, SyntaxError> {
         match self {
-            ExternalDeclaration::Function(def) => Err(SyntaxError::FunctionNotAllowed(def)),
+            ExternalDeclaration::Function(def) => {
+                Err(SyntaxError::FunctionNotAllowed(Box::new(def)))
+            }
             ExternalDeclaration::Declaration(decl) => Ok(decl),
+            ExternalDeclaration::StaticAssert(_) => Err(SyntaxError::Generic(
+                "static assertion not allowed in this context".into(),
+            )),
         }
     }
 }
@@ -378,6 +460,15 @@ mod tests {
     fn assert_no_change(s: &str) {
         assert_eq!(stmt(s).unwrap().data.to_string(), s);
     }
+    fn gnu_stmt(stmt: &str) -> CompileResult<Stmt> {
+        let mut p = parser(stmt).gnu_extensions(true);
+        let exp = p.statement();
+        if let Some(err) = p.error_handler.pop_front() {
+            Err(err)
+        } else {
+            exp.map_err(CompileError::from)
+        }
+    }
 
     #[test]
     // NOTE: this seems to be one of the few tests that checks that the location
@@ -397,6 +488,10 @@ mod tests {
     #[test]
     fn test_goto() {
         assert_no_change("goto a;");
+        let computed = gnu_stmt("goto *labels[i];").unwrap().data;
+        assert_eq!(computed.to_string(), "goto *(labels)[i];");
+        assert!(matches!(computed, StmtType::IndirectGoto(_)));
+        assert!(stmt("goto *p;").is_err());
     }
     #[test]
     fn test_do_while() {
@@ -449,4 +544,12 @@ mod tests {
 }",
         );
     }
+    #[test]
+    fn test_case_range() {
+        let range = gnu_stmt("case 'a' ... 'z': ;").unwrap().data;
+        assert_eq!(range.to_string(), "case 'a' ... 'z':\n    {\n    }");
+        assert!(matches!(range, StmtType::Case(_, Some(_), _)));
+        assert!(stmt("case 1 ... 2: ;").is_err());
+        assert!(gnu_stmt("case 1 ...: ;").is_err());
+    }
 }
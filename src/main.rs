@@ -11,7 +11,7 @@ use arcstr::ArcStr;
 use pico_args::Arguments;
 use saltwater_codegen::{assemble, compile, link};
 use saltwater_parser::data::{error::CompileWarning, Location};
-use saltwater_parser::{preprocess, Error, Files, Opt, Program};
+use saltwater_parser::{preprocess, Error, Files, Opt, Program, Standard};
 use tempfile::NamedTempFile;
 
 static ERRORS: AtomicUsize = AtomicUsize::new(0);
@@ -26,6 +26,8 @@ const HELP: &str = concat!(
     "usage: swcc [FLAGS] [OPTIONS] [<file>]
 
 FLAGS:
+        --annex-g          If set, complex multiplication and division handle infinities and NaNs as described in Annex G.
+                            This requires linking with the runtime library (libgcc or compiler-rt).
         --debug-ast        If set, print the parsed abstract syntax tree (AST) in addition to compiling.
                             The AST does no type checking or validation, it only parses.
         --debug-hir        If set, print the high intermediate representation (HIR) in addition to compiling.
@@ -40,6 +42,8 @@ FLAGS:
                             Note that preprocessing discards whitespace and comments.
                             There is not currently a way to disable this behavior.
     -V, --version          Prints version information
+        -Wimplicit-fallthrough
+                           Warn when a `case` label can be reached from the code before it without `[[fallthrough]];`.
 
 OPTIONS:
         --color <when>       When to use color. May be \"never\", \"auto\", or \"always\". [default: auto]
@@ -51,6 +55,9 @@ OPTIONS:
     -D, --define <id[=val]>  Define an object-like macro.
                               Can be specified multiple times to add multiple macros.
                               `val` defaults to `1`.
+        -std=<standard>      The version of the C standard to follow. May be \"c11\", \"c17\", or \"c23\".
+                              Features from newer standards give a warning. [default: c11]
+                              The \"gnu\" variants (e.g. \"gnu11\") also enable GNU extensions.
 
 ARGS:
     <file>    The file to read C source from. \"-\" means stdin (use ./- to read a file called '-').
@@ -59,8 +66,8 @@ ARGS:
 
 const USAGE: &str = "\
 usage: swcc [--help | -h] [--version | -V] [--debug-ir] [--debug-ast] [--debug-lex]
-            [--debug-hir] [--jit] [--no-link | -c] [--preprocess-only | -E]
-            [-I <dir>] [-D <id[=val]>] [<file>]";
+            [--debug-hir] [--jit] [--no-link | -c] [--preprocess-only | -E] [--annex-g]
+            [-Wimplicit-fallthrough] [-I <dir>] [-D <id[=val]>] [-std=<standard>] [<file>]";
 
 struct BinOpt {
     /// The options that will be passed to `compile()`
@@ -275,6 +282,13 @@ fn str_to_path_buf(s: &str) -> Result<PathBuf, bool> {
     Ok(s.into())
 }
 
+fn parse_standard(s: &str) -> Result<Standard, pico_args::Error> {
+    s.parse()
+        .map_err(|err: &str| pico_args::Error::ArgumentParsingFailed {
+            cause: err.to_string(),
+        })
+}
+
 macro_rules! type_sizes {
     ($($type: ty),* $(,)?) => {
         $(println!("{}: {}", stringify!($type), std::mem::size_of::<$type>());)*
@@ -356,6 +370,12 @@ fn parse_args() -> Result<(BinOpt, PathBuf), pico_args::Error> {
             })?;
         definitions.insert(key.into(), def);
     }
+    let (standard, gnu_extensions) = match input.opt_value_from_str::<_, String>("-std")? {
+        // `-std=gnu11` is `-std=c11` with GNU extensions
+        Some(std) if std.starts_with("gnu") => (parse_standard(&format!("c{}", &std[3..]))?, true),
+        Some(std) => (parse_standard(&std)?, false),
+        None => (Standard::default(), false),
+    };
     let bin_opt = BinOpt {
         preprocess_only: input.contains(["-E", "--preprocess-only"]),
         opt: Opt {
@@ -364,6 +384,10 @@ fn parse_args() -> Result<(BinOpt, PathBuf), pico_args::Error> {
             debug_ast: input.contains("--debug-ast"),
             debug_hir: input.contains("--debug-hir"),
             no_link: input.contains(["-c", "--no-link"]),
+            annex_g: input.contains("--annex-g"),
+            standard,
+            gnu_extensions,
+            implicit_fallthrough: input.contains("-Wimplicit-fallthrough"),
             #[cfg(feature = "jit")]
             jit: input.contains("--jit"),
             max_errors,
@@ -27,8 +27,7 @@ fn cpp_and_save(header: &str, dest: &Path) -> Result<(), Error> {
 fn all_headers() -> Result<(), Error> {
     const STANDARD_HEADERS: &[&str] = &[
         "assert",
-        // _Complex is not yet supported
-        // "complex",
+        "complex",
         "ctype",
         "errno",
         // fenv uses bitfields on my machine
@@ -42,7 +41,7 @@ fn all_headers() -> Result<(), Error> {
         "signal",
         "stdalign",
         "stdarg",
-        // _Atomic is not yet supported
+        // GCC's stdatomic.h uses statement expressions and __auto_type
         //"stdatomic",
         "stdbool",
         // uses __attribute__
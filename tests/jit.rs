@@ -13,3 +13,23 @@ fn jit_readme() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(code, Some(6));
     Ok(())
 }
+
+#[test]
+fn jit_thread_local() {
+    let program = "_Thread_local int i; int main() { return i; }";
+    let Program { result: jit, .. } = JIT::from_string(program, Opt::default());
+    let err = jit
+        .err()
+        .expect("thread-local variables should be an error in the JIT");
+    assert!(err.to_string().contains("JIT"), "{}", err);
+}
+
+#[test]
+fn jit_constructor() {
+    let program = "__attribute__((constructor)) void f() {} int main() {}";
+    let Program { result: jit, .. } = JIT::from_string(program, Opt::default());
+    let err = jit
+        .err()
+        .expect("constructors should be an error in the JIT");
+    assert!(err.to_string().contains("JIT"), "{}", err);
+}
@@ -0,0 +1,23 @@
+// code: 0
+static const unsigned char hello[] = {
+#embed "hello.txt" suffix(, 0)
+};
+int main(void) {
+    const char *expected = "hello";
+    int local[] = {
+#embed "hello.txt"
+    };
+    if (sizeof(hello) != 6 || sizeof(local) != 5 * sizeof(int)) return 1;
+    for (int i = 0; i < 5; i++) {
+        if (hello[i] != expected[i] || local[i] != expected[i]) return 2;
+    }
+    if (hello[5] != 0) return 3;
+#if __has_embed("hello.txt") != 1 || __has_embed("hello.txt" limit(0)) != 2
+    return 4;
+#elif __has_embed("missing.bin")
+    return 5;
+#endif
+    return (
+#embed "hello.txt" limit(1)
+    ) - 'h';
+}
@@ -0,0 +1,4 @@
+// compile-fail
+unsigned char data[] = {
+#embed "hello.txt" offset(1)
+};
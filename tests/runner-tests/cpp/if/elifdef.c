@@ -0,0 +1,17 @@
+// code: 2
+#define TWO
+#if 0
+int main() { return 0; }
+#elifdef ONE
+int main() { return 1; }
+#elifndef ONE
+    #ifdef TWO
+int main() { return 2; }
+    #elifndef TWO
+int main() { return 3; }
+    #endif
+#elifdef TWO
+int main() { return 4; }
+#else
+int main() { return 5; }
+#endif
@@ -0,0 +1,11 @@
+// output: BEGIN:
+// 3 + 4 = 7
+// END
+#include <stdio.h>
+
+int main(void) {
+    char buf[20];
+    snprintf(buf, sizeof buf, "%d + %d = %d", 3, 4, 3 + 4);
+    puts(buf);
+    return 0;
+}
@@ -0,0 +1,26 @@
+// code: 0
+#include <stddef.h>
+
+_Alignas(64) char global;
+struct cache_line {
+    char tag;
+    _Alignas(32) int data[4];
+};
+
+int misaligned(void *p, long align) {
+    return (long)p % align != 0;
+}
+
+int main() {
+    if (misaligned(&global, 64)) return 1;
+    _Alignas(128) char local = 1;
+    _Alignas(double) char small = 2;
+    if (misaligned(&local, 128) || misaligned(&small, 8)) return 2;
+    if (local + small != 3) return 3;
+    struct cache_line line;
+    if (sizeof line != 64 || misaligned(&line.data, 32)) return 4;
+    line.data[3] = 5;
+    if (line.data[3] != 5) return 5;
+    _Alignas(max_align_t) char buf[16];
+    return misaligned(buf, _Alignof(max_align_t));
+}
@@ -0,0 +1,5 @@
+// errors: 4
+_Alignas(3) int not_power_of_two;
+_Alignas(1) int too_weak;
+_Alignas(8) typedef int aligned_int;
+int f(_Alignas(8) int param);
@@ -0,0 +1,52 @@
+// code: 0
+int printf(const char *format, ...) __attribute__((format(printf, 1, 2)));
+void exit(int) __attribute__((__noreturn__));
+
+static int constructed, freed;
+
+__attribute__((constructor)) static void init(void) {
+    constructed = 1;
+}
+__attribute__((constructor(101))) static void init_first(void) {
+    // runs before `init`, which has the default priority
+    constructed = 2;
+}
+
+static void release(int *p) {
+    freed += *p;
+}
+
+struct __attribute__((packed)) packed {
+    char c;
+    int i;
+};
+struct aligned {
+    char c;
+} __attribute__((aligned(8)));
+
+__attribute__((used, section(".data.saltwater"))) int in_section = 5;
+__attribute__((weak)) int weak_function(void) { return 3; }
+__attribute__((visibility("hidden"))) int hidden = 4;
+
+__attribute__((noreturn)) static void die(int code) {
+    exit(code);
+}
+
+int main(void) {
+    __attribute__((unused)) int unused;
+    if (constructed != 1) return 1;
+    if (sizeof(struct packed) != 5) return 2;
+    if (_Alignof(struct aligned) != 8 || sizeof(struct aligned) != 8) return 3;
+    {
+        __attribute__((cleanup(release))) int a = 1;
+        for (int i = 0; i < 3; i++) {
+            int b __attribute__((cleanup(release))) = 10;
+            if (i == 1) break;
+        }
+    }
+    if (freed != 21) return 4;
+    if (in_section + weak_function() + hidden != 12) return 5;
+    char buf[2] __attribute__((aligned(16)));
+    if ((long)buf % 16 != 0) return 6;
+    die(0);
+}
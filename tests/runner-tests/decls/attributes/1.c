@@ -0,0 +1,8 @@
+// errors: 5
+__attribute__((aligned(3))) int a;
+__attribute__((visibility("everywhere"))) int b;
+__attribute__((section(1))) int c;
+__attribute__((format(printf, 2, 3))) int f(const char *fmt, ...);
+void g() {
+    __attribute__((cleanup(g))) int d;
+}
@@ -0,0 +1,8 @@
+// succeeds
+// unknown attributes are only a warning
+__attribute__((__saltwater_does_not_exist__)) int i;
+__attribute__((deprecated("use j instead"))) int k;
+int __attribute__(()) j, *__attribute__((unused)) p;
+int main(void) {
+    return k;
+}
@@ -0,0 +1,12 @@
+// code: 3
+// a zero-initialized object in a custom section can't be stored as BSS
+__attribute__((section(".data.saltwater"))) int counter;
+__attribute__((section(".data.saltwater"))) static long zeroes[4] = {0};
+
+__attribute__((constructor)) static void init(void) {
+    counter += 3;
+}
+
+int main(void) {
+    return counter + zeroes[3];
+}
@@ -0,0 +1,21 @@
+// code: 0
+typedef int aligned_int __attribute__((aligned(8)));
+typedef aligned_int also_aligned;
+
+struct s {
+    char c;
+    aligned_int i;
+};
+
+aligned_int global;
+
+int main(void) {
+    also_aligned local;
+    aligned_int *p = &global;
+    if (_Alignof(aligned_int) != 8 || _Alignof(also_aligned) != 8) return 1;
+    if (sizeof(struct s) != 16 || _Alignof(struct s) != 8) return 2;
+    if ((unsigned long)&global % 8 != 0 || (unsigned long)&local % 8 != 0) return 3;
+    // only the typedef itself is aligned, not pointers to it
+    if (_Alignof(aligned_int *) != 8 || sizeof(p) != 8) return 4;
+    return 0;
+}
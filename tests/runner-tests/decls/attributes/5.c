@@ -0,0 +1,16 @@
+// code: 0
+// a weak declaration is a weak reference, which is null if nothing defines it
+int weak_function(void) __attribute__((weak));
+extern int weak_object __attribute__((weak));
+__attribute__((weak)) int defined_later(void);
+
+int defined_later(void) {
+    return 2;
+}
+
+int main(void) {
+    if (weak_function != 0) return 1;
+    if (&weak_object != 0) return 2;
+    if (defined_later() != 2) return 3;
+    return weak_function ? weak_function() : 0;
+}
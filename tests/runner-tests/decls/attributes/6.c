@@ -0,0 +1,13 @@
+// code: 0
+// glibc's <sys/cdefs.h> does `#define __attribute__(xyz)`, since `__GNUC__` isn't defined
+#include <stdio.h>
+
+struct __attribute__((packed)) p {
+    char c;
+    int i;
+};
+_Static_assert(sizeof(struct p) == 5, "__attribute__ should not be defined away");
+
+int main(void) {
+    return 0;
+}
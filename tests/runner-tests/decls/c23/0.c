@@ -0,0 +1,19 @@
+// code: 42
+constexpr int size = 4;
+static int table[size * 2] = { [size] = 40 };
+int main(void) {
+    auto total = table[4];
+    typeof(total) extra = 2;
+    typeof_unqual(const int) copy = 0;
+    copy = extra;
+    int *p = nullptr;
+    if (p != nullptr || sizeof(table) != size * 2 * sizeof(int)) {
+        return 1;
+    }
+    switch (copy) {
+        case size - 2:
+            return total + copy;
+        default:
+            return 2;
+    }
+}
@@ -0,0 +1,8 @@
+// errors: 3
+int i;
+constexpr int n = i + 1;
+constexpr int m;
+int main(void) {
+    auto *p = &i;
+    return n;
+}
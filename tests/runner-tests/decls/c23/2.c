@@ -0,0 +1,13 @@
+// code: 3
+#include <stddef.h>
+int main(void) {
+    typeof(nullptr) n = nullptr;
+    char *s = n;
+    const char *message = "abc";
+    auto len = sizeof message;
+    auto end = message + 3;
+    if (s != NULL || n != nullptr || len != sizeof(char *)) {
+        return 1;
+    }
+    return end - message;
+}
@@ -0,0 +1,10 @@
+// code: 10
+// before C23, these are ordinary identifiers
+int nullptr = 1;
+int typeof(int constexpr) {
+    return constexpr * 2;
+}
+int main(void) {
+    int typeof_unqual = 3;
+    return nullptr + typeof(typeof_unqual) + typeof_unqual;
+}
@@ -0,0 +1,13 @@
+// output: invalid 1 permission denied
+int printf(const char *format, ...);
+
+#define EPERM 1
+#define EINVAL 22
+const char *messages[] = {
+    [EINVAL] = "invalid",
+    [EPERM] = "permission denied",
+};
+
+int main() {
+    printf("%s %d %s\n", messages[EINVAL], messages[2] == 0, messages[EPERM]);
+}
@@ -0,0 +1,24 @@
+// code: 0
+struct point { int x, y; };
+struct shape {
+    char kind;
+    struct point points[3];
+    union { int i; double d; } u;
+    unsigned flag : 1;
+};
+
+struct shape s = { .points[1].y = 4, .points[2] = { 5, 6 }, 7, .kind = 'a', .u.d = 1.5, .flag = 1 };
+
+// `7` initializes the member after `points`, which is `u.i`, but `.u.d` overrides it
+int check(struct shape *p) {
+    if (p->kind != 'a' || p->u.d != 1.5 || p->flag != 1) return 1;
+    if (p->points[0].x != 0 || p->points[0].y != 0) return 2;
+    if (p->points[1].x != 0 || p->points[1].y != 4) return 3;
+    if (p->points[2].x != 5 || p->points[2].y != 6) return 4;
+    return 0;
+}
+
+int main() {
+    struct shape local = { .points[1].y = 4, .points[2] = { 5, 6 }, 7, .kind = 'a', .u.d = 1.5, .flag = 1 };
+    return check(&s) || check(&local);
+}
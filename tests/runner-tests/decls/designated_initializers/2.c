@@ -0,0 +1,11 @@
+// code: 15
+int main() {
+    int a[] = { [4] = 5, [1] = 2, 3 };
+    int b[2][3] = { [1] = { 1, 2 }, [0][2] = 4 };
+    char s[8] = "hi";
+    int sum = 0;
+    for (int i = 0; i < sizeof(a) / sizeof(*a); i++) sum += a[i];
+    // 10 from a, 7 from b
+    sum += b[0][0] + b[0][1] + b[0][2] + b[1][0] + b[1][1] + b[1][2];
+    return sum - (s[0] == 'h') - (s[2] == 0) - s[7];
+}
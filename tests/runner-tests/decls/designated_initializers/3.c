@@ -0,0 +1,5 @@
+// errors: 4
+struct s { int i; } s = { .j = 1 };
+int a[2] = { [2] = 1 };
+int i = { [0] = 1 };
+struct s t = { [0] = 1 };
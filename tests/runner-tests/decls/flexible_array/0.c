@@ -0,0 +1,24 @@
+// code: 0
+struct packet {
+    char kind;
+    int data[];
+};
+
+int sum(struct packet *p, int len) {
+    int total = 0;
+    for (int i = 0; i < len; i++) total += p->data[i];
+    return total;
+}
+
+int main() {
+    // the flexible array member is aligned, but takes up no space
+    if (sizeof(struct packet) != 4) return 1;
+    int storage[4];
+    struct packet *p = (struct packet *)storage;
+    p->kind = 'a';
+    p->data[0] = 1;
+    p->data[1] = 2;
+    p->data[2] = 3;
+    if (storage[1] != 1 || storage[3] != 3) return 2;
+    return sum(p, 3) != 6;
+}
@@ -0,0 +1,18 @@
+// code: 0
+struct message {
+    int len;
+    char text[];
+};
+
+// GNU extension: the object is large enough to hold the initialized array
+struct message hello = { 6, "hello" };
+struct message empty = { 0 };
+struct { int id; char name[8]; } named = { 1, "ab" };
+struct numbers { long count; int values[]; } numbers = { .count = 3, .values = { 1, 2, 3 } };
+
+int main() {
+    if (hello.text[0] != 'h' || hello.text[4] != 'o' || hello.text[5] != '\0') return 1;
+    if (empty.len != 0) return 2;
+    if (named.name[1] != 'b' || named.name[7] != '\0') return 3;
+    return numbers.values[0] + numbers.values[1] + numbers.values[2] != 6;
+}
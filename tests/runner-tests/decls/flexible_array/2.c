@@ -0,0 +1,9 @@
+// errors: 5
+struct not_last { char data[]; int len; };
+struct only { char data[]; };
+union u { int len; char data[]; };
+struct packet { int len; char data[]; };
+struct packet packets[2];
+int main() {
+    struct packet local = { 1, "a" };
+}
@@ -0,0 +1,16 @@
+// code: 0
+// objects declared `static` or `extern` inside a function have static storage duration
+int f(void) { static int n = 5; return ++n; }
+int g(void) { static int n; extern int shared; return n += shared; }
+int shared = 10;
+
+int main(void) {
+    f();
+    if (f() != 7) return 1;
+    if (g() != 10 || g() != 20) return 2;
+    static int *p = &shared;
+    *p = 1;
+    if (g() != 21) return 3;
+    static int *q = (int[]){1, 2};
+    return q[1] != 2;
+}
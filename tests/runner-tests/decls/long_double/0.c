@@ -0,0 +1,38 @@
+// code: 0
+long double global = 2.5;
+long double zeroes[2];
+
+struct bytes {
+    unsigned long mantissa;
+    unsigned short sign_exp;
+};
+
+long double half(long double x) {
+    return x / 2;
+}
+
+int main() {
+    if (sizeof(long long) != 8 || sizeof(long double) != 16) return 1;
+    if (_Alignof(long double) != 16) return 2;
+    if (_Generic((long long)1, long: 1, long long: 2) != 2) return 3;
+    if (_Generic((long double)1, double: 1, long double: 2) != 2) return 4;
+    if (_Generic((long double)1 * 1.0, double: 1, long double: 2) != 2) return 5;
+
+    long double ld = 1;
+    struct bytes *repr = (struct bytes *)&ld;
+    // x87 extended precision: explicit integer bit and a bias of 16383
+    if (repr->mantissa != 0x8000000000000000ul || repr->sign_exp != 0x3fff) return 6;
+    ld = -ld;
+    if (repr->sign_exp != 0xbfff) return 7;
+
+    repr = (struct bytes *)&global;
+    if (repr->mantissa != 0xa000000000000000ul || repr->sign_exp != 0x4000) return 8;
+    if (zeroes[1] != 0) return 9;
+
+    ld = half(global) + 0.25;
+    ld++;
+    if (ld != 2.5) return 10;
+    long long ll = 1LL << 40;
+    if (ll + ld != 1099511627778.5) return 11;
+    return 0;
+}
@@ -0,0 +1,7 @@
+// output: BEGIN: 1.500000 2.5 0.5 -0.25 END
+int printf(const char *, ...);
+int main() {
+    long double ld = 1.5;
+    // long double is passed in memory, so %al doesn't count it
+    printf("%Lf %.1Lf %g %.2Lf", ld, ld + 1, 0.5, -0.25L);
+}
@@ -0,0 +1,13 @@
+// code: 0
+// glibc expects long double arguments in memory and returns them in st(0)
+long double strtold(const char *, char **);
+int snprintf(char *, unsigned long, const char *, ...);
+int main() {
+    if (strtold("3.0", 0) != 3.0L) return 1;
+    // the result is popped from the x87 stack even if it's unused
+    for (int i = 0; i < 10; i++) strtold("1", 0);
+    char buf[16];
+    snprintf(buf, sizeof buf, "%.3Lf", -1.125L);
+    if (strtold(buf, 0) != -1.125L) return 2;
+    return 0;
+}
@@ -0,0 +1,20 @@
+// code: 0
+long double twice(long double x) {
+    return x * 2;
+}
+// out of integer registers: `big` and `y` go on the stack, in that order, after `x`
+__int128 sum(long double x, int a, int b, int c, int d, int e, __int128 big, long double y) {
+    return (__int128)x + a + b + c + d + e + big + (__int128)y;
+}
+long double _Complex swap(double d, long double _Complex z) {
+    return __imag__ z + (__real__ z + d) * 1.0Li;
+}
+int main() {
+    if (twice(1) != 2) return 1;
+    long double (*f)(long double) = twice;
+    if (f(1.25L) != 2.5L) return 2;
+    if (sum(1, 2, 3, 4, 5, 6, (__int128)1 << 80, 7) != ((__int128)1 << 80) + 28) return 3;
+    long double _Complex z = swap(0.5, 1.0L + 2.0Li);
+    if (__real__ z != 2 || __imag__ z != 1.5) return 4;
+    return 0;
+}
@@ -0,0 +1,11 @@
+// code: 0
+// the suffix of a constant determines its type
+#define TYPE(x) _Generic((x), long: 1, long long: 2, unsigned long long: 3, double: 4, long double: 5, long double _Complex: 6, default: 0)
+int main(void) {
+    int *p = 0LL;
+    if (sizeof(1.5L) != 16) return 1;
+    if (TYPE(1.0L) != 5 || TYPE(1LL) != 2 || TYPE(1llu) != 3 || TYPE(1ULL) != 3 || TYPE(1L) != 1) return 2;
+    if (TYPE(1.0) != 4 || TYPE(2.0Li) != 6) return 3;
+    switch (2) { case 2LL: break; default: return 4; }
+    return p != 0;
+}
@@ -0,0 +1,35 @@
+// code: 0
+#if __has_c_attribute(nodiscard) != 202003 || !__has_c_attribute(gnu::packed)
+#error "nodiscard should be supported"
+#endif
+[[nodiscard]] static int twice(int x) { return x * 2; }
+int square(int x) [[unsequenced]];
+int square(int x) { return x * x; }
+struct [[gnu::packed]] packed { char c; int i; };
+
+int classify(int i) {
+    int result = 0;
+    switch (i) {
+    case 0:
+        result += 1;
+        [[fallthrough]];
+    case 1:
+    case 2:
+        result += 10;
+        break;
+    default:
+        result = -1;
+    }
+    return result;
+}
+
+int main(void) {
+    [[maybe_unused]] int unused;
+    int [[maybe_unused]] also_unused;
+    if (classify(0) != 11 || classify(2) != 10 || classify(5) != -1) return 1;
+    (void)twice(1);
+    if (twice(2) != 4) return 2;
+    if (square(3) != 9) return 3;
+    if (sizeof(struct packed) != 5) return 4;
+    return 0;
+}
@@ -0,0 +1,3 @@
+// errors: 2
+[[nodiscard(1)]] int f(void);
+[[deprecated(2)]] int i;
@@ -0,0 +1,16 @@
+// succeeds
+// these are all warnings, not errors
+[[nodiscard("check the result")]] int f(void) { return 1; }
+[[deprecated]] int old;
+[[vendor::unknown(any [tokens] {here})]] int i;
+
+int main(void) {
+    f();
+    switch (old) {
+    case 1:
+        f();
+    case 2:
+        break;
+    }
+    return 0;
+}
@@ -0,0 +1,9 @@
+// code: 4
+#include <assert.h>
+_Static_assert(sizeof(int) == 4, "int should be 32 bits");
+static_assert(sizeof(char) == 1);
+struct s { int i; _Static_assert(sizeof(int) == 4, "members can be checked too"); };
+int main() {
+    _Static_assert(sizeof(struct s) == sizeof(int), "struct s should not have padding");
+    return sizeof(struct s);
+}
@@ -0,0 +1,7 @@
+// errors: 3
+#include <assert.h>
+_Static_assert(sizeof(int) == 2, "int should be 16 bits");
+struct s { char c; _Static_assert(sizeof(char) == 2); };
+int main() {
+    static_assert(0);
+}
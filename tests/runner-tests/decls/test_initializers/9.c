@@ -0,0 +1,3 @@
+// code: 102
+unsigned char a[] = "abc";
+int main() { return a[1] + sizeof a; }
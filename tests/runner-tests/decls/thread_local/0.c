@@ -0,0 +1,29 @@
+// code: 0
+typedef unsigned long pthread_t;
+int pthread_create(pthread_t *thread, const void *attr, void *(*start)(void *), void *arg);
+int pthread_join(pthread_t thread, void **retval);
+
+_Thread_local int counter = 5;
+__thread long other;
+static _Thread_local int *pointer;
+
+void *work(void *arg) {
+    // each thread starts with the initial value
+    if (counter != 5 || other != 0) return (void *)1;
+    counter = 10;
+    pointer = &counter;
+    *pointer += 1;
+    return (void *)(long)counter;
+}
+
+int main() {
+    counter = 7;
+    pthread_t thread;
+    void *result;
+    if (pthread_create(&thread, 0, work, 0) != 0) return 1;
+    if (pthread_join(thread, &result) != 0) return 2;
+    if ((long)result != 11) return 3;
+    // the main thread's copy wasn't changed
+    if (counter != 7) return 4;
+    return 0;
+}
@@ -0,0 +1,11 @@
+// errors: 7
+_Thread_local int f();
+typedef _Thread_local int thread_int;
+struct s { _Thread_local int i; };
+int g(_Thread_local int i);
+extern int x;
+_Thread_local int x;
+void h() {
+    _Thread_local int local;
+    register _Thread_local int r;
+}
@@ -0,0 +1,4 @@
+// fail
+_Thread_local int i;
+int *p = &i;
+int main() {}
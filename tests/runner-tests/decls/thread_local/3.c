@@ -0,0 +1,21 @@
+// code: 0
+typedef unsigned long pthread_t;
+int pthread_create(pthread_t *thread, const void *attr, void *(*start)(void *), void *arg);
+int pthread_join(pthread_t thread, void **retval);
+
+void *count(void *arg) {
+    // each thread has its own copy, which keeps its value between calls
+    static _Thread_local int calls;
+    return (void *)(long)++calls;
+}
+
+int main(void) {
+    count(0);
+    pthread_t thread;
+    void *result;
+    if (pthread_create(&thread, 0, count, 0) != 0) return 1;
+    if (pthread_join(thread, &result) != 0) return 2;
+    if ((long)result != 1) return 3;
+    if ((long)count(0) != 2) return 4;
+    return 0;
+}
@@ -0,0 +1,19 @@
+// code: 0
+int fill(int n) {
+    int a[n];
+    for (int i = 0; i < n; i++) a[i] = i * i;
+    // the size is fixed when the array is declared
+    n = 100;
+    if (sizeof a != 5 * sizeof(int)) return -1;
+    int total = 0;
+    for (int i = 0; i < sizeof a / sizeof a[0]; i++) total += a[i];
+    return total;
+}
+
+int main() {
+    int n = 3;
+    if (sizeof(char[n + 1]) != 4) return 1;
+    long rows[n][2];
+    if (sizeof rows != 48 || sizeof rows[0] != 16) return 2;
+    return fill(5) != 30;
+}
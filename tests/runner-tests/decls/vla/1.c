@@ -0,0 +1,20 @@
+// code: 0
+double trace(int n, double m[n][n]) {
+    double total = 0;
+    for (int i = 0; i < n; i++) total += m[i][i];
+    return total;
+}
+
+int main() {
+    int n = 3;
+    double m[n][n];
+    for (int i = 0; i < n; i++)
+        for (int j = 0; j < n; j++)
+            m[i][j] = i == j ? i + 1 : 10;
+    if (trace(n, m) != 6) return 1;
+    // pointer arithmetic uses the runtime size of the row
+    double (*row)[n] = m;
+    row = row + 2;
+    if ((*row)[2] != 3 || (*row)[0] != 10) return 2;
+    return 0;
+}
@@ -0,0 +1,12 @@
+// code: 0
+int main() {
+    int total = 0;
+    // each iteration gets a new array
+    for (int n = 1; n <= 1000; n++) {
+        char buf[n * 1024];
+        buf[n * 1024 - 1] = 1;
+        if (n % 2 == 0) total += buf[n * 1024 - 1];
+        if (n == 500) break;
+    }
+    return total != 250;
+}
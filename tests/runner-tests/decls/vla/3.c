@@ -0,0 +1,15 @@
+// errors: 6
+int n = 5;
+int global[n];
+
+int main(void) {
+    int a[n] = {1};
+    static int b[n];
+    struct s { int c[n]; };
+    (int[n]){0};
+    goto inside;
+    {
+        int d[n];
+        inside: n++;
+    }
+}
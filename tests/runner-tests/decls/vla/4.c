@@ -0,0 +1,5 @@
+// code: 7
+// a prototype and a definition with different VLA lengths are compatible
+int f(int n, int a[n][n]);
+int f(int n, int a[n][n]) { return a[1][1]; }
+int main() { int n = 2; int a[n][n]; a[1][1] = 7; return f(n, a); }
@@ -0,0 +1,18 @@
+// errors: 2
+int main(void) {
+    int n = 2;
+    switch (n) {
+        int a[n];
+    case 1:
+        a[0] = 1;
+        break;
+    default:
+        break;
+    }
+    // the whole switch is in the scope of `b`
+    int b[n];
+    switch (n) {
+    case 2:
+        b[0] = 0;
+    }
+}
@@ -0,0 +1,8 @@
+// crash
+// running out of space for a VLA traps instead of writing past the end of the VLA stack
+int main(void) {
+    unsigned long n = -1;
+    char a[n / 2];
+    a[0] = 1;
+    return a[0];
+}
@@ -0,0 +1,19 @@
+// code: 0
+#include <setjmp.h>
+jmp_buf env;
+void leave(int n) {
+    char a[n];
+    a[n - 1] = 1;
+    longjmp(env, a[n - 1]);
+}
+int enter(int n) {
+    char b[n];
+    if (!setjmp(env)) leave(n);
+    return sizeof b;
+}
+int main(void) {
+    // this needs 100 MiB if leaving a scope with longjmp never gives back its arrays
+    for (int i = 0; i < 100; i++)
+        if (enter(512 * 1024) != 512 * 1024) return 1;
+    return 0;
+}
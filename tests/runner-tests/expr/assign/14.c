@@ -0,0 +1,8 @@
+// code: 3
+int main() {
+    const char *s = "abc";
+    const char *start = s;
+    while (*s)
+        s++;
+    return s - start;
+}
@@ -0,0 +1,5 @@
+// errors: 2
+void f(int *const p, int *const *pp) {
+    p++;
+    *pp = 0;
+}
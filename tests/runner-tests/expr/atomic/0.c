@@ -0,0 +1,58 @@
+// code: 0
+#include <stdatomic.h>
+
+_Atomic int counter = 5;
+atomic_flag flag = ATOMIC_FLAG_INIT;
+
+struct point {
+    int x, y;
+};
+
+int main() {
+    if (sizeof(_Atomic int) != sizeof(int) || sizeof(atomic_llong) != 8) return 1;
+    counter += 2;
+    counter++;
+    ++counter;
+    counter -= 1;
+    if (counter != 8) return 2;
+
+    _Atomic(double) d = 1.5;
+    d *= 2;
+    if (d != 3.0) return 3;
+
+    int array[3] = {1, 2, 3};
+    _Atomic(int *) p = array;
+    p += 1;
+    if (*p != 2) return 4;
+    if (atomic_fetch_add(&p, 1) != &array[1] || *p != 3) return 5;
+
+    atomic_int i;
+    atomic_init(&i, 10);
+    atomic_store(&i, 20);
+    if (atomic_load(&i) != 20) return 6;
+    if (atomic_exchange(&i, 30) != 20 || i != 30) return 7;
+    if (atomic_fetch_sub_explicit(&i, 5, memory_order_relaxed) != 30 || i != 25) return 8;
+    if (atomic_fetch_or(&i, 2) != 25 || atomic_fetch_and(&i, 3) != 27 || i != 3) return 9;
+    if (atomic_fetch_xor(&i, 1) != 3 || i != 2) return 10;
+
+    int expected = 1;
+    if (atomic_compare_exchange_strong(&i, &expected, 100)) return 11;
+    if (expected != 2 || i != 2) return 12;
+    if (!atomic_compare_exchange_weak(&i, &expected, 100)) return 13;
+    if (i != 100) return 14;
+
+    if (atomic_flag_test_and_set(&flag)) return 15;
+    if (!atomic_flag_test_and_set(&flag)) return 16;
+    atomic_flag_clear(&flag);
+    if (atomic_flag_test_and_set(&flag)) return 17;
+
+    _Atomic struct point point = { 1, 2 };
+    struct point copy = point;
+    point = (struct point){ 3, 4 };
+    if (copy.x != 1) return 18;
+    copy = point;
+    if (copy.y != 4) return 19;
+
+    atomic_thread_fence(memory_order_seq_cst);
+    return 0;
+}
@@ -0,0 +1,11 @@
+// errors: 6
+typedef int array[3];
+_Atomic array a;
+_Atomic(_Atomic int) b;
+_Atomic(int()) c;
+int main() {
+    double d;
+    __atomic_fetch_and(&d, 1, __ATOMIC_SEQ_CST);
+    __atomic_load_n(&d);
+    __atomic_store_n(d, 1.0, __ATOMIC_SEQ_CST);
+}
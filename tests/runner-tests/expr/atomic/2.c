@@ -0,0 +1,9 @@
+// code: 3
+// booleans are stored as a byte, both in static and automatic storage
+_Bool global = 5;
+struct { _Atomic _Bool value; } flag = { 0 };
+int main() {
+    _Bool local = global;
+    local = !local;
+    return global + !local + !flag.value;
+}
@@ -0,0 +1,17 @@
+// code: 0
+// atomic operations call `pthread_mutex_lock`, which has to match this declaration
+#include <pthread.h>
+
+_Atomic int counter;
+
+void *increment(void *arg) {
+    for (int i = 0; i < 1000; i++) counter++;
+    return arg;
+}
+
+int main() {
+    pthread_t threads[4];
+    for (int i = 0; i < 4; i++) pthread_create(&threads[i], 0, increment, 0);
+    for (int i = 0; i < 4; i++) pthread_join(threads[i], 0);
+    return counter != 4000;
+}
@@ -0,0 +1,24 @@
+// code: 0
+struct flags {
+    unsigned a : 3;
+    int b : 4;
+    unsigned : 0;
+    unsigned c : 5;
+    char d;
+};
+int main() {
+    struct flags f;
+    f.a = 9;
+    f.b = -3;
+    f.c = 31;
+    f.d = 'x';
+    if (f.a != 1 || f.b != -3 || f.c != 31 || f.d != 'x') return 1;
+    f.b += 2;
+    if (f.b != -1) return 2;
+    f.a++;
+    if (f.a != 2 || f.b != -1) return 3;
+    struct flags *p = &f;
+    p->c = p->c - 1;
+    if (f.c != 30 || (f.c = 33) != 1) return 4;
+    return sizeof(struct flags) != 8;
+}
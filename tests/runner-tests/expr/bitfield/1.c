@@ -0,0 +1,11 @@
+// code: 0
+struct header {
+    unsigned version : 4;
+    unsigned length : 4;
+    int offset : 8;
+    unsigned : 4;
+    unsigned flags : 3;
+} h = { 4, 5, -2, 6 };
+int main() {
+    return !(h.version == 4 && h.length == 5 && h.offset == -2 && h.flags == 6);
+}
@@ -0,0 +1,6 @@
+// errors: 2
+struct s { int a : 3; } s;
+int main() {
+    &s.a;
+    return sizeof s.a;
+}
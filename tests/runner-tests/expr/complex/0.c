@@ -0,0 +1,65 @@
+// code: 0
+double _Complex global = __builtin_complex(1.0, 2.0);
+float _Complex zero;
+
+double _Complex conjugate(double _Complex z) {
+    return __builtin_complex(__real__ z, -__imag__ z);
+}
+float _Complex swap(float _Complex z) {
+    return __builtin_complex(__imag__ z, __real__ z);
+}
+static long double _Complex twice(long double _Complex z) {
+    return z * 2;
+}
+struct point {
+    int id;
+    double _Complex position;
+};
+
+int main() {
+    if (sizeof(float _Complex) != 8 || sizeof(double _Complex) != 16) return 1;
+    if (sizeof(long double _Complex) != 32 || _Alignof(double _Complex) != 8) return 2;
+    if (_Generic(1.0f * global, double _Complex: 1, default: 0) != 1) return 3;
+    if (_Generic(zero + 1, float _Complex: 1, default: 0) != 1) return 4;
+
+    double _Complex z = global;
+    if (__real__ z != 1 || __imag__ z != 2) return 5;
+    __imag__ z = 3;
+    if (z != __builtin_complex(1.0, 3.0) || global == z) return 6;
+
+    // (1 + 3i)(2 - i) = 5 + 5i
+    double _Complex product = z * __builtin_complex(2.0, -1.0);
+    if (__real__ product != 5 || __imag__ product != 5) return 7;
+    double _Complex quotient = product / __builtin_complex(2.0, -1.0);
+    if (quotient != z) return 8;
+    z += 1;
+    z -= __builtin_complex(0.0, 1.0);
+    if (z != __builtin_complex(2.0, 2.0)) return 9;
+    z = -z;
+    if (__real__ z != -2 || __imag__ z != -2) return 10;
+
+    // conversions
+    double real = z;
+    if (real != -2) return 11;
+    if (!z || (_Bool)__builtin_complex(0.0, 0.0)) return 12;
+    if (!__builtin_complex(0.0, 1.0)) return 13;
+    float _Complex f = z;
+    if (__real__ f != -2 || __imag__ f != -2) return 14;
+    z = 5;
+    if (__imag__ z != 0 || __real__ 3.0 != 3 || __imag__ 3.0 != 0) return 15;
+
+    // passing and returning
+    z = conjugate(global);
+    if (__real__ z != 1 || __imag__ z != -2) return 16;
+    f = swap(__builtin_complex(1.0f, 2.0f));
+    if (__real__ f != 2 || __imag__ f != 1) return 17;
+    long double _Complex l = twice(__builtin_complex((long double)1, (long double)1.5));
+    if (__real__ l != 2 || __imag__ l != 3) return 18;
+
+    struct point p = {1, __builtin_complex(4.0, 5.0)};
+    if (__imag__ p.position != 5) return 19;
+    double _Complex *ptr = &p.position;
+    __real__ *ptr = 6;
+    if (p.position != __builtin_complex(6.0, 5.0)) return 20;
+    return 0;
+}
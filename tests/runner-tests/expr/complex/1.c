@@ -0,0 +1,10 @@
+// errors: 6
+int main() {
+    double _Complex z = 1;
+    int _Complex i;
+    z < z;
+    z++;
+    int *p = (int *)z;
+    __real__ p;
+    __builtin_complex(1.0, (float)2);
+}
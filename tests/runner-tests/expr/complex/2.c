@@ -0,0 +1,15 @@
+// code: 0
+#include <complex.h>
+double _Complex g = 1.0 + 2.0 * I;
+double _Complex h = -I - 2.0;
+static int f(void) { return __real__ h == -2 && __imag__ h == -1; }
+int main(void) {
+    double _Complex z = 3.0 + 4.0i;
+    if (__real__ z != 3 || __imag__ z != 4) return 1;
+    z = z * I;
+    if (__real__ z != -4 || __imag__ z != 3) return 2;
+    if (__imag__ 2.5j != 2.5 || __real__ 1.0iF != 0) return 3;
+    if (__real__ g != 1 || __imag__ g != 2) return 4;
+    if (!f()) return 5;
+    return 0;
+}
@@ -0,0 +1,26 @@
+// code: 0
+struct point { int x, y; };
+
+int *global = (int[]){ 1, 2, 3 };
+struct point *origin = &(struct point){ 0 };
+
+int sum(int *a, int n) {
+    int total = 0;
+    for (int i = 0; i < n; i++) total += a[i];
+    return total;
+}
+
+int main() {
+    struct point p = (struct point){ .y = 2, .x = 1 };
+    if (p.x != 1 || p.y != 2) return 1;
+    if (sum((int[]){ 1, 2, 3, 4 }, 4) != 10) return 2;
+    if (sizeof (int[]){ [9] = 0 } != 10 * sizeof(int)) return 3;
+    // compound literals are lvalues
+    int *q = &(int){ 5 };
+    *q += 1;
+    if (*q != 6) return 4;
+    (struct point){ 3, 4 }.x = 7;
+    if (global[2] != 3 || origin->x != 0 || origin->y != 0) return 5;
+    global[0] = 4;
+    return sum(global, 3) != 9;
+}
@@ -0,0 +1,10 @@
+// code: 6
+int main() {
+    int total = 0;
+    // the compound literal is initialized again every time it is evaluated
+    for (int i = 0; i < 3; i++) {
+        int *p = (int[]){ i, i + 1 };
+        total += p[1];
+    }
+    return total;
+}
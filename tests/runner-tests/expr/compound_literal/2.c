@@ -0,0 +1,5 @@
+// errors: 2
+int main() {
+    (void){ 1 };
+    return (int){ 1, 2 };
+}
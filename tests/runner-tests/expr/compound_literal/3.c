@@ -0,0 +1,6 @@
+// code: 6
+const int *g = &(const int){1};
+int main(void) {
+    const int *p = &(const int){5};
+    return *g + *p;
+}
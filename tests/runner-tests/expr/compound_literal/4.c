@@ -0,0 +1,5 @@
+// errors: 1
+int main(void) {
+    (const int){1} = 2;
+    return 0;
+}
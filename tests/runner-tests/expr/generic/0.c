@@ -0,0 +1,10 @@
+// code: 6
+int my_iabs(int i) { return i < 0 ? -i : i; }
+long my_labs(long l) { return l < 0 ? -l : l; }
+#define abs(x) _Generic((x), int: my_iabs, long: my_labs, default: my_labs)(x)
+int main() {
+    int i = -2;
+    long l = -3;
+    char c = -1;
+    return abs(i) + abs(l) + abs(c);
+}
@@ -0,0 +1,14 @@
+// code: 5
+int main() {
+    int a[3];
+    int *const p = a;
+    int i = 0;
+    // the controlling expression is not evaluated
+    _Generic(i++, int: 0);
+    // arrays decay and top-level qualifiers are dropped
+    _Generic(a, int *: 1);
+    _Generic(p, int *: 1);
+    // the result is an lvalue if the selected expression is
+    _Generic(i, default: i) = 4;
+    return i + _Generic(p, long: 0, int *: 1);
+}
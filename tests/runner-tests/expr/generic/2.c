@@ -0,0 +1,7 @@
+// errors: 3
+int main() {
+    int i;
+    _Generic(i, int: 1, int: 2);
+    _Generic(i, default: 1, default: 2);
+    return _Generic(i, long: 1);
+}
@@ -0,0 +1,5 @@
+// errors: 2
+int main() {
+    int i = 0;
+    return _Generic(i, int: 0, double: 1 % 2.0, char *: undeclared_thing);
+}
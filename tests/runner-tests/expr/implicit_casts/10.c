@@ -0,0 +1,9 @@
+// code: 5
+int main() {
+    int a[3] = {1, 2, 3};
+    int *const p = a;
+    int *q = p;
+    int *r = a;
+    int *const *pp = &r;
+    return *q + **pp + p[2];
+}
@@ -0,0 +1,2 @@
+// errors: 1
+int main() { int *const *pp = 0; int **q = pp; return 0; }
@@ -0,0 +1,8 @@
+// code: 6
+int sum(int *p, int *const *pp) { return *p + **pp; }
+int main() {
+    int a[2] = {2, 4};
+    int *const p = a;
+    int *q = a + 1;
+    return sum(p, &q);
+}
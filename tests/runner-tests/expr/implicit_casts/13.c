@@ -0,0 +1,3 @@
+// errors: 1
+void f(int **pp) { (void)pp; }
+int main() { int *p = 0; int *const *pp = &p; f(pp); return 0; }
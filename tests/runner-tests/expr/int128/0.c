@@ -0,0 +1,33 @@
+// code: 0
+typedef unsigned long long u64;
+typedef unsigned __int128 u128;
+
+static u128 mul64(u64 a, u64 b) {
+    return (u128)a * b;
+}
+
+// `x` doesn't start at an even register
+static __int128 pass(int padding, __int128 x, __int128 y) {
+    return x - y + padding;
+}
+
+__int128 global = (__int128)1 << 100;
+
+int main(void) {
+    // (2^64 - 1)^2 = 2^128 - 2^65 + 1
+    u128 p = mul64(0xffffffffffffffffull, 0xffffffffffffffffull);
+    if ((u64)(p >> 64) != 0xfffffffffffffffeull || (u64)p != 1) return 1;
+    if (p / 0xffffffffffffffffull != 0xffffffffffffffffull) return 2;
+    if (p % 10 != p - p / 10 * 10) return 3;
+    __int128 neg = -global;
+    if (neg >= 0 || neg / 3 != -(global / 3)) return 4;
+    if (pass(1, global, 1) != global) return 5;
+    double d = global;
+    if (d != 1267650600228229401496703205376.0) return 6;
+    if ((__int128)d != global) return 7;
+    if (sizeof(__int128) != 16 || _Alignof(unsigned __int128) != 16) return 8;
+    int shift = 70;
+    if ((u128)1 << shift >> 69 != 2) return 9;
+    if (!global || !(global >> 100)) return 10;
+    return 0;
+}
@@ -0,0 +1,8 @@
+// errors: 1
+int main(void) {
+    __int128 x = 1;
+    switch (x) {
+        case 1: return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,8 @@
+// code: 2
+int main() {
+    int total = 0;
+    for (int i = 0; i < 10; i++) {
+        total += ({ if (i == 4) break; if (i % 2) continue; i; });
+    }
+    return total;
+}
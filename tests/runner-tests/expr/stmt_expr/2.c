@@ -0,0 +1,5 @@
+// compile-fail
+int main() {
+    goto inside;
+    return ({ inside: ; 1; });
+}
@@ -0,0 +1,6 @@
+// code: 3
+int main() {
+    int n = 3;
+    ({ n++; (void)0; });
+    return ({ int a[n]; sizeof a / sizeof a[0]; }) - 1;
+}
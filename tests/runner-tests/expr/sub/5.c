@@ -0,0 +1,7 @@
+// code: 33
+int main() {
+    int a[5];
+    const int *end = a + 3;
+    long d = end - a;
+    return d * 10 + (&a[4] - &a[1]);
+}
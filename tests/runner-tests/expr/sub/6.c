@@ -0,0 +1,7 @@
+// errors: 2
+int main() {
+    int a[2];
+    char *p = 0;
+    long d = p - a;
+    return a - 1.0;
+}
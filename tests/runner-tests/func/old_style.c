@@ -0,0 +1,10 @@
+// code: 5
+int add(a, b)
+    char a;
+    float b;
+{
+    return a + b;
+}
+int main() {
+    return add(2, 3.0f);
+}
@@ -0,0 +1,16 @@
+// code: 3
+int classify(char c) {
+    switch (c) {
+    case 'a' ... 'z':
+        return 1;
+    case '0' ... '9':
+        return 2;
+    case -5 ... -1:
+        return 3;
+    default:
+        return 0;
+    }
+}
+int main() {
+    return classify('q') + classify('5') - classify('Q') + classify(-3) - classify(-6) - 3;
+}
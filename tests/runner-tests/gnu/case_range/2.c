@@ -0,0 +1,9 @@
+// compile-fail
+int main(int argc) {
+    switch (argc) {
+    case 1 ... 10:
+    case 10 ... 20:
+        return 1;
+    }
+    return 0;
+}
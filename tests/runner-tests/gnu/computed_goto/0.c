@@ -0,0 +1,18 @@
+// code: 7
+int run(const unsigned char *ops) {
+    static void *dispatch[] = { &&op_inc, &&op_double, &&op_halt };
+    int acc = 0;
+    goto *dispatch[*ops++];
+op_inc:
+    acc++;
+    goto *dispatch[*ops++];
+op_double:
+    acc *= 2;
+    goto *dispatch[*ops++];
+op_halt:
+    return acc;
+}
+int main() {
+    unsigned char program[] = { 0, 0, 0, 1, 0, 2 };
+    return run(program);
+}
@@ -0,0 +1,5 @@
+// compile-fail
+int main() {
+    void *p = &&nowhere;
+    goto *p;
+}
@@ -0,0 +1,17 @@
+// code: 3
+int freed;
+void count(int *p) { freed++; }
+
+int main(void) {
+    int n = 4;
+    void *out = &&done;
+    for (int i = 0; i < 3; i++) {
+        // leaving the scope with `goto *` runs the cleanups, the same as `goto done`
+        int a[n];
+        __attribute__((cleanup(count))) int c = 0;
+        a[0] = c;
+        goto *out;
+        done:;
+    }
+    return freed;
+}
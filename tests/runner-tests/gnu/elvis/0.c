@@ -0,0 +1,11 @@
+// code: 12
+int calls;
+int next() {
+    return ++calls;
+}
+int main() {
+    int zero = 0;
+    int a = next() ?: 10;
+    int b = zero ?: 10;
+    return a + b + calls;
+}
@@ -0,0 +1,11 @@
+// code: 5
+typedef unsigned long size_t;
+extern size_t length(__const char *__restrict s) __asm__("strlen");
+__extension__ typedef long long ll;
+static __inline__ int twice(__signed__ int i) {
+    return i * 2;
+}
+int main() {
+    __volatile__ ll n = length("hello") + twice(0);
+    return n;
+}
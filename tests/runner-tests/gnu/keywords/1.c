@@ -0,0 +1,7 @@
+// compile-fail
+int f() __asm__("g") {
+    return 0;
+}
+int main() {
+    return f();
+}
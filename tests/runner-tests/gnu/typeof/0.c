@@ -0,0 +1,7 @@
+// code: 8
+#define max(a, b) ({ typeof(a) _a = (a); typeof(b) _b = (b); _a > _b ? _a : _b; })
+int main() {
+    int i = 3;
+    int m = max(i++, 2);
+    return m + i + max(1, 1);
+}
@@ -0,0 +1,12 @@
+// code: 42
+int main(void) {
+    long million = 1'000'000;
+    int mask = 0b1010'1010;
+    unsigned long big = 0xffff'ffffuwb;
+    double d = 1'0.5;
+    char c = u8'*';
+    if (million != 1000000 || mask != 170 || big != 4294967295 || d != 10.5) {
+        return 1;
+    }
+    return c;
+}
@@ -0,0 +1,12 @@
+// errors: 4
+struct point { int x int y; };
+int f(int a) {
+    int b = a +;
+    return b
+}
+int main() {
+    if (f(1) {
+        return 1;
+    }
+    return 0;
+}
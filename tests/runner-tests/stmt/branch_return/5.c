@@ -0,0 +1,8 @@
+// code: 1
+int f(int c) {
+ switch (c) {
+ case 1: return 1;
+ default: return 0;
+ }
+}
+int main() { return f(1); }
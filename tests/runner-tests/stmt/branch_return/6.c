@@ -0,0 +1,8 @@
+// fail
+int f(int c) {
+ switch (c) {
+ case 1: return 1;
+ case 2: return 0;
+ }
+}
+int main() { return f(1); }
@@ -0,0 +1,2 @@
+// code: 8
+int main() { int i = 0, t = 0; do { if (i == 2) continue; t += i; } while (++i < 5); return t; }
@@ -0,0 +1,2 @@
+// code: 3
+int main() { int i = 0; do { i++; continue; } while (i < 3); return i; }
@@ -0,0 +1,2 @@
+// code: 20
+int main() { int t = 0; for (int i = 0; i < 10; i++) { if (i % 2) continue; t += i; } return t; }
@@ -9,7 +9,7 @@ extern crate tempfile;
 
 use log::info;
 use saltwater_codegen::{assemble, initialize_aot_module, link};
-use saltwater_parser::{Error, Opt};
+use saltwater_parser::{Error, Opt, Standard};
 
 pub fn init() {
     env_logger::builder().is_test(true).init();
@@ -47,6 +47,14 @@ pub fn compile(
     no_link: bool,
 ) -> Result<tempfile::TempPath, Error> {
     let opts = Opt {
+        // tests in `runner-tests/gnu` are compiled with `-std=gnu11`
+        gnu_extensions: filename.components().any(|c| c.as_os_str() == "gnu"),
+        // and tests in `runner-tests/**/c23` with `-std=c23`
+        standard: if filename.components().any(|c| c.as_os_str() == "c23") {
+            Standard::C23
+        } else {
+            Standard::default()
+        },
         filename,
         ..Default::default()
     };